@@ -0,0 +1,98 @@
+//! # FModel Rust Derive
+//!
+//! The derive macros of the [fmodel-rust](https://crates.io/crates/fmodel-rust) crate. Enable the `derive` feature of the `fmodel-rust` crate to use them.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Error, LitStr};
+
+/// Derives the `fmodel_rust::message_type::MessageType` trait.
+///
+/// The message type of the enum is the name of its variant, and the message type of the struct is the name of the struct.
+/// The stable name is given by the `#[message_type(name = "...")]` attribute, so the Rust types can be renamed with no change of the stored messages.
+///
+/// ```ignore
+/// #[derive(MessageType)]
+/// enum OrderEvent {
+///     #[message_type(name = "order.created.v1")]
+///     Created(OrderCreatedEvent),
+///     Cancelled(OrderCancelledEvent),
+/// }
+/// ```
+#[proc_macro_derive(MessageType, attributes(message_type))]
+pub fn derive_message_type(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    message_type(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn message_type(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let (names, body) = match &input.data {
+        Data::Enum(data) => {
+            let mut names: Vec<LitStr> = Vec::with_capacity(data.variants.len());
+            let mut arms = Vec::with_capacity(data.variants.len());
+            for variant in &data.variants {
+                let name = name(&variant.attrs)?.unwrap_or_else(|| {
+                    LitStr::new(&variant.ident.to_string(), variant.ident.span())
+                });
+                if names.iter().any(|other| other.value() == name.value()) {
+                    return Err(Error::new(
+                        name.span(),
+                        format!("the message type `{}` is used twice", name.value()),
+                    ));
+                }
+                let variant = &variant.ident;
+                arms.push(quote! { Self::#variant { .. } => #name, });
+                names.push(name);
+            }
+            // `*self`, so the enum with no variants is matched exhaustively
+            (names, quote! { match *self { #(#arms)* } })
+        }
+        Data::Struct(_) => {
+            let name = name(&input.attrs)?
+                .unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+            (vec![name.clone()], quote! { #name })
+        }
+        Data::Union(_) => {
+            return Err(Error::new(
+                Span::call_site(),
+                "`MessageType` can be derived for the enums and the structs only",
+            ))
+        }
+    };
+    Ok(quote! {
+        impl #impl_generics ::fmodel_rust::message_type::MessageType for #ident #type_generics #where_clause {
+            const MESSAGE_TYPES: &'static [&'static str] = &[#(#names),*];
+            fn message_type(&self) -> &'static str {
+                #body
+            }
+        }
+    })
+}
+
+/// Parses the `#[message_type(name = "...")]` attribute.
+fn name(attributes: &[Attribute]) -> syn::Result<Option<LitStr>> {
+    let mut name = None;
+    for attribute in attributes {
+        if !attribute.path().is_ident("message_type") {
+            continue;
+        }
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                let value: LitStr = meta.value()?.parse()?;
+                if value.value().is_empty() {
+                    return Err(meta.error("the message type can not be empty"));
+                }
+                name = Some(value);
+                Ok(())
+            } else {
+                Err(meta.error("expected `name = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(name)
+}
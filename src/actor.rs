@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::{mpsc, oneshot};
+
+use crate::aggregate::EventRepository;
+use crate::decider::EventFold;
+use crate::middleware::Handler;
+use crate::telemetry::{
+    record, record_result, span, Instrument, Meter, Span, COMMANDS_HANDLED, DECIDE_ERRORS,
+    EVENTS_APPENDED, FOLD_LENGTH,
+};
+use crate::Identifier;
+
+/// Default time after which the idle actor is passivated.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Default number of the commands waiting in the mailbox of the actor.
+pub const DEFAULT_MAILBOX_CAPACITY: usize = 64;
+
+/// The error of the actor runtime.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActorError {
+    /// The actor has stopped before replying, as the `decide`/`evolve` function, or the repository, has panicked.
+    Stopped,
+    /// The mailbox of the actor is full. The command is not handled, so it can be retried later.
+    MailboxFull,
+}
+
+impl Display for ActorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActorError::Stopped => write!(f, "the aggregate actor has stopped before replying"),
+            ActorError::MailboxFull => write!(f, "the mailbox of the aggregate actor is full"),
+        }
+    }
+}
+
+impl std::error::Error for ActorError {}
+
+/// The command, together with the channel to reply to, and the span of the caller.
+struct Envelope<C, E, Version, Error> {
+    command: C,
+    reply: oneshot::Sender<Result<Vec<(E, Version)>, Error>>,
+    span: Span,
+}
+
+/// The mailboxes of the active actors, keyed by the identifier of the aggregate.
+type Mailboxes<C, E, Version, Error> =
+    Arc<Mutex<HashMap<String, mpsc::Sender<Envelope<C, E, Version, Error>>>>>;
+
+/// Actor Runtime.
+///
+/// Event sourced aggregate, running every active aggregate instance as the actor: the `tokio` task with the mailbox, keyed by the [Identifier] of the command.
+/// The actor keeps the current state of the aggregate (and its version) in memory, and handles its commands sequentially, one at a time.
+/// The events are fetched from the [EventRepository] only when the actor is started, and the new events are saved to it after the cached version, as usual.
+/// So the commands of the hot aggregates are decided on the cached state, with no fetching of the event stream.
+///
+/// The actor is passivated (stopped, and its state dropped) once it has been idle for the `idle_timeout`. It is started again by the next command.
+/// If saving the events fails (e.g. on the version conflict with another process), the cached state is dropped, and it is fetched again by the next command.
+///
+/// The mailbox is bounded by the `mailbox_capacity`. The commands sent to the full mailbox are rejected with the [ActorError::MailboxFull], instead of queuing up without limit.
+///
+/// The actors are in-process. Deploy it on the single node, or route the commands of the same aggregate to the same node.
+/// It requires the `tokio` runtime, with the time driver enabled: the actors are spawned on the runtime of the caller, so handling the command outside the runtime panics.
+///
+/// Generic parameters:
+///
+/// - `C` - Command
+/// - `S` - State
+/// - `E` - Event
+/// - `Repository` - Event repository
+/// - `Decider` - Event fold (decide/evolve), e.g. the [Decider](crate::decider::Decider)
+/// - `Version` - Version/Offset/Sequence number
+/// - `Error` - Error
+pub struct ActorRuntime<C, S, E, Repository, Decider, Version, Error>
+where
+    Repository: EventRepository<C, E, Version, Error>,
+    Decider: EventFold<C, S, E, Error>,
+    C: 'static,
+    S: 'static,
+    E: 'static,
+    Error: 'static,
+{
+    repository: Arc<Repository>,
+    decider: Arc<Decider>,
+    mailboxes: Mailboxes<C, E, Version, Error>,
+    idle_timeout: Duration,
+    mailbox_capacity: usize,
+    _marker: PhantomData<(C, S, E, Version, Error)>,
+}
+
+impl<C, S, E, Repository, Decider, Version, Error>
+    ActorRuntime<C, S, E, Repository, Decider, Version, Error>
+where
+    Repository: EventRepository<C, E, Version, Error> + Send + Sync + 'static,
+    Decider: EventFold<C, S, E, Error> + Send + Sync + 'static,
+    C: Identifier + Clone + Send + Sync + 'static,
+    S: Send + Sync + 'static,
+    E: Send + Sync + 'static,
+    Version: Clone + Send + Sync + 'static,
+    Error: From<ActorError> + Send + Sync + 'static,
+{
+    /// Creates a new instance of [ActorRuntime].
+    pub fn new(repository: Repository, decider: Decider) -> Self {
+        ActorRuntime {
+            repository: Arc::new(repository),
+            decider: Arc::new(decider),
+            mailboxes: Arc::new(Mutex::new(HashMap::new())),
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            mailbox_capacity: DEFAULT_MAILBOX_CAPACITY,
+            _marker: PhantomData,
+        }
+    }
+    /// Sets the time after which the idle actor is passivated. It is [DEFAULT_IDLE_TIMEOUT] by default.
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+    /// Sets the number of the commands waiting in the mailbox of every actor. It is [DEFAULT_MAILBOX_CAPACITY] by default.
+    pub fn with_mailbox_capacity(mut self, mailbox_capacity: usize) -> Self {
+        self.mailbox_capacity = mailbox_capacity.max(1);
+        self
+    }
+    /// Returns the repository.
+    pub fn repository(&self) -> &Repository {
+        &self.repository
+    }
+    /// Number of the active (not passivated) actors.
+    pub fn active(&self) -> usize {
+        self.mailboxes.lock().unwrap().len()
+    }
+    /// Handles the command by the actor of the aggregate, starting it if it is not active. Returns the saved events.
+    ///
+    /// It fails with the [ActorError::MailboxFull] if the mailbox of the actor is full, and with the [ActorError::Stopped] if the actor has stopped before replying,
+    /// which happens only if the `decide`/`evolve` function, or the repository, has panicked. The panicked actor is started again by the next command.
+    pub async fn handle(&self, command: &C) -> Result<Vec<(E, Version)>, Error>
+    where
+        Version: Debug,
+    {
+        let span = span!(
+            "handle",
+            component = "ActorRuntime",
+            identifier = %command.identifier(),
+            cached = tracing::field::Empty,
+            events_loaded = tracing::field::Empty,
+            events_produced = tracing::field::Empty,
+            version = tracing::field::Empty,
+            error = tracing::field::Empty
+        );
+        let (reply, response) = oneshot::channel();
+        let result = match self.send(Envelope {
+            command: command.clone(),
+            reply,
+            span: span.clone(),
+        }) {
+            Ok(()) => response
+                .await
+                .unwrap_or_else(|_| Err(ActorError::Stopped.into())),
+            Err(error) => Err(error.into()),
+        };
+        record_result!(span, result);
+        record!(
+            span,
+            "version",
+            result
+                .as_ref()
+                .ok()
+                .and_then(|saved_events| saved_events.last())
+                .map(|(_, version)| tracing::field::debug(version))
+        );
+        Meter::new::<S>("ActorRuntime").increment(COMMANDS_HANDLED, 1);
+        result
+    }
+    /// Sends the envelope to the mailbox of the actor, starting the actor if it is not active.
+    /// The registry is locked while sending, so the actor can not be passivated in between.
+    fn send(&self, envelope: Envelope<C, E, Version, Error>) -> Result<(), ActorError> {
+        let identifier = envelope.command.identifier();
+        let mut mailboxes = self.mailboxes.lock().unwrap();
+        let envelope = match mailboxes.get(&identifier) {
+            Some(mailbox) => match mailbox.try_send(envelope) {
+                Ok(()) => return Ok(()),
+                Err(mpsc::error::TrySendError::Full(_)) => return Err(ActorError::MailboxFull),
+                // The actor has panicked, it is started again
+                Err(mpsc::error::TrySendError::Closed(envelope)) => envelope,
+            },
+            None => envelope,
+        };
+        let (mailbox, receiver) = mpsc::channel(self.mailbox_capacity);
+        tokio::spawn(run(
+            identifier.clone(),
+            receiver,
+            self.repository.clone(),
+            self.decider.clone(),
+            self.mailboxes.clone(),
+            self.idle_timeout,
+        ));
+        // The receiver is alive, it is moved into the spawned task, and the new mailbox is empty
+        let _ = mailbox.try_send(envelope);
+        mailboxes.insert(identifier, mailbox);
+        Ok(())
+    }
+}
+
+/// The actor of the aggregate, handling the commands from its mailbox sequentially, until it is passivated.
+async fn run<C, S, E, Repository, Decider, Version, Error>(
+    identifier: String,
+    mut receiver: mpsc::Receiver<Envelope<C, E, Version, Error>>,
+    repository: Arc<Repository>,
+    decider: Arc<Decider>,
+    mailboxes: Mailboxes<C, E, Version, Error>,
+    idle_timeout: Duration,
+) where
+    Repository: EventRepository<C, E, Version, Error>,
+    Decider: EventFold<C, S, E, Error>,
+    Version: Clone,
+    C: 'static,
+    S: 'static,
+    E: 'static,
+    Error: 'static,
+{
+    let meter = Meter::new::<S>("ActorRuntime");
+    // The cached state, and the version of the last event applied to it
+    let mut cached: Option<(S, Option<Version>)> = None;
+    loop {
+        let envelope = match tokio::time::timeout(idle_timeout, receiver.recv()).await {
+            Ok(Some(envelope)) => envelope,
+            Ok(None) => return,
+            Err(_) => {
+                let mut mailboxes = mailboxes.lock().unwrap();
+                // The commands are sent while the registry is locked, so none can arrive once the mailbox is removed
+                if receiver.is_empty() {
+                    mailboxes.remove(&identifier);
+                    return;
+                }
+                continue;
+            }
+        };
+        let span = envelope.span.clone();
+        let result = handle(
+            &envelope.command,
+            &mut cached,
+            repository.as_ref(),
+            decider.as_ref(),
+            meter,
+            &span,
+        )
+        .instrument(span.clone())
+        .await;
+        // The caller may have stopped waiting for the reply
+        let _ = envelope.reply.send(result);
+    }
+}
+
+/// Handles the command on the `cached` state, fetching the state first if it is not cached.
+/// The cached state is kept if the command is rejected by the decider, and dropped if saving the events fails.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+async fn handle<C, S, E, Repository, Decider, Version, Error>(
+    command: &C,
+    cached: &mut Option<(S, Option<Version>)>,
+    repository: &Repository,
+    decider: &Decider,
+    meter: Meter,
+    span: &Span,
+) -> Result<Vec<(E, Version)>, Error>
+where
+    Repository: EventRepository<C, E, Version, Error>,
+    Decider: EventFold<C, S, E, Error>,
+    Version: Clone,
+{
+    record!(span, "cached", cached.is_some());
+    let (current_state, latest_version) = match cached.take() {
+        Some(cached) => cached,
+        None => {
+            let events = repository
+                .fetch_events(command)
+                .instrument(span!("fetch"))
+                .await?;
+            record!(span, "events_loaded", events.len());
+            meter.record(FOLD_LENGTH, events.len());
+            let mut state = decider.initial_state();
+            let mut latest_version = None;
+            for (event, version) in events {
+                state = decider.evolve_state(&state, &event);
+                latest_version = Some(version);
+            }
+            (state, latest_version)
+        }
+    };
+    let new_events = decider.decide_events(command, &current_state);
+    meter.increment_on_error(DECIDE_ERRORS, &new_events);
+    let new_events = match new_events {
+        Ok(new_events) => new_events,
+        Err(error) => {
+            *cached = Some((current_state, latest_version));
+            return Err(error);
+        }
+    };
+    record!(span, "events_produced", new_events.len());
+    let saved_events = repository
+        .save_after(command, &new_events, &latest_version)
+        .instrument(span!("save"))
+        .await?;
+    meter.increment(EVENTS_APPENDED, saved_events.len());
+    let new_state = new_events.iter().fold(current_state, |state, event| {
+        decider.evolve_state(&state, event)
+    });
+    let latest_version = saved_events
+        .last()
+        .map(|(_, version)| version.clone())
+        .or(latest_version);
+    *cached = Some((new_state, latest_version));
+    Ok(saved_events)
+}
+
+impl<C, S, E, Repository, Decider, Version, Error> Handler<C, Vec<(E, Version)>, Error>
+    for ActorRuntime<C, S, E, Repository, Decider, Version, Error>
+where
+    Repository: EventRepository<C, E, Version, Error> + Send + Sync + 'static,
+    Decider: EventFold<C, S, E, Error> + Send + Sync + 'static,
+    C: Identifier + Clone + Send + Sync + 'static,
+    S: Send + Sync + 'static,
+    E: Send + Sync + 'static,
+    Version: Debug + Clone + Send + Sync + 'static,
+    Error: From<ActorError> + Send + Sync + 'static,
+{
+    /// Handles the command by the actor of the aggregate.
+    async fn handle(&self, command: &C) -> Result<Vec<(E, Version)>, Error> {
+        ActorRuntime::handle(self, command).await
+    }
+}
@@ -1,9 +1,19 @@
+use std::fmt::Debug;
 use std::future::Future;
 use std::marker::PhantomData;
+#[cfg(feature = "stream")]
+use std::pin::Pin;
+#[cfg(feature = "stream")]
+use std::task::{Context, Poll};
 
-use crate::decider::{Decider, EventComputation, StateComputation};
+use crate::decider::{Decider, EventComputation, EventFold, StateComputation};
+use crate::outbox::{OutboxEventRepository, OutboxStateRepository};
 use crate::saga::{ActionComputation, Saga};
-use crate::Identifier;
+use crate::telemetry::{
+    record, record_result, span, Instrument, Meter, Span, COMMANDS_HANDLED, DECIDE_ERRORS,
+    EVENTS_APPENDED, FOLD_LENGTH,
+};
+use crate::{IdempotencyKey, Identifier};
 
 /// Event Repository trait
 ///
@@ -23,9 +33,24 @@ pub trait EventRepository<C, E, Version, Error> {
         command: &C,
     ) -> impl Future<Output = Result<Vec<(E, Version)>, Error>> + Send;
     /// Saves events.
-    /// Desugared `async fn save(&self, events: &[E], latest_version: &Option<Version>) -> Result<Vec<(E, Version)>, Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`
+    /// Desugared `async fn save(&self, events: &[E]) -> Result<Vec<(E, Version)>, Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`
     /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
     fn save(&self, events: &[E]) -> impl Future<Output = Result<Vec<(E, Version)>, Error>> + Send;
+    /// Saves events, only if the stream of the command is still at the `latest_version` - the version of the last event fetched by [EventRepository::fetch_events], or `None` if no event was fetched.
+    /// This way, the events decided on the stale state are rejected (optimistic locking), instead of being appended after the events saved concurrently.
+    /// The events of the other streams (emitted by the orchestrating aggregates) are appended to the end of their streams.
+    ///
+    /// It saves the events with no check, by default. The repositories supporting the optimistic locking override it.
+    /// Desugared `async fn save_after(&self, command: &C, events: &[E], latest_version: &Option<Version>) -> Result<Vec<(E, Version)>, Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn save_after(
+        &self,
+        _command: &C,
+        events: &[E],
+        _latest_version: &Option<Version>,
+    ) -> impl Future<Output = Result<Vec<(E, Version)>, Error>> + Send {
+        self.save(events)
+    }
 
     /// Version provider. It is used to provide the version/sequence of the stream to wich this event belongs to. Optimistic locking is useing this version to check if the event is already saved.
     /// Desugared `async fn version_provider(&self, event: &E) -> Result<Option<Version>, Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`
@@ -51,9 +76,24 @@ pub trait EventRepository<C, E, Version, Error> {
     /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
     fn fetch_events(&self, command: &C) -> impl Future<Output = Result<Vec<(E, Version)>, Error>>;
     /// Saves events.
-    /// Desugared `async fn save(&self, events: &[E], latest_version: &Option<Version>) -> Result<Vec<(E, Version)>, Error>;` to a normal `fn` that returns `impl Future`
+    /// Desugared `async fn save(&self, events: &[E]) -> Result<Vec<(E, Version)>, Error>;` to a normal `fn` that returns `impl Future`
     /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
     fn save(&self, events: &[E]) -> impl Future<Output = Result<Vec<(E, Version)>, Error>>;
+    /// Saves events, only if the stream of the command is still at the `latest_version` - the version of the last event fetched by [EventRepository::fetch_events], or `None` if no event was fetched.
+    /// This way, the events decided on the stale state are rejected (optimistic locking), instead of being appended after the events saved concurrently.
+    /// The events of the other streams (emitted by the orchestrating aggregates) are appended to the end of their streams.
+    ///
+    /// It saves the events with no check, by default. The repositories supporting the optimistic locking override it.
+    /// Desugared `async fn save_after(&self, command: &C, events: &[E], latest_version: &Option<Version>) -> Result<Vec<(E, Version)>, Error>;` to a normal `fn` that returns `impl Future`
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn save_after(
+        &self,
+        _command: &C,
+        events: &[E],
+        _latest_version: &Option<Version>,
+    ) -> impl Future<Output = Result<Vec<(E, Version)>, Error>> {
+        self.save(events)
+    }
 
     /// Version provider. It is used to provide the version/sequence of the stream to wich this event belongs to. Optimistic locking is useing this version to check if the event is already saved.
     /// Desugared `async fn version_provider(&self, event: &E) -> Result<Option<Version>, Error>;` to a normal `fn` that returns `impl Future`
@@ -61,6 +101,289 @@ pub trait EventRepository<C, E, Version, Error> {
     fn version_provider(&self, event: &E) -> impl Future<Output = Result<Option<Version>, Error>>;
 }
 
+/// Idempotent Event Repository trait
+///
+/// Event repository that records the idempotency keys of the processed commands, together with the events they produced.
+/// The keys can be scoped per stream (for example, by the [Identifier] of the command) or globally; it is up to the implementation.
+///
+/// Generic parameters:
+///
+/// - `C` - Command
+/// - `E` - Event
+/// - `Version` - Version/Offset/Sequence number
+/// - `Error` - Error
+#[cfg(not(feature = "not-send-futures"))]
+pub trait IdempotentEventRepository<C, E, Version, Error>:
+    EventRepository<C, E, Version, Error>
+{
+    /// Fetches the events produced by the already processed command with the same idempotency key, if any.
+    /// Desugared `async fn fetch_processed(&self, command: &C, idempotency_key: &str) -> Result<Option<Vec<(E, Version)>>, Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn fetch_processed(
+        &self,
+        command: &C,
+        idempotency_key: &str,
+    ) -> impl Future<Output = Result<Option<Vec<(E, Version)>>, Error>> + Send;
+    /// Saves events and records the idempotency key of the command they are produced by, atomically.
+    /// Like [EventRepository::save_after], the events are saved only if the stream of the command is still at the `latest_version`.
+    ///
+    /// The key already recorded (by the concurrent duplicate, handled in the meantime) must be rejected atomically, too: the events are not saved,
+    /// and the events recorded with the key are returned instead. Otherwise, both duplicates passing [IdempotentEventRepository::fetch_processed] would be saved.
+    /// Desugared `async fn save_processed(&self, command: &C, idempotency_key: &str, events: &[E], latest_version: &Option<Version>) -> Result<Vec<(E, Version)>, Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn save_processed(
+        &self,
+        command: &C,
+        idempotency_key: &str,
+        events: &[E],
+        latest_version: &Option<Version>,
+    ) -> impl Future<Output = Result<Vec<(E, Version)>, Error>> + Send;
+}
+
+/// Idempotent Event Repository trait
+///
+/// Event repository that records the idempotency keys of the processed commands, together with the events they produced.
+/// The keys can be scoped per stream (for example, by the [Identifier] of the command) or globally; it is up to the implementation.
+///
+/// Generic parameters:
+///
+/// - `C` - Command
+/// - `E` - Event
+/// - `Version` - Version/Offset/Sequence number
+/// - `Error` - Error
+#[cfg(feature = "not-send-futures")]
+pub trait IdempotentEventRepository<C, E, Version, Error>:
+    EventRepository<C, E, Version, Error>
+{
+    /// Fetches the events produced by the already processed command with the same idempotency key, if any.
+    /// Desugared `async fn fetch_processed(&self, command: &C, idempotency_key: &str) -> Result<Option<Vec<(E, Version)>>, Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn fetch_processed(
+        &self,
+        command: &C,
+        idempotency_key: &str,
+    ) -> impl Future<Output = Result<Option<Vec<(E, Version)>>, Error>>;
+    /// Saves events and records the idempotency key of the command they are produced by, atomically.
+    /// Like [EventRepository::save_after], the events are saved only if the stream of the command is still at the `latest_version`.
+    ///
+    /// The key already recorded (by the concurrent duplicate, handled in the meantime) must be rejected atomically, too: the events are not saved,
+    /// and the events recorded with the key are returned instead. Otherwise, both duplicates passing [IdempotentEventRepository::fetch_processed] would be saved.
+    /// Desugared `async fn save_processed(&self, command: &C, idempotency_key: &str, events: &[E], latest_version: &Option<Version>) -> Result<Vec<(E, Version)>, Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn save_processed(
+        &self,
+        command: &C,
+        idempotency_key: &str,
+        events: &[E],
+        latest_version: &Option<Version>,
+    ) -> impl Future<Output = Result<Vec<(E, Version)>, Error>>;
+}
+
+/// Event Stream trait
+///
+/// The events of the stream, pulled one at a time, so the long streams are folded with no loading of all the events into the memory.
+///
+/// Generic parameters:
+///
+/// - `E` - Event
+/// - `Version` - Version/Offset/Sequence number
+/// - `Error` - Error
+#[cfg(not(feature = "not-send-futures"))]
+pub trait EventStream<E, Version, Error> {
+    /// Pulls the next event of the stream, or `None` once the stream is exhausted.
+    /// Desugared `async fn next(&mut self) -> Option<Result<(E, Version), Error>>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn next(&mut self) -> impl Future<Output = Option<Result<(E, Version), Error>>> + Send;
+}
+
+/// Event Stream trait
+///
+/// The events of the stream, pulled one at a time, so the long streams are folded with no loading of all the events into the memory.
+///
+/// Generic parameters:
+///
+/// - `E` - Event
+/// - `Version` - Version/Offset/Sequence number
+/// - `Error` - Error
+#[cfg(feature = "not-send-futures")]
+pub trait EventStream<E, Version, Error> {
+    /// Pulls the next event of the stream, or `None` once the stream is exhausted.
+    /// Desugared `async fn next(&mut self) -> Option<Result<(E, Version), Error>>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn next(&mut self) -> impl Future<Output = Option<Result<(E, Version), Error>>>;
+}
+
+/// The pending pull of the [EventStreamAdapter], owning the stream until the next event is pulled.
+#[cfg(all(feature = "stream", not(feature = "not-send-futures")))]
+type PendingPull<'a, S, E, Version, Error> =
+    Pin<Box<dyn Future<Output = (S, Option<Result<(E, Version), Error>>)> + Send + 'a>>;
+
+/// The pending pull of the [EventStreamAdapter], owning the stream until the next event is pulled.
+#[cfg(all(feature = "stream", feature = "not-send-futures"))]
+type PendingPull<'a, S, E, Version, Error> =
+    Pin<Box<dyn Future<Output = (S, Option<Result<(E, Version), Error>>)> + 'a>>;
+
+/// Event Stream Adapter.
+///
+/// Adapts the [EventStream] to the `futures::Stream`, so the combinators of the `futures` ecosystem apply to the event streams.
+/// The stream is moved into the pending pull, and back, so it is pulled by the [EventStream::next] as usual.
+///
+/// Generic parameters:
+///
+/// - `S` - Event stream
+/// - `E` - Event
+/// - `Version` - Version/Offset/Sequence number
+/// - `Error` - Error
+#[cfg(feature = "stream")]
+pub struct EventStreamAdapter<'a, S, E, Version, Error> {
+    stream: Option<S>,
+    pending: Option<PendingPull<'a, S, E, Version, Error>>,
+}
+
+#[cfg(feature = "stream")]
+impl<S, E, Version, Error> EventStreamAdapter<'_, S, E, Version, Error> {
+    /// Creates a new instance of [EventStreamAdapter].
+    pub fn new(stream: S) -> Self {
+        EventStreamAdapter {
+            stream: Some(stream),
+            pending: None,
+        }
+    }
+}
+
+// The stream is never pinned: it is moved into the boxed (pinned) pull, and back out of it
+#[cfg(feature = "stream")]
+impl<S, E, Version, Error> Unpin for EventStreamAdapter<'_, S, E, Version, Error> {}
+
+#[cfg(all(feature = "stream", not(feature = "not-send-futures")))]
+impl<'a, S, E, Version, Error> futures_core::Stream for EventStreamAdapter<'a, S, E, Version, Error>
+where
+    S: EventStream<E, Version, Error> + Send + 'a,
+{
+    type Item = Result<(E, Version), Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if this.pending.is_none() {
+            let Some(mut stream) = this.stream.take() else {
+                return Poll::Ready(None);
+            };
+            this.pending = Some(Box::pin(async move {
+                let event = stream.next().await;
+                (stream, event)
+            }));
+        }
+        let (stream, event) = match this.pending.as_mut().map(|pull| pull.as_mut().poll(cx)) {
+            Some(Poll::Ready(pulled)) => pulled,
+            _ => return Poll::Pending,
+        };
+        this.pending = None;
+        // The exhausted stream is dropped, so it is not pulled again
+        if event.is_some() {
+            this.stream = Some(stream);
+        }
+        Poll::Ready(event)
+    }
+}
+
+#[cfg(all(feature = "stream", feature = "not-send-futures"))]
+impl<'a, S, E, Version, Error> futures_core::Stream for EventStreamAdapter<'a, S, E, Version, Error>
+where
+    S: EventStream<E, Version, Error> + 'a,
+{
+    type Item = Result<(E, Version), Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if this.pending.is_none() {
+            let Some(mut stream) = this.stream.take() else {
+                return Poll::Ready(None);
+            };
+            this.pending = Some(Box::pin(async move {
+                let event = stream.next().await;
+                (stream, event)
+            }));
+        }
+        let (stream, event) = match this.pending.as_mut().map(|pull| pull.as_mut().poll(cx)) {
+            Some(Poll::Ready(pulled)) => pulled,
+            _ => return Poll::Pending,
+        };
+        this.pending = None;
+        // The exhausted stream is dropped, so it is not pulled again
+        if event.is_some() {
+            this.stream = Some(stream);
+        }
+        Poll::Ready(event)
+    }
+}
+
+/// Streaming Event Repository trait
+///
+/// Event repository that streams the current events, instead of fetching them all at once. See [EventSourcedAggregate::handle_streaming].
+///
+/// Generic parameters:
+///
+/// - `C` - Command
+/// - `E` - Event
+/// - `Version` - Version/Offset/Sequence number
+/// - `Error` - Error
+#[cfg(not(feature = "not-send-futures"))]
+pub trait StreamingEventRepository<C, E, Version, Error>:
+    EventRepository<C, E, Version, Error>
+{
+    /// Streams current events, based on the command, ordered by their version.
+    /// The stream is lazy: the events are read as they are pulled, and the errors are returned by the stream.
+    fn stream_events(&self, command: &C) -> impl EventStream<E, Version, Error> + Send;
+}
+
+/// Streaming Event Repository trait
+///
+/// Event repository that streams the current events, instead of fetching them all at once. See [EventSourcedAggregate::handle_streaming].
+///
+/// Generic parameters:
+///
+/// - `C` - Command
+/// - `E` - Event
+/// - `Version` - Version/Offset/Sequence number
+/// - `Error` - Error
+#[cfg(feature = "not-send-futures")]
+pub trait StreamingEventRepository<C, E, Version, Error>:
+    EventRepository<C, E, Version, Error>
+{
+    /// Streams current events, based on the command, ordered by their version.
+    /// The stream is lazy: the events are read as they are pulled, and the errors are returned by the stream.
+    fn stream_events(&self, command: &C) -> impl EventStream<E, Version, Error>;
+}
+
+/// Opens the `handle` span of the event-sourced `component`.
+/// The `identifier` and the `version` fields are recorded by the `handle_traced` methods only, as they need the [Identifier] of the command and the [Debug] version.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+fn event_sourced_span(component: &'static str) -> Span {
+    span!(
+        "handle",
+        component = component,
+        identifier = tracing::field::Empty,
+        events_loaded = tracing::field::Empty,
+        events_produced = tracing::field::Empty,
+        version = tracing::field::Empty,
+        error = tracing::field::Empty
+    )
+}
+
+/// Opens the `handle` span of the state-stored `component`.
+/// The `identifier` and the `version` fields are recorded by the `handle_traced` methods only, as they need the [Identifier] of the command and the [Debug] version.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+fn state_stored_span(component: &'static str) -> Span {
+    span!(
+        "handle",
+        component = component,
+        identifier = tracing::field::Empty,
+        state_loaded = tracing::field::Empty,
+        version = tracing::field::Empty,
+        error = tracing::field::Empty
+    )
+}
+
 /// Event Sourced Aggregate.
 ///
 /// It is using a `Decider` / [EventComputation] to compute new events based on the current events and the command.
@@ -97,6 +420,26 @@ where
     }
 }
 
+impl<C, S, E, Repository, Decider, Version, Error> EventFold<C, S, E, Error>
+    for EventSourcedAggregate<C, S, E, Repository, Decider, Version, Error>
+where
+    Repository: EventRepository<C, E, Version, Error>,
+    Decider: EventComputation<C, S, E, Error> + EventFold<C, S, E, Error>,
+{
+    /// The initial state, the events are folded into.
+    fn initial_state(&self) -> S {
+        self.decider.initial_state()
+    }
+    /// Evolves the state by the event.
+    fn evolve_state(&self, state: &S, event: &E) -> S {
+        self.decider.evolve_state(state, event)
+    }
+    /// Decides the new events based on the (folded) state and the command.
+    fn decide_events(&self, command: &C, state: &S) -> Result<Vec<E>, Error> {
+        self.decider.decide_events(command, state)
+    }
+}
+
 #[cfg(not(feature = "not-send-futures"))]
 impl<C, S, E, Repository, Decider, Version, Error> EventRepository<C, E, Version, Error>
     for EventSourcedAggregate<C, S, E, Repository, Decider, Version, Error>
@@ -117,6 +460,17 @@ where
     async fn save(&self, events: &[E]) -> Result<Vec<(E, Version)>, Error> {
         self.repository.save(events).await
     }
+    /// Saves events, only if the stream of the command is still at the `latest_version`.
+    async fn save_after(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<Version>,
+    ) -> Result<Vec<(E, Version)>, Error> {
+        self.repository
+            .save_after(command, events, latest_version)
+            .await
+    }
     /// Version provider. It is used to provide the version/sequence of the event. Optimistic locking is useing this version to check if the event is already saved.
     async fn version_provider(&self, event: &E) -> Result<Option<Version>, Error> {
         self.repository.version_provider(event).await
@@ -138,6 +492,17 @@ where
     async fn save(&self, events: &[E]) -> Result<Vec<(E, Version)>, Error> {
         self.repository.save(events).await
     }
+    /// Saves events, only if the stream of the command is still at the `latest_version`.
+    async fn save_after(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<Version>,
+    ) -> Result<Vec<(E, Version)>, Error> {
+        self.repository
+            .save_after(command, events, latest_version)
+            .await
+    }
     /// Version provider. It is used to provide the version/sequence of the event. Optimistic locking is useing this version to check if the event is already saved.
     async fn version_provider(&self, event: &E) -> Result<Option<Version>, Error> {
         self.repository.version_provider(event).await
@@ -166,14 +531,193 @@ where
     }
     /// Handles the command by fetching the events from the repository, computing new events based on the current events and the command, and saving the new events to the repository.
     pub async fn handle(&self, command: &C) -> Result<Vec<(E, Version)>, Error> {
-        let events: Vec<(E, Version)> = self.fetch_events(command).await?;
-        let mut current_events: Vec<E> = vec![];
-        for (event, _) in events {
-            current_events.push(event);
+        let span = event_sourced_span("EventSourcedAggregate");
+        self.handle_with(command, &span, |new_events, latest_version| async move {
+            self.save_after(command, &new_events, &latest_version).await
+        })
+        .await
+    }
+    /// Handles the command like [EventSourcedAggregate::handle] does, and records the [Identifier] of the command and the version of the last saved event in the `handle` span.
+    /// With the `tracing` feature disabled, it is the same as [EventSourcedAggregate::handle].
+    pub async fn handle_traced(&self, command: &C) -> Result<Vec<(E, Version)>, Error>
+    where
+        C: Identifier,
+        Version: Debug,
+    {
+        let span = event_sourced_span("EventSourcedAggregate");
+        record!(
+            span,
+            "identifier",
+            tracing::field::display(command.identifier())
+        );
+        let result = self
+            .handle_with(command, &span, |new_events, latest_version| async move {
+                self.save_after(command, &new_events, &latest_version).await
+            })
+            .await;
+        record!(
+            span,
+            "version",
+            result
+                .as_ref()
+                .ok()
+                .and_then(|saved_events| saved_events.last())
+                .map(|(_, version)| tracing::field::debug(version))
+        );
+        result
+    }
+    /// Handles the command by fetching the events from the repository, and computing new events based on the current events and the command, within the `span`.
+    /// The new events are saved by the `save` function, with the version of the last fetched event.
+    async fn handle_with<F, Fut>(
+        &self,
+        command: &C,
+        span: &Span,
+        save: F,
+    ) -> Result<Vec<(E, Version)>, Error>
+    where
+        F: FnOnce(Vec<E>, Option<Version>) -> Fut,
+        Fut: Future<Output = Result<Vec<(E, Version)>, Error>>,
+    {
+        let load = async {
+            let events: Vec<(E, Version)> = self.fetch_events(command).await?;
+            let events_loaded = events.len();
+            let mut current_events: Vec<E> = vec![];
+            let mut latest_version: Option<Version> = None;
+            for (event, version) in events {
+                current_events.push(event);
+                latest_version = Some(version);
+            }
+            Ok((current_events, latest_version, events_loaded))
+        };
+        Self::handle_in(
+            span,
+            load,
+            |current_events| self.compute_new_events(&current_events, command),
+            save,
+        )
+        .await
+    }
+    /// Handles the command within the `span`.
+    /// The `load` future loads the current events (or the state folded from them), with the version of the last loaded event and the number of the loaded events.
+    /// The new events are computed by the `decide` function, and saved by the `save` function, with the version of the last loaded event.
+    async fn handle_in<Current, L, D, F, Fut>(
+        span: &Span,
+        load: L,
+        decide: D,
+        save: F,
+    ) -> Result<Vec<(E, Version)>, Error>
+    where
+        L: Future<Output = Result<(Current, Option<Version>, usize), Error>>,
+        D: FnOnce(Current) -> Result<Vec<E>, Error>,
+        F: FnOnce(Vec<E>, Option<Version>) -> Fut,
+        Fut: Future<Output = Result<Vec<(E, Version)>, Error>>,
+    {
+        let meter = Meter::new::<S>("EventSourcedAggregate");
+        let result = async {
+            let (current, latest_version, events_loaded) = load.instrument(span!("fetch")).await?;
+            record!(span, "events_loaded", events_loaded);
+            meter.record(FOLD_LENGTH, events_loaded);
+            let new_events = decide(current);
+            meter.increment_on_error(DECIDE_ERRORS, &new_events);
+            let new_events = new_events?;
+            record!(span, "events_produced", new_events.len());
+            let saved_events = save(new_events, latest_version)
+                .instrument(span!("save"))
+                .await?;
+            meter.increment(EVENTS_APPENDED, saved_events.len());
+            Ok(saved_events)
         }
-        let new_events = self.compute_new_events(&current_events, command)?;
-        let saved_events = self.save(&new_events).await?;
-        Ok(saved_events)
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result);
+        meter.increment(COMMANDS_HANDLED, 1);
+        result
+    }
+    /// Handles the command like [EventSourcedAggregate::handle] does, but folds the current events into the state one at a time, as they are pulled from the [StreamingEventRepository].
+    /// The memory does not grow with the length of the stream, as only the folded state is kept.
+    pub async fn handle_streaming(&self, command: &C) -> Result<Vec<(E, Version)>, Error>
+    where
+        Repository: StreamingEventRepository<C, E, Version, Error>,
+        Decider: EventFold<C, S, E, Error>,
+    {
+        let span = event_sourced_span("EventSourcedAggregate");
+        let load = async {
+            let mut events = self.repository.stream_events(command);
+            let mut state = self.decider.initial_state();
+            let mut events_loaded = 0;
+            let mut latest_version: Option<Version> = None;
+            while let Some(event) = events.next().await {
+                let (event, version) = event?;
+                latest_version = Some(version);
+                state = self.decider.evolve_state(&state, &event);
+                events_loaded += 1;
+            }
+            Ok((state, latest_version, events_loaded))
+        };
+        Self::handle_in(
+            &span,
+            load,
+            |state| self.decider.decide_events(command, &state),
+            |new_events, latest_version| async move {
+                self.save_after(command, &new_events, &latest_version).await
+            },
+        )
+        .await
+    }
+    /// Handles the command like [EventSourcedAggregate::handle] does, unless the command with the same [IdempotencyKey] is already processed.
+    /// For the duplicate command, the originally produced events are returned, and the command is not decided again.
+    /// Commands with no idempotency key are always handled.
+    pub async fn handle_idempotent(&self, command: &C) -> Result<Vec<(E, Version)>, Error>
+    where
+        Repository: IdempotentEventRepository<C, E, Version, Error>,
+        C: IdempotencyKey,
+    {
+        let Some(idempotency_key) = command.idempotency_key() else {
+            return self.handle(command).await;
+        };
+        match self
+            .repository
+            .fetch_processed(command, &idempotency_key)
+            .await
+        {
+            Ok(None) => {
+                let span = event_sourced_span("EventSourcedAggregate");
+                self.handle_with(command, &span, |new_events, latest_version| async move {
+                    self.repository
+                        .save_processed(command, &idempotency_key, &new_events, &latest_version)
+                        .await
+                })
+                .await
+            }
+            processed_events => {
+                // The duplicate is handled without deciding, but it still counts as the handled command.
+                Meter::new::<S>("EventSourcedAggregate").increment(COMMANDS_HANDLED, 1);
+                processed_events.map(Option::unwrap_or_default)
+            }
+        }
+    }
+    /// Handles the command like [EventSourcedAggregate::handle] does, and stores the outgoing messages computed by the `saga` from the new events into the outbox, in the same transaction with the events.
+    /// See [OutboxEventRepository] and [OutboxRelay](crate::outbox::OutboxRelay).
+    pub async fn handle_with_outbox<A, Saga>(
+        &self,
+        command: &C,
+        saga: &Saga,
+    ) -> Result<Vec<(E, Version)>, Error>
+    where
+        Repository: OutboxEventRepository<C, E, A, Version, Error>,
+        Saga: ActionComputation<E, A> + Sync,
+    {
+        let span = event_sourced_span("EventSourcedAggregate");
+        self.handle_with(command, &span, |new_events, latest_version| async move {
+            let messages: Vec<A> = new_events
+                .iter()
+                .flat_map(|event| saga.compute_new_actions(event))
+                .collect();
+            self.repository
+                .save_with_outbox(command, &new_events, &latest_version, &messages)
+                .await
+        })
+        .await
     }
 }
 
@@ -194,14 +738,193 @@ where
     }
     /// Handles the command by fetching the events from the repository, computing new events based on the current events and the command, and saving the new events to the repository.
     pub async fn handle(&self, command: &C) -> Result<Vec<(E, Version)>, Error> {
-        let events: Vec<(E, Version)> = self.fetch_events(command).await?;
-        let mut current_events: Vec<E> = vec![];
-        for (event, _) in events {
-            current_events.push(event);
+        let span = event_sourced_span("EventSourcedAggregate");
+        self.handle_with(command, &span, |new_events, latest_version| async move {
+            self.save_after(command, &new_events, &latest_version).await
+        })
+        .await
+    }
+    /// Handles the command like [EventSourcedAggregate::handle] does, and records the [Identifier] of the command and the version of the last saved event in the `handle` span.
+    /// With the `tracing` feature disabled, it is the same as [EventSourcedAggregate::handle].
+    pub async fn handle_traced(&self, command: &C) -> Result<Vec<(E, Version)>, Error>
+    where
+        C: Identifier,
+        Version: Debug,
+    {
+        let span = event_sourced_span("EventSourcedAggregate");
+        record!(
+            span,
+            "identifier",
+            tracing::field::display(command.identifier())
+        );
+        let result = self
+            .handle_with(command, &span, |new_events, latest_version| async move {
+                self.save_after(command, &new_events, &latest_version).await
+            })
+            .await;
+        record!(
+            span,
+            "version",
+            result
+                .as_ref()
+                .ok()
+                .and_then(|saved_events| saved_events.last())
+                .map(|(_, version)| tracing::field::debug(version))
+        );
+        result
+    }
+    /// Handles the command by fetching the events from the repository, and computing new events based on the current events and the command, within the `span`.
+    /// The new events are saved by the `save` function, with the version of the last fetched event.
+    async fn handle_with<F, Fut>(
+        &self,
+        command: &C,
+        span: &Span,
+        save: F,
+    ) -> Result<Vec<(E, Version)>, Error>
+    where
+        F: FnOnce(Vec<E>, Option<Version>) -> Fut,
+        Fut: Future<Output = Result<Vec<(E, Version)>, Error>>,
+    {
+        let load = async {
+            let events: Vec<(E, Version)> = self.fetch_events(command).await?;
+            let events_loaded = events.len();
+            let mut current_events: Vec<E> = vec![];
+            let mut latest_version: Option<Version> = None;
+            for (event, version) in events {
+                current_events.push(event);
+                latest_version = Some(version);
+            }
+            Ok((current_events, latest_version, events_loaded))
+        };
+        Self::handle_in(
+            span,
+            load,
+            |current_events| self.compute_new_events(&current_events, command),
+            save,
+        )
+        .await
+    }
+    /// Handles the command within the `span`.
+    /// The `load` future loads the current events (or the state folded from them), with the version of the last loaded event and the number of the loaded events.
+    /// The new events are computed by the `decide` function, and saved by the `save` function, with the version of the last loaded event.
+    async fn handle_in<Current, L, D, F, Fut>(
+        span: &Span,
+        load: L,
+        decide: D,
+        save: F,
+    ) -> Result<Vec<(E, Version)>, Error>
+    where
+        L: Future<Output = Result<(Current, Option<Version>, usize), Error>>,
+        D: FnOnce(Current) -> Result<Vec<E>, Error>,
+        F: FnOnce(Vec<E>, Option<Version>) -> Fut,
+        Fut: Future<Output = Result<Vec<(E, Version)>, Error>>,
+    {
+        let meter = Meter::new::<S>("EventSourcedAggregate");
+        let result = async {
+            let (current, latest_version, events_loaded) = load.instrument(span!("fetch")).await?;
+            record!(span, "events_loaded", events_loaded);
+            meter.record(FOLD_LENGTH, events_loaded);
+            let new_events = decide(current);
+            meter.increment_on_error(DECIDE_ERRORS, &new_events);
+            let new_events = new_events?;
+            record!(span, "events_produced", new_events.len());
+            let saved_events = save(new_events, latest_version)
+                .instrument(span!("save"))
+                .await?;
+            meter.increment(EVENTS_APPENDED, saved_events.len());
+            Ok(saved_events)
+        }
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result);
+        meter.increment(COMMANDS_HANDLED, 1);
+        result
+    }
+    /// Handles the command like [EventSourcedAggregate::handle] does, but folds the current events into the state one at a time, as they are pulled from the [StreamingEventRepository].
+    /// The memory does not grow with the length of the stream, as only the folded state is kept.
+    pub async fn handle_streaming(&self, command: &C) -> Result<Vec<(E, Version)>, Error>
+    where
+        Repository: StreamingEventRepository<C, E, Version, Error>,
+        Decider: EventFold<C, S, E, Error>,
+    {
+        let span = event_sourced_span("EventSourcedAggregate");
+        let load = async {
+            let mut events = self.repository.stream_events(command);
+            let mut state = self.decider.initial_state();
+            let mut events_loaded = 0;
+            let mut latest_version: Option<Version> = None;
+            while let Some(event) = events.next().await {
+                let (event, version) = event?;
+                latest_version = Some(version);
+                state = self.decider.evolve_state(&state, &event);
+                events_loaded += 1;
+            }
+            Ok((state, latest_version, events_loaded))
+        };
+        Self::handle_in(
+            &span,
+            load,
+            |state| self.decider.decide_events(command, &state),
+            |new_events, latest_version| async move {
+                self.save_after(command, &new_events, &latest_version).await
+            },
+        )
+        .await
+    }
+    /// Handles the command like [EventSourcedAggregate::handle] does, unless the command with the same [IdempotencyKey] is already processed.
+    /// For the duplicate command, the originally produced events are returned, and the command is not decided again.
+    /// Commands with no idempotency key are always handled.
+    pub async fn handle_idempotent(&self, command: &C) -> Result<Vec<(E, Version)>, Error>
+    where
+        Repository: IdempotentEventRepository<C, E, Version, Error>,
+        C: IdempotencyKey,
+    {
+        let Some(idempotency_key) = command.idempotency_key() else {
+            return self.handle(command).await;
+        };
+        match self
+            .repository
+            .fetch_processed(command, &idempotency_key)
+            .await
+        {
+            Ok(None) => {
+                let span = event_sourced_span("EventSourcedAggregate");
+                self.handle_with(command, &span, |new_events, latest_version| async move {
+                    self.repository
+                        .save_processed(command, &idempotency_key, &new_events, &latest_version)
+                        .await
+                })
+                .await
+            }
+            processed_events => {
+                // The duplicate is handled without deciding, but it still counts as the handled command.
+                Meter::new::<S>("EventSourcedAggregate").increment(COMMANDS_HANDLED, 1);
+                processed_events.map(Option::unwrap_or_default)
+            }
         }
-        let new_events = self.compute_new_events(&current_events, command)?;
-        let saved_events = self.save(&new_events).await?;
-        Ok(saved_events)
+    }
+    /// Handles the command like [EventSourcedAggregate::handle] does, and stores the outgoing messages computed by the `saga` from the new events into the outbox, in the same transaction with the events.
+    /// See [OutboxEventRepository] and [OutboxRelay](crate::outbox::OutboxRelay).
+    pub async fn handle_with_outbox<A, Saga>(
+        &self,
+        command: &C,
+        saga: &Saga,
+    ) -> Result<Vec<(E, Version)>, Error>
+    where
+        Repository: OutboxEventRepository<C, E, A, Version, Error>,
+        Saga: ActionComputation<E, A>,
+    {
+        let span = event_sourced_span("EventSourcedAggregate");
+        self.handle_with(command, &span, |new_events, latest_version| async move {
+            let messages: Vec<A> = new_events
+                .iter()
+                .flat_map(|event| saga.compute_new_actions(event))
+                .collect();
+            self.repository
+                .save_with_outbox(command, &new_events, &latest_version, &messages)
+                .await
+        })
+        .await
     }
 }
 
@@ -354,19 +1077,90 @@ where
     }
     /// Handles the command by fetching the state from the repository, computing new state based on the current state and the command, and saving the new state to the repository.
     pub async fn handle(&self, command: &C) -> Result<(S, Version), Error> {
-        let state_version = self.fetch_state(command).await?;
-        match state_version {
-            None => {
-                let new_state = self.compute_new_state(None, command)?;
-                let saved_state = self.save(&new_state, &None).await?;
-                Ok(saved_state)
-            }
-            Some((state, version)) => {
-                let new_state = self.compute_new_state(Some(state), command)?;
-                let saved_state = self.save(&new_state, &Some(version)).await?;
-                Ok(saved_state)
-            }
+        let span = state_stored_span("StateStoredAggregate");
+        self.handle_with(command, &span, |new_state, version| async move {
+            self.save(&new_state, &version).await
+        })
+        .await
+    }
+    /// Handles the command like [StateStoredAggregate::handle] does, and records the [Identifier] of the command and the version of the saved state in the `handle` span.
+    /// With the `tracing` feature disabled, it is the same as [StateStoredAggregate::handle].
+    pub async fn handle_traced(&self, command: &C) -> Result<(S, Version), Error>
+    where
+        C: Identifier,
+        Version: Debug,
+    {
+        let span = state_stored_span("StateStoredAggregate");
+        record!(
+            span,
+            "identifier",
+            tracing::field::display(command.identifier())
+        );
+        let result = self
+            .handle_with(command, &span, |new_state, version| async move {
+                self.save(&new_state, &version).await
+            })
+            .await;
+        record!(
+            span,
+            "version",
+            result
+                .as_ref()
+                .ok()
+                .map(|(_, version)| tracing::field::debug(version))
+        );
+        result
+    }
+    /// Handles the command by fetching the state from the repository, and computing new state based on the current state and the command, within the `span`.
+    /// The new state is saved by the `save` function, with the version of the fetched state.
+    async fn handle_with<F, Fut>(
+        &self,
+        command: &C,
+        span: &Span,
+        save: F,
+    ) -> Result<(S, Version), Error>
+    where
+        F: FnOnce(S, Option<Version>) -> Fut,
+        Fut: Future<Output = Result<(S, Version), Error>>,
+    {
+        let meter = Meter::new::<S>("StateStoredAggregate");
+        let result = async {
+            let state_version = self.fetch_state(command).instrument(span!("fetch")).await?;
+            record!(span, "state_loaded", state_version.is_some());
+            let (current_state, version) = match state_version {
+                None => (None, None),
+                Some((state, version)) => (Some(state), Some(version)),
+            };
+            let new_state = self.compute_new_state(current_state, command);
+            meter.increment_on_error(DECIDE_ERRORS, &new_state);
+            let saved_state = save(new_state?, version).instrument(span!("save")).await?;
+            Ok(saved_state)
         }
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result);
+        meter.increment(COMMANDS_HANDLED, 1);
+        result
+    }
+    /// Handles the command like [StateStoredAggregate::handle] does, and stores the outgoing messages computed by the `saga` from the new state into the outbox, in the same transaction with the state.
+    /// See [OutboxStateRepository] and [OutboxRelay](crate::outbox::OutboxRelay).
+    pub async fn handle_with_outbox<A, Saga>(
+        &self,
+        command: &C,
+        saga: &Saga,
+    ) -> Result<(S, Version), Error>
+    where
+        Repository: OutboxStateRepository<C, S, A, Version, Error>,
+        Saga: ActionComputation<S, A> + Sync,
+    {
+        let span = state_stored_span("StateStoredAggregate");
+        self.handle_with(command, &span, |new_state, version| async move {
+            let messages = saga.compute_new_actions(&new_state);
+            self.repository
+                .save_with_outbox(&new_state, &version, &messages)
+                .await
+        })
+        .await
     }
 }
 
@@ -387,19 +1181,90 @@ where
     }
     /// Handles the command by fetching the state from the repository, computing new state based on the current state and the command, and saving the new state to the repository.
     pub async fn handle(&self, command: &C) -> Result<(S, Version), Error> {
-        let state_version = self.fetch_state(command).await?;
-        match state_version {
-            None => {
-                let new_state = self.compute_new_state(None, command)?;
-                let saved_state = self.save(&new_state, &None).await?;
-                Ok(saved_state)
-            }
-            Some((state, version)) => {
-                let new_state = self.compute_new_state(Some(state), command)?;
-                let saved_state = self.save(&new_state, &Some(version)).await?;
-                Ok(saved_state)
-            }
+        let span = state_stored_span("StateStoredAggregate");
+        self.handle_with(command, &span, |new_state, version| async move {
+            self.save(&new_state, &version).await
+        })
+        .await
+    }
+    /// Handles the command like [StateStoredAggregate::handle] does, and records the [Identifier] of the command and the version of the saved state in the `handle` span.
+    /// With the `tracing` feature disabled, it is the same as [StateStoredAggregate::handle].
+    pub async fn handle_traced(&self, command: &C) -> Result<(S, Version), Error>
+    where
+        C: Identifier,
+        Version: Debug,
+    {
+        let span = state_stored_span("StateStoredAggregate");
+        record!(
+            span,
+            "identifier",
+            tracing::field::display(command.identifier())
+        );
+        let result = self
+            .handle_with(command, &span, |new_state, version| async move {
+                self.save(&new_state, &version).await
+            })
+            .await;
+        record!(
+            span,
+            "version",
+            result
+                .as_ref()
+                .ok()
+                .map(|(_, version)| tracing::field::debug(version))
+        );
+        result
+    }
+    /// Handles the command by fetching the state from the repository, and computing new state based on the current state and the command, within the `span`.
+    /// The new state is saved by the `save` function, with the version of the fetched state.
+    async fn handle_with<F, Fut>(
+        &self,
+        command: &C,
+        span: &Span,
+        save: F,
+    ) -> Result<(S, Version), Error>
+    where
+        F: FnOnce(S, Option<Version>) -> Fut,
+        Fut: Future<Output = Result<(S, Version), Error>>,
+    {
+        let meter = Meter::new::<S>("StateStoredAggregate");
+        let result = async {
+            let state_version = self.fetch_state(command).instrument(span!("fetch")).await?;
+            record!(span, "state_loaded", state_version.is_some());
+            let (current_state, version) = match state_version {
+                None => (None, None),
+                Some((state, version)) => (Some(state), Some(version)),
+            };
+            let new_state = self.compute_new_state(current_state, command);
+            meter.increment_on_error(DECIDE_ERRORS, &new_state);
+            let saved_state = save(new_state?, version).instrument(span!("save")).await?;
+            Ok(saved_state)
         }
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result);
+        meter.increment(COMMANDS_HANDLED, 1);
+        result
+    }
+    /// Handles the command like [StateStoredAggregate::handle] does, and stores the outgoing messages computed by the `saga` from the new state into the outbox, in the same transaction with the state.
+    /// See [OutboxStateRepository] and [OutboxRelay](crate::outbox::OutboxRelay).
+    pub async fn handle_with_outbox<A, Saga>(
+        &self,
+        command: &C,
+        saga: &Saga,
+    ) -> Result<(S, Version), Error>
+    where
+        Repository: OutboxStateRepository<C, S, A, Version, Error>,
+        Saga: ActionComputation<S, A>,
+    {
+        let span = state_stored_span("StateStoredAggregate");
+        self.handle_with(command, &span, |new_state, version| async move {
+            let messages = saga.compute_new_actions(&new_state);
+            self.repository
+                .save_with_outbox(&new_state, &version, &messages)
+                .await
+        })
+        .await
     }
 }
 
@@ -443,6 +1308,17 @@ where
     async fn save(&self, events: &[E]) -> Result<Vec<(E, Version)>, Error> {
         self.repository.save(events).await
     }
+    /// Saves events, only if the stream of the command is still at the `latest_version`.
+    async fn save_after(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<Version>,
+    ) -> Result<Vec<(E, Version)>, Error> {
+        self.repository
+            .save_after(command, events, latest_version)
+            .await
+    }
     /// Version provider. It is used to provide the version/sequence of the event. Optimistic locking is useing this version to check if the event is already saved.
     async fn version_provider(&self, event: &E) -> Result<Option<Version>, Error> {
         self.repository.version_provider(event).await
@@ -463,6 +1339,17 @@ where
     async fn save(&self, events: &[E]) -> Result<Vec<(E, Version)>, Error> {
         self.repository.save(events).await
     }
+    /// Saves events, only if the stream of the command is still at the `latest_version`.
+    async fn save_after(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<Version>,
+    ) -> Result<Vec<(E, Version)>, Error> {
+        self.repository
+            .save_after(command, events, latest_version)
+            .await
+    }
     /// Version provider. It is used to provide the version/sequence of the event. Optimistic locking is useing this version to check if the event is already saved.
     async fn version_provider(&self, event: &E) -> Result<Option<Version>, Error> {
         self.repository.version_provider(event).await
@@ -499,16 +1386,73 @@ where
         E: Identifier,
         C: Identifier,
     {
-        let events: Vec<(E, Version)> = self.fetch_events(command).await?;
-        let mut current_events: Vec<E> = vec![];
-        for (event, _) in events {
-            current_events.push(event);
+        let span = event_sourced_span("EventSourcedOrchestratingAggregate");
+        self.handle_in(command, &span).await
+    }
+    /// Handles the command like [EventSourcedOrchestratingAggregate::handle] does, and records the [Identifier] of the command and the version of the last saved event in the `handle` span.
+    /// With the `tracing` feature disabled, it is the same as [EventSourcedOrchestratingAggregate::handle].
+    pub async fn handle_traced(&self, command: &C) -> Result<Vec<(E, Version)>, Error>
+    where
+        E: Identifier,
+        C: Identifier,
+        Version: Debug,
+    {
+        let span = event_sourced_span("EventSourcedOrchestratingAggregate");
+        record!(
+            span,
+            "identifier",
+            tracing::field::display(command.identifier())
+        );
+        let result = self.handle_in(command, &span).await;
+        record!(
+            span,
+            "version",
+            result
+                .as_ref()
+                .ok()
+                .and_then(|saved_events| saved_events.last())
+                .map(|(_, version)| tracing::field::debug(version))
+        );
+        result
+    }
+    /// Handles the command within the `span`, see [EventSourcedOrchestratingAggregate::handle].
+    async fn handle_in(&self, command: &C, span: &Span) -> Result<Vec<(E, Version)>, Error>
+    where
+        E: Identifier,
+        C: Identifier,
+    {
+        let meter = Meter::new::<S>("EventSourcedOrchestratingAggregate");
+        let result = async {
+            let events: Vec<(E, Version)> = self
+                .fetch_events(command)
+                .instrument(span!("fetch"))
+                .await?;
+            record!(span, "events_loaded", events.len());
+            meter.record(FOLD_LENGTH, events.len());
+            let mut current_events: Vec<E> = vec![];
+            let mut latest_version: Option<Version> = None;
+            for (event, version) in events {
+                current_events.push(event);
+                latest_version = Some(version);
+            }
+            let new_events = self
+                .compute_new_events_dynamically(&current_events, command)
+                .await;
+            meter.increment_on_error(DECIDE_ERRORS, &new_events);
+            let new_events = new_events?;
+            record!(span, "events_produced", new_events.len());
+            let saved_events = self
+                .save_after(command, &new_events, &latest_version)
+                .instrument(span!("save"))
+                .await?;
+            meter.increment(EVENTS_APPENDED, saved_events.len());
+            Ok(saved_events)
         }
-        let new_events = self
-            .compute_new_events_dynamically(&current_events, command)
-            .await?;
-        let saved_events = self.save(&new_events).await?;
-        Ok(saved_events)
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result);
+        meter.increment(COMMANDS_HANDLED, 1);
+        result
     }
     /// Computes new events based on the current events and the command.
     /// It is using a [Decider] and [Saga] to compute new events based on the current events and the command.
@@ -592,16 +1536,73 @@ where
         E: Identifier,
         C: Identifier,
     {
-        let events: Vec<(E, Version)> = self.fetch_events(command).await?;
-        let mut current_events: Vec<E> = vec![];
-        for (event, _) in events {
-            current_events.push(event);
+        let span = event_sourced_span("EventSourcedOrchestratingAggregate");
+        self.handle_in(command, &span).await
+    }
+    /// Handles the command like [EventSourcedOrchestratingAggregate::handle] does, and records the [Identifier] of the command and the version of the last saved event in the `handle` span.
+    /// With the `tracing` feature disabled, it is the same as [EventSourcedOrchestratingAggregate::handle].
+    pub async fn handle_traced(&self, command: &C) -> Result<Vec<(E, Version)>, Error>
+    where
+        E: Identifier,
+        C: Identifier,
+        Version: Debug,
+    {
+        let span = event_sourced_span("EventSourcedOrchestratingAggregate");
+        record!(
+            span,
+            "identifier",
+            tracing::field::display(command.identifier())
+        );
+        let result = self.handle_in(command, &span).await;
+        record!(
+            span,
+            "version",
+            result
+                .as_ref()
+                .ok()
+                .and_then(|saved_events| saved_events.last())
+                .map(|(_, version)| tracing::field::debug(version))
+        );
+        result
+    }
+    /// Handles the command within the `span`, see [EventSourcedOrchestratingAggregate::handle].
+    async fn handle_in(&self, command: &C, span: &Span) -> Result<Vec<(E, Version)>, Error>
+    where
+        E: Identifier,
+        C: Identifier,
+    {
+        let meter = Meter::new::<S>("EventSourcedOrchestratingAggregate");
+        let result = async {
+            let events: Vec<(E, Version)> = self
+                .fetch_events(command)
+                .instrument(span!("fetch"))
+                .await?;
+            record!(span, "events_loaded", events.len());
+            meter.record(FOLD_LENGTH, events.len());
+            let mut current_events: Vec<E> = vec![];
+            let mut latest_version: Option<Version> = None;
+            for (event, version) in events {
+                current_events.push(event);
+                latest_version = Some(version);
+            }
+            let new_events = self
+                .compute_new_events_dynamically(&current_events, command)
+                .await;
+            meter.increment_on_error(DECIDE_ERRORS, &new_events);
+            let new_events = new_events?;
+            record!(span, "events_produced", new_events.len());
+            let saved_events = self
+                .save_after(command, &new_events, &latest_version)
+                .instrument(span!("save"))
+                .await?;
+            meter.increment(EVENTS_APPENDED, saved_events.len());
+            Ok(saved_events)
         }
-        let new_events = self
-            .compute_new_events_dynamically(&current_events, command)
-            .await?;
-        let saved_events = self.save(&new_events).await?;
-        Ok(saved_events)
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result);
+        meter.increment(COMMANDS_HANDLED, 1);
+        result
     }
     /// Computes new events based on the current events and the command.
     /// It is using a [Decider] and [Saga] to compute new events based on the current events and the command.
@@ -771,19 +1772,56 @@ where
     }
     /// Handles the command by fetching the state from the repository, computing new state based on the current state and the command, and saving the new state to the repository.
     pub async fn handle(&self, command: &C) -> Result<(S, Version), Error> {
-        let state_version = self.fetch_state(command).await?;
-        match state_version {
-            None => {
-                let new_state = self.compute_new_state(None, command)?;
-                let saved_state = self.save(&new_state, &None).await?;
-                Ok(saved_state)
-            }
-            Some((state, version)) => {
-                let new_state = self.compute_new_state(Some(state), command)?;
-                let saved_state = self.save(&new_state, &Some(version)).await?;
-                Ok(saved_state)
-            }
+        let span = state_stored_span("StateStoredOrchestratingAggregate");
+        self.handle_in(command, &span).await
+    }
+    /// Handles the command like [StateStoredOrchestratingAggregate::handle] does, and records the [Identifier] of the command and the version of the saved state in the `handle` span.
+    /// With the `tracing` feature disabled, it is the same as [StateStoredOrchestratingAggregate::handle].
+    pub async fn handle_traced(&self, command: &C) -> Result<(S, Version), Error>
+    where
+        C: Identifier,
+        Version: Debug,
+    {
+        let span = state_stored_span("StateStoredOrchestratingAggregate");
+        record!(
+            span,
+            "identifier",
+            tracing::field::display(command.identifier())
+        );
+        let result = self.handle_in(command, &span).await;
+        record!(
+            span,
+            "version",
+            result
+                .as_ref()
+                .ok()
+                .map(|(_, version)| tracing::field::debug(version))
+        );
+        result
+    }
+    /// Handles the command within the `span`, see [StateStoredOrchestratingAggregate::handle].
+    async fn handle_in(&self, command: &C, span: &Span) -> Result<(S, Version), Error> {
+        let meter = Meter::new::<S>("StateStoredOrchestratingAggregate");
+        let result = async {
+            let state_version = self.fetch_state(command).instrument(span!("fetch")).await?;
+            record!(span, "state_loaded", state_version.is_some());
+            let (current_state, version) = match state_version {
+                None => (None, None),
+                Some((state, version)) => (Some(state), Some(version)),
+            };
+            let new_state = self.compute_new_state(current_state, command);
+            meter.increment_on_error(DECIDE_ERRORS, &new_state);
+            let saved_state = self
+                .save(&new_state?, &version)
+                .instrument(span!("save"))
+                .await?;
+            Ok(saved_state)
         }
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result);
+        meter.increment(COMMANDS_HANDLED, 1);
+        result
     }
 }
 
@@ -809,18 +1847,55 @@ where
     }
     /// Handles the command by fetching the state from the repository, computing new state based on the current state and the command, and saving the new state to the repository.
     pub async fn handle(&self, command: &C) -> Result<(S, Version), Error> {
-        let state_version = self.fetch_state(command).await?;
-        match state_version {
-            None => {
-                let new_state = self.compute_new_state(None, command)?;
-                let saved_state = self.save(&new_state, &None).await?;
-                Ok(saved_state)
-            }
-            Some((state, version)) => {
-                let new_state = self.compute_new_state(Some(state), command)?;
-                let saved_state = self.save(&new_state, &Some(version)).await?;
-                Ok(saved_state)
-            }
+        let span = state_stored_span("StateStoredOrchestratingAggregate");
+        self.handle_in(command, &span).await
+    }
+    /// Handles the command like [StateStoredOrchestratingAggregate::handle] does, and records the [Identifier] of the command and the version of the saved state in the `handle` span.
+    /// With the `tracing` feature disabled, it is the same as [StateStoredOrchestratingAggregate::handle].
+    pub async fn handle_traced(&self, command: &C) -> Result<(S, Version), Error>
+    where
+        C: Identifier,
+        Version: Debug,
+    {
+        let span = state_stored_span("StateStoredOrchestratingAggregate");
+        record!(
+            span,
+            "identifier",
+            tracing::field::display(command.identifier())
+        );
+        let result = self.handle_in(command, &span).await;
+        record!(
+            span,
+            "version",
+            result
+                .as_ref()
+                .ok()
+                .map(|(_, version)| tracing::field::debug(version))
+        );
+        result
+    }
+    /// Handles the command within the `span`, see [StateStoredOrchestratingAggregate::handle].
+    async fn handle_in(&self, command: &C, span: &Span) -> Result<(S, Version), Error> {
+        let meter = Meter::new::<S>("StateStoredOrchestratingAggregate");
+        let result = async {
+            let state_version = self.fetch_state(command).instrument(span!("fetch")).await?;
+            record!(span, "state_loaded", state_version.is_some());
+            let (current_state, version) = match state_version {
+                None => (None, None),
+                Some((state, version)) => (Some(state), Some(version)),
+            };
+            let new_state = self.compute_new_state(current_state, command);
+            meter.increment_on_error(DECIDE_ERRORS, &new_state);
+            let saved_state = self
+                .save(&new_state?, &version)
+                .instrument(span!("save"))
+                .await?;
+            Ok(saved_state)
         }
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result);
+        meter.increment(COMMANDS_HANDLED, 1);
+        result
     }
 }
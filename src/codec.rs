@@ -0,0 +1,399 @@
+use std::fmt::{Display, Formatter};
+
+use serde::ser::{
+    Impossible, SerializeStruct, SerializeStructVariant, SerializeTupleStruct,
+    SerializeTupleVariant,
+};
+#[cfg(any(
+    feature = "json",
+    feature = "cbor",
+    feature = "msgpack",
+    feature = "bincode"
+))]
+use serde::{de::DeserializeOwned, Serialize};
+
+/// The event, encoded by the [EventCodec].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedEvent {
+    /// The name of the event type, e.g. the variant of the event enum (`Created`). It is stored along the payload, so the events can be filtered, routed and upcast with no decoding.
+    pub event_type: String,
+    /// The encoded event.
+    pub payload: Vec<u8>,
+}
+
+/// The error of the [EventCodec].
+#[derive(Debug)]
+pub enum CodecError {
+    /// The event could not be encoded.
+    Encode(Box<dyn std::error::Error + Send + Sync>),
+    /// The payload could not be decoded into the event of the given event type.
+    Decode(String, Box<dyn std::error::Error + Send + Sync>),
+    /// No decoder is registered for the event type (see `message_type::MessageRegistry`).
+    Unknown(String),
+}
+
+impl Display for CodecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodecError::Encode(error) => write!(f, "the event could not be encoded: {}", error),
+            CodecError::Decode(event_type, error) => {
+                write!(
+                    f,
+                    "the `{}` event could not be decoded: {}",
+                    event_type, error
+                )
+            }
+            CodecError::Unknown(event_type) => {
+                write!(f, "the `{}` event type is not registered", event_type)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CodecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CodecError::Encode(error) => Some(error.as_ref()),
+            CodecError::Decode(_, error) => Some(error.as_ref()),
+            CodecError::Unknown(_) => None,
+        }
+    }
+}
+
+/// Event Codec trait
+///
+/// Turns the events into the bytes, and back, so the storage adapters do not depend on the wire format, and the format can be changed with no change of the domain types.
+/// The encoded event carries the name of its type, next to the payload.
+///
+/// The codecs are shipped for the JSON (`json` feature), CBOR (`cbor` feature), MessagePack (`msgpack` feature) and bincode (`bincode` feature) formats.
+/// They name the event type by the variant of the event enum (see [event_type]).
+///
+/// The codecs are used by the binary stores (`redb::RedbEventRepository`). The SQL stores (`sqlite`, `postgres`) and the file log (`file_log`) are out of scope:
+/// they store the events as JSON, on purpose - queryable by the SQL (`JSONB`), and readable in the log lines - and do not take the codec.
+///
+/// Generic parameters:
+///
+/// - `E` - Event
+pub trait EventCodec<E> {
+    /// The name of the format, e.g. `json`.
+    fn format(&self) -> &'static str;
+    /// Encodes the event.
+    fn encode(&self, event: &E) -> Result<EncodedEvent, CodecError>;
+    /// Decodes the event.
+    fn decode(&self, event: &EncodedEvent) -> Result<E, CodecError>;
+}
+
+/// Returns the name of the event type: the name of the variant if the event is the (externally tagged) enum, or the name of the struct.
+/// It is the name the `serde` serializes the event with, so it follows the `#[serde(rename = "...")]` attributes.
+pub fn event_type<E: serde::Serialize + ?Sized>(event: &E) -> Result<&'static str, CodecError> {
+    event
+        .serialize(TypeName)
+        .map_err(|error| CodecError::Encode(Box::new(error)))
+}
+
+/// Encodes the event by the `serde` format.
+#[cfg(any(
+    feature = "json",
+    feature = "cbor",
+    feature = "msgpack",
+    feature = "bincode"
+))]
+fn encode_with<E, Error>(
+    event: &E,
+    to_bytes: impl FnOnce(&E) -> Result<Vec<u8>, Error>,
+) -> Result<EncodedEvent, CodecError>
+where
+    E: Serialize,
+    Error: std::error::Error + Send + Sync + 'static,
+{
+    Ok(EncodedEvent {
+        event_type: event_type(event)?.to_string(),
+        payload: to_bytes(event).map_err(|error| CodecError::Encode(Box::new(error)))?,
+    })
+}
+
+/// Decodes the event by the `serde` format.
+#[cfg(any(
+    feature = "json",
+    feature = "cbor",
+    feature = "msgpack",
+    feature = "bincode"
+))]
+fn decode_with<E, Error>(
+    event: &EncodedEvent,
+    from_bytes: impl FnOnce(&[u8]) -> Result<E, Error>,
+) -> Result<E, CodecError>
+where
+    E: DeserializeOwned,
+    Error: std::error::Error + Send + Sync + 'static,
+{
+    from_bytes(&event.payload)
+        .map_err(|error| CodecError::Decode(event.event_type.clone(), Box::new(error)))
+}
+
+/// JSON [EventCodec], on `serde_json`.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+#[cfg(feature = "json")]
+impl<E: Serialize + DeserializeOwned> EventCodec<E> for JsonCodec {
+    fn format(&self) -> &'static str {
+        "json"
+    }
+    fn encode(&self, event: &E) -> Result<EncodedEvent, CodecError> {
+        encode_with(event, serde_json::to_vec)
+    }
+    fn decode(&self, event: &EncodedEvent) -> Result<E, CodecError> {
+        decode_with(event, |payload| serde_json::from_slice(payload))
+    }
+}
+
+/// CBOR [EventCodec], on `ciborium`.
+#[cfg(feature = "cbor")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CborCodec;
+
+#[cfg(feature = "cbor")]
+impl<E: Serialize + DeserializeOwned> EventCodec<E> for CborCodec {
+    fn format(&self) -> &'static str {
+        "cbor"
+    }
+    fn encode(&self, event: &E) -> Result<EncodedEvent, CodecError> {
+        encode_with(event, |event| {
+            let mut payload = Vec::new();
+            ciborium::into_writer(event, &mut payload).map(|()| payload)
+        })
+    }
+    fn decode(&self, event: &EncodedEvent) -> Result<E, CodecError> {
+        decode_with(event, |payload| ciborium::from_reader(payload))
+    }
+}
+
+/// MessagePack [EventCodec], on `rmp-serde`. The structs are encoded as the maps, with the field names, so the fields can be added and reordered.
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePackCodec;
+
+#[cfg(feature = "msgpack")]
+impl<E: Serialize + DeserializeOwned> EventCodec<E> for MessagePackCodec {
+    fn format(&self) -> &'static str {
+        "msgpack"
+    }
+    fn encode(&self, event: &E) -> Result<EncodedEvent, CodecError> {
+        encode_with(event, rmp_serde::to_vec_named)
+    }
+    fn decode(&self, event: &EncodedEvent) -> Result<E, CodecError> {
+        decode_with(event, |payload| rmp_serde::from_slice(payload))
+    }
+}
+
+/// bincode [EventCodec]. It is the most compact, and the fastest, but the format is not self-describing: the fields can not be added, removed or reordered, with no upcasting.
+#[cfg(feature = "bincode")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BincodeCodec;
+
+#[cfg(feature = "bincode")]
+impl<E: Serialize + DeserializeOwned> EventCodec<E> for BincodeCodec {
+    fn format(&self) -> &'static str {
+        "bincode"
+    }
+    fn encode(&self, event: &E) -> Result<EncodedEvent, CodecError> {
+        encode_with(event, bincode::serialize)
+    }
+    fn decode(&self, event: &EncodedEvent) -> Result<E, CodecError> {
+        decode_with(event, |payload| bincode::deserialize(payload))
+    }
+}
+
+/// The value is neither the enum variant nor the struct, so it has no type name.
+#[derive(Debug)]
+struct Unnamed(String);
+
+impl Display for Unnamed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the event has no type name: {}", self.0)
+    }
+}
+
+impl std::error::Error for Unnamed {}
+
+impl serde::ser::Error for Unnamed {
+    fn custom<T: Display>(message: T) -> Self {
+        Unnamed(message.to_string())
+    }
+}
+
+/// The `serde` serializer capturing the name of the enum variant, or the struct, with no serialization of the fields.
+struct TypeName;
+
+/// The captured name, skipping the fields.
+struct Named(&'static str);
+
+macro_rules! unnamed {
+    ($($method:ident($($argument:ty),*)),+ $(,)?) => {
+        $(
+            fn $method(self, $(_: $argument),*) -> Result<Self::Ok, Self::Error> {
+                Err(Unnamed(stringify!($method).trim_start_matches("serialize_").to_string()))
+            }
+        )+
+    };
+}
+
+impl serde::Serializer for TypeName {
+    type Ok = &'static str;
+    type Error = Unnamed;
+    type SerializeSeq = Impossible<&'static str, Unnamed>;
+    type SerializeTuple = Impossible<&'static str, Unnamed>;
+    type SerializeTupleStruct = Named;
+    type SerializeTupleVariant = Named;
+    type SerializeMap = Impossible<&'static str, Unnamed>;
+    type SerializeStruct = Named;
+    type SerializeStructVariant = Named;
+
+    unnamed!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+    );
+
+    fn serialize_some<T: serde::Serialize + ?Sized>(
+        self,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(name)
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(variant)
+    }
+    fn serialize_newtype_struct<T: serde::Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(name)
+    }
+    fn serialize_newtype_variant<T: serde::Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(variant)
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(Unnamed("seq".to_string()))
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(Unnamed("tuple".to_string()))
+    }
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Ok(Named(name))
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Ok(Named(variant))
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(Unnamed("map".to_string()))
+    }
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(Named(name))
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(Named(variant))
+    }
+}
+
+impl SerializeTupleStruct for Named {
+    type Ok = &'static str;
+    type Error = Unnamed;
+    fn serialize_field<T: serde::Serialize + ?Sized>(&mut self, _value: &T) -> Result<(), Unnamed> {
+        Ok(())
+    }
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.0)
+    }
+}
+
+impl SerializeTupleVariant for Named {
+    type Ok = &'static str;
+    type Error = Unnamed;
+    fn serialize_field<T: serde::Serialize + ?Sized>(&mut self, _value: &T) -> Result<(), Unnamed> {
+        Ok(())
+    }
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.0)
+    }
+}
+
+impl SerializeStruct for Named {
+    type Ok = &'static str;
+    type Error = Unnamed;
+    fn serialize_field<T: serde::Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        _value: &T,
+    ) -> Result<(), Unnamed> {
+        Ok(())
+    }
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.0)
+    }
+}
+
+impl SerializeStructVariant for Named {
+    type Ok = &'static str;
+    type Error = Unnamed;
+    fn serialize_field<T: serde::Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        _value: &T,
+    ) -> Result<(), Unnamed> {
+        Ok(())
+    }
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.0)
+    }
+}
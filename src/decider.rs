@@ -3,6 +3,7 @@ use std::rc::Rc;
 #[cfg(not(feature = "not-send-futures"))]
 use std::sync::Arc;
 
+use crate::telemetry::{record_result, span};
 use crate::{
     DecideFunction, Decider3, Decider4, Decider5, Decider6, EvolveFunction, InitialStateFunction,
     Sum, Sum3, Sum4, Sum5, Sum6,
@@ -720,6 +721,17 @@ pub trait EventComputation<C, S, E, Error = ()> {
     fn compute_new_events(&self, current_events: &[E], command: &C) -> Result<Vec<E>, Error>;
 }
 
+/// Formalizes the incremental `Event Computation` algorithm: the current events are folded into the state one at a time, and the command is decided on the folded state.
+/// It lets the event stream be folded as it is read, with no loading of all the events into the memory.
+pub trait EventFold<C, S, E, Error = ()> {
+    /// The initial state, the events are folded into.
+    fn initial_state(&self) -> S;
+    /// Evolves the state by the event.
+    fn evolve_state(&self, state: &S, event: &E) -> S;
+    /// Decides the new events based on the (folded) state and the command.
+    fn decide_events(&self, command: &C, state: &S) -> Result<Vec<E>, Error>;
+}
+
 /// Formalizes the `State Computation` algorithm / state-stored system for the `decider` to handle commands based on the current state, and produce new state.
 pub trait StateComputation<C, S, E, Error = ()> {
     /// Computes new state based on the current state and the command.
@@ -729,12 +741,43 @@ pub trait StateComputation<C, S, E, Error = ()> {
 impl<C, S, E, Error> EventComputation<C, S, E, Error> for Decider<'_, C, S, E, Error> {
     /// Computes new events based on the current events and the command.
     fn compute_new_events(&self, current_events: &[E], command: &C) -> Result<Vec<E>, Error> {
-        let current_state: S = current_events
-            .iter()
-            .fold((self.initial_state)(), |state, event| {
-                (self.evolve)(&state, event)
-            });
-        (self.decide)(command, &current_state)
+        let current_state: S = span!("evolve", events = current_events.len()).in_scope(|| {
+            current_events
+                .iter()
+                .fold((self.initial_state)(), |state, event| {
+                    (self.evolve)(&state, event)
+                })
+        });
+        let decide_span = span!(
+            "decide",
+            events_produced = tracing::field::Empty,
+            error = tracing::field::Empty
+        );
+        let new_events = decide_span.in_scope(|| (self.decide)(command, &current_state));
+        record_result!(decide_span, new_events, "events_produced", Vec::len);
+        new_events
+    }
+}
+
+impl<C, S, E, Error> EventFold<C, S, E, Error> for Decider<'_, C, S, E, Error> {
+    /// The initial state, the events are folded into.
+    fn initial_state(&self) -> S {
+        (self.initial_state)()
+    }
+    /// Evolves the state by the event.
+    fn evolve_state(&self, state: &S, event: &E) -> S {
+        (self.evolve)(state, event)
+    }
+    /// Decides the new events based on the (folded) state and the command.
+    fn decide_events(&self, command: &C, state: &S) -> Result<Vec<E>, Error> {
+        let decide_span = span!(
+            "decide",
+            events_produced = tracing::field::Empty,
+            error = tracing::field::Empty
+        );
+        let new_events = decide_span.in_scope(|| (self.decide)(command, state));
+        record_result!(decide_span, new_events, "events_produced", Vec::len);
+        new_events
     }
 }
 
@@ -742,13 +785,21 @@ impl<C, S, E, Error> StateComputation<C, S, E, Error> for Decider<'_, C, S, E, E
     /// Computes new state based on the current state and the command.
     fn compute_new_state(&self, current_state: Option<S>, command: &C) -> Result<S, Error> {
         let effective_current_state = current_state.unwrap_or_else(|| (self.initial_state)());
-        let events = (self.decide)(command, &effective_current_state);
+        let decide_span = span!(
+            "decide",
+            events_produced = tracing::field::Empty,
+            error = tracing::field::Empty
+        );
+        let events = decide_span.in_scope(|| (self.decide)(command, &effective_current_state));
+        record_result!(decide_span, events, "events_produced", Vec::len);
         events.map(|result| {
-            result
-                .into_iter()
-                .fold(effective_current_state, |state, event| {
-                    (self.evolve)(&state, &event)
-                })
+            span!("evolve", events = result.len()).in_scope(|| {
+                result
+                    .into_iter()
+                    .fold(effective_current_state, |state, event| {
+                        (self.evolve)(&state, &event)
+                    })
+            })
         })
     }
 }
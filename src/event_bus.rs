@@ -0,0 +1,372 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::aggregate::EventRepository;
+use crate::keyed_lock::KeyedLock;
+use crate::middleware::Handler;
+
+/// Default number of the events buffered per subscriber, before the [Overflow] policy applies.
+pub const DEFAULT_CAPACITY: usize = 64;
+
+/// The policy applied to the event published to the subscriber whose mailbox is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// The event is dropped for this subscriber, and counted (see [Subscriber::dropped]). The subscriber keeps receiving the later events.
+    #[default]
+    Drop,
+    /// The subscriber is unregistered: it handles the events already buffered, and stops. The events not delivered are counted (see [Subscriber::dropped]).
+    Unsubscribe,
+}
+
+/// The statistics of the subscriber, shared with its task.
+#[derive(Debug, Default)]
+struct Counters {
+    handled: AtomicU64,
+    failed: AtomicU64,
+    dropped: AtomicU64,
+    stopped: AtomicBool,
+}
+
+/// The registered subscribers.
+type Subscriptions<E> = Arc<Mutex<Vec<Subscription<E>>>>;
+
+/// Marks the subscriber as stopped once its task ends, including the panic of the handler, and unregisters it.
+struct Stopped<E> {
+    counters: Arc<Counters>,
+    subscriptions: Weak<Mutex<Vec<Subscription<E>>>>,
+}
+
+impl<E> Drop for Stopped<E> {
+    fn drop(&mut self) {
+        self.counters.stopped.store(true, Ordering::SeqCst);
+        if let Some(subscriptions) = self.subscriptions.upgrade() {
+            if let Ok(mut subscriptions) = subscriptions.lock() {
+                subscriptions.retain(|subscription| {
+                    !Arc::ptr_eq(&subscription.subscriber.counters, &self.counters)
+                });
+            }
+        }
+    }
+}
+
+/// The registered subscriber: its mailbox, its overflow policy, and its statistics.
+struct Subscription<E> {
+    mailbox: mpsc::Sender<E>,
+    overflow: Overflow,
+    subscriber: Subscriber,
+}
+
+/// Subscriber.
+///
+/// The handle of the subscriber registered on the [EventBus], with the statistics of the events it has handled.
+#[derive(Debug, Clone)]
+pub struct Subscriber {
+    name: Arc<str>,
+    counters: Arc<Counters>,
+}
+
+impl Subscriber {
+    /// Returns the name of the subscriber.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Number of the events the subscriber has handled successfully.
+    pub fn handled(&self) -> u64 {
+        self.counters.handled.load(Ordering::SeqCst)
+    }
+    /// Number of the events the subscriber has failed to handle. See [EventBus::subscribe_with] for the errors.
+    pub fn failed(&self) -> u64 {
+        self.counters.failed.load(Ordering::SeqCst)
+    }
+    /// Number of the events not delivered to the subscriber, as its mailbox was full. See [Overflow].
+    pub fn dropped(&self) -> u64 {
+        self.counters.dropped.load(Ordering::SeqCst)
+    }
+    /// Has the subscriber stopped? It stops once the bus is shut down, once it is unsubscribed on [Overflow::Unsubscribe], or if its handler has panicked.
+    pub fn is_stopped(&self) -> bool {
+        self.counters.stopped.load(Ordering::SeqCst)
+    }
+}
+
+/// Event Bus.
+///
+/// In-process, asynchronous event bus, fanning the published events out to the registered subscribers: any [Handler], e.g. the
+/// [MaterializedView](crate::materialized_view::MaterializedView) or the [SagaManager](crate::saga_manager::SagaManager).
+/// The aggregates publish the saved events by the [EventBusRepository].
+///
+/// Every subscriber is the `tokio` task with the bounded mailbox, handling the events one at a time, in the order they are published.
+/// The publisher never waits for the subscribers: the event is offered to every mailbox, and the [Overflow] policy of the subscriber applies if its mailbox is full.
+/// So the slow (or stuck) subscriber loses the events, or is unsubscribed, while the publishers and the other subscribers are not slowed down.
+///
+/// The subscribers are isolated: the error of the handler is counted (see [Subscriber::failed]), passed to the error handler of the subscriber (see [EventBus::subscribe_with]),
+/// and the subscriber moves on to the next event, while the other subscribers are not affected.
+/// The subscriber whose handler has panicked is stopped, and unregistered.
+///
+/// The events are not durable: the events buffered in the mailboxes are lost on crash. Use the [EventSubscription](crate::subscription::EventSubscription)
+/// or the [ProjectionRunner](crate::projection::ProjectionRunner) over the durable event source, if the events must not be lost.
+///
+/// Generic parameters:
+///
+/// - `E` - Event
+pub struct EventBus<E> {
+    subscriptions: Subscriptions<E>,
+    tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    capacity: usize,
+    overflow: Overflow,
+    runtime: Option<Handle>,
+}
+
+impl<E> Clone for EventBus<E> {
+    fn clone(&self) -> Self {
+        EventBus {
+            subscriptions: Arc::clone(&self.subscriptions),
+            tasks: Arc::clone(&self.tasks),
+            capacity: self.capacity,
+            overflow: self.overflow,
+            runtime: self.runtime.clone(),
+        }
+    }
+}
+
+impl<E> Default for EventBus<E> {
+    fn default() -> Self {
+        EventBus {
+            subscriptions: Arc::new(Mutex::new(Vec::new())),
+            tasks: Arc::new(Mutex::new(Vec::new())),
+            capacity: DEFAULT_CAPACITY,
+            overflow: Overflow::default(),
+            runtime: None,
+        }
+    }
+}
+
+impl<E> EventBus<E>
+where
+    E: Clone + Send + Sync + 'static,
+{
+    /// Creates a new instance of [EventBus], with no subscribers.
+    pub fn new() -> Self {
+        EventBus::default()
+    }
+    /// Sets the number of the events buffered per subscriber, before the [Overflow] policy applies. It is [DEFAULT_CAPACITY] by default.
+    /// It applies to the subscribers registered afterwards.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+    /// Sets the policy applied to the events published to the full mailbox of the subscriber. It is [Overflow::Drop] by default.
+    /// It applies to the subscribers registered afterwards.
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+    /// Sets the `tokio` runtime the subscribers are spawned on, so they can be registered from outside of the runtime.
+    /// By default, they are spawned on the runtime of the caller.
+    pub fn with_runtime(mut self, runtime: Handle) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+    /// Registers the `handler` as the subscriber, receiving the events published from now on. The errors of the handler are counted only.
+    ///
+    /// The subscriber is spawned on the runtime set by [EventBus::with_runtime], or on the runtime of the caller otherwise.
+    /// With no runtime set, it panics if called from outside of the `tokio` runtime.
+    pub fn subscribe<H, Output, Error>(&self, name: &str, handler: H) -> Subscriber
+    where
+        H: Handler<E, Output, Error> + Send + Sync + 'static,
+        Output: Send + 'static,
+        Error: Send + 'static,
+    {
+        self.subscribe_with(name, handler, |_: &E, _: Error| {})
+    }
+    /// Registers the `handler` as the subscriber, like [EventBus::subscribe] does, passing the errors of the handler, together with the failed event, to the `on_error`.
+    pub fn subscribe_with<H, Output, Error, F>(
+        &self,
+        name: &str,
+        handler: H,
+        on_error: F,
+    ) -> Subscriber
+    where
+        H: Handler<E, Output, Error> + Send + Sync + 'static,
+        Output: Send + 'static,
+        Error: Send + 'static,
+        F: Fn(&E, Error) + Send + Sync + 'static,
+    {
+        let (mailbox, mut receiver) = mpsc::channel::<E>(self.capacity);
+        let subscriber = Subscriber {
+            name: Arc::from(name),
+            counters: Arc::new(Counters::default()),
+        };
+        let stopped = Stopped {
+            counters: Arc::clone(&subscriber.counters),
+            subscriptions: Arc::downgrade(&self.subscriptions),
+        };
+        // The subscriber is registered before its task is spawned, so the panicked task finds it to unregister
+        self.subscriptions.lock().unwrap().push(Subscription {
+            mailbox,
+            overflow: self.overflow,
+            subscriber: subscriber.clone(),
+        });
+        let task = async move {
+            let counters = Arc::clone(&stopped.counters);
+            let _stopped = stopped;
+            while let Some(event) = receiver.recv().await {
+                match handler.handle(&event).await {
+                    Ok(_) => {
+                        counters.handled.fetch_add(1, Ordering::SeqCst);
+                    }
+                    Err(error) => {
+                        counters.failed.fetch_add(1, Ordering::SeqCst);
+                        on_error(&event, error);
+                    }
+                }
+            }
+        };
+        let task = match &self.runtime {
+            Some(runtime) => runtime.spawn(task),
+            None => tokio::spawn(task),
+        };
+        self.tasks.lock().unwrap().push(task);
+        subscriber
+    }
+    /// Returns the registered subscribers.
+    pub fn subscribers(&self) -> Vec<Subscriber> {
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|subscription| subscription.subscriber.clone())
+            .collect()
+    }
+    /// Publishes the events to all the subscribers, applying their [Overflow] policy to the full mailboxes.
+    /// It does not wait: it returns once the events are offered to the mailboxes, not once they are handled.
+    ///
+    /// The subscribers that have stopped (e.g. on the panic of the handler) are unregistered.
+    pub fn publish(&self, events: &[E]) {
+        self.subscriptions.lock().unwrap().retain(|subscription| {
+            let counters = &subscription.subscriber.counters;
+            for (index, event) in events.iter().enumerate() {
+                match subscription.mailbox.try_send(event.clone()) {
+                    Ok(()) => {}
+                    Err(mpsc::error::TrySendError::Full(_)) => match subscription.overflow {
+                        Overflow::Drop => {
+                            counters.dropped.fetch_add(1, Ordering::SeqCst);
+                        }
+                        Overflow::Unsubscribe => {
+                            counters
+                                .dropped
+                                .fetch_add((events.len() - index) as u64, Ordering::SeqCst);
+                            return false;
+                        }
+                    },
+                    Err(mpsc::error::TrySendError::Closed(_)) => return false,
+                }
+            }
+            true
+        });
+    }
+    /// Shuts the bus down: the subscribers are unregistered, and it waits until they have handled the events already published.
+    pub async fn shutdown(&self) {
+        self.subscriptions.lock().unwrap().clear();
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        for task in tasks {
+            // The panic of the handler is isolated, it has stopped the subscriber only
+            let _ = task.await;
+        }
+    }
+}
+
+/// Event Bus Repository.
+///
+/// [EventRepository] publishing the saved events to the [EventBus], once they are saved by the inner repository.
+///
+/// The events are published right after the inner save completes, with no `.await` in between, and the publishing does not wait.
+/// So the save future, if dropped (canceled), either has not completed the inner save, and nothing is published,
+/// or all the saved events have been offered to all the subscribers. The events are not published if the process stops in between, though.
+///
+/// The saves (together with their publishing) are serialized by the in-process lock, so the saves are published in the order they are committed by the inner repository,
+/// and every subscriber receives the events of every stream in the order of their versions. The saves are not concurrent, even for the different streams.
+///
+/// Generic parameters:
+///
+/// - `Repository` - Inner event repository
+/// - `E` - Event
+pub struct EventBusRepository<Repository, E> {
+    repository: Repository,
+    bus: EventBus<E>,
+    lock: KeyedLock,
+}
+
+/// The key of the lock serializing the saves of the [EventBusRepository].
+const SAVE_KEY: &str = "save";
+
+impl<Repository, E> EventBusRepository<Repository, E> {
+    /// Creates a new instance of [EventBusRepository], publishing the events saved by the `repository` to the `bus`.
+    pub fn new(repository: Repository, bus: EventBus<E>) -> Self {
+        EventBusRepository {
+            repository,
+            bus,
+            lock: KeyedLock::new(),
+        }
+    }
+    /// Returns the inner repository.
+    pub fn repository(&self) -> &Repository {
+        &self.repository
+    }
+    /// Returns the bus.
+    pub fn bus(&self) -> &EventBus<E> {
+        &self.bus
+    }
+}
+
+impl<C, E, Version, Error, Repository> EventRepository<C, E, Version, Error>
+    for EventBusRepository<Repository, E>
+where
+    Repository: EventRepository<C, E, Version, Error> + Sync,
+    C: Sync,
+    E: Clone + Send + Sync + 'static,
+    Version: Send + Sync,
+    Error: Send,
+{
+    /// Fetches current events, based on the command.
+    async fn fetch_events(&self, command: &C) -> Result<Vec<(E, Version)>, Error> {
+        self.repository.fetch_events(command).await
+    }
+    /// Saves events, and publishes them to the bus.
+    async fn save(&self, events: &[E]) -> Result<Vec<(E, Version)>, Error> {
+        let _guard = self.lock.lock(SAVE_KEY).await;
+        let saved_events = self.repository.save(events).await?;
+        let published: Vec<E> = saved_events
+            .iter()
+            .map(|(event, _)| event.clone())
+            .collect();
+        self.bus.publish(&published);
+        Ok(saved_events)
+    }
+    /// Saves events, only if the stream of the command is still at the `latest_version`, and publishes them to the bus.
+    async fn save_after(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<Version>,
+    ) -> Result<Vec<(E, Version)>, Error> {
+        let _guard = self.lock.lock(SAVE_KEY).await;
+        let saved_events = self
+            .repository
+            .save_after(command, events, latest_version)
+            .await?;
+        let published: Vec<E> = saved_events
+            .iter()
+            .map(|(event, _)| event.clone())
+            .collect();
+        self.bus.publish(&published);
+        Ok(saved_events)
+    }
+    /// Version provider. It is used to provide the version/sequence of the event. Optimistic locking is useing this version to check if the event is already saved.
+    async fn version_provider(&self, event: &E) -> Result<Option<Version>, Error> {
+        self.repository.version_provider(event).await
+    }
+}
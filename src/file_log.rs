@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::{Deserialize, Serialize};
+
+use crate::aggregate::EventRepository;
+use crate::Identifier;
+
+/// The error of the file event log.
+#[derive(Debug)]
+pub enum FileLogError {
+    /// The I/O error.
+    Io(std::io::Error),
+    /// The error of encoding/decoding the event.
+    Serialization(serde_json::Error),
+    /// The record at the offset is corrupted: its checksum or version does not match. It is not the torn write at the end of the log, so it is not recovered.
+    Corrupted(u64),
+    /// The event stream, identified by the identifier, was modified since the events were decided on it.
+    Conflict(String),
+}
+
+impl Display for FileLogError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileLogError::Io(error) => write!(f, "I/O error: {}", error),
+            FileLogError::Serialization(error) => write!(f, "serialization error: {}", error),
+            FileLogError::Corrupted(offset) => {
+                write!(f, "corrupted record at the offset {}", offset)
+            }
+            FileLogError::Conflict(identifier) => {
+                write!(f, "concurrent modification of `{}`", identifier)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FileLogError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FileLogError::Io(error) => Some(error),
+            FileLogError::Serialization(error) => Some(error),
+            FileLogError::Corrupted(_) | FileLogError::Conflict(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for FileLogError {
+    fn from(error: std::io::Error) -> Self {
+        FileLogError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for FileLogError {
+    fn from(error: serde_json::Error) -> Self {
+        FileLogError::Serialization(error)
+    }
+}
+
+/// The record of the log: the event, with its stream and version.
+#[derive(Serialize, Deserialize)]
+struct Record<T> {
+    stream_id: String,
+    version: u64,
+    /// `true` for the last event of the append, committing the events appended together.
+    commit: bool,
+    event: T,
+}
+
+/// The location of the record in the log file.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    offset: u64,
+    length: usize,
+}
+
+/// Encodes the record into the line of the log: the CRC-32 checksum of the JSON, in hex, and the JSON.
+fn encode<T: Serialize>(record: &Record<T>, buffer: &mut Vec<u8>) -> Result<(), FileLogError> {
+    let json = serde_json::to_vec(record)?;
+    write!(buffer, "{:08x} ", crc32fast::hash(&json))?;
+    buffer.extend_from_slice(&json);
+    buffer.push(b'\n');
+    Ok(())
+}
+
+/// Decodes the line of the log, stored at the `offset`, verifying its checksum.
+fn decode<T: DeserializeOwned>(line: &[u8], offset: u64) -> Result<Record<T>, FileLogError> {
+    let corrupted = || FileLogError::Corrupted(offset);
+    let line = line.strip_suffix(b"\n").ok_or_else(corrupted)?;
+    if line.len() < 9 || line[8] != b' ' {
+        return Err(corrupted());
+    }
+    let checksum = std::str::from_utf8(&line[..8])
+        .ok()
+        .and_then(|checksum| u32::from_str_radix(checksum, 16).ok())
+        .ok_or_else(corrupted)?;
+    let json = &line[9..];
+    if crc32fast::hash(json) != checksum {
+        return Err(corrupted());
+    }
+    Ok(serde_json::from_slice(json)?)
+}
+
+/// Writes the `lines` to the new file at the `path`, and flushes it to the disk.
+fn write_file(path: &Path, lines: &[u8]) -> Result<(), FileLogError> {
+    let mut file = File::create(path)?;
+    file.write_all(lines)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// The open log file, with the index of its streams.
+struct Log {
+    file: File,
+    index: HashMap<String, Vec<Entry>>,
+    /// The end of the last committed record. The log is truncated to it, if the append fails.
+    end: u64,
+}
+
+impl Log {
+    /// Opens the log file, creating it if it does not exist, and indexes its records.
+    /// The torn write at the end of the log (the partial record, or the records of the append that was not committed) is truncated.
+    /// Returns the log, and the number of the truncated bytes.
+    fn open(path: &Path) -> Result<(Log, u64), FileLogError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let mut reader = BufReader::new(&file);
+        let mut index: HashMap<String, Vec<Entry>> = HashMap::new();
+        let mut pending: Vec<(String, Entry)> = Vec::new();
+        let (mut offset, mut end) = (0, 0);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let length = reader.read_until(b'\n', &mut line)?;
+            if length == 0 {
+                break;
+            }
+            let record = match decode::<IgnoredAny>(&line, offset) {
+                Ok(record) => record,
+                // Only the last record can be torn
+                Err(_) if reader.fill_buf()?.is_empty() => break,
+                Err(FileLogError::Serialization(_)) => return Err(FileLogError::Corrupted(offset)),
+                Err(error) => return Err(error),
+            };
+            let version = index.get(&record.stream_id).map_or(0, Vec::len)
+                + pending
+                    .iter()
+                    .filter(|(stream_id, _)| *stream_id == record.stream_id)
+                    .count();
+            if record.version != version as u64 {
+                return Err(FileLogError::Corrupted(offset));
+            }
+            pending.push((record.stream_id, Entry { offset, length }));
+            offset += length as u64;
+            if record.commit {
+                for (stream_id, entry) in pending.drain(..) {
+                    index.entry(stream_id).or_default().push(entry);
+                }
+                end = offset;
+            }
+        }
+        drop(reader);
+        let truncated = file.metadata()?.len() - end;
+        if truncated > 0 {
+            file.set_len(end)?;
+            file.sync_data()?;
+        }
+        Ok((Log { file, index, end }, truncated))
+    }
+
+    /// Reads the record, located by the `entry`.
+    fn read<T: DeserializeOwned>(&mut self, entry: &Entry) -> Result<Record<T>, FileLogError> {
+        let mut line = vec![0; entry.length];
+        self.file.seek(SeekFrom::Start(entry.offset))?;
+        self.file.read_exact(&mut line)?;
+        decode(&line, entry.offset)
+    }
+
+    /// Writes the lines of the records, and flushes them to the disk. The partially written lines are truncated, if it fails.
+    fn write(&mut self, lines: &[u8]) -> Result<(), FileLogError> {
+        let result = self
+            .file
+            .write_all(lines)
+            .and_then(|()| self.file.sync_data());
+        if let Err(error) = result {
+            let _ = self.file.set_len(self.end);
+            return Err(error.into());
+        }
+        Ok(())
+    }
+}
+
+/// File Event Repository.
+///
+/// [EventRepository] on the local append-only log file, requiring no database. It fits the edge deployments and the CLI tools.
+/// The events are grouped into the streams by their [Identifier]. The command is routed to the stream by its [Identifier], too.
+///
+/// Every event is stored as the line of JSON (the stream, the version and the event), prefixed by the CRC-32 checksum of the line, in hex.
+/// The JSON is fixed, so the log stays readable by the line-oriented tools; the [EventCodec](crate::codec::EventCodec) is not used by the log.
+/// The events of the single append are written at once, and flushed to the disk. The last event of the append is flagged as the commit.
+/// The versions of the streams start at `0`.
+///
+/// The log is indexed in memory, by the stream, when it is opened, so the events of the stream are read with no scanning of the log.
+/// The torn write at the end of the log (left by the crash, or the power loss, while appending) is truncated when it is opened, so the append is atomic.
+/// The corrupted record anywhere else fails the opening with [FileLogError::Corrupted].
+///
+/// The aggregates save the events with [EventRepository::save_after], so the events decided on the stale version of the command stream are rejected with [FileLogError::Conflict].
+///
+/// The log is owned by the single process: the appends are serialized by the mutex, and the file is not locked against the other processes.
+pub struct FileEventRepository {
+    path: PathBuf,
+    log: Mutex<Log>,
+    recovered: u64,
+}
+
+impl FileEventRepository {
+    /// Opens the log file at the `path`, creating it if it does not exist, and truncates the torn write at its end.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, FileLogError> {
+        let path = path.as_ref().to_path_buf();
+        let (log, recovered) = Log::open(&path)?;
+        Ok(FileEventRepository {
+            path,
+            log: Mutex::new(log),
+            recovered,
+        })
+    }
+    /// Returns the path of the log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Number of the bytes of the torn write, truncated when the log was opened.
+    pub fn recovered(&self) -> u64 {
+        self.recovered
+    }
+    /// Number of the streams in the log.
+    pub fn streams(&self) -> usize {
+        self.log().index.len()
+    }
+    /// Compacts the log, removing the streams (e.g. of the archived aggregates) the `retain` function returns `false` for.
+    /// The retained events keep their order and versions. Returns the number of the removed events.
+    ///
+    /// The compacted log is written to the temporary file next to the log, flushed, opened, and only then renamed over the log, so the log is intact (and still used) if it fails.
+    pub fn compact<F: Fn(&str) -> bool>(&self, retain: F) -> Result<usize, FileLogError> {
+        let mut log = self.log();
+        let mut entries: Vec<(&String, &Entry)> = log
+            .index
+            .iter()
+            .flat_map(|(stream_id, entries)| entries.iter().map(move |entry| (stream_id, entry)))
+            .collect();
+        entries.sort_by_key(|(_, entry)| entry.offset);
+        let removed = entries
+            .iter()
+            .filter(|(stream_id, _)| !retain(stream_id))
+            .count();
+        if removed == 0 {
+            return Ok(0);
+        }
+        let entries: Vec<Entry> = entries.into_iter().map(|(_, entry)| *entry).collect();
+        let mut records: Vec<Record<serde_json::Value>> = Vec::with_capacity(entries.len());
+        for entry in &entries {
+            records.push(log.read(entry)?);
+        }
+        // The appends are kept atomic: the last retained event of every append commits it
+        let mut lines = Vec::new();
+        let mut batch: Vec<Record<serde_json::Value>> = Vec::new();
+        for record in records {
+            let commit = record.commit;
+            if retain(&record.stream_id) {
+                batch.push(record);
+            }
+            if commit {
+                if let Some(last) = batch.last_mut() {
+                    last.commit = true;
+                }
+                for record in batch.drain(..) {
+                    encode(&record, &mut lines)?;
+                }
+            }
+        }
+        let path = self.path.with_extension("compacting");
+        let compacted = write_file(&path, &lines)
+            .and_then(|()| Log::open(&path))
+            .and_then(|(compacted, _)| {
+                std::fs::rename(&path, &self.path)?;
+                Ok(compacted)
+            });
+        match compacted {
+            // The renamed file is the log from now on, even if the directory is not synced
+            Ok(compacted) => *log = compacted,
+            Err(error) => {
+                let _ = std::fs::remove_file(&path);
+                return Err(error);
+            }
+        }
+        #[cfg(unix)]
+        if let Some(directory) = self.path.parent() {
+            File::open(directory)?.sync_all()?;
+        }
+        Ok(removed)
+    }
+    fn log(&self) -> MutexGuard<'_, Log> {
+        self.log.lock().unwrap()
+    }
+    /// Reads the events of the stream, identified by the `identifier`, ordered by their version.
+    fn fetch_stream<E: DeserializeOwned>(
+        &self,
+        identifier: &str,
+    ) -> Result<Vec<(E, u64)>, FileLogError> {
+        let mut log = self.log();
+        let entries = log.index.get(identifier).cloned().unwrap_or_default();
+        entries
+            .iter()
+            .map(|entry| {
+                let record = log.read::<E>(entry)?;
+                Ok((record.event, record.version))
+            })
+            .collect()
+    }
+    /// Appends the events to their streams, at once.
+    /// If the `expected` stream is given, the events are appended only if it is still at the expected version, or [FileLogError::Conflict] is returned.
+    fn append<E: Identifier + Serialize + Clone>(
+        &self,
+        events: &[E],
+        expected: Option<(&str, Option<u64>)>,
+    ) -> Result<Vec<(E, u64)>, FileLogError> {
+        let mut log = self.log();
+        if let Some((stream_id, expected_version)) = expected {
+            let latest_version = log
+                .index
+                .get(stream_id)
+                .map(|entries| entries.len() as u64 - 1);
+            if latest_version != expected_version {
+                return Err(FileLogError::Conflict(stream_id.to_string()));
+            }
+        }
+        let mut versions: HashMap<String, u64> = HashMap::new();
+        let mut lines = Vec::new();
+        let mut entries = Vec::with_capacity(events.len());
+        let mut saved_events = Vec::with_capacity(events.len());
+        for (i, event) in events.iter().enumerate() {
+            let stream_id = event.identifier();
+            let version = versions.entry(stream_id.clone()).or_insert_with(|| {
+                log.index
+                    .get(&stream_id)
+                    .map_or(0, |entries| entries.len() as u64)
+            });
+            let offset = log.end + lines.len() as u64;
+            encode(
+                &Record {
+                    stream_id: stream_id.clone(),
+                    version: *version,
+                    commit: i == events.len() - 1,
+                    event,
+                },
+                &mut lines,
+            )?;
+            entries.push((
+                stream_id,
+                Entry {
+                    offset,
+                    length: (log.end + lines.len() as u64 - offset) as usize,
+                },
+            ));
+            saved_events.push((event.clone(), *version));
+            *version += 1;
+        }
+        if lines.is_empty() {
+            return Ok(saved_events);
+        }
+        log.write(&lines)?;
+        log.end += lines.len() as u64;
+        for (stream_id, entry) in entries {
+            log.index.entry(stream_id).or_default().push(entry);
+        }
+        Ok(saved_events)
+    }
+    /// Returns the latest version of the stream, identified by the `identifier`.
+    fn latest_version(&self, identifier: &str) -> Option<u64> {
+        self.log()
+            .index
+            .get(identifier)
+            .map(|entries| entries.len() as u64 - 1)
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<C, E, Error> EventRepository<C, E, u64, Error> for FileEventRepository
+where
+    C: Identifier + Sync,
+    E: Identifier + Serialize + DeserializeOwned + Clone + Send + Sync,
+    Error: From<FileLogError> + Send,
+{
+    /// Fetches the events of the stream, identified by the command [Identifier].
+    async fn fetch_events(&self, command: &C) -> Result<Vec<(E, u64)>, Error> {
+        Ok(self.fetch_stream(&command.identifier())?)
+    }
+    /// Appends the events to their streams, with the next versions.
+    async fn save(&self, events: &[E]) -> Result<Vec<(E, u64)>, Error> {
+        Ok(self.append(events, None)?)
+    }
+    /// Appends the events to their streams, with the next versions, if the stream of the command is still at the `latest_version`.
+    async fn save_after(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<u64>,
+    ) -> Result<Vec<(E, u64)>, Error> {
+        Ok(self.append(events, Some((&command.identifier(), *latest_version)))?)
+    }
+    /// Returns the latest version of the event stream.
+    async fn version_provider(&self, event: &E) -> Result<Option<u64>, Error> {
+        Ok(self.latest_version(&event.identifier()))
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<C, E, Error> EventRepository<C, E, u64, Error> for FileEventRepository
+where
+    C: Identifier,
+    E: Identifier + Serialize + DeserializeOwned + Clone,
+    Error: From<FileLogError>,
+{
+    /// Fetches the events of the stream, identified by the command [Identifier].
+    async fn fetch_events(&self, command: &C) -> Result<Vec<(E, u64)>, Error> {
+        Ok(self.fetch_stream(&command.identifier())?)
+    }
+    /// Appends the events to their streams, with the next versions.
+    async fn save(&self, events: &[E]) -> Result<Vec<(E, u64)>, Error> {
+        Ok(self.append(events, None)?)
+    }
+    /// Appends the events to their streams, with the next versions, if the stream of the command is still at the `latest_version`.
+    async fn save_after(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<u64>,
+    ) -> Result<Vec<(E, u64)>, Error> {
+        Ok(self.append(events, Some((&command.identifier(), *latest_version)))?)
+    }
+    /// Returns the latest version of the event stream.
+    async fn version_provider(&self, event: &E) -> Result<Option<u64>, Error> {
+        Ok(self.latest_version(&event.identifier()))
+    }
+}
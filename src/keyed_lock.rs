@@ -0,0 +1,238 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+
+use crate::middleware::Handler;
+use crate::Identifier;
+
+/// Keyed Lock.
+///
+/// In-process asynchronous lock, per key. The holders of the different keys do not block each other.
+/// The lock is fair: the waiters of the key acquire it in the order they started waiting. Once released, the lock is handed off directly to the first waiter, and only this waiter is woken.
+/// A waiter that is dropped (e.g. canceled by the timeout) leaves the queue, and passes the lock on if it was already handed off to it.
+/// The key is forgotten once its lock is released with no waiters, so the memory is bound by the number of the keys locked at the moment.
+#[derive(Debug, Default)]
+pub struct KeyedLock {
+    slots: Mutex<HashMap<String, Slot>>,
+    next_waiter: AtomicU64,
+}
+
+/// The locked key, with the queue of its waiters. The key is locked as long as its slot exists.
+#[derive(Debug, Default)]
+struct Slot {
+    waiters: VecDeque<Waiter>,
+}
+
+/// The waiter of the locked key.
+#[derive(Debug)]
+struct Waiter {
+    id: u64,
+    waker: Option<Waker>,
+    handed_off: bool,
+}
+
+impl KeyedLock {
+    /// Creates a new instance of [KeyedLock].
+    pub fn new() -> Self {
+        KeyedLock::default()
+    }
+    /// Acquires the lock of the `key`, waiting until it is handed off by the previous holder.
+    /// The lock is released when the returned guard is dropped.
+    pub async fn lock(&self, key: &str) -> KeyedLockGuard<'_> {
+        let waiter = {
+            let mut slots = self.slots.lock().unwrap();
+            match slots.get_mut(key) {
+                None => {
+                    slots.insert(key.to_string(), Slot::default());
+                    None
+                }
+                Some(slot) => {
+                    let id = self.next_waiter.fetch_add(1, Ordering::Relaxed);
+                    slot.waiters.push_back(Waiter {
+                        id,
+                        waker: None,
+                        handed_off: false,
+                    });
+                    Some(id)
+                }
+            }
+        };
+        if let Some(id) = waiter {
+            Acquire {
+                lock: self,
+                key,
+                id,
+                acquired: false,
+            }
+            .await;
+        }
+        KeyedLockGuard {
+            lock: self,
+            key: key.to_string(),
+        }
+    }
+    /// Number of the keys locked at the moment.
+    pub fn len(&self) -> usize {
+        self.slots.lock().unwrap().len()
+    }
+    /// Returns `true` if no key is locked at the moment.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Hands the lock off to the first waiter of the key, or forgets the key if there are no waiters.
+    fn unlock(&self, key: &str) {
+        let mut slots = self.slots.lock().unwrap();
+        Self::hand_off(&mut slots, key);
+    }
+    fn hand_off(slots: &mut HashMap<String, Slot>, key: &str) {
+        let Some(slot) = slots.get_mut(key) else {
+            return;
+        };
+        match slot.waiters.front_mut() {
+            None => {
+                slots.remove(key);
+            }
+            Some(waiter) => {
+                waiter.handed_off = true;
+                if let Some(waker) = waiter.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+    }
+}
+
+/// The future of the waiter, resolved once the lock of the key is handed off to it.
+struct Acquire<'a> {
+    lock: &'a KeyedLock,
+    key: &'a str,
+    id: u64,
+    acquired: bool,
+}
+
+impl Future for Acquire<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut slots = self.lock.slots.lock().unwrap();
+        let slot = slots
+            .get_mut(self.key)
+            .expect("the key is locked while it has waiters");
+        let index = slot
+            .waiters
+            .iter()
+            .position(|waiter| waiter.id == self.id)
+            .expect("the waiter is queued until it acquires the lock");
+        if slot.waiters[index].handed_off {
+            slot.waiters.remove(index);
+            drop(slots);
+            self.acquired = true;
+            Poll::Ready(())
+        } else {
+            slot.waiters[index].waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        if self.acquired {
+            return;
+        }
+        let mut slots = self.lock.slots.lock().unwrap();
+        let Some(slot) = slots.get_mut(self.key) else {
+            return;
+        };
+        let Some(index) = slot.waiters.iter().position(|waiter| waiter.id == self.id) else {
+            return;
+        };
+        let waiter = slot.waiters.remove(index);
+        if waiter.is_some_and(|waiter| waiter.handed_off) {
+            KeyedLock::hand_off(&mut slots, self.key);
+        }
+    }
+}
+
+/// The guard of the key, locked by the [KeyedLock]. The lock is released on drop.
+#[derive(Debug)]
+pub struct KeyedLockGuard<'a> {
+    lock: &'a KeyedLock,
+    key: String,
+}
+
+impl KeyedLockGuard<'_> {
+    /// Returns the locked key.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl Drop for KeyedLockGuard<'_> {
+    fn drop(&mut self) {
+        self.lock.unlock(&self.key);
+    }
+}
+
+/// [Handler] serialized per identifier.
+///
+/// The inputs with the same [Identifier] are handled one at a time, while the inputs with different identifiers are handled in parallel.
+/// It prevents the concurrent commands of the same aggregate from racing (and conflicting or overwriting each other), in the single-node deployments with no locking in the database.
+/// The lock is in-process, so it does not serialize the handlers running in the other processes.
+///
+/// Generic parameters:
+///
+/// - `H` - Inner handler
+pub struct Serialized<H> {
+    handler: H,
+    lock: KeyedLock,
+}
+
+impl<H> Serialized<H> {
+    /// Creates a new instance of [Serialized], wrapping the `handler`.
+    pub fn new(handler: H) -> Self {
+        Serialized {
+            handler,
+            lock: KeyedLock::new(),
+        }
+    }
+    /// Returns the inner handler.
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+    /// Returns the lock, keyed by the identifiers of the inputs being handled.
+    pub fn lock(&self) -> &KeyedLock {
+        &self.lock
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<H, Input, Output, Error> Handler<Input, Output, Error> for Serialized<H>
+where
+    H: Handler<Input, Output, Error> + Sync,
+    Input: Identifier + Sync,
+    Output: Send,
+    Error: Send,
+{
+    /// Handles the input by the inner handler, holding the lock of the input identifier.
+    async fn handle(&self, input: &Input) -> Result<Output, Error> {
+        let _guard = self.lock.lock(&input.identifier()).await;
+        self.handler.handle(input).await
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<H, Input, Output, Error> Handler<Input, Output, Error> for Serialized<H>
+where
+    H: Handler<Input, Output, Error>,
+    Input: Identifier,
+{
+    /// Handles the input by the inner handler, holding the lock of the input identifier.
+    async fn handle(&self, input: &Input) -> Result<Output, Error> {
+        let _guard = self.lock.lock(&input.identifier()).await;
+        self.handler.handle(input).await
+    }
+}
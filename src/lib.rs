@@ -51,10 +51,15 @@
 //! In order to handle the command, aggregate needs to fetch the current state (represented as a list/vector of events)
 //! via `EventRepository.fetchEvents` async function, and then delegate the command to the decider which can produce new
 //! events as
-//! a result. Produced events are then stored via `EventRepository.save` async function.
+//! a result. Produced events are then stored via `EventRepository.save_after` async function, with the version of the last fetched event,
+//! so the repositories supporting the optimistic locking (the SQLite, PostgreSQL, redb and file log stores) reject the events decided on the stale state.
 //!
 //! It is a formalization of the event sourced information system.
 //!
+//! For the very long streams, `EventSourcedAggregate::handle_streaming` pulls the events one at a time from the [aggregate::StreamingEventRepository],
+//! folding them into the state as they are read (see [decider::EventFold]), so the memory does not grow with the length of the stream.
+//! With the `stream` feature enabled, the event stream can be adapted to the `futures::Stream` via `aggregate::EventStreamAdapter`.
+//!
 //! ### State-stored aggregate
 //!
 //! [aggregate::StateStoredAggregate] is using/delegating a `Decider` to handle commands and produce new state.
@@ -66,6 +71,72 @@
 //! delegate the command to the decider which can produce new state as a result. New state is then stored
 //! via `StateRepository.save` async function.
 //!
+//! ### Transactional outbox
+//!
+//! Both aggregates can store the outgoing messages, computed by a saga, in the same transaction with the events/state via
+//! `handle_with_outbox` and the [outbox::OutboxEventRepository]/[outbox::OutboxStateRepository]. The [outbox::OutboxRelay]
+//! drains the outbox into the [saga_manager::ActionPublisher], with `at least once` semantics.
+//!
+//! ### Idempotent command handling
+//!
+//! Clients retry the requests. The event-sourced aggregate can recognize the retried command by its [IdempotencyKey] via
+//! `handle_idempotent` and the [aggregate::IdempotentEventRepository], returning the originally produced events instead of deciding the command again.
+//! The concurrent duplicates are told apart by the repository, which records the key atomically with the events.
+//!
+//! ### Middleware
+//!
+//! The aggregates, materialized views and saga managers implement the common [middleware::Handler] trait. Cross-cutting logic
+//! (validation, authorization, auditing, ...) is implemented once, as the [middleware::Interceptor] with `before`/`after`/`on_error` hooks,
+//! and stacked around any handler with [middleware::Intercepted].
+//!
+//! With the `tower` feature enabled, any handler can be adapted to the `tower::Service` via `service::HandlerService`,
+//! so the layers from the `tower` ecosystem (timeouts, rate limiting, load shedding, tracing, ...) apply directly.
+//!
+//! With the `tracing` feature enabled, the components open the `debug` spans (target `fmodel`) for `handle`, `handle_batch`, `fetch`, `evolve`, `decide`, `react`, `save` and `publish`,
+//! with the number of the loaded/produced events and the `error` flag.
+//! The `handle_traced` methods of the aggregates and the materialized views record the identifier of the input and the saved version (by its `Debug` format) too,
+//! so they require the `Identifier` of the input and the `Debug` version.
+//! Wrap the handler with `middleware::Traced` to add the error message. With the feature disabled, the spans compile to nothing, and no bound is added.
+//!
+//! With the `metrics` feature enabled, the aggregates (including the orchestrating ones), materialized views, saga managers and projection runners
+//! emit the counters, histograms and gauges via the `metrics` facade (commands handled, decide errors, events appended, fold length, events handled, projection lag, ...),
+//! with the standard names listed in the [telemetry] module. Install any `metrics` recorder/exporter to collect them.
+//!
+//! ### Serializing the commands per identifier
+//!
+//! Concurrent commands of the same aggregate race: they conflict (with the optimistic locking in the repository), or overwrite each other (without it).
+//! For the single-node deployments with no locking in the database, wrap the aggregate with [keyed_lock::Serialized]. It handles the commands with the same
+//! [Identifier] one at a time, while the commands with different identifiers are handled in parallel.
+//!
+//! ### Actor runtime
+//!
+//! With the `actor` feature enabled, the event-sourced aggregate can be run by the `actor::ActorRuntime`: every active aggregate instance is the `tokio` task
+//! with the mailbox, keyed by the [Identifier]. It keeps the state of the aggregate in memory, handles its commands sequentially, saves the new events
+//! via the [aggregate::EventRepository], and is passivated once idle. The event stream is fetched only when the actor is started, so the commands of the hot aggregates
+//! are handled with low latency. The mailboxes are bounded, and the command sent to the full mailbox fails with the `actor::ActorError::MailboxFull`,
+//! so the `Error` type of the runtime must implement `From<actor::ActorError>`. It is not available with the `not-send-futures` feature.
+//!
+//! ### Storage adapters
+//!
+//! The repositories are the ports, implemented by the infrastructure of your choice. The optional adapters are shipped for the common cases:
+//!
+//! - `sqlite` feature - `sqlite::SqliteEventRepository`, `sqlite::SqliteStateRepository` and `sqlite::SqliteViewStateRepository`, the event, state and view stores
+//!   on the embedded SQLite database, with the payloads encoded as JSON. The event store streams the events page by page, too (`sqlite::SqliteEventStream`)
+//! - `file-log` feature - `file_log::FileEventRepository`, the event store on the local append-only log file, with the checksummed JSON Lines records,
+//!   the in-memory index of the streams, the recovery of the torn writes, and the compaction
+//! - `redb` feature - `redb::RedbEventRepository`, `redb::RedbStateRepository` and `redb::RedbViewStateRepository`, the event, state and view stores
+//!   on the embedded (pure Rust) redb key-value store, sharing the single database file, with the ACID transactions
+//! - `postgres` feature - `postgres::PostgresEventRepository`, `postgres::PostgresStateRepository` and `postgres::PostgresViewStateRepository`, the event, state and view stores
+//!   on the PostgreSQL database, and `postgres::PostgresEventSource`, feeding the projections with the events notified by `LISTEN`/`NOTIFY`
+//!
+//! The binary stores encode the events by the `codec::EventCodec`, storing the name of the event type (the variant of the event enum) next to the payload.
+//! The codecs are shipped for JSON (`json` feature, the default of the stores), CBOR (`cbor` feature), MessagePack (`msgpack` feature) and bincode (`bincode` feature),
+//! so the wire format can be changed with no change of the domain types. The SQL stores and the file log keep the events as JSON, to keep them queryable/readable; they do not take the codec.
+//!
+//! The names derived from the Rust types change with the refactoring. Implement the [message_type::MessageType] trait (or derive it, with the `derive` feature)
+//! to give the events and commands the stable names, and store them by the `message_type::MessageTypeCodec`. The [message_type::MessageRegistry] maps the names
+//! to the decoders, decoding the messages of the different types, read from the shared log, into the common type.
+//!
 //! ## View
 //!
 //! `View`  is a datatype that represents the event handling algorithm, responsible for translating the events into
@@ -104,6 +175,31 @@
 //! suspending function first, and then delegate the event to the view, which can produce new state as a result. New state
 //! is then stored via `ViewStateRepository.save` suspending function.
 //!
+//! With the [materialized_view::VersionedViewStateRepository], the materialized view is additionally storing the version of the
+//! last applied event, and handles the events via `MaterializedView.handle_versioned`. Redelivered events are skipped and missing
+//! events (including the leading ones) are detected, making the projection idempotent under `at least once` delivery.
+//!
+//! ### Projection Runner
+//!
+//! [projection::ProjectionRunner] is feeding the materialized view with the events read from the [projection::EventSource] in
+//! the global order. It stores the position of the last handled event via [projection::CheckpointRepository], so the
+//! projection resumes from it after a restart. It supports both catch-up and live tailing modes.
+//!
+//! [materialized_view::ViewRebuild] is replaying the full event history into a fresh view state repository, while the old
+//! one keeps serving, and switches over to it once it has caught up (blue-green replacement).
+//!
+//! ### Event Subscription
+//!
+//! [subscription::EventSubscription] delivers the events of any [projection::EventSource], stored after the global position, to any
+//! [middleware::Handler], so the materialized views and the saga managers consume them directly. The events are selected by
+//! the [subscription::EventFilter], by their [Identifier], their [message_type::MessageType] or any predicate.
+//! The [subscription::InMemoryEventLog] is the event source of the events saved in-process, by the repositories wrapped with the [subscription::EventLogRepository].
+//!
+//! With the `event-bus` feature enabled, the `event_bus::EventBus` pushes the events saved by the aggregates (via the `event_bus::EventBusRepository`)
+//! to the registered views and saga managers, in-process. Every subscriber is the `tokio` task with the bounded mailbox: the publishers never wait for
+//! the slow subscriber, which loses the events or is unsubscribed once its mailbox is full (`event_bus::Overflow`), and the failing one does not affect the others.
+//! It is not available with the `not-send-futures` feature.
+//!
 //!
 //! ## Saga
 //!
@@ -131,6 +227,41 @@
 //!
 //! It is using a [saga::Saga] to react to the action result and to publish the new actions.
 //! It is using an [saga_manager::ActionPublisher] to publish the new actions.
+//! Publishing is retried with backoff according to the [saga_manager::RetryPolicy], and actions that still cannot be
+//! published can be captured by a [saga_manager::DeadLetterSink] and re-driven later.
+//!
+//! ### Process
+//!
+//! `Process` is a stateful `Saga`. It evolves its own state `S` with every action result, and reacts to the action result based on the new state.
+//! This makes the multi-step workflows possible, for example waiting for both, the payment and the stock reservation, before shipping the order.
+//!
+//! `Process` is a pure domain component.
+//!
+//! ```rust
+//! pub type ProcessReactFunction<'a, AR, S, A> = Box<dyn Fn(&S, &AR) -> Vec<A> + 'a + Send + Sync>;
+//! pub type EvolveFunction<'a, S, E> = Box<dyn Fn(&S, &E) -> S + 'a + Send + Sync>;
+//! pub type InitialStateFunction<'a, S> = Box<dyn Fn() -> S + 'a + Send + Sync>;
+//! pub struct Process<'a, AR: 'a, S: 'a, A: 'a> {
+//!     pub react: ProcessReactFunction<'a, AR, S, A>,
+//!     pub evolve: EvolveFunction<'a, S, AR>,
+//!     pub initial_state: InitialStateFunction<'a, S>,
+//! }
+//! ```
+//!
+//! ### Process Manager
+//!
+//! [process_manager::ProcessManager] is using/delegating a `Process` to evolve the state and to react to the action result.
+//! It loads and saves the state of the process per correlation identifier via [process_manager::ProcessStateRepository],
+//! and publishes the new actions via [saga_manager::ActionPublisher].
+//!
+//! It belongs to the Application layer.
+//!
+//! ### Deadlines
+//!
+//! Sagas and processes can emit [scheduler::ScheduledAction]s, which are due after a delay, for example "cancel the order if it is not paid in 30 minutes".
+//! The [scheduler::ScheduledActionPublisher] stores them into the [scheduler::Scheduler], and the [scheduler::DeadlineRunner] publishes them once they are due.
+//! The pending actions are canceled by their identifier via `Scheduler.cancel`, for example once the order is paid.
+//! The time is read from the injectable [scheduler::Clock], so the deadlines can be tested deterministically with the [scheduler::ManualClock].
 //!
 //! ## Clear separation between data and behaviour
 //!
@@ -312,18 +443,61 @@ use saga::Saga;
 use serde::{Deserialize, Serialize};
 use view::View;
 
+/// Actor module - belongs to the `Application` layer - runs the aggregates as the in-memory actors, with the cached state
+#[cfg(all(feature = "actor", not(feature = "not-send-futures")))]
+pub mod actor;
 /// Aggregate module - belongs to the `Application` layer - composes pure logic and effects (fetching, storing)
 pub mod aggregate;
+/// Codec module - belongs to the `Infrastructure` layer - encodes the events into the bytes, and back, by the pluggable wire format
+pub mod codec;
 /// Decider module - belongs to the `Domain` layer - pure decision making component - pure logic
 pub mod decider;
+/// Event Bus module - belongs to the `Application` layer - fans the saved events out to the views and sagas, in-process
+#[cfg(all(feature = "event-bus", not(feature = "not-send-futures")))]
+pub mod event_bus;
+/// File Log module - belongs to the `Infrastructure` layer - event store on the local append-only log file
+#[cfg(feature = "file-log")]
+pub mod file_log;
+/// Keyed Lock module - belongs to the `Application` layer - serializes the handling of the inputs per identifier, in-process
+pub mod keyed_lock;
 /// Materialized View module - belongs to the `Application` layer - composes pure event handling algorithm and effects (fetching, storing)
 pub mod materialized_view;
+/// Message Type module - belongs to the `Infrastructure` layer - stable names of the message types, and the registry of their decoders
+pub mod message_type;
+/// Middleware module - belongs to the `Application` layer - wraps the handlers (aggregates, materialized views, saga managers) with the cross-cutting interceptors
+pub mod middleware;
+/// Outbox module - belongs to the `Application` layer - stores the outgoing messages together with the events/state, and relays them to the action publisher
+pub mod outbox;
+/// PostgreSQL module - belongs to the `Infrastructure` layer - event, state and view stores, and the event source, on the PostgreSQL database
+#[cfg(feature = "postgres")]
+pub mod postgres;
+/// Process module - belongs to the `Domain` layer - pure stateful mapper of action results/events into new actions/commands
+pub mod process;
+/// Process Manager module - belongs to the `Application` layer - composes pure process and effects (fetching, storing, publishing)
+pub mod process_manager;
+/// Projection module - belongs to the `Application` layer - feeds the materialized view from the event log and tracks its checkpoint
+pub mod projection;
+/// Redb module - belongs to the `Infrastructure` layer - event, state and view stores on the embedded redb key-value store
+#[cfg(feature = "redb")]
+pub mod redb;
 /// Saga module - belongs to the `Domain` layer - pure mapper of action results/events into new actions/commands
 pub mod saga;
 /// Saga Manager module - belongs to the `Application` layer - composes pure saga and effects (publishing)
 pub mod saga_manager;
+/// Scheduler module - belongs to the `Application` layer - stores the scheduled actions/deadlines, and dispatches them once they are due
+pub mod scheduler;
+/// Service module - belongs to the `Application` layer - adapts the handlers to the `tower::Service`
+#[cfg(feature = "tower")]
+pub mod service;
 /// Given-When-Then Test specificatin domain specific language - unit testing
 pub mod specification;
+/// SQLite module - belongs to the `Infrastructure` layer - event, state and view stores on the embedded SQLite database
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+/// Subscription module - belongs to the `Application` layer - subscribes the handlers to the events stored after the global position, filtered by the event type or identifier
+pub mod subscription;
+/// Telemetry module - belongs to the `Application` layer - metrics and spans of the application components, emitted when the `metrics`/`tracing` features are enabled
+pub mod telemetry;
 /// View module - belongs to the `Domain` layer - pure event handling algorithm
 pub mod view;
 
@@ -340,6 +514,9 @@ pub type InitialStateFunction<'a, S> = Box<dyn Fn() -> S + 'a + Send + Sync>;
 /// The [ReactFunction] function is used to decide what actions/A to execute next based on the action result/AR.
 #[cfg(not(feature = "not-send-futures"))]
 pub type ReactFunction<'a, AR, A> = Box<dyn Fn(&AR) -> Vec<A> + 'a + Send + Sync>;
+/// The [ProcessReactFunction] function is used to decide what actions/A to execute next based on the current state/S of the process and the action result/AR.
+#[cfg(not(feature = "not-send-futures"))]
+pub type ProcessReactFunction<'a, AR, S, A> = Box<dyn Fn(&S, &AR) -> Vec<A> + 'a + Send + Sync>;
 
 /// The [DecideFunction] function is used to decide which events to produce based on the command and the current state.
 #[cfg(feature = "not-send-futures")]
@@ -353,6 +530,9 @@ pub type InitialStateFunction<'a, S> = Box<dyn Fn() -> S + 'a>;
 /// The [ReactFunction] function is used to decide what actions/A to execute next based on the action result/AR.
 #[cfg(feature = "not-send-futures")]
 pub type ReactFunction<'a, AR, A> = Box<dyn Fn(&AR) -> Vec<A> + 'a>;
+/// The [ProcessReactFunction] function is used to decide what actions/A to execute next based on the current state/S of the process and the action result/AR.
+#[cfg(feature = "not-send-futures")]
+pub type ProcessReactFunction<'a, AR, S, A> = Box<dyn Fn(&S, &AR) -> Vec<A> + 'a>;
 
 /// Generic Combined/Sum Enum of two variants
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -484,3 +664,24 @@ where
         }
     }
 }
+
+/// Provides the idempotency key of the command.
+/// It is used to recognize the retried/duplicated command, for example the command ID or the `Idempotency-Key` HTTP header.
+/// Commands with no key are always handled.
+pub trait IdempotencyKey {
+    /// Returns the idempotency key of the command, if any
+    fn idempotency_key(&self) -> Option<String>;
+}
+
+impl<A, B> IdempotencyKey for Sum<A, B>
+where
+    A: IdempotencyKey,
+    B: IdempotencyKey,
+{
+    fn idempotency_key(&self) -> Option<String> {
+        match self {
+            Sum::First(a) => a.idempotency_key(),
+            Sum::Second(b) => b.idempotency_key(),
+        }
+    }
+}
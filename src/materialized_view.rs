@@ -1,7 +1,28 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt::Debug;
 use std::future::Future;
+use std::hash::Hash;
 use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 
+use crate::projection::{CheckpointRepository, EventSource, ProjectionRunner, DEFAULT_BATCH_SIZE};
+use crate::telemetry::{record, record_result, span, Instrument, Meter, Span, EVENTS_HANDLED};
 use crate::view::ViewStateComputation;
+use crate::Identifier;
+
+/// Opens the `handle` span of the materialized view.
+/// The `identifier` field is recorded by [MaterializedView::handle_traced] only, as it needs the [Identifier] of the event.
+fn handle_span() -> Span {
+    span!(
+        "handle",
+        component = "MaterializedView",
+        identifier = tracing::field::Empty,
+        state_loaded = tracing::field::Empty,
+        error = tracing::field::Empty
+    )
+}
 
 /// View State Repository trait
 ///
@@ -46,6 +67,9 @@ pub trait ViewStateRepository<E, S, Error> {
 /// It is using a `View` / [ViewStateComputation] to compute new state based on the current state and the event.
 /// It is using a [ViewStateRepository] to fetch the current state and to save the new state.
 ///
+/// With the [VersionedViewStateRepository], the state is stored together with the version of the last applied event, and the events are handled via [MaterializedView::handle_versioned].
+/// This makes the view idempotent under the redelivery of the events (`at least once` delivery): duplicated events are skipped, and gaps are detected.
+///
 /// Generic parameters:
 ///
 /// - `S` - State
@@ -55,7 +79,6 @@ pub trait ViewStateRepository<E, S, Error> {
 /// - `Error` - Error
 pub struct MaterializedView<S, E, Repository, View, Error>
 where
-    Repository: ViewStateRepository<E, S, Error>,
     View: ViewStateComputation<E, S>,
 {
     repository: Repository,
@@ -63,10 +86,24 @@ where
     _marker: PhantomData<(S, E, Error)>,
 }
 
+impl<S, E, Repository, View, Error> MaterializedView<S, E, Repository, View, Error>
+where
+    View: ViewStateComputation<E, S>,
+{
+    /// Creates a new instance of [MaterializedView].
+    /// The `repository` is a [ViewStateRepository], a [VersionedViewStateRepository], or both.
+    pub fn new(repository: Repository, view: View) -> Self {
+        MaterializedView {
+            repository,
+            view,
+            _marker: PhantomData,
+        }
+    }
+}
+
 impl<S, E, Repository, View, Error> ViewStateComputation<E, S>
     for MaterializedView<S, E, Repository, View, Error>
 where
-    Repository: ViewStateRepository<E, S, Error>,
     View: ViewStateComputation<E, S>,
 {
     /// Computes new state based on the current state and the events.
@@ -123,42 +160,671 @@ where
     S: Sync,
     Error: Sync,
 {
-    /// Creates a new instance of [MaterializedView].
-    pub fn new(repository: Repository, view: View) -> Self {
-        MaterializedView {
-            repository,
-            view,
-            _marker: PhantomData,
+    /// Handles the event by fetching the state from the repository, computing new state based on the current state and the event, and saving the new state to the repository.
+    pub async fn handle(&self, event: &E) -> Result<S, Error> {
+        self.handle_in(event, &handle_span()).await
+    }
+    /// Handles the event like [MaterializedView::handle] does, and records the [Identifier] of the event in the `handle` span.
+    /// With the `tracing` feature disabled, it is the same as [MaterializedView::handle].
+    pub async fn handle_traced(&self, event: &E) -> Result<S, Error>
+    where
+        E: Identifier,
+    {
+        let span = handle_span();
+        record!(
+            span,
+            "identifier",
+            tracing::field::display(event.identifier())
+        );
+        self.handle_in(event, &span).await
+    }
+    /// Handles the event within the `span`, see [MaterializedView::handle].
+    async fn handle_in(&self, event: &E, span: &Span) -> Result<S, Error> {
+        let meter = Meter::new::<S>("MaterializedView");
+        let result = async {
+            let state = self.fetch_state(event).instrument(span!("fetch")).await?;
+            record!(span, "state_loaded", state.is_some());
+            let new_state = self.compute_new_state(state, &[event]);
+            let saved_state = self.save(&new_state).instrument(span!("save")).await?;
+            Ok(saved_state)
         }
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result);
+        meter.increment(EVENTS_HANDLED, 1);
+        result
+    }
+    /// Handles the batch of events, grouped by the [Identifier] of the event. See [MaterializedView::handle_batch_by].
+    ///
+    /// Use it only if the [ViewStateRepository] stores the view state under the [Identifier] of the event.
+    /// If the state is stored under another key (e.g. a view per customer, fed by the order events), the events of the same state fall into different groups,
+    /// every group folds its events into the same fetched state, and the later save overwrites the earlier one. Use [MaterializedView::handle_batch_by] with the key of the repository instead.
+    pub async fn handle_batch(&self, events: &[E]) -> Result<Vec<S>, Error>
+    where
+        E: Identifier,
+    {
+        self.handle_batch_by(events, Identifier::identifier).await
     }
+    /// Handles the batch of events, grouped by the `key` the [ViewStateRepository] stores the view state under.
+    /// The order of the events within the group is kept. The state of every group is fetched once (by the first event of the group), all the events of the group are folded into the new state, and the new state is saved once.
+    /// Returns the saved states, in the order the groups first appear in the batch.
+    pub async fn handle_batch_by<K, F>(&self, events: &[E], key: F) -> Result<Vec<S>, Error>
+    where
+        K: Eq + Hash,
+        F: Fn(&E) -> K,
+    {
+        let mut indexes: HashMap<K, usize> = HashMap::new();
+        let mut groups: Vec<Vec<&E>> = vec![];
+        for event in events {
+            match indexes.entry(key(event)) {
+                Entry::Occupied(index) => groups[*index.get()].push(event),
+                Entry::Vacant(index) => {
+                    index.insert(groups.len());
+                    groups.push(vec![event]);
+                }
+            }
+        }
+        let span = span!(
+            "handle_batch",
+            component = "MaterializedView",
+            events = events.len(),
+            groups = groups.len(),
+            error = tracing::field::Empty
+        );
+        let result = async {
+            let mut saved_states = Vec::with_capacity(groups.len());
+            for group in groups {
+                let state = self
+                    .fetch_state(group[0])
+                    .instrument(span!("fetch"))
+                    .await?;
+                let new_state = self.compute_new_state(state, &group);
+                saved_states.push(self.save(&new_state).instrument(span!("save")).await?);
+            }
+            Ok(saved_states)
+        }
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result);
+        Meter::new::<S>("MaterializedView").increment(EVENTS_HANDLED, events.len());
+        result
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<S, E, Repository, View, Error> MaterializedView<S, E, Repository, View, Error>
+where
+    Repository: ViewStateRepository<E, S, Error>,
+    View: ViewStateComputation<E, S>,
+{
     /// Handles the event by fetching the state from the repository, computing new state based on the current state and the event, and saving the new state to the repository.
     pub async fn handle(&self, event: &E) -> Result<S, Error> {
-        let state = self.fetch_state(event).await?;
-        let new_state = self.compute_new_state(state, &[event]);
-        let saved_state = self.save(&new_state).await?;
-        Ok(saved_state)
+        self.handle_in(event, &handle_span()).await
+    }
+    /// Handles the event like [MaterializedView::handle] does, and records the [Identifier] of the event in the `handle` span.
+    /// With the `tracing` feature disabled, it is the same as [MaterializedView::handle].
+    pub async fn handle_traced(&self, event: &E) -> Result<S, Error>
+    where
+        E: Identifier,
+    {
+        let span = handle_span();
+        record!(
+            span,
+            "identifier",
+            tracing::field::display(event.identifier())
+        );
+        self.handle_in(event, &span).await
+    }
+    /// Handles the event within the `span`, see [MaterializedView::handle].
+    async fn handle_in(&self, event: &E, span: &Span) -> Result<S, Error> {
+        let meter = Meter::new::<S>("MaterializedView");
+        let result = async {
+            let state = self.fetch_state(event).instrument(span!("fetch")).await?;
+            record!(span, "state_loaded", state.is_some());
+            let new_state = self.compute_new_state(state, &[event]);
+            let saved_state = self.save(&new_state).instrument(span!("save")).await?;
+            Ok(saved_state)
+        }
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result);
+        meter.increment(EVENTS_HANDLED, 1);
+        result
+    }
+    /// Handles the batch of events, grouped by the [Identifier] of the event. See [MaterializedView::handle_batch_by].
+    ///
+    /// Use it only if the [ViewStateRepository] stores the view state under the [Identifier] of the event.
+    /// If the state is stored under another key (e.g. a view per customer, fed by the order events), the events of the same state fall into different groups,
+    /// every group folds its events into the same fetched state, and the later save overwrites the earlier one. Use [MaterializedView::handle_batch_by] with the key of the repository instead.
+    pub async fn handle_batch(&self, events: &[E]) -> Result<Vec<S>, Error>
+    where
+        E: Identifier,
+    {
+        self.handle_batch_by(events, Identifier::identifier).await
+    }
+    /// Handles the batch of events, grouped by the `key` the [ViewStateRepository] stores the view state under.
+    /// The order of the events within the group is kept. The state of every group is fetched once (by the first event of the group), all the events of the group are folded into the new state, and the new state is saved once.
+    /// Returns the saved states, in the order the groups first appear in the batch.
+    pub async fn handle_batch_by<K, F>(&self, events: &[E], key: F) -> Result<Vec<S>, Error>
+    where
+        K: Eq + Hash,
+        F: Fn(&E) -> K,
+    {
+        let mut indexes: HashMap<K, usize> = HashMap::new();
+        let mut groups: Vec<Vec<&E>> = vec![];
+        for event in events {
+            match indexes.entry(key(event)) {
+                Entry::Occupied(index) => groups[*index.get()].push(event),
+                Entry::Vacant(index) => {
+                    index.insert(groups.len());
+                    groups.push(vec![event]);
+                }
+            }
+        }
+        let span = span!(
+            "handle_batch",
+            component = "MaterializedView",
+            events = events.len(),
+            groups = groups.len(),
+            error = tracing::field::Empty
+        );
+        let result = async {
+            let mut saved_states = Vec::with_capacity(groups.len());
+            for group in groups {
+                let state = self
+                    .fetch_state(group[0])
+                    .instrument(span!("fetch"))
+                    .await?;
+                let new_state = self.compute_new_state(state, &group);
+                saved_states.push(self.save(&new_state).instrument(span!("save")).await?);
+            }
+            Ok(saved_states)
+        }
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result);
+        Meter::new::<S>("MaterializedView").increment(EVENTS_HANDLED, events.len());
+        result
+    }
+}
+
+/// Versioned View State Repository trait
+///
+/// Stores the view state together with the version of the last event applied to it.
+///
+/// Generic parameters:
+///
+/// - `E` - Event
+/// - `S` - State
+/// - `Version` - Version of the last applied event
+/// - `Error` - Error
+#[cfg(not(feature = "not-send-futures"))]
+pub trait VersionedViewStateRepository<E, S, Version, Error> {
+    /// Fetches current state, and the version of the last event applied to it, based on the event.
+    /// Desugared `async fn fetch_state(&self, event: &E) -> Result<Option<(S, Version)>, Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn fetch_state(
+        &self,
+        event: &E,
+    ) -> impl Future<Output = Result<Option<(S, Version)>, Error>> + Send;
+    /// Saves the new state, together with the version of the last event applied to it.
+    /// Desugared `async fn save(&self, state: &S, version: &Version) -> Result<(S, Version), Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn save(
+        &self,
+        state: &S,
+        version: &Version,
+    ) -> impl Future<Output = Result<(S, Version), Error>> + Send;
+}
+
+/// Versioned View State Repository trait
+///
+/// Stores the view state together with the version of the last event applied to it.
+///
+/// Generic parameters:
+///
+/// - `E` - Event
+/// - `S` - State
+/// - `Version` - Version of the last applied event
+/// - `Error` - Error
+#[cfg(feature = "not-send-futures")]
+pub trait VersionedViewStateRepository<E, S, Version, Error> {
+    /// Fetches current state, and the version of the last event applied to it, based on the event.
+    /// Desugared `async fn fetch_state(&self, event: &E) -> Result<Option<(S, Version)>, Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn fetch_state(&self, event: &E) -> impl Future<Output = Result<Option<(S, Version)>, Error>>;
+    /// Saves the new state, together with the version of the last event applied to it.
+    /// Desugared `async fn save(&self, state: &S, version: &Version) -> Result<(S, Version), Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn save(
+        &self,
+        state: &S,
+        version: &Version,
+    ) -> impl Future<Output = Result<(S, Version), Error>>;
+}
+
+/// Sequence number of the event, used to detect already applied (duplicated) and missing events.
+pub trait SequenceNumber: PartialOrd {
+    /// Returns `true` if this sequence number directly follows the `previous` one, with no gap in between.
+    fn follows(&self, previous: &Self) -> bool;
+    /// Returns `true` if this is the sequence number of the first event of the stream.
+    /// The integers start at zero, as the versions of the event repositories do. Implement the trait on a newtype to start at another number.
+    fn is_initial(&self) -> bool;
+}
+
+macro_rules! impl_sequence_number {
+    ($($t:ty),*) => {
+        $(
+            impl SequenceNumber for $t {
+                fn follows(&self, previous: &Self) -> bool {
+                    previous.checked_add(1) == Some(*self)
+                }
+                fn is_initial(&self) -> bool {
+                    *self == 0
+                }
+            }
+        )*
+    };
+}
+
+impl_sequence_number!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+/// Outcome of handling the versioned event by the [MaterializedView::handle_versioned].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViewUpdate<S, Version> {
+    /// The event is applied. Contains the new state and the version of the event
+    Applied(S, Version),
+    /// The event was already applied. Contains the current state and the version of the last applied event
+    Duplicate(S, Version),
+    /// The events between the last applied version (or the beginning of the stream) and the version of the event are missing.
+    /// Contains the current state and the version of the last applied event, or `None` if no event is applied yet
+    Gap(Option<(S, Version)>),
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<S, E, Repository, View, Error> MaterializedView<S, E, Repository, View, Error>
+where
+    Repository: Sync,
+    View: ViewStateComputation<E, S> + Sync,
+    E: Sync,
+    S: Sync,
+    Error: Sync,
+{
+    /// Handles the event of the given `version`, with the [VersionedViewStateRepository].
+    /// The event is applied only if it directly follows the version of the last applied event, or if it is the first event of the stream (see [SequenceNumber::is_initial]) and there is no state yet.
+    /// Already applied events are skipped, and missing events are reported as a gap. In both cases the stored state is left untouched.
+    pub async fn handle_versioned<Version>(
+        &self,
+        event: &E,
+        version: &Version,
+    ) -> Result<ViewUpdate<S, Version>, Error>
+    where
+        Repository: VersionedViewStateRepository<E, S, Version, Error>,
+        Version: SequenceNumber + Debug,
+        E: Identifier,
+    {
+        let span = span!(
+            "handle",
+            component = "MaterializedView",
+            identifier = %event.identifier(),
+            version = ?version,
+            state_loaded = tracing::field::Empty,
+            error = tracing::field::Empty
+        );
+        let meter = Meter::new::<S>("MaterializedView");
+        let result = async {
+            let state = VersionedViewStateRepository::fetch_state(&self.repository, event)
+                .instrument(span!("fetch"))
+                .await?;
+            record!(span, "state_loaded", state.is_some());
+            let current_state = match state {
+                None if version.is_initial() => None,
+                Some((state, last_version)) if version.follows(&last_version) => Some(state),
+                Some((state, last_version)) if *version <= last_version => {
+                    return Ok(ViewUpdate::Duplicate(state, last_version));
+                }
+                state => return Ok(ViewUpdate::Gap(state)),
+            };
+            let new_state = self.compute_new_state(current_state, &[event]);
+            let (state, version) = self
+                .repository
+                .save(&new_state, version)
+                .instrument(span!("save"))
+                .await?;
+            Ok(ViewUpdate::Applied(state, version))
+        }
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result);
+        meter.increment(EVENTS_HANDLED, 1);
+        result
     }
 }
 
 #[cfg(feature = "not-send-futures")]
 impl<S, E, Repository, View, Error> MaterializedView<S, E, Repository, View, Error>
 where
+    View: ViewStateComputation<E, S>,
+{
+    /// Handles the event of the given `version`, with the [VersionedViewStateRepository].
+    /// The event is applied only if it directly follows the version of the last applied event, or if it is the first event of the stream (see [SequenceNumber::is_initial]) and there is no state yet.
+    /// Already applied events are skipped, and missing events are reported as a gap. In both cases the stored state is left untouched.
+    pub async fn handle_versioned<Version>(
+        &self,
+        event: &E,
+        version: &Version,
+    ) -> Result<ViewUpdate<S, Version>, Error>
+    where
+        Repository: VersionedViewStateRepository<E, S, Version, Error>,
+        Version: SequenceNumber + Debug,
+        E: Identifier,
+    {
+        let span = span!(
+            "handle",
+            component = "MaterializedView",
+            identifier = %event.identifier(),
+            version = ?version,
+            state_loaded = tracing::field::Empty,
+            error = tracing::field::Empty
+        );
+        let meter = Meter::new::<S>("MaterializedView");
+        let result = async {
+            let state = VersionedViewStateRepository::fetch_state(&self.repository, event)
+                .instrument(span!("fetch"))
+                .await?;
+            record!(span, "state_loaded", state.is_some());
+            let current_state = match state {
+                None if version.is_initial() => None,
+                Some((state, last_version)) if version.follows(&last_version) => Some(state),
+                Some((state, last_version)) if *version <= last_version => {
+                    return Ok(ViewUpdate::Duplicate(state, last_version));
+                }
+                state => return Ok(ViewUpdate::Gap(state)),
+            };
+            let new_state = self.compute_new_state(current_state, &[event]);
+            let (state, version) = self
+                .repository
+                .save(&new_state, version)
+                .instrument(span!("save"))
+                .await?;
+            Ok(ViewUpdate::Applied(state, version))
+        }
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result);
+        meter.increment(EVENTS_HANDLED, 1);
+        result
+    }
+}
+
+/// Progress of the [ViewRebuild].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RebuildProgress<Position> {
+    /// Number of the events handled so far
+    pub handled: usize,
+    /// Position of the last handled event
+    pub position: Option<Position>,
+}
+
+/// Outcome of the [ViewRebuild] run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RebuildStatus<Position> {
+    /// The rebuilt view has caught up with the event source, and it is ready to switch over
+    CaughtUp(RebuildProgress<Position>),
+    /// The rebuild was aborted. It can be resumed from the reached position
+    Aborted(RebuildProgress<Position>),
+}
+
+/// View Rebuild.
+///
+/// It is replaying the full event history, read from the [EventSource], into a fresh `target` [MaterializedView].
+/// The old view keeps serving in the meantime. Once the `target` has caught up, [ViewRebuild::switch_over] hands it over to a new [ProjectionRunner] (blue-green replacement).
+/// Use it when the `evolve` logic of the [View](crate::view::View) changes and the projection has to be rebuilt from scratch.
+///
+/// Generic parameters:
+///
+/// - `S` - State
+/// - `E` - Event
+/// - `Position` - Global position/offset of the event in the event log
+/// - `Source` - Event source
+/// - `Repository` - View State repository of the `target` view
+/// - `View` - View
+/// - `Error` - Error
+pub struct ViewRebuild<S, E, Position, Source, Repository, View, Error>
+where
+    Source: EventSource<E, Position, Error>,
     Repository: ViewStateRepository<E, S, Error>,
     View: ViewStateComputation<E, S>,
 {
-    /// Creates a new instance of [MaterializedView].
-    pub fn new(repository: Repository, view: View) -> Self {
-        MaterializedView {
-            repository,
-            view,
-            _marker: PhantomData,
+    source: Source,
+    target: MaterializedView<S, E, Repository, View, Error>,
+    batch_size: usize,
+    aborted: AtomicBool,
+    position: Mutex<Option<Position>>,
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<S, E, Position, Source, Repository, View, Error>
+    ViewRebuild<S, E, Position, Source, Repository, View, Error>
+where
+    Source: EventSource<E, Position, Error> + Sync,
+    Repository: ViewStateRepository<E, S, Error> + Sync,
+    View: ViewStateComputation<E, S> + Sync,
+    E: Sync,
+    S: Sync,
+    Position: Sync,
+    Error: Sync,
+{
+    /// Creates a new instance of [ViewRebuild].
+    /// The `target` materialized view should be backed by a fresh/empty [ViewStateRepository].
+    pub fn new(source: Source, target: MaterializedView<S, E, Repository, View, Error>) -> Self {
+        ViewRebuild {
+            source,
+            target,
+            batch_size: DEFAULT_BATCH_SIZE,
+            aborted: AtomicBool::new(false),
+            position: Mutex::new(None),
         }
     }
-    /// Handles the event by fetching the state from the repository, computing new state based on the current state and the event, and saving the new state to the repository.
-    pub async fn handle(&self, event: &E) -> Result<S, Error> {
-        let state = self.fetch_state(event).await?;
-        let new_state = self.compute_new_state(state, &[event]);
-        let saved_state = self.save(&new_state).await?;
-        Ok(saved_state)
+    /// Sets the maximum number of events read from the [EventSource] at once.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+    /// Requests the running rebuild to stop after the batch it is currently handling.
+    /// The request is cleared by the next [run](ViewRebuild::run)/[resume](ViewRebuild::resume), so the aborted rebuild can be resumed.
+    pub fn abort(&self) {
+        self.aborted.store(true, Ordering::SeqCst);
+    }
+    /// Returns `true` if the rebuild was aborted.
+    pub fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::SeqCst)
+    }
+    /// Returns the position of the last event replayed into the `target` materialized view, or `None` if no event is replayed yet.
+    pub fn position(&self) -> Option<Position>
+    where
+        Position: Clone,
+    {
+        self.position.lock().unwrap().clone()
+    }
+    /// Replays the full event history into the `target` materialized view, starting from the beginning of the event source.
+    /// The `on_progress` callback is called after every handled batch.
+    pub async fn run<F>(&self, on_progress: F) -> Result<RebuildStatus<Position>, Error>
+    where
+        F: FnMut(&RebuildProgress<Position>) + Send,
+        Position: Clone,
+    {
+        self.resume(None, on_progress).await
+    }
+    /// Continues replaying the event history into the `target` materialized view, from the `position` a previous run has stopped at.
+    /// The `position` becomes the [position](ViewRebuild::position) of the rebuild, until a later event is replayed.
+    /// The `on_progress` callback is called after every handled batch.
+    pub async fn resume<F>(
+        &self,
+        position: Option<Position>,
+        mut on_progress: F,
+    ) -> Result<RebuildStatus<Position>, Error>
+    where
+        F: FnMut(&RebuildProgress<Position>) + Send,
+        Position: Clone,
+    {
+        self.aborted.store(false, Ordering::SeqCst);
+        *self.position.lock().unwrap() = position.clone();
+        let mut progress = RebuildProgress {
+            handled: 0,
+            position,
+        };
+        loop {
+            if self.is_aborted() {
+                return Ok(RebuildStatus::Aborted(progress));
+            }
+            let events = self
+                .source
+                .read_events(&progress.position, self.batch_size)
+                .await?;
+            if events.is_empty() {
+                return Ok(RebuildStatus::CaughtUp(progress));
+            }
+            for (event, position) in events {
+                self.target.handle(&event).await?;
+                progress.handled += 1;
+                progress.position = Some(position);
+                *self.position.lock().unwrap() = progress.position.clone();
+            }
+            on_progress(&progress);
+        }
+    }
+    /// Switches over to the rebuilt view, once it has caught up with the event source.
+    /// It stores the [position](ViewRebuild::position) of the last replayed event as the checkpoint of the `projection` (or deletes the checkpoint, if no event is replayed),
+    /// and returns the [ProjectionRunner] that keeps the rebuilt view up to date from now on.
+    /// Stop the runner of the old view before switching over, as both runners share the same checkpoint.
+    pub async fn switch_over<Checkpoints>(
+        self,
+        projection: &str,
+        checkpoints: Checkpoints,
+    ) -> Result<ProjectionRunner<S, E, Position, Source, Checkpoints, Repository, View, Error>, Error>
+    where
+        Checkpoints: CheckpointRepository<Position, Error> + Sync,
+    {
+        let position = self.position.into_inner().unwrap();
+        match position {
+            Some(position) => checkpoints.save_checkpoint(projection, &position).await?,
+            None => checkpoints.delete_checkpoint(projection).await?,
+        }
+        Ok(
+            ProjectionRunner::new(projection, self.source, checkpoints, self.target)
+                .with_batch_size(self.batch_size),
+        )
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<S, E, Position, Source, Repository, View, Error>
+    ViewRebuild<S, E, Position, Source, Repository, View, Error>
+where
+    Source: EventSource<E, Position, Error>,
+    Repository: ViewStateRepository<E, S, Error>,
+    View: ViewStateComputation<E, S>,
+{
+    /// Creates a new instance of [ViewRebuild].
+    /// The `target` materialized view should be backed by a fresh/empty [ViewStateRepository].
+    pub fn new(source: Source, target: MaterializedView<S, E, Repository, View, Error>) -> Self {
+        ViewRebuild {
+            source,
+            target,
+            batch_size: DEFAULT_BATCH_SIZE,
+            aborted: AtomicBool::new(false),
+            position: Mutex::new(None),
+        }
+    }
+    /// Sets the maximum number of events read from the [EventSource] at once.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+    /// Requests the running rebuild to stop after the batch it is currently handling.
+    /// The request is cleared by the next [run](ViewRebuild::run)/[resume](ViewRebuild::resume), so the aborted rebuild can be resumed.
+    pub fn abort(&self) {
+        self.aborted.store(true, Ordering::SeqCst);
+    }
+    /// Returns `true` if the rebuild was aborted.
+    pub fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::SeqCst)
+    }
+    /// Returns the position of the last event replayed into the `target` materialized view, or `None` if no event is replayed yet.
+    pub fn position(&self) -> Option<Position>
+    where
+        Position: Clone,
+    {
+        self.position.lock().unwrap().clone()
+    }
+    /// Replays the full event history into the `target` materialized view, starting from the beginning of the event source.
+    /// The `on_progress` callback is called after every handled batch.
+    pub async fn run<F>(&self, on_progress: F) -> Result<RebuildStatus<Position>, Error>
+    where
+        F: FnMut(&RebuildProgress<Position>),
+        Position: Clone,
+    {
+        self.resume(None, on_progress).await
+    }
+    /// Continues replaying the event history into the `target` materialized view, from the `position` a previous run has stopped at.
+    /// The `position` becomes the [position](ViewRebuild::position) of the rebuild, until a later event is replayed.
+    /// The `on_progress` callback is called after every handled batch.
+    pub async fn resume<F>(
+        &self,
+        position: Option<Position>,
+        mut on_progress: F,
+    ) -> Result<RebuildStatus<Position>, Error>
+    where
+        F: FnMut(&RebuildProgress<Position>),
+        Position: Clone,
+    {
+        self.aborted.store(false, Ordering::SeqCst);
+        *self.position.lock().unwrap() = position.clone();
+        let mut progress = RebuildProgress {
+            handled: 0,
+            position,
+        };
+        loop {
+            if self.is_aborted() {
+                return Ok(RebuildStatus::Aborted(progress));
+            }
+            let events = self
+                .source
+                .read_events(&progress.position, self.batch_size)
+                .await?;
+            if events.is_empty() {
+                return Ok(RebuildStatus::CaughtUp(progress));
+            }
+            for (event, position) in events {
+                self.target.handle(&event).await?;
+                progress.handled += 1;
+                progress.position = Some(position);
+                *self.position.lock().unwrap() = progress.position.clone();
+            }
+            on_progress(&progress);
+        }
+    }
+    /// Switches over to the rebuilt view, once it has caught up with the event source.
+    /// It stores the [position](ViewRebuild::position) of the last replayed event as the checkpoint of the `projection` (or deletes the checkpoint, if no event is replayed),
+    /// and returns the [ProjectionRunner] that keeps the rebuilt view up to date from now on.
+    /// Stop the runner of the old view before switching over, as both runners share the same checkpoint.
+    pub async fn switch_over<Checkpoints>(
+        self,
+        projection: &str,
+        checkpoints: Checkpoints,
+    ) -> Result<ProjectionRunner<S, E, Position, Source, Checkpoints, Repository, View, Error>, Error>
+    where
+        Checkpoints: CheckpointRepository<Position, Error>,
+    {
+        let position = self.position.into_inner().unwrap();
+        match position {
+            Some(position) => checkpoints.save_checkpoint(projection, &position).await?,
+            None => checkpoints.delete_checkpoint(projection).await?,
+        }
+        Ok(
+            ProjectionRunner::new(projection, self.source, checkpoints, self.target)
+                .with_batch_size(self.batch_size),
+        )
     }
 }
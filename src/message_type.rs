@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+#[cfg(feature = "not-send-futures")]
+use std::rc::Rc;
+#[cfg(not(feature = "not-send-futures"))]
+use std::sync::Arc;
+
+use crate::codec::{CodecError, EncodedEvent, EventCodec};
+
+/// Derives the [MessageType] trait: the message type of the enum is the name of its variant, and the message type of the struct is the name of the struct,
+/// unless given by the `#[message_type(name = "...")]` attribute.
+#[cfg(feature = "derive")]
+pub use fmodel_rust_derive::MessageType;
+
+/// Message Type trait
+///
+/// The stable name of the type of the message (event or command), independent of the names of the Rust types and of the layout of the enums.
+/// It is stored/sent along the payload, so the consumers written in other languages can route and decode the messages,
+/// and the Rust types and variants can be renamed with no migration of the stored messages.
+///
+/// Derive it with `#[derive(MessageType)]` (`derive` feature), giving the stable names by the `#[message_type(name = "...")]` attributes.
+pub trait MessageType {
+    /// All the message types of this type, e.g. one per variant of the enum.
+    const MESSAGE_TYPES: &'static [&'static str];
+    /// The message type of this message.
+    fn message_type(&self) -> &'static str;
+}
+
+/// The [EventCodec] naming the events by their [MessageType], instead of the names the `serde` serializes them with.
+/// The payload is encoded by the inner codec.
+///
+/// The payload keeps the representation of the `serde`: the externally tagged enum carries the name of its variant in the payload.
+/// Rename the variant by the `#[serde(rename = "...")]` attribute too, to keep the payload stable as well.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessageTypeCodec<Codec>(pub Codec);
+
+impl<E, Codec> EventCodec<E> for MessageTypeCodec<Codec>
+where
+    E: MessageType,
+    Codec: EventCodec<E>,
+{
+    fn format(&self) -> &'static str {
+        self.0.format()
+    }
+    fn encode(&self, event: &E) -> Result<EncodedEvent, CodecError> {
+        let mut encoded = self.0.encode(event)?;
+        encoded.event_type = event.message_type().to_string();
+        Ok(encoded)
+    }
+    fn decode(&self, event: &EncodedEvent) -> Result<E, CodecError> {
+        self.0.decode(event)
+    }
+}
+
+/// The decoder of the registered message type.
+#[cfg(not(feature = "not-send-futures"))]
+type Decoder<'a, M> = Arc<dyn Fn(&EncodedEvent) -> Result<M, CodecError> + 'a + Send + Sync>;
+/// The decoder of the registered message type.
+#[cfg(feature = "not-send-futures")]
+type Decoder<'a, M> = Rc<dyn Fn(&EncodedEvent) -> Result<M, CodecError> + 'a>;
+
+/// Message Registry
+///
+/// Maps the [MessageType] names to the decoders, so the messages of the different types, e.g. the events of all the aggregates read from the shared log,
+/// are decoded into the common type `M` by the name stored along the payload.
+///
+/// Generic parameters:
+///
+/// - `M` - Message
+pub struct MessageRegistry<'a, M> {
+    decoders: HashMap<&'static str, Decoder<'a, M>>,
+}
+
+impl<M> Default for MessageRegistry<'_, M> {
+    fn default() -> Self {
+        MessageRegistry {
+            decoders: HashMap::new(),
+        }
+    }
+}
+
+impl<'a, M> MessageRegistry<'a, M> {
+    /// Creates a new, empty instance of [MessageRegistry].
+    pub fn new() -> Self {
+        MessageRegistry::default()
+    }
+
+    /// Registers all the message types of `T`, decoded by the `codec` and mapped into the message by `into`.
+    ///
+    /// Panics if any of the message types is registered already, as the messages could not be decoded unambiguously.
+    #[cfg(not(feature = "not-send-futures"))]
+    pub fn register<T, Codec>(
+        mut self,
+        codec: Codec,
+        into: impl Fn(T) -> M + 'a + Send + Sync,
+    ) -> Self
+    where
+        T: MessageType,
+        Codec: EventCodec<T> + 'a + Send + Sync,
+    {
+        let decoder: Decoder<'a, M> = Arc::new(move |event| codec.decode(event).map(&into));
+        self.insert(T::MESSAGE_TYPES, decoder);
+        self
+    }
+
+    /// Registers all the message types of `T`, decoded by the `codec` and mapped into the message by `into`.
+    ///
+    /// Panics if any of the message types is registered already, as the messages could not be decoded unambiguously.
+    #[cfg(feature = "not-send-futures")]
+    pub fn register<T, Codec>(mut self, codec: Codec, into: impl Fn(T) -> M + 'a) -> Self
+    where
+        T: MessageType,
+        Codec: EventCodec<T> + 'a,
+    {
+        let decoder: Decoder<'a, M> = Rc::new(move |event| codec.decode(event).map(&into));
+        self.insert(T::MESSAGE_TYPES, decoder);
+        self
+    }
+
+    fn insert(&mut self, message_types: &'static [&'static str], decoder: Decoder<'a, M>) {
+        for message_type in message_types {
+            if self
+                .decoders
+                .insert(message_type, decoder.clone())
+                .is_some()
+            {
+                panic!("the message type `{}` is registered twice", message_type);
+            }
+        }
+    }
+
+    /// Decodes the message by the decoder registered for its message type.
+    pub fn decode(&self, message: &EncodedEvent) -> Result<M, CodecError> {
+        let decoder = self
+            .decoders
+            .get(message.event_type.as_str())
+            .ok_or_else(|| CodecError::Unknown(message.event_type.clone()))?;
+        decoder(message)
+    }
+
+    /// Is the message type registered?
+    pub fn contains(&self, message_type: &str) -> bool {
+        self.decoders.contains_key(message_type)
+    }
+
+    /// The registered message types, in no particular order.
+    pub fn message_types(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.decoders.keys().copied()
+    }
+}
@@ -0,0 +1,383 @@
+#[cfg(feature = "tracing")]
+use std::fmt::Display;
+use std::future::Future;
+
+#[cfg(feature = "tracing")]
+use tracing::Instrument;
+
+use crate::aggregate::{
+    EventRepository, EventSourcedAggregate, StateRepository, StateStoredAggregate,
+};
+use crate::decider::{EventComputation, StateComputation};
+use crate::materialized_view::{MaterializedView, ViewStateRepository};
+use crate::saga::ActionComputation;
+use crate::saga_manager::{ActionPublisher, SagaManager};
+use crate::view::ViewStateComputation;
+#[cfg(feature = "tracing")]
+use crate::Identifier;
+
+/// Handler trait
+///
+/// Common shape of the application components, handling the input (command/event/action result) and producing the output.
+/// It is implemented by the [EventSourcedAggregate], [StateStoredAggregate], [MaterializedView] and [SagaManager], so they can be wrapped by the [Interceptor]s.
+///
+/// Generic parameters:
+///
+/// - `Input` - Command / Event / Action Result
+/// - `Output` - Result of the handling
+/// - `Error` - Error
+#[cfg(not(feature = "not-send-futures"))]
+pub trait Handler<Input, Output, Error> {
+    /// Handles the input.
+    /// Desugared `async fn handle(&self, input: &Input) -> Result<Output, Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn handle(&self, input: &Input) -> impl Future<Output = Result<Output, Error>> + Send;
+}
+
+/// Handler trait
+///
+/// Common shape of the application components, handling the input (command/event/action result) and producing the output.
+/// It is implemented by the [EventSourcedAggregate], [StateStoredAggregate], [MaterializedView] and [SagaManager], so they can be wrapped by the [Interceptor]s.
+///
+/// Generic parameters:
+///
+/// - `Input` - Command / Event / Action Result
+/// - `Output` - Result of the handling
+/// - `Error` - Error
+#[cfg(feature = "not-send-futures")]
+pub trait Handler<Input, Output, Error> {
+    /// Handles the input.
+    /// Desugared `async fn handle(&self, input: &Input) -> Result<Output, Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn handle(&self, input: &Input) -> impl Future<Output = Result<Output, Error>>;
+}
+
+/// Interceptor trait
+///
+/// Cross-cutting logic (validation, authorization, auditing, ...) around the [Handler]. All the hooks do nothing by default.
+///
+/// Generic parameters:
+///
+/// - `Input` - Command / Event / Action Result
+/// - `Output` - Result of the handling
+/// - `Error` - Error
+#[cfg(not(feature = "not-send-futures"))]
+pub trait Interceptor<Input, Output, Error> {
+    /// Runs before the input is handled. Returning the error rejects the input, and the handler is not called.
+    /// Desugared `async fn before(&self, input: &Input) -> Result<(), Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn before(&self, _input: &Input) -> impl Future<Output = Result<(), Error>> + Send {
+        async { Ok(()) }
+    }
+    /// Runs after the input is successfully handled.
+    /// Desugared `async fn after(&self, input: &Input, output: &Output);` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn after(&self, _input: &Input, _output: &Output) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+    /// Runs after the handling of the input failed, or the input is rejected by the inner interceptor.
+    /// Desugared `async fn on_error(&self, input: &Input, error: &Error);` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn on_error(&self, _input: &Input, _error: &Error) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+}
+
+/// Interceptor trait
+///
+/// Cross-cutting logic (validation, authorization, auditing, ...) around the [Handler]. All the hooks do nothing by default.
+///
+/// Generic parameters:
+///
+/// - `Input` - Command / Event / Action Result
+/// - `Output` - Result of the handling
+/// - `Error` - Error
+#[cfg(feature = "not-send-futures")]
+pub trait Interceptor<Input, Output, Error> {
+    /// Runs before the input is handled. Returning the error rejects the input, and the handler is not called.
+    /// Desugared `async fn before(&self, input: &Input) -> Result<(), Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn before(&self, _input: &Input) -> impl Future<Output = Result<(), Error>> {
+        async { Ok(()) }
+    }
+    /// Runs after the input is successfully handled.
+    /// Desugared `async fn after(&self, input: &Input, output: &Output);` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn after(&self, _input: &Input, _output: &Output) -> impl Future<Output = ()> {
+        async {}
+    }
+    /// Runs after the handling of the input failed, or the input is rejected by the inner interceptor.
+    /// Desugared `async fn on_error(&self, input: &Input, error: &Error);` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn on_error(&self, _input: &Input, _error: &Error) -> impl Future<Output = ()> {
+        async {}
+    }
+}
+
+/// [Handler] wrapped by the [Interceptor].
+///
+/// It is a [Handler] itself, so the interceptors can be stacked. The last added interceptor is the outermost one:
+/// its `before` hook runs first, and its `after`/`on_error` hooks run last.
+///
+/// Generic parameters:
+///
+/// - `H` - Inner handler
+/// - `I` - Interceptor
+pub struct Intercepted<H, I> {
+    handler: H,
+    interceptor: I,
+}
+
+impl<H, I> Intercepted<H, I> {
+    /// Creates a new instance of [Intercepted], wrapping the `handler` with the `interceptor`.
+    pub fn new(handler: H, interceptor: I) -> Self {
+        Intercepted {
+            handler,
+            interceptor,
+        }
+    }
+    /// Wraps this handler with one more `interceptor`, which becomes the outermost one.
+    pub fn intercept<I2>(self, interceptor: I2) -> Intercepted<Self, I2> {
+        Intercepted::new(self, interceptor)
+    }
+    /// Returns the inner handler.
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<H, I, Input, Output, Error> Handler<Input, Output, Error> for Intercepted<H, I>
+where
+    H: Handler<Input, Output, Error> + Sync,
+    I: Interceptor<Input, Output, Error> + Sync,
+    Input: Sync,
+    Output: Send,
+    Error: Send,
+{
+    /// Handles the input by the inner handler, running the hooks of the interceptor around it.
+    async fn handle(&self, input: &Input) -> Result<Output, Error> {
+        let result = match self.interceptor.before(input).await {
+            Ok(()) => self.handler.handle(input).await,
+            Err(error) => Err(error),
+        };
+        match &result {
+            Ok(output) => self.interceptor.after(input, output).await,
+            Err(error) => self.interceptor.on_error(input, error).await,
+        }
+        result
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<H, I, Input, Output, Error> Handler<Input, Output, Error> for Intercepted<H, I>
+where
+    H: Handler<Input, Output, Error>,
+    I: Interceptor<Input, Output, Error>,
+{
+    /// Handles the input by the inner handler, running the hooks of the interceptor around it.
+    async fn handle(&self, input: &Input) -> Result<Output, Error> {
+        let result = match self.interceptor.before(input).await {
+            Ok(()) => self.handler.handle(input).await,
+            Err(error) => Err(error),
+        };
+        match &result {
+            Ok(output) => self.interceptor.after(input, output).await,
+            Err(error) => self.interceptor.on_error(input, error).await,
+        }
+        result
+    }
+}
+
+/// [Handler] wrapped by the `tracing` span.
+///
+/// The application components open their own `handle` span, with the number of the loaded/produced events and the error flag (and, by their `handle_traced` methods, the [Identifier] of the input and the version).
+/// They can not record the error message, as their `Error` type is not bound to [Display].
+/// [Traced] opens the outer `message` span with the [Identifier] of the input, and records the error message of the failed handling.
+///
+/// Generic parameters:
+///
+/// - `H` - Inner handler
+#[cfg(feature = "tracing")]
+pub struct Traced<H> {
+    handler: H,
+}
+
+#[cfg(feature = "tracing")]
+impl<H> Traced<H> {
+    /// Creates a new instance of [Traced], wrapping the `handler`.
+    pub fn new(handler: H) -> Self {
+        Traced { handler }
+    }
+    /// Returns the inner handler.
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+}
+
+#[cfg(all(feature = "tracing", not(feature = "not-send-futures")))]
+impl<H, Input, Output, Error> Handler<Input, Output, Error> for Traced<H>
+where
+    H: Handler<Input, Output, Error> + Sync,
+    Input: Identifier + Sync,
+    Output: Send,
+    Error: Display + Send,
+{
+    /// Handles the input by the inner handler, within the `message` span carrying the identifier of the input.
+    async fn handle(&self, input: &Input) -> Result<Output, Error> {
+        let span = tracing::info_span!(
+            target: "fmodel",
+            "message",
+            identifier = %input.identifier(),
+            error = tracing::field::Empty
+        );
+        let result = self.handler.handle(input).instrument(span.clone()).await;
+        if let Err(error) = &result {
+            span.record("error", tracing::field::display(error));
+        }
+        result
+    }
+}
+
+#[cfg(all(feature = "tracing", feature = "not-send-futures"))]
+impl<H, Input, Output, Error> Handler<Input, Output, Error> for Traced<H>
+where
+    H: Handler<Input, Output, Error>,
+    Input: Identifier,
+    Error: Display,
+{
+    /// Handles the input by the inner handler, within the `message` span carrying the identifier of the input.
+    async fn handle(&self, input: &Input) -> Result<Output, Error> {
+        let span = tracing::info_span!(
+            target: "fmodel",
+            "message",
+            identifier = %input.identifier(),
+            error = tracing::field::Empty
+        );
+        let result = self.handler.handle(input).instrument(span.clone()).await;
+        if let Err(error) = &result {
+            span.record("error", tracing::field::display(error));
+        }
+        result
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<C, S, E, Repository, Decider, Version, Error> Handler<C, Vec<(E, Version)>, Error>
+    for EventSourcedAggregate<C, S, E, Repository, Decider, Version, Error>
+where
+    Repository: EventRepository<C, E, Version, Error> + Sync,
+    Decider: EventComputation<C, S, E, Error> + Sync,
+    C: Sync,
+    S: Send + Sync,
+    E: Send + Sync,
+    Version: Send + Sync,
+    Error: Send + Sync,
+{
+    /// Handles the command, see [EventSourcedAggregate::handle].
+    async fn handle(&self, command: &C) -> Result<Vec<(E, Version)>, Error> {
+        EventSourcedAggregate::handle(self, command).await
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<C, S, E, Repository, Decider, Version, Error> Handler<C, Vec<(E, Version)>, Error>
+    for EventSourcedAggregate<C, S, E, Repository, Decider, Version, Error>
+where
+    Repository: EventRepository<C, E, Version, Error>,
+    Decider: EventComputation<C, S, E, Error>,
+{
+    /// Handles the command, see [EventSourcedAggregate::handle].
+    async fn handle(&self, command: &C) -> Result<Vec<(E, Version)>, Error> {
+        EventSourcedAggregate::handle(self, command).await
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<C, S, E, Repository, Decider, Version, Error> Handler<C, (S, Version), Error>
+    for StateStoredAggregate<C, S, E, Repository, Decider, Version, Error>
+where
+    Repository: StateRepository<C, S, Version, Error> + Sync,
+    Decider: StateComputation<C, S, E, Error> + Sync,
+    C: Sync,
+    S: Send + Sync,
+    E: Send + Sync,
+    Version: Send + Sync,
+    Error: Send + Sync,
+{
+    /// Handles the command, see [StateStoredAggregate::handle].
+    async fn handle(&self, command: &C) -> Result<(S, Version), Error> {
+        StateStoredAggregate::handle(self, command).await
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<C, S, E, Repository, Decider, Version, Error> Handler<C, (S, Version), Error>
+    for StateStoredAggregate<C, S, E, Repository, Decider, Version, Error>
+where
+    Repository: StateRepository<C, S, Version, Error>,
+    Decider: StateComputation<C, S, E, Error>,
+{
+    /// Handles the command, see [StateStoredAggregate::handle].
+    async fn handle(&self, command: &C) -> Result<(S, Version), Error> {
+        StateStoredAggregate::handle(self, command).await
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<S, E, Repository, View, Error> Handler<E, S, Error>
+    for MaterializedView<S, E, Repository, View, Error>
+where
+    Repository: ViewStateRepository<E, S, Error> + Sync,
+    View: ViewStateComputation<E, S> + Sync,
+    E: Send + Sync,
+    S: Send + Sync,
+    Error: Send + Sync,
+{
+    /// Handles the event, see [MaterializedView::handle].
+    async fn handle(&self, event: &E) -> Result<S, Error> {
+        MaterializedView::handle(self, event).await
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<S, E, Repository, View, Error> Handler<E, S, Error>
+    for MaterializedView<S, E, Repository, View, Error>
+where
+    Repository: ViewStateRepository<E, S, Error>,
+    View: ViewStateComputation<E, S>,
+{
+    /// Handles the event, see [MaterializedView::handle].
+    async fn handle(&self, event: &E) -> Result<S, Error> {
+        MaterializedView::handle(self, event).await
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<A, AR, Publisher, Saga, Error> Handler<AR, Vec<A>, Error>
+    for SagaManager<A, AR, Publisher, Saga, Error>
+where
+    Publisher: ActionPublisher<A, Error> + Sync,
+    Saga: ActionComputation<AR, A> + Sync,
+    A: Send + Sync,
+    AR: Sync,
+    Error: Send + Sync,
+{
+    /// Handles the action result, see [SagaManager::handle].
+    async fn handle(&self, action_result: &AR) -> Result<Vec<A>, Error> {
+        SagaManager::handle(self, action_result).await
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<A, AR, Publisher, Saga, Error> Handler<AR, Vec<A>, Error>
+    for SagaManager<A, AR, Publisher, Saga, Error>
+where
+    Publisher: ActionPublisher<A, Error>,
+    Saga: ActionComputation<AR, A>,
+{
+    /// Handles the action result, see [SagaManager::handle].
+    async fn handle(&self, action_result: &AR) -> Result<Vec<A>, Error> {
+        SagaManager::handle(self, action_result).await
+    }
+}
@@ -0,0 +1,277 @@
+use std::future::Future;
+use std::marker::PhantomData;
+
+use crate::aggregate::{EventRepository, StateRepository};
+use crate::projection::DEFAULT_BATCH_SIZE;
+use crate::saga_manager::ActionPublisher;
+
+/// Outbox Event Repository trait
+///
+/// Event repository that is able to store the outgoing messages (actions/commands/integration events) in the same transaction with the events.
+///
+/// Generic parameters:
+///
+/// - `C` - Command
+/// - `E` - Event
+/// - `A` - Outgoing message / Action
+/// - `Version` - Version/Offset/Sequence number
+/// - `Error` - Error
+#[cfg(not(feature = "not-send-futures"))]
+pub trait OutboxEventRepository<C, E, A, Version, Error>:
+    EventRepository<C, E, Version, Error>
+{
+    /// Saves events and the outgoing messages to the outbox, atomically.
+    /// Like [EventRepository::save_after], the events are saved only if the stream of the command is still at the `latest_version`.
+    /// Desugared `async fn save_with_outbox(&self, command: &C, events: &[E], latest_version: &Option<Version>, messages: &[A]) -> Result<Vec<(E, Version)>, Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn save_with_outbox(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<Version>,
+        messages: &[A],
+    ) -> impl Future<Output = Result<Vec<(E, Version)>, Error>> + Send;
+}
+
+/// Outbox State Repository trait
+///
+/// State repository that is able to store the outgoing messages (actions/commands/integration events) in the same transaction with the state.
+///
+/// Generic parameters:
+///
+/// - `C` - Command
+/// - `S` - State
+/// - `A` - Outgoing message / Action
+/// - `Version` - Version
+/// - `Error` - Error
+#[cfg(not(feature = "not-send-futures"))]
+pub trait OutboxStateRepository<C, S, A, Version, Error>:
+    StateRepository<C, S, Version, Error>
+{
+    /// Saves state and the outgoing messages to the outbox, atomically.
+    /// Desugared `async fn save_with_outbox(&self, state: &S, version: &Option<Version>, messages: &[A]) -> Result<(S, Version), Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn save_with_outbox(
+        &self,
+        state: &S,
+        version: &Option<Version>,
+        messages: &[A],
+    ) -> impl Future<Output = Result<(S, Version), Error>> + Send;
+}
+
+/// Outbox trait
+///
+/// Reads the pending outgoing messages, and marks them as delivered.
+///
+/// Generic parameters:
+///
+/// - `A` - Outgoing message / Action
+/// - `Id` - Identifier of the message in the outbox
+/// - `Error` - Error
+#[cfg(not(feature = "not-send-futures"))]
+pub trait Outbox<A, Id, Error> {
+    /// Fetches at most `limit` pending messages, in the order they were stored in.
+    /// Desugared `async fn fetch_pending(&self, limit: usize) -> Result<Vec<(Id, A)>, Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn fetch_pending(
+        &self,
+        limit: usize,
+    ) -> impl Future<Output = Result<Vec<(Id, A)>, Error>> + Send;
+    /// Marks the messages as delivered, so they are not fetched again.
+    /// Desugared `async fn acknowledge(&self, ids: &[Id]) -> Result<(), Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn acknowledge(&self, ids: &[Id]) -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+/// Outbox Event Repository trait
+///
+/// Event repository that is able to store the outgoing messages (actions/commands/integration events) in the same transaction with the events.
+///
+/// Generic parameters:
+///
+/// - `C` - Command
+/// - `E` - Event
+/// - `A` - Outgoing message / Action
+/// - `Version` - Version/Offset/Sequence number
+/// - `Error` - Error
+#[cfg(feature = "not-send-futures")]
+pub trait OutboxEventRepository<C, E, A, Version, Error>:
+    EventRepository<C, E, Version, Error>
+{
+    /// Saves events and the outgoing messages to the outbox, atomically.
+    /// Like [EventRepository::save_after], the events are saved only if the stream of the command is still at the `latest_version`.
+    /// Desugared `async fn save_with_outbox(&self, command: &C, events: &[E], latest_version: &Option<Version>, messages: &[A]) -> Result<Vec<(E, Version)>, Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn save_with_outbox(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<Version>,
+        messages: &[A],
+    ) -> impl Future<Output = Result<Vec<(E, Version)>, Error>>;
+}
+
+/// Outbox State Repository trait
+///
+/// State repository that is able to store the outgoing messages (actions/commands/integration events) in the same transaction with the state.
+///
+/// Generic parameters:
+///
+/// - `C` - Command
+/// - `S` - State
+/// - `A` - Outgoing message / Action
+/// - `Version` - Version
+/// - `Error` - Error
+#[cfg(feature = "not-send-futures")]
+pub trait OutboxStateRepository<C, S, A, Version, Error>:
+    StateRepository<C, S, Version, Error>
+{
+    /// Saves state and the outgoing messages to the outbox, atomically.
+    /// Desugared `async fn save_with_outbox(&self, state: &S, version: &Option<Version>, messages: &[A]) -> Result<(S, Version), Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn save_with_outbox(
+        &self,
+        state: &S,
+        version: &Option<Version>,
+        messages: &[A],
+    ) -> impl Future<Output = Result<(S, Version), Error>>;
+}
+
+/// Outbox trait
+///
+/// Reads the pending outgoing messages, and marks them as delivered.
+///
+/// Generic parameters:
+///
+/// - `A` - Outgoing message / Action
+/// - `Id` - Identifier of the message in the outbox
+/// - `Error` - Error
+#[cfg(feature = "not-send-futures")]
+pub trait Outbox<A, Id, Error> {
+    /// Fetches at most `limit` pending messages, in the order they were stored in.
+    /// Desugared `async fn fetch_pending(&self, limit: usize) -> Result<Vec<(Id, A)>, Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn fetch_pending(&self, limit: usize) -> impl Future<Output = Result<Vec<(Id, A)>, Error>>;
+    /// Marks the messages as delivered, so they are not fetched again.
+    /// Desugared `async fn acknowledge(&self, ids: &[Id]) -> Result<(), Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn acknowledge(&self, ids: &[Id]) -> impl Future<Output = Result<(), Error>>;
+}
+
+/// Outbox Relay.
+///
+/// It is draining the [Outbox] into the [ActionPublisher].
+/// Messages are acknowledged only after they are published, so they are delivered `at least once`: a crash between the publishing and the acknowledgement publishes them again.
+///
+/// Generic parameters:
+///
+/// - `A` - Outgoing message / Action
+/// - `Id` - Identifier of the message in the outbox
+/// - `Storage` - Outbox storage
+/// - `Publisher` - Action Publisher
+/// - `Error` - Error
+pub struct OutboxRelay<A, Id, Storage, Publisher, Error>
+where
+    Storage: Outbox<A, Id, Error>,
+    Publisher: ActionPublisher<A, Error>,
+{
+    outbox: Storage,
+    action_publisher: Publisher,
+    batch_size: usize,
+    _marker: PhantomData<(A, Id, Error)>,
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<A, Id, Storage, Publisher, Error> OutboxRelay<A, Id, Storage, Publisher, Error>
+where
+    Storage: Outbox<A, Id, Error> + Sync,
+    Publisher: ActionPublisher<A, Error> + Sync,
+    A: Sync,
+    Id: Sync,
+    Error: Sync,
+{
+    /// Creates a new instance of [OutboxRelay].
+    pub fn new(outbox: Storage, action_publisher: Publisher) -> Self {
+        OutboxRelay {
+            outbox,
+            action_publisher,
+            batch_size: DEFAULT_BATCH_SIZE,
+            _marker: PhantomData,
+        }
+    }
+    /// Sets the maximum number of messages fetched from the [Outbox] at once.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+    /// Publishes the next batch of pending messages, and acknowledges them once published.
+    /// Returns the number of the relayed messages. Zero means that the outbox is drained.
+    pub async fn relay(&self) -> Result<usize, Error> {
+        let pending = self.outbox.fetch_pending(self.batch_size).await?;
+        if pending.is_empty() {
+            return Ok(0);
+        }
+        let (ids, messages): (Vec<Id>, Vec<A>) = pending.into_iter().unzip();
+        self.action_publisher.publish(&messages).await?;
+        self.outbox.acknowledge(&ids).await?;
+        Ok(ids.len())
+    }
+    /// Publishes all the pending messages, until the outbox is drained.
+    /// Returns the number of the relayed messages.
+    pub async fn drain(&self) -> Result<usize, Error> {
+        let mut total = 0;
+        loop {
+            let count = self.relay().await?;
+            if count == 0 {
+                return Ok(total);
+            }
+            total += count;
+        }
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<A, Id, Storage, Publisher, Error> OutboxRelay<A, Id, Storage, Publisher, Error>
+where
+    Storage: Outbox<A, Id, Error>,
+    Publisher: ActionPublisher<A, Error>,
+{
+    /// Creates a new instance of [OutboxRelay].
+    pub fn new(outbox: Storage, action_publisher: Publisher) -> Self {
+        OutboxRelay {
+            outbox,
+            action_publisher,
+            batch_size: DEFAULT_BATCH_SIZE,
+            _marker: PhantomData,
+        }
+    }
+    /// Sets the maximum number of messages fetched from the [Outbox] at once.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+    /// Publishes the next batch of pending messages, and acknowledges them once published.
+    /// Returns the number of the relayed messages. Zero means that the outbox is drained.
+    pub async fn relay(&self) -> Result<usize, Error> {
+        let pending = self.outbox.fetch_pending(self.batch_size).await?;
+        if pending.is_empty() {
+            return Ok(0);
+        }
+        let (ids, messages): (Vec<Id>, Vec<A>) = pending.into_iter().unzip();
+        self.action_publisher.publish(&messages).await?;
+        self.outbox.acknowledge(&ids).await?;
+        Ok(ids.len())
+    }
+    /// Publishes all the pending messages, until the outbox is drained.
+    /// Returns the number of the relayed messages.
+    pub async fn drain(&self) -> Result<usize, Error> {
+        let mut total = 0;
+        loop {
+            let count = self.relay().await?;
+            if count == 0 {
+                return Ok(total);
+            }
+            total += count;
+        }
+    }
+}
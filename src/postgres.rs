@@ -0,0 +1,740 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::future::poll_fn;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{watch, Mutex, MutexGuard};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::tls::TlsStream;
+use tokio_postgres::{AsyncMessage, Client, Connection, NoTls, Row};
+
+use crate::aggregate::{EventRepository, StateRepository};
+use crate::materialized_view::{VersionedViewStateRepository, ViewStateRepository};
+use crate::projection::EventSource;
+use crate::Identifier;
+
+/// The schema of the PostgreSQL event store.
+///
+/// - `position` - global position of the event, in the order the events are inserted. It starts at `1`.
+/// - `stream_id` - [Identifier] of the event stream
+/// - `version` - version of the event, within the stream. It starts at `0`.
+/// - `payload` - the event, encoded as JSON
+/// - `transaction_id` - identifier of the transaction the event is appended by. The [PostgresEventSource] orders the events by it, and the position.
+///
+/// The `(stream_id, version)` pair is unique, so the concurrent appends of the same version to the stream conflict.
+/// The `transaction_id` column is added to the `events` table created with no such column.
+pub const EVENTS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS events (
+    position BIGSERIAL PRIMARY KEY,
+    stream_id TEXT NOT NULL,
+    version BIGINT NOT NULL,
+    payload JSONB NOT NULL,
+    transaction_id XID8 NOT NULL DEFAULT pg_current_xact_id(),
+    UNIQUE (stream_id, version)
+);
+ALTER TABLE events ADD COLUMN IF NOT EXISTS transaction_id XID8 NOT NULL DEFAULT pg_current_xact_id();
+CREATE INDEX IF NOT EXISTS events_transaction_id_position ON events (transaction_id, position)";
+
+/// The schema of the PostgreSQL state store.
+///
+/// - `id` - [Identifier] of the state
+/// - `version` - version of the state. It starts at `0`, and is incremented by every save.
+/// - `payload` - the state, encoded as JSON
+pub const STATES_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS states (
+    id TEXT PRIMARY KEY,
+    version BIGINT NOT NULL,
+    payload JSONB NOT NULL
+)";
+
+/// The schema of the PostgreSQL view store.
+///
+/// - `id` - [Identifier] of the view state
+/// - `version` - version of the last event applied to the view state, or the number of the saves (counting from `0`) if the events are not versioned
+/// - `payload` - the view state, encoded as JSON
+pub const VIEWS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS views (
+    id TEXT PRIMARY KEY,
+    version BIGINT NOT NULL,
+    payload JSONB NOT NULL
+)";
+
+/// The channel notified with the global position of the last event, once the events are appended.
+/// The channels are shared by the schemas of the database, so use the dedicated database per event store.
+pub const EVENTS_CHANNEL: &str = "fmodel_events";
+
+/// The error of the PostgreSQL stores.
+#[derive(Debug)]
+pub enum PostgresError {
+    /// The database error.
+    Database(tokio_postgres::Error),
+    /// The error of encoding/decoding the payload.
+    Serialization(serde_json::Error),
+    /// The version was already taken by the concurrent write of the stream, identified by the identifier.
+    Conflict(String),
+    /// The connection listening for the notifications is closed.
+    Disconnected,
+}
+
+impl Display for PostgresError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PostgresError::Database(error) => write!(f, "database error: {}", error),
+            PostgresError::Serialization(error) => write!(f, "serialization error: {}", error),
+            PostgresError::Conflict(identifier) => {
+                write!(f, "concurrent modification of `{}`", identifier)
+            }
+            PostgresError::Disconnected => write!(f, "the listening connection is closed"),
+        }
+    }
+}
+
+impl std::error::Error for PostgresError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PostgresError::Database(error) => Some(error),
+            PostgresError::Serialization(error) => Some(error),
+            PostgresError::Conflict(_) | PostgresError::Disconnected => None,
+        }
+    }
+}
+
+impl From<tokio_postgres::Error> for PostgresError {
+    fn from(error: tokio_postgres::Error) -> Self {
+        PostgresError::Database(error)
+    }
+}
+
+impl From<serde_json::Error> for PostgresError {
+    fn from(error: serde_json::Error) -> Self {
+        PostgresError::Serialization(error)
+    }
+}
+
+/// Connects to the database, with no TLS, and spawns the connection on the `tokio` runtime.
+/// The connection errors are reported by the queries of the client, once the connection is closed.
+async fn connect(config: &str) -> Result<Client, PostgresError> {
+    let (client, connection) = tokio_postgres::connect(config, NoTls).await?;
+    tokio::spawn(async move {
+        let _ = connection.await;
+    });
+    Ok(client)
+}
+
+/// Decodes the payload (the first column) and the version/position (the second column) of the row.
+fn decode<T: DeserializeOwned>(row: &Row) -> Result<(T, i64), PostgresError> {
+    let payload: serde_json::Value = row.try_get(0)?;
+    Ok((serde_json::from_value(payload)?, row.try_get(1)?))
+}
+
+/// Fetches the payload and the version of the row, identified by the `id`, from the `table`.
+async fn fetch_row<S: DeserializeOwned>(
+    client: &Client,
+    table: &str,
+    id: &str,
+) -> Result<Option<(S, i64)>, PostgresError> {
+    let row = client
+        .query_opt(
+            &format!("SELECT payload, version FROM {} WHERE id = $1", table),
+            &[&id],
+        )
+        .await?;
+    row.as_ref().map(decode).transpose()
+}
+
+/// Maps the unique constraint violation to the [PostgresError::Conflict].
+fn conflict(error: tokio_postgres::Error, identifier: &str) -> PostgresError {
+    match error.code() {
+        Some(&SqlState::UNIQUE_VIOLATION) => PostgresError::Conflict(identifier.to_string()),
+        _ => PostgresError::Database(error),
+    }
+}
+
+/// PostgreSQL Event Repository.
+///
+/// [EventRepository] on the PostgreSQL database, storing the events in the `events` table (see [EVENTS_SCHEMA]).
+/// The events are encoded as JSON, and grouped into the streams by their [Identifier]. The command is routed to the stream by its [Identifier], too.
+/// The JSON is fixed, so the events stay queryable as `JSONB`; the [EventCodec](crate::codec::EventCodec) is not used by the store.
+///
+/// The new events are appended in the single transaction, even if they belong to the different streams, with the next versions of their streams.
+/// The transaction notifies the [EVENTS_CHANNEL] on commit, so the [PostgresEventSource] wakes up.
+///
+/// The aggregates save the events with [EventRepository::save_after], so the events of the command stream are inserted right after the version the command was decided on.
+/// If the concurrent writer has taken the version already, the unique `(stream_id, version)` constraint rejects the save with [PostgresError::Conflict],
+/// and the command can be retried on the fresh state. The events of the other streams are appended to the end of their streams, and conflict the same way.
+/// The appends take no table lock, so the appends of the different streams do not wait for each other.
+///
+/// The client is guarded by the mutex, so the queries of the repository run one at a time. Use the repository per connection to scale.
+pub struct PostgresEventRepository {
+    client: Mutex<Client>,
+}
+
+impl PostgresEventRepository {
+    /// Creates a new instance of [PostgresEventRepository] on the `client`, creating the `events` table if it does not exist.
+    pub async fn new(client: Client) -> Result<Self, PostgresError> {
+        client.batch_execute(EVENTS_SCHEMA).await?;
+        Ok(PostgresEventRepository {
+            client: Mutex::new(client),
+        })
+    }
+    /// Connects to the database, configured by the connection string (e.g. `host=localhost user=postgres`), with no TLS.
+    /// The connection is spawned on the `tokio` runtime.
+    pub async fn connect(config: &str) -> Result<Self, PostgresError> {
+        PostgresEventRepository::new(connect(config).await?).await
+    }
+    /// Returns the client, locked for the exclusive use.
+    pub async fn client(&self) -> MutexGuard<'_, Client> {
+        self.client.lock().await
+    }
+    /// Fetches the events of the stream, identified by the `identifier`, ordered by their version.
+    async fn fetch_stream<E: DeserializeOwned>(
+        &self,
+        identifier: &str,
+    ) -> Result<Vec<(E, i64)>, PostgresError> {
+        self.client()
+            .await
+            .query(
+                "SELECT payload, version FROM events WHERE stream_id = $1 ORDER BY version",
+                &[&identifier],
+            )
+            .await?
+            .iter()
+            .map(decode)
+            .collect()
+    }
+    /// Appends the events to their streams, in the single transaction, and notifies the [EVENTS_CHANNEL].
+    /// If the `expected` stream is given, its events are inserted right after the expected version, so the concurrent append conflicts.
+    async fn append<E: Identifier + Serialize + Clone>(
+        &self,
+        events: &[E],
+        expected: Option<(&str, Option<i64>)>,
+    ) -> Result<Vec<(E, i64)>, PostgresError> {
+        let mut client = self.client().await;
+        let transaction = client.transaction().await?;
+        let mut saved_events = Vec::with_capacity(events.len());
+        let mut versions: HashMap<String, i64> = HashMap::new();
+        let mut position: Option<i64> = None;
+        for event in events {
+            let identifier = event.identifier();
+            let version = match (versions.get(&identifier), expected) {
+                (Some(version), _) => version + 1,
+                (None, Some((stream_id, expected_version))) if stream_id == identifier => {
+                    expected_version.map_or(0, |version| version + 1)
+                }
+                (None, _) => transaction
+                    .query_one(
+                        "SELECT MAX(version) FROM events WHERE stream_id = $1",
+                        &[&identifier],
+                    )
+                    .await?
+                    .try_get::<_, Option<i64>>(0)?
+                    .map_or(0, |version| version + 1),
+            };
+            let row = transaction
+                .query_one(
+                    "INSERT INTO events (stream_id, version, payload) VALUES ($1, $2, $3) RETURNING position",
+                    &[&identifier, &version, &serde_json::to_value(event)?],
+                )
+                .await
+                .map_err(|error| conflict(error, &identifier))?;
+            position = Some(row.try_get(0)?);
+            versions.insert(identifier, version);
+            saved_events.push((event.clone(), version));
+        }
+        if let Some(position) = position {
+            transaction
+                .execute(
+                    "SELECT pg_notify($1, $2)",
+                    &[&EVENTS_CHANNEL, &position.to_string()],
+                )
+                .await?;
+        }
+        transaction.commit().await?;
+        Ok(saved_events)
+    }
+    /// Returns the latest version of the stream, identified by the `identifier`.
+    async fn latest_version(&self, identifier: &str) -> Result<Option<i64>, PostgresError> {
+        Ok(self
+            .client()
+            .await
+            .query_one(
+                "SELECT MAX(version) FROM events WHERE stream_id = $1",
+                &[&identifier],
+            )
+            .await?
+            .try_get(0)?)
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<C, E, Error> EventRepository<C, E, i64, Error> for PostgresEventRepository
+where
+    C: Identifier + Sync,
+    E: Identifier + Serialize + DeserializeOwned + Clone + Send + Sync,
+    Error: From<PostgresError> + Send,
+{
+    /// Fetches the events of the stream, identified by the command [Identifier].
+    async fn fetch_events(&self, command: &C) -> Result<Vec<(E, i64)>, Error> {
+        Ok(self.fetch_stream(&command.identifier()).await?)
+    }
+    /// Appends the events to their streams, with the next versions.
+    async fn save(&self, events: &[E]) -> Result<Vec<(E, i64)>, Error> {
+        Ok(self.append(events, None).await?)
+    }
+    /// Appends the events to their streams, right after the `latest_version` of the command stream.
+    async fn save_after(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<i64>,
+    ) -> Result<Vec<(E, i64)>, Error> {
+        Ok(self
+            .append(events, Some((&command.identifier(), *latest_version)))
+            .await?)
+    }
+    /// Returns the latest version of the event stream.
+    async fn version_provider(&self, event: &E) -> Result<Option<i64>, Error> {
+        Ok(self.latest_version(&event.identifier()).await?)
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<C, E, Error> EventRepository<C, E, i64, Error> for PostgresEventRepository
+where
+    C: Identifier,
+    E: Identifier + Serialize + DeserializeOwned + Clone,
+    Error: From<PostgresError>,
+{
+    /// Fetches the events of the stream, identified by the command [Identifier].
+    async fn fetch_events(&self, command: &C) -> Result<Vec<(E, i64)>, Error> {
+        Ok(self.fetch_stream(&command.identifier()).await?)
+    }
+    /// Appends the events to their streams, with the next versions.
+    async fn save(&self, events: &[E]) -> Result<Vec<(E, i64)>, Error> {
+        Ok(self.append(events, None).await?)
+    }
+    /// Appends the events to their streams, right after the `latest_version` of the command stream.
+    async fn save_after(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<i64>,
+    ) -> Result<Vec<(E, i64)>, Error> {
+        Ok(self
+            .append(events, Some((&command.identifier(), *latest_version)))
+            .await?)
+    }
+    /// Returns the latest version of the event stream.
+    async fn version_provider(&self, event: &E) -> Result<Option<i64>, Error> {
+        Ok(self.latest_version(&event.identifier()).await?)
+    }
+}
+
+/// PostgreSQL Event Source.
+///
+/// [EventSource] on the `events` table (see [EVENTS_SCHEMA]), filled by the [PostgresEventRepository]. The position is the global position of the event.
+/// It listens to the [EVENTS_CHANNEL], so [EventSource::wait_for_events] returns as soon as the new events are committed, with no polling of the table.
+///
+/// The concurrent appends may commit in the other order than the one of their positions. So, the events are read in the order of their transactions (and the positions within the transaction),
+/// and only once no older transaction is in progress - the event committed late is never skipped. The positions read are not increasing, then; use them as the opaque cursor.
+/// The long-running transaction of the database holds the events appended after it started back, until it ends.
+///
+/// The connection is dedicated to the event source: it is driven by the spawned `tokio` task, forwarding the notifications.
+pub struct PostgresEventSource {
+    client: Client,
+    notified: watch::Receiver<u64>,
+}
+
+impl PostgresEventSource {
+    /// Connects to the database, configured by the connection string (e.g. `host=localhost user=postgres`), with no TLS, and listens to the [EVENTS_CHANNEL].
+    pub async fn connect(config: &str) -> Result<Self, PostgresError> {
+        let (client, connection) = tokio_postgres::connect(config, NoTls).await?;
+        PostgresEventSource::new(client, connection).await
+    }
+    /// Creates a new instance of [PostgresEventSource] on the `client` and its `connection` (e.g. the TLS one), and listens to the [EVENTS_CHANNEL].
+    /// The connection is spawned on the `tokio` runtime. The `events` table is created if it does not exist.
+    pub async fn new<S, T>(
+        client: Client,
+        mut connection: Connection<S, T>,
+    ) -> Result<Self, PostgresError>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+        T: AsyncRead + AsyncWrite + TlsStream + Unpin + Send + 'static,
+    {
+        let (sender, notified) = watch::channel(0);
+        tokio::spawn(async move {
+            while let Some(Ok(message)) = poll_fn(|cx| connection.poll_message(cx)).await {
+                if let AsyncMessage::Notification(_) = message {
+                    sender.send_modify(|notified| *notified += 1);
+                }
+            }
+        });
+        client.batch_execute(EVENTS_SCHEMA).await?;
+        client
+            .batch_execute(&format!("LISTEN {}", EVENTS_CHANNEL))
+            .await?;
+        Ok(PostgresEventSource { client, notified })
+    }
+    /// Returns the client.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+    /// Reads at most `limit` events stored after the `position`, in the order of their transactions.
+    /// The events of the transactions not older than the oldest transaction in progress are not read yet.
+    async fn read<E: DeserializeOwned>(
+        &self,
+        position: &Option<i64>,
+        limit: usize,
+    ) -> Result<Vec<(E, i64)>, PostgresError> {
+        self.client
+            .query(
+                &format!(
+                    "SELECT payload, position FROM events WHERE {} AND {} ORDER BY transaction_id, position LIMIT $2",
+                    AFTER_POSITION, COMMITTED
+                ),
+                &[&position.unwrap_or(0), &(limit as i64)],
+            )
+            .await?
+            .iter()
+            .map(decode)
+            .collect()
+    }
+    /// Counts the events stored after the `position`.
+    async fn count(&self, position: &Option<i64>) -> Result<usize, PostgresError> {
+        let row = self
+            .client
+            .query_one(
+                &format!("SELECT COUNT(*) FROM events WHERE {}", AFTER_POSITION),
+                &[&position.unwrap_or(0)],
+            )
+            .await?;
+        let count: i64 = row.try_get(0)?;
+        Ok(count as usize)
+    }
+    /// Waits until the event after the `position` can be read.
+    /// The events held back by the older transaction in progress are polled for, every [HOLD_BACK_INTERVAL].
+    async fn wait(&self, position: &Option<i64>) -> Result<(), PostgresError> {
+        let position = position.unwrap_or(0);
+        let mut notified = self.notified.clone();
+        loop {
+            notified.borrow_and_update();
+            let row = self
+                .client
+                .query_one(
+                    &format!(
+                        "SELECT EXISTS (SELECT 1 FROM events WHERE {} AND {}), EXISTS (SELECT 1 FROM events WHERE {})",
+                        AFTER_POSITION, COMMITTED, AFTER_POSITION
+                    ),
+                    &[&position],
+                )
+                .await?;
+            let (readable, stored): (bool, bool) = (row.try_get(0)?, row.try_get(1)?);
+            if readable {
+                return Ok(());
+            }
+            if stored {
+                tokio::time::sleep(HOLD_BACK_INTERVAL).await;
+            } else {
+                notified
+                    .changed()
+                    .await
+                    .map_err(|_| PostgresError::Disconnected)?;
+            }
+        }
+    }
+}
+
+/// The condition selecting the events after the position `$1`, in the order of their transactions.
+const AFTER_POSITION: &str = "(transaction_id, position) > (COALESCE((SELECT transaction_id FROM events WHERE position = $1), '0'::xid8), $1)";
+
+/// The condition selecting the events of the transactions older than the oldest transaction in progress.
+/// No event can be committed before them, anymore.
+const COMMITTED: &str = "transaction_id < pg_snapshot_xmin(pg_current_snapshot())";
+
+/// The interval the [PostgresEventSource] polls for the committed events, held back by the older transaction in progress.
+pub const HOLD_BACK_INTERVAL: Duration = Duration::from_millis(10);
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<E, Error> EventSource<E, i64, Error> for PostgresEventSource
+where
+    E: DeserializeOwned + Send,
+    Error: From<PostgresError> + Send,
+{
+    /// Reads at most `limit` events stored after the `position`, in the global order.
+    async fn read_events(
+        &self,
+        position: &Option<i64>,
+        limit: usize,
+    ) -> Result<Vec<(E, i64)>, Error> {
+        Ok(self.read(position, limit).await?)
+    }
+    /// Waits until the events after the `position` are appended, as notified on the [EVENTS_CHANNEL].
+    async fn wait_for_events(&self, position: &Option<i64>) -> Result<(), Error> {
+        Ok(self.wait(position).await?)
+    }
+    /// Counts the events stored after the `position`, including the ones held back by the older transaction in progress.
+    /// Returns `None` if the events can not be counted.
+    async fn count_events(&self, position: &Option<i64>) -> Option<usize> {
+        self.count(position).await.ok()
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<E, Error> EventSource<E, i64, Error> for PostgresEventSource
+where
+    E: DeserializeOwned,
+    Error: From<PostgresError>,
+{
+    /// Reads at most `limit` events stored after the `position`, in the global order.
+    async fn read_events(
+        &self,
+        position: &Option<i64>,
+        limit: usize,
+    ) -> Result<Vec<(E, i64)>, Error> {
+        Ok(self.read(position, limit).await?)
+    }
+    /// Waits until the events after the `position` are appended, as notified on the [EVENTS_CHANNEL].
+    async fn wait_for_events(&self, position: &Option<i64>) -> Result<(), Error> {
+        Ok(self.wait(position).await?)
+    }
+    /// Counts the events stored after the `position`, including the ones held back by the older transaction in progress.
+    /// Returns `None` if the events can not be counted.
+    async fn count_events(&self, position: &Option<i64>) -> Option<usize> {
+        self.count(position).await.ok()
+    }
+}
+
+/// PostgreSQL State Repository.
+///
+/// [StateRepository] on the PostgreSQL database, storing the states in the `states` table (see [STATES_SCHEMA]), keyed by their [Identifier].
+/// The states are encoded as JSON. The command is routed to the state by its [Identifier].
+///
+/// The state is saved with the compare-and-swap: the new state is inserted only if there is no state with the same identifier yet,
+/// and updated only if its version is still the one it was fetched with. Otherwise, it fails with [PostgresError::Conflict].
+///
+/// The queries are pipelined over the single connection.
+pub struct PostgresStateRepository {
+    client: Client,
+}
+
+impl PostgresStateRepository {
+    /// Creates a new instance of [PostgresStateRepository] on the `client`, creating the `states` table if it does not exist.
+    pub async fn new(client: Client) -> Result<Self, PostgresError> {
+        client.batch_execute(STATES_SCHEMA).await?;
+        Ok(PostgresStateRepository { client })
+    }
+    /// Connects to the database, configured by the connection string (e.g. `host=localhost user=postgres`), with no TLS.
+    /// The connection is spawned on the `tokio` runtime.
+    pub async fn connect(config: &str) -> Result<Self, PostgresError> {
+        PostgresStateRepository::new(connect(config).await?).await
+    }
+    /// Returns the client.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+    /// Saves the state, if its current version is the expected `version`.
+    async fn compare_and_swap<S: Identifier + Serialize + Clone>(
+        &self,
+        state: &S,
+        version: &Option<i64>,
+    ) -> Result<(S, i64), PostgresError> {
+        let identifier = state.identifier();
+        let payload = serde_json::to_value(state)?;
+        let (updated, new_version) = match version {
+            None => (
+                self.client
+                    .execute(
+                        "INSERT INTO states (id, version, payload) VALUES ($1, 0, $2) ON CONFLICT (id) DO NOTHING",
+                        &[&identifier, &payload],
+                    )
+                    .await?,
+                0,
+            ),
+            Some(version) => (
+                self.client
+                    .execute(
+                        "UPDATE states SET version = version + 1, payload = $3 WHERE id = $1 AND version = $2",
+                        &[&identifier, version, &payload],
+                    )
+                    .await?,
+                version + 1,
+            ),
+        };
+        if updated == 0 {
+            return Err(PostgresError::Conflict(identifier));
+        }
+        Ok((state.clone(), new_version))
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<C, S, Error> StateRepository<C, S, i64, Error> for PostgresStateRepository
+where
+    C: Identifier + Sync,
+    S: Identifier + Serialize + DeserializeOwned + Clone + Send + Sync,
+    Error: From<PostgresError> + Send,
+{
+    /// Fetches the state, identified by the command [Identifier].
+    async fn fetch_state(&self, command: &C) -> Result<Option<(S, i64)>, Error> {
+        Ok(fetch_row(&self.client, "states", &command.identifier()).await?)
+    }
+    /// Saves the state, if it was not modified since it was fetched with the `version`.
+    async fn save(&self, state: &S, version: &Option<i64>) -> Result<(S, i64), Error> {
+        Ok(self.compare_and_swap(state, version).await?)
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<C, S, Error> StateRepository<C, S, i64, Error> for PostgresStateRepository
+where
+    C: Identifier,
+    S: Identifier + Serialize + DeserializeOwned + Clone,
+    Error: From<PostgresError>,
+{
+    /// Fetches the state, identified by the command [Identifier].
+    async fn fetch_state(&self, command: &C) -> Result<Option<(S, i64)>, Error> {
+        Ok(fetch_row(&self.client, "states", &command.identifier()).await?)
+    }
+    /// Saves the state, if it was not modified since it was fetched with the `version`.
+    async fn save(&self, state: &S, version: &Option<i64>) -> Result<(S, i64), Error> {
+        Ok(self.compare_and_swap(state, version).await?)
+    }
+}
+
+/// PostgreSQL View State Repository.
+///
+/// [ViewStateRepository] and [VersionedViewStateRepository] on the PostgreSQL database, storing the view states in the `views` table (see [VIEWS_SCHEMA]), keyed by their [Identifier].
+/// The view states are encoded as JSON. The event is routed to the view state by its [Identifier].
+///
+/// As the [VersionedViewStateRepository], the view state is saved with the compare-and-swap: it is updated only if the version of the last event applied to it is lower than the new one.
+/// Otherwise, it fails with [PostgresError::Conflict], so the concurrent handler of the older event can not overwrite the newer state.
+/// As the [ViewStateRepository], the last save wins, and the version counts the saves.
+///
+/// The queries are pipelined over the single connection.
+pub struct PostgresViewStateRepository {
+    client: Client,
+}
+
+impl PostgresViewStateRepository {
+    /// Creates a new instance of [PostgresViewStateRepository] on the `client`, creating the `views` table if it does not exist.
+    pub async fn new(client: Client) -> Result<Self, PostgresError> {
+        client.batch_execute(VIEWS_SCHEMA).await?;
+        Ok(PostgresViewStateRepository { client })
+    }
+    /// Connects to the database, configured by the connection string (e.g. `host=localhost user=postgres`), with no TLS.
+    /// The connection is spawned on the `tokio` runtime.
+    pub async fn connect(config: &str) -> Result<Self, PostgresError> {
+        PostgresViewStateRepository::new(connect(config).await?).await
+    }
+    /// Returns the client.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+    /// Saves the view state, counting the saves in its version.
+    async fn upsert<S: Identifier + Serialize + Clone>(
+        &self,
+        state: &S,
+    ) -> Result<S, PostgresError> {
+        self.client
+            .execute(
+                "INSERT INTO views (id, version, payload) VALUES ($1, 0, $2)
+                 ON CONFLICT (id) DO UPDATE SET version = views.version + 1, payload = EXCLUDED.payload",
+                &[&state.identifier(), &serde_json::to_value(state)?],
+            )
+            .await?;
+        Ok(state.clone())
+    }
+    /// Saves the view state, if the version of the last event applied to it is lower than the new `version`.
+    async fn compare_and_swap<S: Identifier + Serialize + Clone>(
+        &self,
+        state: &S,
+        version: &i64,
+    ) -> Result<(S, i64), PostgresError> {
+        let identifier = state.identifier();
+        let updated = self
+            .client
+            .execute(
+                "INSERT INTO views (id, version, payload) VALUES ($1, $2, $3)
+                 ON CONFLICT (id) DO UPDATE SET version = EXCLUDED.version, payload = EXCLUDED.payload
+                 WHERE views.version < EXCLUDED.version",
+                &[&identifier, version, &serde_json::to_value(state)?],
+            )
+            .await?;
+        if updated == 0 {
+            return Err(PostgresError::Conflict(identifier));
+        }
+        Ok((state.clone(), *version))
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<E, S, Error> ViewStateRepository<E, S, Error> for PostgresViewStateRepository
+where
+    E: Identifier + Sync,
+    S: Identifier + Serialize + DeserializeOwned + Clone + Send + Sync,
+    Error: From<PostgresError> + Send,
+{
+    /// Fetches the view state, identified by the event [Identifier].
+    async fn fetch_state(&self, event: &E) -> Result<Option<S>, Error> {
+        Ok(fetch_row(&self.client, "views", &event.identifier())
+            .await?
+            .map(|(state, _)| state))
+    }
+    /// Saves the view state.
+    async fn save(&self, state: &S) -> Result<S, Error> {
+        Ok(self.upsert(state).await?)
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<E, S, Error> ViewStateRepository<E, S, Error> for PostgresViewStateRepository
+where
+    E: Identifier,
+    S: Identifier + Serialize + DeserializeOwned + Clone,
+    Error: From<PostgresError>,
+{
+    /// Fetches the view state, identified by the event [Identifier].
+    async fn fetch_state(&self, event: &E) -> Result<Option<S>, Error> {
+        Ok(fetch_row(&self.client, "views", &event.identifier())
+            .await?
+            .map(|(state, _)| state))
+    }
+    /// Saves the view state.
+    async fn save(&self, state: &S) -> Result<S, Error> {
+        Ok(self.upsert(state).await?)
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<E, S, Error> VersionedViewStateRepository<E, S, i64, Error> for PostgresViewStateRepository
+where
+    E: Identifier + Sync,
+    S: Identifier + Serialize + DeserializeOwned + Clone + Send + Sync,
+    Error: From<PostgresError> + Send,
+{
+    /// Fetches the view state, and the version of the last event applied to it, identified by the event [Identifier].
+    async fn fetch_state(&self, event: &E) -> Result<Option<(S, i64)>, Error> {
+        Ok(fetch_row(&self.client, "views", &event.identifier()).await?)
+    }
+    /// Saves the view state, if the version of the last event applied to it is lower than the new `version`.
+    async fn save(&self, state: &S, version: &i64) -> Result<(S, i64), Error> {
+        Ok(self.compare_and_swap(state, version).await?)
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<E, S, Error> VersionedViewStateRepository<E, S, i64, Error> for PostgresViewStateRepository
+where
+    E: Identifier,
+    S: Identifier + Serialize + DeserializeOwned + Clone,
+    Error: From<PostgresError>,
+{
+    /// Fetches the view state, and the version of the last event applied to it, identified by the event [Identifier].
+    async fn fetch_state(&self, event: &E) -> Result<Option<(S, i64)>, Error> {
+        Ok(fetch_row(&self.client, "views", &event.identifier()).await?)
+    }
+    /// Saves the view state, if the version of the last event applied to it is lower than the new `version`.
+    async fn save(&self, state: &S, version: &i64) -> Result<(S, i64), Error> {
+        Ok(self.compare_and_swap(state, version).await?)
+    }
+}
@@ -0,0 +1,192 @@
+#[cfg(feature = "not-send-futures")]
+use std::rc::Rc;
+#[cfg(not(feature = "not-send-futures"))]
+use std::sync::Arc;
+
+use crate::{EvolveFunction, InitialStateFunction, ProcessReactFunction};
+
+/// [Process] is a datatype that represents the stateful central point of control, deciding what to execute next (`A`), based on the action result (`AR`) and its own state (`S`).
+/// It has three generic parameters `AR`/Action Result, `S`/State, `A`/Action , representing the type of the values that Process may contain or use.
+/// `'a` is used as a lifetime parameter, indicating that all references contained within the struct (e.g., references within the function closures) must have a lifetime that is at least as long as 'a.
+///
+/// Unlike the [Saga](crate::saga::Saga), which has no memory, the Process remembers the action results it has seen so far.
+/// It first evolves its state with the action result, and then reacts to the action result, based on the new state.
+/// This is useful for multi-step workflows, for example to ship the order only after both, the payment and the stock reservation are done.
+///
+/// ## Example
+///
+/// ```
+/// use fmodel_rust::process::Process;
+///
+/// #[derive(Clone, Default)]
+/// struct ShippingState {
+///     paid: bool,
+///     reserved: bool,
+/// }
+///
+/// enum OrderEvent {
+///     Paid(u32),
+///     Reserved(u32),
+/// }
+///
+/// #[derive(Debug, PartialEq)]
+/// enum ShipmentCommand {
+///     Ship(u32),
+/// }
+///
+/// fn process<'a>() -> Process<'a, OrderEvent, ShippingState, ShipmentCommand> {
+///     Process {
+///         evolve: Box::new(|state, event| {
+///             let mut new_state = state.clone();
+///             match event {
+///                 OrderEvent::Paid(_) => new_state.paid = true,
+///                 OrderEvent::Reserved(_) => new_state.reserved = true,
+///             }
+///             new_state
+///         }),
+///         react: Box::new(|state, event| match event {
+///             OrderEvent::Paid(order_id) | OrderEvent::Reserved(order_id) => {
+///                 if state.paid && state.reserved {
+///                     vec![ShipmentCommand::Ship(*order_id)]
+///                 } else {
+///                     vec![]
+///                 }
+///             }
+///         }),
+///         initial_state: Box::new(ShippingState::default),
+///     }
+/// }
+///
+/// let process = process();
+/// let state = (process.initial_state)();
+/// let state = (process.evolve)(&state, &OrderEvent::Paid(1));
+/// assert!((process.react)(&state, &OrderEvent::Paid(1)).is_empty());
+/// let state = (process.evolve)(&state, &OrderEvent::Reserved(1));
+/// assert_eq!((process.react)(&state, &OrderEvent::Reserved(1)), vec![ShipmentCommand::Ship(1)]);
+/// ```
+pub struct Process<'a, AR: 'a, S: 'a, A: 'a> {
+    /// The `react` function is driving the next action based on the current state and the action result.
+    pub react: ProcessReactFunction<'a, AR, S, A>,
+    /// The `evolve` function is used to evolve the state of the process based on the action result.
+    pub evolve: EvolveFunction<'a, S, AR>,
+    /// The `initial_state` function is used to produce the initial state of the process.
+    pub initial_state: InitialStateFunction<'a, S>,
+}
+
+impl<'a, AR, S, A> Process<'a, AR, S, A> {
+    /// Maps the Process over the A/Action type parameter.
+    /// Creates a new instance of [Process]`<AR, S, A2>`.
+    #[cfg(not(feature = "not-send-futures"))]
+    pub fn map_action<A2, F>(self, f: F) -> Process<'a, AR, S, A2>
+    where
+        F: Fn(&A) -> A2 + Send + Sync + 'a,
+    {
+        let new_react = Box::new(move |s: &S, ar: &AR| {
+            let a = (self.react)(s, ar);
+            a.into_iter().map(|a: A| f(&a)).collect()
+        });
+
+        Process {
+            react: new_react,
+            evolve: self.evolve,
+            initial_state: self.initial_state,
+        }
+    }
+
+    /// Maps the Process over the A/Action type parameter.
+    /// Creates a new instance of [Process]`<AR, S, A2>`.
+    #[cfg(feature = "not-send-futures")]
+    pub fn map_action<A2, F>(self, f: F) -> Process<'a, AR, S, A2>
+    where
+        F: Fn(&A) -> A2 + 'a,
+    {
+        let new_react = Box::new(move |s: &S, ar: &AR| {
+            let a = (self.react)(s, ar);
+            a.into_iter().map(|a: A| f(&a)).collect()
+        });
+
+        Process {
+            react: new_react,
+            evolve: self.evolve,
+            initial_state: self.initial_state,
+        }
+    }
+
+    /// Maps the Process over the AR/ActionResult type parameter.
+    /// Creates a new instance of [Process]`<AR2, S, A>`.
+    #[cfg(not(feature = "not-send-futures"))]
+    pub fn map_action_result<AR2, F>(self, f: F) -> Process<'a, AR2, S, A>
+    where
+        F: Fn(&AR2) -> AR + Send + Sync + 'a,
+    {
+        let f = Arc::new(f);
+        let f_clone = Arc::clone(&f);
+
+        let new_react = Box::new(move |s: &S, ar2: &AR2| {
+            let ar = f(ar2);
+            (self.react)(s, &ar)
+        });
+
+        let new_evolve = Box::new(move |s: &S, ar2: &AR2| {
+            let ar = f_clone(ar2);
+            (self.evolve)(s, &ar)
+        });
+
+        Process {
+            react: new_react,
+            evolve: new_evolve,
+            initial_state: self.initial_state,
+        }
+    }
+
+    /// Maps the Process over the AR/ActionResult type parameter.
+    /// Creates a new instance of [Process]`<AR2, S, A>`.
+    #[cfg(feature = "not-send-futures")]
+    pub fn map_action_result<AR2, F>(self, f: F) -> Process<'a, AR2, S, A>
+    where
+        F: Fn(&AR2) -> AR + 'a,
+    {
+        let f = Rc::new(f);
+        let f_clone = Rc::clone(&f);
+
+        let new_react = Box::new(move |s: &S, ar2: &AR2| {
+            let ar = f(ar2);
+            (self.react)(s, &ar)
+        });
+
+        let new_evolve = Box::new(move |s: &S, ar2: &AR2| {
+            let ar = f_clone(ar2);
+            (self.evolve)(s, &ar)
+        });
+
+        Process {
+            react: new_react,
+            evolve: new_evolve,
+            initial_state: self.initial_state,
+        }
+    }
+}
+
+/// Formalizes the `Process Computation` algorithm for the `process` to handle action results based on the current state, and produce new state and new actions.
+pub trait ProcessComputation<AR, S, A> {
+    /// Computes new state and new commands/actions based on the current state and the event/action_result.
+    fn compute_new_state_and_actions(
+        &self,
+        current_state: Option<S>,
+        action_result: &AR,
+    ) -> (S, Vec<A>);
+}
+
+impl<AR, S, A> ProcessComputation<AR, S, A> for Process<'_, AR, S, A> {
+    /// Computes new state and new commands/actions based on the current state and the event/action_result.
+    fn compute_new_state_and_actions(
+        &self,
+        current_state: Option<S>,
+        action_result: &AR,
+    ) -> (S, Vec<A>) {
+        let effective_current_state = current_state.unwrap_or_else(|| (self.initial_state)());
+        let new_state = (self.evolve)(&effective_current_state, action_result);
+        let new_actions = (self.react)(&new_state, action_result);
+        (new_state, new_actions)
+    }
+}
@@ -0,0 +1,269 @@
+use std::future::Future;
+use std::marker::PhantomData;
+
+use crate::process::ProcessComputation;
+use crate::saga_manager::ActionPublisher;
+use crate::Identifier;
+
+/// Process State Repository trait
+///
+/// The state of every process instance is stored under its correlation identifier.
+///
+/// Generic parameters:
+///
+/// - `S` - State
+/// - `Version` - Version
+/// - `Error` - Error
+#[cfg(not(feature = "not-send-futures"))]
+pub trait ProcessStateRepository<S, Version, Error> {
+    /// Fetches current state of the process, based on the correlation identifier.
+    /// Desugared `async fn fetch_state(&self, correlation_id: &str) -> Result<Option<(S, Version)>, Error>;` to a normal `fn` that returns `impl Future` and adds bound `Send`
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn fetch_state(
+        &self,
+        correlation_id: &str,
+    ) -> impl Future<Output = Result<Option<(S, Version)>, Error>> + Send;
+    /// Saves state of the process, under the correlation identifier.
+    /// Desugared `async fn save(&self, correlation_id: &str, state: &S, version: &Option<Version>) -> Result<(S, Version), Error>;` to a normal `fn` that returns `impl Future` and adds bound `Send`
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn save(
+        &self,
+        correlation_id: &str,
+        state: &S,
+        version: &Option<Version>,
+    ) -> impl Future<Output = Result<(S, Version), Error>> + Send;
+}
+
+/// Process State Repository trait
+///
+/// The state of every process instance is stored under its correlation identifier.
+///
+/// Generic parameters:
+///
+/// - `S` - State
+/// - `Version` - Version
+/// - `Error` - Error
+#[cfg(feature = "not-send-futures")]
+pub trait ProcessStateRepository<S, Version, Error> {
+    /// Fetches current state of the process, based on the correlation identifier.
+    /// Desugared `async fn fetch_state(&self, correlation_id: &str) -> Result<Option<(S, Version)>, Error>;` to a normal `fn` that returns `impl Future`
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn fetch_state(
+        &self,
+        correlation_id: &str,
+    ) -> impl Future<Output = Result<Option<(S, Version)>, Error>>;
+    /// Saves state of the process, under the correlation identifier.
+    /// Desugared `async fn save(&self, correlation_id: &str, state: &S, version: &Option<Version>) -> Result<(S, Version), Error>;` to a normal `fn` that returns `impl Future`
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn save(
+        &self,
+        correlation_id: &str,
+        state: &S,
+        version: &Option<Version>,
+    ) -> impl Future<Output = Result<(S, Version), Error>>;
+}
+
+/// Process Manager.
+///
+/// It is using a `Process` / [ProcessComputation] to compute new state and new actions based on the current state and the action result.
+/// It is using a [ProcessStateRepository] to fetch the current state and to save the new state, per correlation identifier of the action result.
+/// It is using an [ActionPublisher] to publish the new actions.
+///
+/// Generic parameters:
+/// - `AR` - Action Result / Event
+/// - `S` - State
+/// - `A` - Action / Command
+/// - `Repository` - Process state repository
+/// - `Publisher` - Action Publisher
+/// - `Process` - Process computation
+/// - `Version` - Version
+/// - `Error` - Error
+pub struct ProcessManager<AR, S, A, Repository, Publisher, Process, Version, Error>
+where
+    Repository: ProcessStateRepository<S, Version, Error>,
+    Publisher: ActionPublisher<A, Error>,
+    Process: ProcessComputation<AR, S, A>,
+{
+    repository: Repository,
+    action_publisher: Publisher,
+    process: Process,
+    _marker: PhantomData<(AR, S, A, Version, Error)>,
+}
+
+impl<AR, S, A, Repository, Publisher, Process, Version, Error> ProcessComputation<AR, S, A>
+    for ProcessManager<AR, S, A, Repository, Publisher, Process, Version, Error>
+where
+    Repository: ProcessStateRepository<S, Version, Error>,
+    Publisher: ActionPublisher<A, Error>,
+    Process: ProcessComputation<AR, S, A>,
+{
+    /// Computes new state and new actions based on the current state and the action result.
+    fn compute_new_state_and_actions(
+        &self,
+        current_state: Option<S>,
+        action_result: &AR,
+    ) -> (S, Vec<A>) {
+        self.process
+            .compute_new_state_and_actions(current_state, action_result)
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<AR, S, A, Repository, Publisher, Process, Version, Error>
+    ProcessStateRepository<S, Version, Error>
+    for ProcessManager<AR, S, A, Repository, Publisher, Process, Version, Error>
+where
+    Repository: ProcessStateRepository<S, Version, Error> + Sync,
+    Publisher: ActionPublisher<A, Error> + Sync,
+    Process: ProcessComputation<AR, S, A> + Sync,
+    AR: Sync,
+    S: Sync,
+    A: Sync,
+    Version: Sync,
+    Error: Sync,
+{
+    /// Fetches current state of the process, based on the correlation identifier.
+    async fn fetch_state(&self, correlation_id: &str) -> Result<Option<(S, Version)>, Error> {
+        self.repository.fetch_state(correlation_id).await
+    }
+    /// Saves state of the process, under the correlation identifier.
+    async fn save(
+        &self,
+        correlation_id: &str,
+        state: &S,
+        version: &Option<Version>,
+    ) -> Result<(S, Version), Error> {
+        self.repository.save(correlation_id, state, version).await
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<AR, S, A, Repository, Publisher, Process, Version, Error>
+    ProcessStateRepository<S, Version, Error>
+    for ProcessManager<AR, S, A, Repository, Publisher, Process, Version, Error>
+where
+    Repository: ProcessStateRepository<S, Version, Error>,
+    Publisher: ActionPublisher<A, Error>,
+    Process: ProcessComputation<AR, S, A>,
+{
+    /// Fetches current state of the process, based on the correlation identifier.
+    async fn fetch_state(&self, correlation_id: &str) -> Result<Option<(S, Version)>, Error> {
+        self.repository.fetch_state(correlation_id).await
+    }
+    /// Saves state of the process, under the correlation identifier.
+    async fn save(
+        &self,
+        correlation_id: &str,
+        state: &S,
+        version: &Option<Version>,
+    ) -> Result<(S, Version), Error> {
+        self.repository.save(correlation_id, state, version).await
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<AR, S, A, Repository, Publisher, Process, Version, Error> ActionPublisher<A, Error>
+    for ProcessManager<AR, S, A, Repository, Publisher, Process, Version, Error>
+where
+    Repository: ProcessStateRepository<S, Version, Error> + Sync,
+    Publisher: ActionPublisher<A, Error> + Sync,
+    Process: ProcessComputation<AR, S, A> + Sync,
+    AR: Sync,
+    S: Sync,
+    A: Sync,
+    Version: Sync,
+    Error: Sync,
+{
+    /// Publishes the action/command to some external system, returning either the actions that are successfully published or error.
+    async fn publish(&self, action: &[A]) -> Result<Vec<A>, Error> {
+        self.action_publisher.publish(action).await
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<AR, S, A, Repository, Publisher, Process, Version, Error> ActionPublisher<A, Error>
+    for ProcessManager<AR, S, A, Repository, Publisher, Process, Version, Error>
+where
+    Repository: ProcessStateRepository<S, Version, Error>,
+    Publisher: ActionPublisher<A, Error>,
+    Process: ProcessComputation<AR, S, A>,
+{
+    /// Publishes the action/command to some external system, returning either the actions that are successfully published or error.
+    async fn publish(&self, action: &[A]) -> Result<Vec<A>, Error> {
+        self.action_publisher.publish(action).await
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<AR, S, A, Repository, Publisher, Process, Version, Error>
+    ProcessManager<AR, S, A, Repository, Publisher, Process, Version, Error>
+where
+    Repository: ProcessStateRepository<S, Version, Error> + Sync,
+    Publisher: ActionPublisher<A, Error> + Sync,
+    Process: ProcessComputation<AR, S, A> + Sync,
+    AR: Identifier + Sync,
+    S: Sync,
+    A: Sync,
+    Version: Sync,
+    Error: Sync,
+{
+    /// Creates a new instance of [ProcessManager].
+    pub fn new(repository: Repository, action_publisher: Publisher, process: Process) -> Self {
+        ProcessManager {
+            repository,
+            action_publisher,
+            process,
+            _marker: PhantomData,
+        }
+    }
+    /// Handles the `action result` by fetching the state of the process correlated by the action result [Identifier], computing new state and new `actions`, saving the new state, and publishing new `actions` to the external system.
+    /// The state is saved before the actions are published, so the failed publishing can be retried without losing the progress of the process.
+    /// Returns the new state with its version, and the published actions.
+    pub async fn handle(&self, action_result: &AR) -> Result<((S, Version), Vec<A>), Error> {
+        let correlation_id = action_result.identifier();
+        let (current_state, version) = match self.fetch_state(&correlation_id).await? {
+            None => (None, None),
+            Some((state, version)) => (Some(state), Some(version)),
+        };
+        let (new_state, new_actions) =
+            self.compute_new_state_and_actions(current_state, action_result);
+        let saved_state = self.save(&correlation_id, &new_state, &version).await?;
+        let published_actions = self.publish(&new_actions).await?;
+        Ok((saved_state, published_actions))
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<AR, S, A, Repository, Publisher, Process, Version, Error>
+    ProcessManager<AR, S, A, Repository, Publisher, Process, Version, Error>
+where
+    Repository: ProcessStateRepository<S, Version, Error>,
+    Publisher: ActionPublisher<A, Error>,
+    Process: ProcessComputation<AR, S, A>,
+    AR: Identifier,
+{
+    /// Creates a new instance of [ProcessManager].
+    pub fn new(repository: Repository, action_publisher: Publisher, process: Process) -> Self {
+        ProcessManager {
+            repository,
+            action_publisher,
+            process,
+            _marker: PhantomData,
+        }
+    }
+    /// Handles the `action result` by fetching the state of the process correlated by the action result [Identifier], computing new state and new `actions`, saving the new state, and publishing new `actions` to the external system.
+    /// The state is saved before the actions are published, so the failed publishing can be retried without losing the progress of the process.
+    /// Returns the new state with its version, and the published actions.
+    pub async fn handle(&self, action_result: &AR) -> Result<((S, Version), Vec<A>), Error> {
+        let correlation_id = action_result.identifier();
+        let (current_state, version) = match self.fetch_state(&correlation_id).await? {
+            None => (None, None),
+            Some((state, version)) => (Some(state), Some(version)),
+        };
+        let (new_state, new_actions) =
+            self.compute_new_state_and_actions(current_state, action_result);
+        let saved_state = self.save(&correlation_id, &new_state, &version).await?;
+        let published_actions = self.publish(&new_actions).await?;
+        Ok((saved_state, published_actions))
+    }
+}
@@ -0,0 +1,361 @@
+use std::future::Future;
+use std::marker::PhantomData;
+
+use crate::materialized_view::{MaterializedView, ViewStateRepository};
+#[cfg(feature = "metrics")]
+use crate::telemetry::projection_lag;
+use crate::view::ViewStateComputation;
+
+/// Event Source trait
+///
+/// Reads events in the global order they were stored in (across all streams/identifiers).
+///
+/// Generic parameters:
+///
+/// - `E` - Event
+/// - `Position` - Global position/offset of the event in the event log
+/// - `Error` - Error
+#[cfg(not(feature = "not-send-futures"))]
+pub trait EventSource<E, Position, Error> {
+    /// Reads at most `limit` events stored after the `position`, in the global order. Reads from the beginning of the log if the `position` is `None`.
+    /// Desugared `async fn read_events(&self, position: &Option<Position>, limit: usize) -> Result<Vec<(E, Position)>, Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn read_events(
+        &self,
+        position: &Option<Position>,
+        limit: usize,
+    ) -> impl Future<Output = Result<Vec<(E, Position)>, Error>> + Send;
+    /// Waits until new events might be available after the `position`. It is used to tail the log once all the events are read.
+    /// Desugared `async fn wait_for_events(&self, position: &Option<Position>) -> Result<(), Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn wait_for_events(
+        &self,
+        position: &Option<Position>,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+    /// Counts the events stored after the `position`. It is reported as the lag of the projection, with the `metrics` feature enabled.
+    /// Returns `None` if the source can not count the events, which is the default.
+    fn count_events(
+        &self,
+        _position: &Option<Position>,
+    ) -> impl Future<Output = Option<usize>> + Send {
+        std::future::ready(None)
+    }
+}
+
+/// Event Source trait
+///
+/// Reads events in the global order they were stored in (across all streams/identifiers).
+///
+/// Generic parameters:
+///
+/// - `E` - Event
+/// - `Position` - Global position/offset of the event in the event log
+/// - `Error` - Error
+#[cfg(feature = "not-send-futures")]
+pub trait EventSource<E, Position, Error> {
+    /// Reads at most `limit` events stored after the `position`, in the global order. Reads from the beginning of the log if the `position` is `None`.
+    /// Desugared `async fn read_events(&self, position: &Option<Position>, limit: usize) -> Result<Vec<(E, Position)>, Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn read_events(
+        &self,
+        position: &Option<Position>,
+        limit: usize,
+    ) -> impl Future<Output = Result<Vec<(E, Position)>, Error>>;
+    /// Waits until new events might be available after the `position`. It is used to tail the log once all the events are read.
+    /// Desugared `async fn wait_for_events(&self, position: &Option<Position>) -> Result<(), Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn wait_for_events(
+        &self,
+        position: &Option<Position>,
+    ) -> impl Future<Output = Result<(), Error>>;
+    /// Counts the events stored after the `position`. It is reported as the lag of the projection, with the `metrics` feature enabled.
+    /// Returns `None` if the source can not count the events, which is the default.
+    fn count_events(&self, _position: &Option<Position>) -> impl Future<Output = Option<usize>> {
+        std::future::ready(None)
+    }
+}
+
+/// Checkpoint Repository trait
+///
+/// Stores the position of the last event processed by the projection, so the projection can resume from it.
+///
+/// Generic parameters:
+///
+/// - `Position` - Global position/offset of the event in the event log
+/// - `Error` - Error
+#[cfg(not(feature = "not-send-futures"))]
+pub trait CheckpointRepository<Position, Error> {
+    /// Fetches the checkpoint of the projection, identified by its name.
+    /// Desugared `async fn fetch_checkpoint(&self, projection: &str) -> Result<Option<Position>, Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn fetch_checkpoint(
+        &self,
+        projection: &str,
+    ) -> impl Future<Output = Result<Option<Position>, Error>> + Send;
+    /// Saves the checkpoint of the projection, identified by its name.
+    /// Desugared `async fn save_checkpoint(&self, projection: &str, position: &Position) -> Result<(), Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn save_checkpoint(
+        &self,
+        projection: &str,
+        position: &Position,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+    /// Deletes the checkpoint of the projection, identified by its name, so the projection starts from the beginning of the event source.
+    /// Desugared `async fn delete_checkpoint(&self, projection: &str) -> Result<(), Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn delete_checkpoint(&self, projection: &str)
+        -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+/// Checkpoint Repository trait
+///
+/// Stores the position of the last event processed by the projection, so the projection can resume from it.
+///
+/// Generic parameters:
+///
+/// - `Position` - Global position/offset of the event in the event log
+/// - `Error` - Error
+#[cfg(feature = "not-send-futures")]
+pub trait CheckpointRepository<Position, Error> {
+    /// Fetches the checkpoint of the projection, identified by its name.
+    /// Desugared `async fn fetch_checkpoint(&self, projection: &str) -> Result<Option<Position>, Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn fetch_checkpoint(
+        &self,
+        projection: &str,
+    ) -> impl Future<Output = Result<Option<Position>, Error>>;
+    /// Saves the checkpoint of the projection, identified by its name.
+    /// Desugared `async fn save_checkpoint(&self, projection: &str, position: &Position) -> Result<(), Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn save_checkpoint(
+        &self,
+        projection: &str,
+        position: &Position,
+    ) -> impl Future<Output = Result<(), Error>>;
+    /// Deletes the checkpoint of the projection, identified by its name, so the projection starts from the beginning of the event source.
+    /// Desugared `async fn delete_checkpoint(&self, projection: &str) -> Result<(), Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn delete_checkpoint(&self, projection: &str) -> impl Future<Output = Result<(), Error>>;
+}
+
+/// Default number of events read from the [EventSource] at once.
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// Projection Runner.
+///
+/// It is feeding the [MaterializedView] with the events read from the [EventSource], in the global order.
+/// It is using a [CheckpointRepository] to persist the position of the last processed event, and to resume from it after a restart.
+///
+/// The checkpoint is saved after the batch of events is handled, so the events are delivered to the view `at least once`.
+/// With the `metrics` feature enabled, the number of the events left after the checkpoint (see [EventSource::count_events]) is reported as the lag of the projection.
+///
+/// Generic parameters:
+///
+/// - `S` - State
+/// - `E` - Event
+/// - `Position` - Global position/offset of the event in the event log
+/// - `Source` - Event source
+/// - `Checkpoints` - Checkpoint repository
+/// - `Repository` - View State repository
+/// - `View` - View
+/// - `Error` - Error
+pub struct ProjectionRunner<S, E, Position, Source, Checkpoints, Repository, View, Error>
+where
+    Source: EventSource<E, Position, Error>,
+    Checkpoints: CheckpointRepository<Position, Error>,
+    Repository: ViewStateRepository<E, S, Error>,
+    View: ViewStateComputation<E, S>,
+{
+    name: String,
+    source: Source,
+    checkpoints: Checkpoints,
+    materialized_view: MaterializedView<S, E, Repository, View, Error>,
+    batch_size: usize,
+    _marker: PhantomData<Position>,
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<S, E, Position, Source, Checkpoints, Repository, View, Error>
+    ProjectionRunner<S, E, Position, Source, Checkpoints, Repository, View, Error>
+where
+    Source: EventSource<E, Position, Error> + Sync,
+    Checkpoints: CheckpointRepository<Position, Error> + Sync,
+    Repository: ViewStateRepository<E, S, Error> + Sync,
+    View: ViewStateComputation<E, S> + Sync,
+    E: Sync,
+    S: Sync,
+    Position: Sync,
+    Error: Sync,
+{
+    /// Creates a new instance of [ProjectionRunner].
+    /// The `name` identifies the projection checkpoint in the [CheckpointRepository].
+    pub fn new(
+        name: &str,
+        source: Source,
+        checkpoints: Checkpoints,
+        materialized_view: MaterializedView<S, E, Repository, View, Error>,
+    ) -> Self {
+        ProjectionRunner {
+            name: name.to_string(),
+            source,
+            checkpoints,
+            materialized_view,
+            batch_size: DEFAULT_BATCH_SIZE,
+            _marker: PhantomData,
+        }
+    }
+    /// Sets the maximum number of events read from the [EventSource] at once.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+    /// Returns the name of the projection.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Returns the materialized view this runner is feeding.
+    pub fn materialized_view(&self) -> &MaterializedView<S, E, Repository, View, Error> {
+        &self.materialized_view
+    }
+    /// Handles the next batch of events stored after the checkpoint, and moves the checkpoint forward.
+    /// Returns the number of the handled events. Zero means that the projection has caught up with the event source.
+    pub async fn run_once(&self) -> Result<usize, Error> {
+        let checkpoint = self.checkpoints.fetch_checkpoint(&self.name).await?;
+        let events = self
+            .source
+            .read_events(&checkpoint, self.batch_size)
+            .await?;
+        let count = events.len();
+        let mut last_position = None;
+        for (event, position) in events {
+            self.materialized_view.handle(&event).await?;
+            last_position = Some(position);
+        }
+        if let Some(position) = &last_position {
+            self.checkpoints
+                .save_checkpoint(&self.name, position)
+                .await?;
+        }
+        #[cfg(feature = "metrics")]
+        if let Some(lag) = self
+            .source
+            .count_events(&last_position.or(checkpoint))
+            .await
+        {
+            projection_lag(&self.name, lag);
+        }
+        Ok(count)
+    }
+    /// Catch-up mode. Handles all the events stored after the checkpoint, until the projection has caught up with the event source.
+    /// Returns the number of the handled events.
+    pub async fn catch_up(&self) -> Result<usize, Error> {
+        let mut total = 0;
+        loop {
+            let count = self.run_once().await?;
+            if count == 0 {
+                return Ok(total);
+            }
+            total += count;
+        }
+    }
+    /// Live mode. Catches up with the event source, and then keeps tailing it, waiting for the new events via [EventSource::wait_for_events].
+    /// It returns only on error. Drop the future to stop the projection, the checkpoint guarantees it resumes from the last handled batch.
+    pub async fn run(&self) -> Result<(), Error> {
+        loop {
+            self.catch_up().await?;
+            let checkpoint = self.checkpoints.fetch_checkpoint(&self.name).await?;
+            self.source.wait_for_events(&checkpoint).await?;
+        }
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<S, E, Position, Source, Checkpoints, Repository, View, Error>
+    ProjectionRunner<S, E, Position, Source, Checkpoints, Repository, View, Error>
+where
+    Source: EventSource<E, Position, Error>,
+    Checkpoints: CheckpointRepository<Position, Error>,
+    Repository: ViewStateRepository<E, S, Error>,
+    View: ViewStateComputation<E, S>,
+{
+    /// Creates a new instance of [ProjectionRunner].
+    /// The `name` identifies the projection checkpoint in the [CheckpointRepository].
+    pub fn new(
+        name: &str,
+        source: Source,
+        checkpoints: Checkpoints,
+        materialized_view: MaterializedView<S, E, Repository, View, Error>,
+    ) -> Self {
+        ProjectionRunner {
+            name: name.to_string(),
+            source,
+            checkpoints,
+            materialized_view,
+            batch_size: DEFAULT_BATCH_SIZE,
+            _marker: PhantomData,
+        }
+    }
+    /// Sets the maximum number of events read from the [EventSource] at once.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+    /// Returns the name of the projection.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Returns the materialized view this runner is feeding.
+    pub fn materialized_view(&self) -> &MaterializedView<S, E, Repository, View, Error> {
+        &self.materialized_view
+    }
+    /// Handles the next batch of events stored after the checkpoint, and moves the checkpoint forward.
+    /// Returns the number of the handled events. Zero means that the projection has caught up with the event source.
+    pub async fn run_once(&self) -> Result<usize, Error> {
+        let checkpoint = self.checkpoints.fetch_checkpoint(&self.name).await?;
+        let events = self
+            .source
+            .read_events(&checkpoint, self.batch_size)
+            .await?;
+        let count = events.len();
+        let mut last_position = None;
+        for (event, position) in events {
+            self.materialized_view.handle(&event).await?;
+            last_position = Some(position);
+        }
+        if let Some(position) = &last_position {
+            self.checkpoints
+                .save_checkpoint(&self.name, position)
+                .await?;
+        }
+        #[cfg(feature = "metrics")]
+        if let Some(lag) = self
+            .source
+            .count_events(&last_position.or(checkpoint))
+            .await
+        {
+            projection_lag(&self.name, lag);
+        }
+        Ok(count)
+    }
+    /// Catch-up mode. Handles all the events stored after the checkpoint, until the projection has caught up with the event source.
+    /// Returns the number of the handled events.
+    pub async fn catch_up(&self) -> Result<usize, Error> {
+        let mut total = 0;
+        loop {
+            let count = self.run_once().await?;
+            if count == 0 {
+                return Ok(total);
+            }
+            total += count;
+        }
+    }
+    /// Live mode. Catches up with the event source, and then keeps tailing it, waiting for the new events via [EventSource::wait_for_events].
+    /// It returns only on error. Drop the future to stop the projection, the checkpoint guarantees it resumes from the last handled batch.
+    pub async fn run(&self) -> Result<(), Error> {
+        loop {
+            self.catch_up().await?;
+            let checkpoint = self.checkpoints.fetch_checkpoint(&self.name).await?;
+            self.source.wait_for_events(&checkpoint).await?;
+        }
+    }
+}
@@ -0,0 +1,534 @@
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+
+use ::redb::{Database, ReadableTable, TableDefinition};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::aggregate::{EventRepository, StateRepository};
+use crate::codec::{CodecError, EncodedEvent, EventCodec, JsonCodec};
+use crate::materialized_view::{VersionedViewStateRepository, ViewStateRepository};
+use crate::Identifier;
+
+/// The table of the events, keyed by the [Identifier] of the event stream and the version of the event, within the stream. The version starts at `0`.
+/// The value is the event type, and the event, encoded by the [EventCodec] of the [RedbEventRepository].
+pub const EVENTS: TableDefinition<(&str, u64), (&str, &[u8])> = TableDefinition::new("events");
+
+/// The table of the states, keyed by their [Identifier].
+/// The value is the version of the state (starting at `0`, and incremented by every save), and the state, encoded as JSON.
+pub const STATES: TableDefinition<&str, (u64, &[u8])> = TableDefinition::new("states");
+
+/// The table of the view states, keyed by their [Identifier].
+/// The value is the version of the last event applied to the view state (or the number of the saves, counting from `0`, if the events are not versioned), and the view state, encoded as JSON.
+pub const VIEWS: TableDefinition<&str, (u64, &[u8])> = TableDefinition::new("views");
+
+/// The error of the redb stores.
+#[derive(Debug)]
+pub enum RedbError {
+    /// The database error. It is boxed, as it is large.
+    Database(Box<::redb::Error>),
+    /// The error of encoding/decoding the payload.
+    Serialization(serde_json::Error),
+    /// The error of encoding/decoding the event, by the [EventCodec].
+    Codec(CodecError),
+    /// The event stream/state/view state, identified by the identifier, was modified by the concurrent write.
+    Conflict(String),
+}
+
+impl Display for RedbError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RedbError::Database(error) => write!(f, "database error: {}", error),
+            RedbError::Serialization(error) => write!(f, "serialization error: {}", error),
+            RedbError::Codec(error) => write!(f, "codec error: {}", error),
+            RedbError::Conflict(identifier) => {
+                write!(f, "concurrent modification of `{}`", identifier)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RedbError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RedbError::Database(error) => Some(error.as_ref()),
+            RedbError::Serialization(error) => Some(error),
+            RedbError::Codec(error) => Some(error),
+            RedbError::Conflict(_) => None,
+        }
+    }
+}
+
+/// Converts the errors of the redb operations into the [RedbError::Database].
+macro_rules! from_redb_error {
+    ($($error:ident),+) => {
+        $(
+            impl From<::redb::$error> for RedbError {
+                fn from(error: ::redb::$error) -> Self {
+                    RedbError::Database(Box::new(error.into()))
+                }
+            }
+        )+
+    };
+}
+
+from_redb_error!(
+    Error,
+    DatabaseError,
+    TransactionError,
+    TableError,
+    StorageError,
+    CommitError
+);
+
+impl From<CodecError> for RedbError {
+    fn from(error: CodecError) -> Self {
+        RedbError::Codec(error)
+    }
+}
+
+impl From<serde_json::Error> for RedbError {
+    fn from(error: serde_json::Error) -> Self {
+        RedbError::Serialization(error)
+    }
+}
+
+/// Creates the `table` in the `database`, if it does not exist.
+fn create_table<K: ::redb::Key + 'static, V: ::redb::Value + 'static>(
+    database: &Database,
+    table: TableDefinition<K, V>,
+) -> Result<(), RedbError> {
+    let transaction = database.begin_write()?;
+    transaction.open_table(table)?;
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Fetches the version and the payload, identified by the `id`, from the `table` of the states/views.
+fn fetch_row<S: DeserializeOwned>(
+    database: &Database,
+    table: TableDefinition<&str, (u64, &[u8])>,
+    id: &str,
+) -> Result<Option<(S, u64)>, RedbError> {
+    let transaction = database.begin_read()?;
+    let table = transaction.open_table(table)?;
+    let row = table.get(id)?;
+    match row {
+        Some(row) => {
+            let (version, payload) = row.value();
+            Ok(Some((serde_json::from_slice(payload)?, version)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Redb Event Repository.
+///
+/// [EventRepository] on the embedded [redb](https://docs.rs/redb) key-value store, storing the events in the [EVENTS] table.
+/// The events are encoded by the [EventCodec] (JSON by default, see [RedbEventRepository::with_codec]), and grouped into the streams by their [Identifier]. The command is routed to the stream by its [Identifier], too.
+///
+/// The new events are appended in the single ACID write transaction, even if they belong to the different streams, with the next versions of their streams.
+/// The aggregates save the events with [EventRepository::save_after]: the latest version of the command stream is checked within the write transaction,
+/// and the events decided on the stale version are rejected with [RedbError::Conflict], like the [RedbStateRepository] does.
+///
+/// The database is shared by the [RedbEventRepository], [RedbStateRepository] and [RedbViewStateRepository], as it can be opened only once.
+/// The operations are blocking. It fits the single-binary deployments, with no SQL.
+///
+/// Generic parameters:
+///
+/// - `Codec` - Event codec
+pub struct RedbEventRepository<Codec = JsonCodec> {
+    database: Arc<Database>,
+    codec: Codec,
+}
+
+impl RedbEventRepository {
+    /// Creates a new instance of [RedbEventRepository] on the `database`, with the [JsonCodec], creating the [EVENTS] table if it does not exist.
+    pub fn new(database: Arc<Database>) -> Result<Self, RedbError> {
+        RedbEventRepository::with_codec(database, JsonCodec)
+    }
+    /// Opens the database file at the `path`, creating it if it does not exist.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, RedbError> {
+        RedbEventRepository::new(Arc::new(Database::create(path)?))
+    }
+}
+
+impl<Codec> RedbEventRepository<Codec> {
+    /// Creates a new instance of [RedbEventRepository] on the `database`, with the `codec`, creating the [EVENTS] table if it does not exist.
+    pub fn with_codec(database: Arc<Database>, codec: Codec) -> Result<Self, RedbError> {
+        create_table(&database, EVENTS)?;
+        Ok(RedbEventRepository { database, codec })
+    }
+    /// Returns the database.
+    pub fn database(&self) -> &Arc<Database> {
+        &self.database
+    }
+    /// Returns the codec.
+    pub fn codec(&self) -> &Codec {
+        &self.codec
+    }
+    /// Fetches the events of the stream, identified by the `identifier`, ordered by their version.
+    fn fetch_stream<E>(&self, identifier: &str) -> Result<Vec<(E, u64)>, RedbError>
+    where
+        Codec: EventCodec<E>,
+    {
+        let transaction = self.database.begin_read()?;
+        let table = transaction.open_table(EVENTS)?;
+        table
+            .range((identifier, 0)..=(identifier, u64::MAX))?
+            .map(|row| {
+                let (key, value) = row?;
+                let (event_type, payload) = value.value();
+                let event = self.codec.decode(&EncodedEvent {
+                    event_type: event_type.to_string(),
+                    payload: payload.to_vec(),
+                })?;
+                Ok((event, key.value().1))
+            })
+            .collect()
+    }
+    /// Returns the latest version of the stream, identified by the `identifier`.
+    fn stream_version(&self, identifier: &str) -> Result<Option<u64>, RedbError> {
+        let transaction = self.database.begin_read()?;
+        latest_version(&transaction.open_table(EVENTS)?, identifier)
+    }
+    /// Appends the events to their streams, in the single transaction.
+    /// If the `expected` stream is given, its events are appended only if it is still at the expected version, or [RedbError::Conflict] is returned.
+    fn append<E: Identifier + Clone>(
+        &self,
+        events: &[E],
+        mut expected: Option<(&str, Option<u64>)>,
+    ) -> Result<Vec<(E, u64)>, RedbError>
+    where
+        Codec: EventCodec<E>,
+    {
+        let transaction = self.database.begin_write()?;
+        let mut saved_events = Vec::with_capacity(events.len());
+        {
+            let mut table = transaction.open_table(EVENTS)?;
+            for event in events {
+                let identifier = event.identifier();
+                let latest = latest_version(&table, &identifier)?;
+                if let Some((stream_id, expected_version)) = expected {
+                    if stream_id == identifier {
+                        if latest != expected_version {
+                            return Err(RedbError::Conflict(identifier));
+                        }
+                        // The next events of the stream follow the ones appended by this transaction
+                        expected = None;
+                    }
+                }
+                let version = latest.map_or(0, |version| version + 1);
+                let encoded = self.codec.encode(event)?;
+                table.insert(
+                    (identifier.as_str(), version),
+                    (encoded.event_type.as_str(), encoded.payload.as_slice()),
+                )?;
+                saved_events.push((event.clone(), version));
+            }
+        }
+        transaction.commit()?;
+        Ok(saved_events)
+    }
+}
+
+/// Returns the latest version of the stream, identified by the `identifier`, from the [EVENTS] `table`.
+fn latest_version(
+    table: &impl ReadableTable<(&'static str, u64), (&'static str, &'static [u8])>,
+    identifier: &str,
+) -> Result<Option<u64>, RedbError> {
+    match table
+        .range((identifier, 0)..=(identifier, u64::MAX))?
+        .next_back()
+    {
+        Some(row) => Ok(Some(row?.0.value().1)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<C, E, Codec, Error> EventRepository<C, E, u64, Error> for RedbEventRepository<Codec>
+where
+    C: Identifier + Sync,
+    E: Identifier + Clone + Send + Sync,
+    Codec: EventCodec<E> + Sync,
+    Error: From<RedbError> + Send,
+{
+    /// Fetches the events of the stream, identified by the command [Identifier].
+    async fn fetch_events(&self, command: &C) -> Result<Vec<(E, u64)>, Error> {
+        Ok(self.fetch_stream(&command.identifier())?)
+    }
+    /// Appends the events to their streams, with the next versions.
+    async fn save(&self, events: &[E]) -> Result<Vec<(E, u64)>, Error> {
+        Ok(self.append(events, None)?)
+    }
+    /// Appends the events to their streams, with the next versions, if the stream of the command is still at the `latest_version`.
+    async fn save_after(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<u64>,
+    ) -> Result<Vec<(E, u64)>, Error> {
+        Ok(self.append(events, Some((&command.identifier(), *latest_version)))?)
+    }
+    /// Returns the latest version of the event stream.
+    async fn version_provider(&self, event: &E) -> Result<Option<u64>, Error> {
+        Ok(self.stream_version(&event.identifier())?)
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<C, E, Codec, Error> EventRepository<C, E, u64, Error> for RedbEventRepository<Codec>
+where
+    C: Identifier,
+    E: Identifier + Clone,
+    Codec: EventCodec<E>,
+    Error: From<RedbError>,
+{
+    /// Fetches the events of the stream, identified by the command [Identifier].
+    async fn fetch_events(&self, command: &C) -> Result<Vec<(E, u64)>, Error> {
+        Ok(self.fetch_stream(&command.identifier())?)
+    }
+    /// Appends the events to their streams, with the next versions.
+    async fn save(&self, events: &[E]) -> Result<Vec<(E, u64)>, Error> {
+        Ok(self.append(events, None)?)
+    }
+    /// Appends the events to their streams, with the next versions, if the stream of the command is still at the `latest_version`.
+    async fn save_after(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<u64>,
+    ) -> Result<Vec<(E, u64)>, Error> {
+        Ok(self.append(events, Some((&command.identifier(), *latest_version)))?)
+    }
+    /// Returns the latest version of the event stream.
+    async fn version_provider(&self, event: &E) -> Result<Option<u64>, Error> {
+        Ok(self.stream_version(&event.identifier())?)
+    }
+}
+
+/// Redb State Repository.
+///
+/// [StateRepository] on the embedded [redb](https://docs.rs/redb) key-value store, storing the states in the [STATES] table, keyed by their [Identifier].
+/// The states are encoded as JSON. The command is routed to the state by its [Identifier].
+///
+/// The state is saved with the compare-and-swap, in the ACID write transaction: the new state is inserted only if there is no state with the same identifier yet,
+/// and updated only if its version is still the one it was fetched with. Otherwise, it fails with [RedbError::Conflict].
+///
+/// The database is shared by the [RedbEventRepository], [RedbStateRepository] and [RedbViewStateRepository], as it can be opened only once.
+/// The operations are blocking. It fits the single-binary deployments, with no SQL.
+pub struct RedbStateRepository {
+    database: Arc<Database>,
+}
+
+impl RedbStateRepository {
+    /// Creates a new instance of [RedbStateRepository] on the `database`, creating the [STATES] table if it does not exist.
+    pub fn new(database: Arc<Database>) -> Result<Self, RedbError> {
+        create_table(&database, STATES)?;
+        Ok(RedbStateRepository { database })
+    }
+    /// Opens the database file at the `path`, creating it if it does not exist.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, RedbError> {
+        RedbStateRepository::new(Arc::new(Database::create(path)?))
+    }
+    /// Returns the database.
+    pub fn database(&self) -> &Arc<Database> {
+        &self.database
+    }
+    /// Saves the state, if its current version is the expected `version`.
+    fn compare_and_swap<S: Identifier + Serialize + Clone>(
+        &self,
+        state: &S,
+        version: &Option<u64>,
+    ) -> Result<(S, u64), RedbError> {
+        let identifier = state.identifier();
+        let payload = serde_json::to_vec(state)?;
+        let transaction = self.database.begin_write()?;
+        let new_version = {
+            let mut table = transaction.open_table(STATES)?;
+            let current_version = table.get(identifier.as_str())?.map(|row| row.value().0);
+            if current_version != *version {
+                return Err(RedbError::Conflict(identifier));
+            }
+            let new_version = version.map_or(0, |version| version + 1);
+            table.insert(identifier.as_str(), (new_version, payload.as_slice()))?;
+            new_version
+        };
+        transaction.commit()?;
+        Ok((state.clone(), new_version))
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<C, S, Error> StateRepository<C, S, u64, Error> for RedbStateRepository
+where
+    C: Identifier + Sync,
+    S: Identifier + Serialize + DeserializeOwned + Clone + Send + Sync,
+    Error: From<RedbError> + Send,
+{
+    /// Fetches the state, identified by the command [Identifier].
+    async fn fetch_state(&self, command: &C) -> Result<Option<(S, u64)>, Error> {
+        Ok(fetch_row(&self.database, STATES, &command.identifier())?)
+    }
+    /// Saves the state, if it was not modified since it was fetched with the `version`.
+    async fn save(&self, state: &S, version: &Option<u64>) -> Result<(S, u64), Error> {
+        Ok(self.compare_and_swap(state, version)?)
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<C, S, Error> StateRepository<C, S, u64, Error> for RedbStateRepository
+where
+    C: Identifier,
+    S: Identifier + Serialize + DeserializeOwned + Clone,
+    Error: From<RedbError>,
+{
+    /// Fetches the state, identified by the command [Identifier].
+    async fn fetch_state(&self, command: &C) -> Result<Option<(S, u64)>, Error> {
+        Ok(fetch_row(&self.database, STATES, &command.identifier())?)
+    }
+    /// Saves the state, if it was not modified since it was fetched with the `version`.
+    async fn save(&self, state: &S, version: &Option<u64>) -> Result<(S, u64), Error> {
+        Ok(self.compare_and_swap(state, version)?)
+    }
+}
+
+/// Redb View State Repository.
+///
+/// [ViewStateRepository] and [VersionedViewStateRepository] on the embedded [redb](https://docs.rs/redb) key-value store, storing the view states in the [VIEWS] table, keyed by their [Identifier].
+/// The view states are encoded as JSON. The event is routed to the view state by its [Identifier].
+///
+/// As the [VersionedViewStateRepository], the view state is saved with the compare-and-swap: it is updated only if the version of the last event applied to it is lower than the new one.
+/// Otherwise, it fails with [RedbError::Conflict], so the concurrent handler of the older event can not overwrite the newer state.
+/// As the [ViewStateRepository], the last save wins, and the version counts the saves.
+///
+/// The database is shared by the [RedbEventRepository], [RedbStateRepository] and [RedbViewStateRepository], as it can be opened only once.
+/// The operations are blocking. It fits the single-binary deployments, with no SQL.
+pub struct RedbViewStateRepository {
+    database: Arc<Database>,
+}
+
+impl RedbViewStateRepository {
+    /// Creates a new instance of [RedbViewStateRepository] on the `database`, creating the [VIEWS] table if it does not exist.
+    pub fn new(database: Arc<Database>) -> Result<Self, RedbError> {
+        create_table(&database, VIEWS)?;
+        Ok(RedbViewStateRepository { database })
+    }
+    /// Opens the database file at the `path`, creating it if it does not exist.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, RedbError> {
+        RedbViewStateRepository::new(Arc::new(Database::create(path)?))
+    }
+    /// Returns the database.
+    pub fn database(&self) -> &Arc<Database> {
+        &self.database
+    }
+    /// Saves the view state, with the version computed from the current one by the `new_version` function. If it returns `None`, it fails with [RedbError::Conflict].
+    fn update<S: Identifier + Serialize + Clone>(
+        &self,
+        state: &S,
+        new_version: impl FnOnce(Option<u64>) -> Option<u64>,
+    ) -> Result<(S, u64), RedbError> {
+        let identifier = state.identifier();
+        let payload = serde_json::to_vec(state)?;
+        let transaction = self.database.begin_write()?;
+        let version = {
+            let mut table = transaction.open_table(VIEWS)?;
+            let current_version = table.get(identifier.as_str())?.map(|row| row.value().0);
+            let Some(version) = new_version(current_version) else {
+                return Err(RedbError::Conflict(identifier));
+            };
+            table.insert(identifier.as_str(), (version, payload.as_slice()))?;
+            version
+        };
+        transaction.commit()?;
+        Ok((state.clone(), version))
+    }
+    /// Saves the view state, counting the saves in its version.
+    fn upsert<S: Identifier + Serialize + Clone>(&self, state: &S) -> Result<S, RedbError> {
+        self.update(state, |current| {
+            Some(current.map_or(0, |current| current + 1))
+        })
+        .map(|(state, _)| state)
+    }
+    /// Saves the view state, if the version of the last event applied to it is lower than the new `version`.
+    fn compare_and_swap<S: Identifier + Serialize + Clone>(
+        &self,
+        state: &S,
+        version: &u64,
+    ) -> Result<(S, u64), RedbError> {
+        self.update(state, |current| match current {
+            Some(current) if current >= *version => None,
+            _ => Some(*version),
+        })
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<E, S, Error> ViewStateRepository<E, S, Error> for RedbViewStateRepository
+where
+    E: Identifier + Sync,
+    S: Identifier + Serialize + DeserializeOwned + Clone + Send + Sync,
+    Error: From<RedbError> + Send,
+{
+    /// Fetches the view state, identified by the event [Identifier].
+    async fn fetch_state(&self, event: &E) -> Result<Option<S>, Error> {
+        Ok(fetch_row(&self.database, VIEWS, &event.identifier())?.map(|(state, _)| state))
+    }
+    /// Saves the view state.
+    async fn save(&self, state: &S) -> Result<S, Error> {
+        Ok(self.upsert(state)?)
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<E, S, Error> ViewStateRepository<E, S, Error> for RedbViewStateRepository
+where
+    E: Identifier,
+    S: Identifier + Serialize + DeserializeOwned + Clone,
+    Error: From<RedbError>,
+{
+    /// Fetches the view state, identified by the event [Identifier].
+    async fn fetch_state(&self, event: &E) -> Result<Option<S>, Error> {
+        Ok(fetch_row(&self.database, VIEWS, &event.identifier())?.map(|(state, _)| state))
+    }
+    /// Saves the view state.
+    async fn save(&self, state: &S) -> Result<S, Error> {
+        Ok(self.upsert(state)?)
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<E, S, Error> VersionedViewStateRepository<E, S, u64, Error> for RedbViewStateRepository
+where
+    E: Identifier + Sync,
+    S: Identifier + Serialize + DeserializeOwned + Clone + Send + Sync,
+    Error: From<RedbError> + Send,
+{
+    /// Fetches the view state, and the version of the last event applied to it, identified by the event [Identifier].
+    async fn fetch_state(&self, event: &E) -> Result<Option<(S, u64)>, Error> {
+        Ok(fetch_row(&self.database, VIEWS, &event.identifier())?)
+    }
+    /// Saves the view state, if the version of the last event applied to it is lower than the new `version`.
+    async fn save(&self, state: &S, version: &u64) -> Result<(S, u64), Error> {
+        Ok(self.compare_and_swap(state, version)?)
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<E, S, Error> VersionedViewStateRepository<E, S, u64, Error> for RedbViewStateRepository
+where
+    E: Identifier,
+    S: Identifier + Serialize + DeserializeOwned + Clone,
+    Error: From<RedbError>,
+{
+    /// Fetches the view state, and the version of the last event applied to it, identified by the event [Identifier].
+    async fn fetch_state(&self, event: &E) -> Result<Option<(S, u64)>, Error> {
+        Ok(fetch_row(&self.database, VIEWS, &event.identifier())?)
+    }
+    /// Saves the view state, if the version of the last event applied to it is lower than the new `version`.
+    async fn save(&self, state: &S, version: &u64) -> Result<(S, u64), Error> {
+        Ok(self.compare_and_swap(state, version)?)
+    }
+}
@@ -1,3 +1,4 @@
+use crate::telemetry::{record, span};
 use crate::{ReactFunction, Saga3, Saga4, Saga5, Saga6, Sum, Sum3, Sum4, Sum5, Sum6};
 
 /// [Saga] is a datatype that represents the central point of control, deciding what to execute next (`A`), based on the action result (`AR`).
@@ -310,6 +311,9 @@ pub trait ActionComputation<AR, A> {
 impl<AR, A> ActionComputation<AR, A> for Saga<'_, AR, A> {
     /// Computes new commands/actions based on the event/action_result.
     fn compute_new_actions(&self, event: &AR) -> Vec<A> {
-        (self.react)(event).into_iter().collect()
+        let span = span!("react", actions_produced = tracing::field::Empty);
+        let actions: Vec<A> = span.in_scope(|| (self.react)(event).into_iter().collect());
+        record!(span, "actions_produced", actions.len());
+        actions
     }
 }
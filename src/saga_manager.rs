@@ -1,7 +1,12 @@
 use std::future::Future;
 use std::marker::PhantomData;
+use std::pin::Pin;
+use std::time::Duration;
 
 use crate::saga::ActionComputation;
+use crate::telemetry::{
+    record_result, span, Instrument, Meter, Span, ACTIONS_PUBLISHED, PUBLISH_ERRORS,
+};
 
 /// Publishes the action/command to some external system.
 ///
@@ -31,10 +36,133 @@ pub trait ActionPublisher<A, Error> {
     fn publish(&self, action: &[A]) -> impl Future<Output = Result<Vec<A>, Error>>;
 }
 
+/// The [SleepFunction] function is used to wait for the backoff duration, before the next publishing attempt.
+/// It keeps the [SagaManager] independent of the async runtime. For example, `|duration| tokio::time::sleep(duration)`.
+#[cfg(not(feature = "not-send-futures"))]
+pub type SleepFunction =
+    Box<dyn Fn(Duration) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// The [SleepFunction] function is used to wait for the backoff duration, before the next publishing attempt.
+/// It keeps the [SagaManager] independent of the async runtime. For example, `|duration| tokio::time::sleep(duration)`.
+#[cfg(feature = "not-send-futures")]
+pub type SleepFunction = Box<dyn Fn(Duration) -> Pin<Box<dyn Future<Output = ()>>>>;
+
+/// Retry policy of the action publishing, with exponential backoff.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Maximum number of publishing attempts, including the first one
+    pub max_attempts: u32,
+    /// Backoff before the first retry
+    pub initial_backoff: Duration,
+    /// Factor the backoff is multiplied by, after every retry
+    pub multiplier: u32,
+    /// Upper limit of the backoff
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Policy that publishes the actions only once, with no retries.
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            initial_backoff: Duration::ZERO,
+            multiplier: 1,
+            max_backoff: Duration::ZERO,
+        }
+    }
+    /// Computes the backoff before the next attempt, after the given number of failed `attempts`.
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        let factor = self
+            .multiplier
+            .checked_pow(attempts.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Three attempts, with the backoff starting at 100 milliseconds and doubling, up to 10 seconds.
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            multiplier: 2,
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Opens the `handle` span of the saga manager.
+fn handle_span() -> Span {
+    span!(
+        "handle",
+        component = "SagaManager",
+        actions_published = tracing::field::Empty,
+        error = tracing::field::Empty
+    )
+}
+
+/// Dead letter - the action result and the actions which could not be published, together with the error.
+///
+/// Generic parameters:
+///
+/// - `AR` - Action Result / Event
+/// - `A` - Action / Command
+/// - `Error` - Error
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeadLetter<AR, A, Error> {
+    /// The action result the actions are computed from
+    pub action_result: AR,
+    /// The computed actions, which could not be published
+    pub actions: Vec<A>,
+    /// The error of the last publishing attempt
+    pub error: Error,
+    /// Number of the publishing attempts
+    pub attempts: u32,
+}
+
+/// Captures the dead letters, so they can be inspected and re-driven later via [SagaManager::redrive].
+///
+/// Generic parameters:
+///
+/// - `AR` - Action Result / Event
+/// - `A` - Action / Command
+/// - `Error` - Error
+#[cfg(not(feature = "not-send-futures"))]
+pub trait DeadLetterSink<AR, A, Error> {
+    /// Stores the dead letter.
+    /// Desugared `async fn send(&self, dead_letter: DeadLetter<AR, A, Error>) -> Result<(), Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn send(
+        &self,
+        dead_letter: DeadLetter<AR, A, Error>,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+/// Captures the dead letters, so they can be inspected and re-driven later via [SagaManager::redrive].
+///
+/// Generic parameters:
+///
+/// - `AR` - Action Result / Event
+/// - `A` - Action / Command
+/// - `Error` - Error
+#[cfg(feature = "not-send-futures")]
+pub trait DeadLetterSink<AR, A, Error> {
+    /// Stores the dead letter.
+    /// Desugared `async fn send(&self, dead_letter: DeadLetter<AR, A, Error>) -> Result<(), Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn send(
+        &self,
+        dead_letter: DeadLetter<AR, A, Error>,
+    ) -> impl Future<Output = Result<(), Error>>;
+}
+
 /// Saga Manager.
 ///
 /// It is using a `Saga` to react to the action result and to publish the new actions.
-/// It is using an [ActionPublisher] to publish the new actions.
+/// It is using an [ActionPublisher] to publish the new actions, retrying the publishing according to the [RetryPolicy].
 ///
 /// Generic parameters:
 /// - `A` - Action / Command
@@ -48,6 +176,8 @@ where
 {
     action_publisher: Publisher,
     saga: Saga,
+    retry_policy: RetryPolicy,
+    sleep: Option<SleepFunction>,
     _marker: PhantomData<(A, AR, Error)>,
 }
 
@@ -106,17 +236,128 @@ where
         SagaManager {
             action_publisher,
             saga,
+            retry_policy: RetryPolicy::none(),
+            sleep: None,
             _marker: PhantomData,
         }
     }
+    /// Retries the failed publishing of the actions according to the `retry_policy`.
+    /// The `sleep` function is used to wait for the backoff between the attempts, for example `|duration| tokio::time::sleep(duration)`.
+    pub fn with_retry<F, Fut>(mut self, retry_policy: RetryPolicy, sleep: F) -> Self
+    where
+        F: Fn(Duration) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.retry_policy = retry_policy;
+        self.sleep = Some(Box::new(move |duration| Box::pin(sleep(duration))));
+        self
+    }
+    /// Publishes the actions, retrying according to the [RetryPolicy].
+    /// Returns the error of the last attempt, and the number of the attempts made.
+    async fn publish_with_retry(&self, actions: &[A]) -> Result<Vec<A>, (Error, u32)> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match self.publish(actions).await {
+                Ok(published_actions) => return Ok(published_actions),
+                Err(error) if attempts >= self.retry_policy.max_attempts => {
+                    return Err((error, attempts))
+                }
+                Err(_) => {
+                    if let Some(sleep) = &self.sleep {
+                        sleep(self.retry_policy.backoff(attempts)).await;
+                    }
+                }
+            }
+        }
+    }
+    /// Publishes the actions like [SagaManager::publish_with_retry] does, within the `publish` span, and counts the published actions and the failed publishing.
+    async fn publish_instrumented(&self, actions: &[A]) -> Result<Vec<A>, (Error, u32)> {
+        let meter = Meter::new::<AR>("SagaManager");
+        let span = span!(
+            "publish",
+            actions = actions.len(),
+            error = tracing::field::Empty
+        );
+        let published_actions = self
+            .publish_with_retry(actions)
+            .instrument(span.clone())
+            .await;
+        record_result!(span, published_actions);
+        meter.increment_on_error(PUBLISH_ERRORS, &published_actions);
+        if let Ok(published_actions) = &published_actions {
+            meter.increment(ACTIONS_PUBLISHED, published_actions.len());
+        }
+        published_actions
+    }
+    /// Handles the `action result` like [SagaManager::handle] does, but captures the failed publishing as a [DeadLetter] in the `sink`, instead of returning the error.
+    /// Returns no actions if the dead letter is captured. The error is returned only if the `sink` fails to store the dead letter.
+    pub async fn handle_with_dead_letter<Sink>(
+        &self,
+        action_result: &AR,
+        sink: &Sink,
+    ) -> Result<Vec<A>, Error>
+    where
+        Sink: DeadLetterSink<AR, A, Error> + Sync,
+        AR: Clone,
+    {
+        let span = handle_span();
+        let result = async {
+            let new_actions = self.compute_new_actions(action_result);
+            match self.publish_instrumented(&new_actions).await {
+                Ok(published_actions) => Ok(published_actions),
+                Err((error, attempts)) => {
+                    sink.send(DeadLetter {
+                        action_result: action_result.clone(),
+                        actions: new_actions,
+                        error,
+                        attempts,
+                    })
+                    .instrument(span!("dead_letter"))
+                    .await?;
+                    Ok(vec![])
+                }
+            }
+        }
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result, "actions_published", Vec::len);
+        result
+    }
+    /// Re-drives the [DeadLetter], publishing its actions again, with retries.
+    /// The actions are not recomputed, they are published as they were captured.
+    pub async fn redrive(&self, dead_letter: &DeadLetter<AR, A, Error>) -> Result<Vec<A>, Error> {
+        let span = span!(
+            "redrive",
+            component = "SagaManager",
+            actions_published = tracing::field::Empty,
+            error = tracing::field::Empty
+        );
+        let result = self
+            .publish_instrumented(&dead_letter.actions)
+            .instrument(span.clone())
+            .await
+            .map_err(|(error, _)| error);
+        record_result!(span, result, "actions_published", Vec::len);
+        result
+    }
     /// Handles the `action result` by computing new `actions` based on `action result`, and publishing new `actions` to the external system.
+    /// The publishing is retried according to the [RetryPolicy], and the error of the last attempt is returned.
     /// In most cases:
     ///  - the `action result` is an `event` that you react,
     ///  - the `actions` are `commands` that you publish downstream.
     pub async fn handle(&self, action_result: &AR) -> Result<Vec<A>, Error> {
-        let new_actions = self.compute_new_actions(action_result);
-        let published_actions = self.publish(&new_actions).await?;
-        Ok(published_actions)
+        let span = handle_span();
+        let result = async {
+            let new_actions = self.compute_new_actions(action_result);
+            self.publish_instrumented(&new_actions)
+                .await
+                .map_err(|(error, _)| error)
+        }
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result, "actions_published", Vec::len);
+        result
     }
 }
 
@@ -131,16 +372,127 @@ where
         SagaManager {
             action_publisher,
             saga,
+            retry_policy: RetryPolicy::none(),
+            sleep: None,
             _marker: PhantomData,
         }
     }
+    /// Retries the failed publishing of the actions according to the `retry_policy`.
+    /// The `sleep` function is used to wait for the backoff between the attempts, for example `|duration| tokio::time::sleep(duration)`.
+    pub fn with_retry<F, Fut>(mut self, retry_policy: RetryPolicy, sleep: F) -> Self
+    where
+        F: Fn(Duration) -> Fut + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.retry_policy = retry_policy;
+        self.sleep = Some(Box::new(move |duration| Box::pin(sleep(duration))));
+        self
+    }
+    /// Publishes the actions, retrying according to the [RetryPolicy].
+    /// Returns the error of the last attempt, and the number of the attempts made.
+    async fn publish_with_retry(&self, actions: &[A]) -> Result<Vec<A>, (Error, u32)> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match self.publish(actions).await {
+                Ok(published_actions) => return Ok(published_actions),
+                Err(error) if attempts >= self.retry_policy.max_attempts => {
+                    return Err((error, attempts))
+                }
+                Err(_) => {
+                    if let Some(sleep) = &self.sleep {
+                        sleep(self.retry_policy.backoff(attempts)).await;
+                    }
+                }
+            }
+        }
+    }
+    /// Publishes the actions like [SagaManager::publish_with_retry] does, within the `publish` span, and counts the published actions and the failed publishing.
+    async fn publish_instrumented(&self, actions: &[A]) -> Result<Vec<A>, (Error, u32)> {
+        let meter = Meter::new::<AR>("SagaManager");
+        let span = span!(
+            "publish",
+            actions = actions.len(),
+            error = tracing::field::Empty
+        );
+        let published_actions = self
+            .publish_with_retry(actions)
+            .instrument(span.clone())
+            .await;
+        record_result!(span, published_actions);
+        meter.increment_on_error(PUBLISH_ERRORS, &published_actions);
+        if let Ok(published_actions) = &published_actions {
+            meter.increment(ACTIONS_PUBLISHED, published_actions.len());
+        }
+        published_actions
+    }
+    /// Handles the `action result` like [SagaManager::handle] does, but captures the failed publishing as a [DeadLetter] in the `sink`, instead of returning the error.
+    /// Returns no actions if the dead letter is captured. The error is returned only if the `sink` fails to store the dead letter.
+    pub async fn handle_with_dead_letter<Sink>(
+        &self,
+        action_result: &AR,
+        sink: &Sink,
+    ) -> Result<Vec<A>, Error>
+    where
+        Sink: DeadLetterSink<AR, A, Error>,
+        AR: Clone,
+    {
+        let span = handle_span();
+        let result = async {
+            let new_actions = self.compute_new_actions(action_result);
+            match self.publish_instrumented(&new_actions).await {
+                Ok(published_actions) => Ok(published_actions),
+                Err((error, attempts)) => {
+                    sink.send(DeadLetter {
+                        action_result: action_result.clone(),
+                        actions: new_actions,
+                        error,
+                        attempts,
+                    })
+                    .instrument(span!("dead_letter"))
+                    .await?;
+                    Ok(vec![])
+                }
+            }
+        }
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result, "actions_published", Vec::len);
+        result
+    }
+    /// Re-drives the [DeadLetter], publishing its actions again, with retries.
+    /// The actions are not recomputed, they are published as they were captured.
+    pub async fn redrive(&self, dead_letter: &DeadLetter<AR, A, Error>) -> Result<Vec<A>, Error> {
+        let span = span!(
+            "redrive",
+            component = "SagaManager",
+            actions_published = tracing::field::Empty,
+            error = tracing::field::Empty
+        );
+        let result = self
+            .publish_instrumented(&dead_letter.actions)
+            .instrument(span.clone())
+            .await
+            .map_err(|(error, _)| error);
+        record_result!(span, result, "actions_published", Vec::len);
+        result
+    }
     /// Handles the `action result` by computing new `actions` based on `action result`, and publishing new `actions` to the external system.
+    /// The publishing is retried according to the [RetryPolicy], and the error of the last attempt is returned.
     /// In most cases:
     ///  - the `action result` is an `event` that you react,
     ///  - the `actions` are `commands` that you publish downstream.
     pub async fn handle(&self, action_result: &AR) -> Result<Vec<A>, Error> {
-        let new_actions = self.compute_new_actions(action_result);
-        let published_actions = self.publish(&new_actions).await?;
-        Ok(published_actions)
+        let span = handle_span();
+        let result = async {
+            let new_actions = self.compute_new_actions(action_result);
+            self.publish_instrumented(&new_actions)
+                .await
+                .map_err(|(error, _)| error)
+        }
+        .instrument(span.clone())
+        .await;
+        record_result!(span, result, "actions_published", Vec::len);
+        result
     }
 }
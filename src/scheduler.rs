@@ -0,0 +1,553 @@
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::projection::DEFAULT_BATCH_SIZE;
+use crate::saga_manager::ActionPublisher;
+use crate::Identifier;
+
+/// The error of scheduling the action.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchedulerError {
+    /// The due time, the current time plus the delay, can not be represented by the [SystemTime]. Contains the delay.
+    DueTimeOverflow(Duration),
+    /// The [ManualClock] can not be moved forward by the duration, as the time can not be represented by the [SystemTime]. Contains the duration.
+    ClockOverflow(Duration),
+}
+
+impl Display for SchedulerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchedulerError::DueTimeOverflow(delay) => {
+                write!(f, "the due time after the delay of {:?} overflows", delay)
+            }
+            SchedulerError::ClockOverflow(duration) => {
+                write!(f, "the clock moved forward by {:?} overflows", duration)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchedulerError {}
+
+/// Action that should be executed after the `delay`.
+///
+/// Sagas and processes stay pure by emitting the delay only. The due time is computed by the [ScheduledActionPublisher], using the [Clock].
+/// For example, "cancel the order if it is not paid in 30 minutes" is modeled by reacting to the order created event with the cancel command, delayed by 30 minutes.
+///
+/// Generic parameters:
+///
+/// - `A` - Action / Command
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledAction<A> {
+    /// The action to execute
+    pub action: A,
+    /// The delay, after which the action is due
+    pub delay: Duration,
+}
+
+impl<A> ScheduledAction<A> {
+    /// Creates the action, which is due after the `delay`.
+    pub fn after(delay: Duration, action: A) -> Self {
+        ScheduledAction { action, delay }
+    }
+    /// Creates the action, which is due immediately.
+    pub fn now(action: A) -> Self {
+        ScheduledAction {
+            action,
+            delay: Duration::ZERO,
+        }
+    }
+}
+
+/// Provides the current time. Inject the [ManualClock] to control the time in the tests.
+pub trait Clock {
+    /// Returns the current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// [Clock] reading the system wall-clock time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    /// Returns the current system time.
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// [Clock] that is moved forward manually. The clones share the same time.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<SystemTime>>,
+}
+
+impl ManualClock {
+    /// Creates a new instance of [ManualClock], starting at the given time.
+    pub fn new(now: SystemTime) -> Self {
+        ManualClock {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+    /// Moves the clock forward by the `duration`.
+    /// Fails with the [SchedulerError::ClockOverflow] if the time overflows, and the clock is left at its current time.
+    pub fn advance(&self, duration: Duration) -> Result<(), SchedulerError> {
+        let mut now = self.now.lock().unwrap();
+        *now = now
+            .checked_add(duration)
+            .ok_or(SchedulerError::ClockOverflow(duration))?;
+        Ok(())
+    }
+}
+
+impl Default for ManualClock {
+    /// Starts at the [SystemTime::UNIX_EPOCH].
+    fn default() -> Self {
+        ManualClock::new(SystemTime::UNIX_EPOCH)
+    }
+}
+
+impl Clock for ManualClock {
+    /// Returns the current manual time.
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// Scheduler trait
+///
+/// Stores the actions until they are due, and reads the due actions. The pending actions can be canceled by their [Identifier].
+///
+/// Generic parameters:
+///
+/// - `A` - Action / Command
+/// - `Id` - Identifier of the scheduled action
+/// - `Error` - Error
+#[cfg(not(feature = "not-send-futures"))]
+pub trait Scheduler<A, Id, Error> {
+    /// Schedules the actions, every one to be due at its time. Returns the identifiers of the scheduled actions, in the order of the actions.
+    /// The actions are scheduled atomically: either all of them are scheduled, or none, if it fails.
+    /// Desugared `async fn schedule(&self, actions: &[(A, SystemTime)]) -> Result<Vec<Id>, Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn schedule(
+        &self,
+        actions: &[(A, SystemTime)],
+    ) -> impl Future<Output = Result<Vec<Id>, Error>> + Send;
+    /// Fetches at most `limit` actions, which are due at the `now` time, the earliest first.
+    /// Desugared `async fn fetch_due(&self, now: &SystemTime, limit: usize) -> Result<Vec<(Id, A)>, Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn fetch_due(
+        &self,
+        now: &SystemTime,
+        limit: usize,
+    ) -> impl Future<Output = Result<Vec<(Id, A)>, Error>> + Send;
+    /// Removes the dispatched actions, so they are not fetched again.
+    /// Desugared `async fn acknowledge(&self, ids: &[Id]) -> Result<(), Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn acknowledge(&self, ids: &[Id]) -> impl Future<Output = Result<(), Error>> + Send;
+    /// Cancels the scheduled actions with the given `key` (the [Identifier] of the action), which are not dispatched yet. Returns the number of the canceled actions.
+    /// All the pending actions with the `key` are canceled, not only the last scheduled one. For example, both the payment reminder and the deadline to cancel the unpaid order are canceled once the order is paid.
+    /// The action fetched by the running [DeadlineRunner], and not acknowledged yet, is canceled too, but it may still be published by that run.
+    /// Desugared `async fn cancel(&self, key: &str) -> Result<usize, Error>;` to a normal `fn` that returns `impl Future`, and adds bound `Send`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls. This is true even when one form has a Send bound.
+    fn cancel(&self, key: &str) -> impl Future<Output = Result<usize, Error>> + Send;
+}
+
+/// Scheduler trait
+///
+/// Stores the actions until they are due, and reads the due actions. The pending actions can be canceled by their [Identifier].
+///
+/// Generic parameters:
+///
+/// - `A` - Action / Command
+/// - `Id` - Identifier of the scheduled action
+/// - `Error` - Error
+#[cfg(feature = "not-send-futures")]
+pub trait Scheduler<A, Id, Error> {
+    /// Schedules the actions, every one to be due at its time. Returns the identifiers of the scheduled actions, in the order of the actions.
+    /// The actions are scheduled atomically: either all of them are scheduled, or none, if it fails.
+    /// Desugared `async fn schedule(&self, actions: &[(A, SystemTime)]) -> Result<Vec<Id>, Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn schedule(&self, actions: &[(A, SystemTime)])
+        -> impl Future<Output = Result<Vec<Id>, Error>>;
+    /// Fetches at most `limit` actions, which are due at the `now` time, the earliest first.
+    /// Desugared `async fn fetch_due(&self, now: &SystemTime, limit: usize) -> Result<Vec<(Id, A)>, Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn fetch_due(
+        &self,
+        now: &SystemTime,
+        limit: usize,
+    ) -> impl Future<Output = Result<Vec<(Id, A)>, Error>>;
+    /// Removes the dispatched actions, so they are not fetched again.
+    /// Desugared `async fn acknowledge(&self, ids: &[Id]) -> Result<(), Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn acknowledge(&self, ids: &[Id]) -> impl Future<Output = Result<(), Error>>;
+    /// Cancels the scheduled actions with the given `key` (the [Identifier] of the action), which are not dispatched yet. Returns the number of the canceled actions.
+    /// All the pending actions with the `key` are canceled, not only the last scheduled one. For example, both the payment reminder and the deadline to cancel the unpaid order are canceled once the order is paid.
+    /// The action fetched by the running [DeadlineRunner], and not acknowledged yet, is canceled too, but it may still be published by that run.
+    /// Desugared `async fn cancel(&self, key: &str) -> Result<usize, Error>;` to a normal `fn` that returns `impl Future`.
+    /// You can freely move between the `async fn` and `-> impl Future` spelling in your traits and impls.
+    fn cancel(&self, key: &str) -> impl Future<Output = Result<usize, Error>>;
+}
+
+/// In-memory [Scheduler]. The scheduled actions are lost on restart, so it is meant for the tests and the prototypes.
+/// The clones share the same scheduled actions.
+#[derive(Debug)]
+pub struct InMemoryScheduler<A> {
+    scheduled: Arc<Mutex<InMemorySchedule<A>>>,
+}
+
+#[derive(Debug)]
+struct InMemorySchedule<A> {
+    next_id: u64,
+    actions: Vec<(u64, A, SystemTime)>,
+}
+
+impl<A> InMemoryScheduler<A> {
+    /// Creates a new instance of [InMemoryScheduler].
+    pub fn new() -> Self {
+        InMemoryScheduler {
+            scheduled: Arc::new(Mutex::new(InMemorySchedule {
+                next_id: 0,
+                actions: vec![],
+            })),
+        }
+    }
+    /// Returns the number of the scheduled actions, which are not acknowledged yet.
+    pub fn len(&self) -> usize {
+        self.scheduled.lock().unwrap().actions.len()
+    }
+    /// Returns `true` if there are no scheduled actions.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<A> Default for InMemoryScheduler<A> {
+    fn default() -> Self {
+        InMemoryScheduler::new()
+    }
+}
+
+impl<A> Clone for InMemoryScheduler<A> {
+    fn clone(&self) -> Self {
+        InMemoryScheduler {
+            scheduled: Arc::clone(&self.scheduled),
+        }
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<A, Error> Scheduler<A, u64, Error> for InMemoryScheduler<A>
+where
+    A: Identifier + Clone + Send + Sync,
+{
+    /// Schedules the actions, every one to be due at its time. Returns the identifiers of the scheduled actions.
+    async fn schedule(&self, actions: &[(A, SystemTime)]) -> Result<Vec<u64>, Error> {
+        let mut scheduled = self.scheduled.lock().unwrap();
+        let mut ids = Vec::with_capacity(actions.len());
+        for (action, due_at) in actions {
+            let id = scheduled.next_id;
+            scheduled.next_id += 1;
+            scheduled.actions.push((id, action.clone(), *due_at));
+            ids.push(id);
+        }
+        Ok(ids)
+    }
+    /// Fetches at most `limit` actions, which are due at the `now` time, the earliest first.
+    async fn fetch_due(&self, now: &SystemTime, limit: usize) -> Result<Vec<(u64, A)>, Error> {
+        let scheduled = self.scheduled.lock().unwrap();
+        let mut due: Vec<&(u64, A, SystemTime)> = scheduled
+            .actions
+            .iter()
+            .filter(|(_, _, due_at)| due_at <= now)
+            .collect();
+        due.sort_by_key(|(id, _, due_at)| (*due_at, *id));
+        Ok(due
+            .into_iter()
+            .take(limit)
+            .map(|(id, action, _)| (*id, action.clone()))
+            .collect())
+    }
+    /// Removes the dispatched actions, so they are not fetched again.
+    async fn acknowledge(&self, ids: &[u64]) -> Result<(), Error> {
+        self.scheduled
+            .lock()
+            .unwrap()
+            .actions
+            .retain(|(id, _, _)| !ids.contains(id));
+        Ok(())
+    }
+    /// Cancels all the scheduled actions with the given `key` (the [Identifier] of the action), which are not acknowledged yet. Returns the number of the canceled actions.
+    async fn cancel(&self, key: &str) -> Result<usize, Error> {
+        let mut scheduled = self.scheduled.lock().unwrap();
+        let count = scheduled.actions.len();
+        scheduled
+            .actions
+            .retain(|(_, action, _)| action.identifier() != key);
+        Ok(count - scheduled.actions.len())
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<A, Error> Scheduler<A, u64, Error> for InMemoryScheduler<A>
+where
+    A: Identifier + Clone,
+{
+    /// Schedules the actions, every one to be due at its time. Returns the identifiers of the scheduled actions.
+    async fn schedule(&self, actions: &[(A, SystemTime)]) -> Result<Vec<u64>, Error> {
+        let mut scheduled = self.scheduled.lock().unwrap();
+        let mut ids = Vec::with_capacity(actions.len());
+        for (action, due_at) in actions {
+            let id = scheduled.next_id;
+            scheduled.next_id += 1;
+            scheduled.actions.push((id, action.clone(), *due_at));
+            ids.push(id);
+        }
+        Ok(ids)
+    }
+    /// Fetches at most `limit` actions, which are due at the `now` time, the earliest first.
+    async fn fetch_due(&self, now: &SystemTime, limit: usize) -> Result<Vec<(u64, A)>, Error> {
+        let scheduled = self.scheduled.lock().unwrap();
+        let mut due: Vec<&(u64, A, SystemTime)> = scheduled
+            .actions
+            .iter()
+            .filter(|(_, _, due_at)| due_at <= now)
+            .collect();
+        due.sort_by_key(|(id, _, due_at)| (*due_at, *id));
+        Ok(due
+            .into_iter()
+            .take(limit)
+            .map(|(id, action, _)| (*id, action.clone()))
+            .collect())
+    }
+    /// Removes the dispatched actions, so they are not fetched again.
+    async fn acknowledge(&self, ids: &[u64]) -> Result<(), Error> {
+        self.scheduled
+            .lock()
+            .unwrap()
+            .actions
+            .retain(|(id, _, _)| !ids.contains(id));
+        Ok(())
+    }
+    /// Cancels all the scheduled actions with the given `key` (the [Identifier] of the action), which are not acknowledged yet. Returns the number of the canceled actions.
+    async fn cancel(&self, key: &str) -> Result<usize, Error> {
+        let mut scheduled = self.scheduled.lock().unwrap();
+        let count = scheduled.actions.len();
+        scheduled
+            .actions
+            .retain(|(_, action, _)| action.identifier() != key);
+        Ok(count - scheduled.actions.len())
+    }
+}
+
+/// Scheduled Action Publisher.
+///
+/// [ActionPublisher] of the [ScheduledAction]s, storing them into the [Scheduler] with the due time computed by the [Clock].
+/// Use it as the publisher of the [SagaManager](crate::saga_manager::SagaManager) or [ProcessManager](crate::process_manager::ProcessManager), whose saga/process emits the [ScheduledAction]s.
+///
+/// Generic parameters:
+///
+/// - `A` - Action / Command
+/// - `Id` - Identifier of the scheduled action
+/// - `Storage` - Scheduler
+/// - `C` - Clock
+/// - `Error` - Error
+pub struct ScheduledActionPublisher<A, Id, Storage, C, Error>
+where
+    Storage: Scheduler<A, Id, Error>,
+    C: Clock,
+{
+    scheduler: Storage,
+    clock: C,
+    _marker: PhantomData<(A, Id, Error)>,
+}
+
+impl<A, Id, Storage, C, Error> ScheduledActionPublisher<A, Id, Storage, C, Error>
+where
+    Storage: Scheduler<A, Id, Error>,
+    C: Clock,
+{
+    /// Creates a new instance of [ScheduledActionPublisher].
+    pub fn new(scheduler: Storage, clock: C) -> Self {
+        ScheduledActionPublisher {
+            scheduler,
+            clock,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<A, Id, Storage, C, Error> ActionPublisher<ScheduledAction<A>, Error>
+    for ScheduledActionPublisher<A, Id, Storage, C, Error>
+where
+    Storage: Scheduler<A, Id, Error> + Sync,
+    C: Clock + Sync,
+    A: Clone + Send + Sync,
+    Id: Sync,
+    Error: From<SchedulerError> + Sync,
+{
+    /// Schedules the actions, returning the scheduled actions.
+    /// The actions are scheduled together, by the single [Scheduler::schedule], so either all of them are scheduled, or none.
+    /// Fails with the [SchedulerError::DueTimeOverflow] if the due time of any action overflows, before any action is scheduled.
+    async fn publish(
+        &self,
+        action: &[ScheduledAction<A>],
+    ) -> Result<Vec<ScheduledAction<A>>, Error> {
+        let now = self.clock.now();
+        let actions = action
+            .iter()
+            .map(|scheduled_action| {
+                now.checked_add(scheduled_action.delay)
+                    .map(|due_at| (scheduled_action.action.clone(), due_at))
+                    .ok_or(SchedulerError::DueTimeOverflow(scheduled_action.delay))
+            })
+            .collect::<Result<Vec<(A, SystemTime)>, SchedulerError>>()?;
+        self.scheduler.schedule(&actions).await?;
+        Ok(Vec::from(action))
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<A, Id, Storage, C, Error> ActionPublisher<ScheduledAction<A>, Error>
+    for ScheduledActionPublisher<A, Id, Storage, C, Error>
+where
+    Storage: Scheduler<A, Id, Error>,
+    C: Clock,
+    A: Clone,
+    Error: From<SchedulerError>,
+{
+    /// Schedules the actions, returning the scheduled actions.
+    /// The actions are scheduled together, by the single [Scheduler::schedule], so either all of them are scheduled, or none.
+    /// Fails with the [SchedulerError::DueTimeOverflow] if the due time of any action overflows, before any action is scheduled.
+    async fn publish(
+        &self,
+        action: &[ScheduledAction<A>],
+    ) -> Result<Vec<ScheduledAction<A>>, Error> {
+        let now = self.clock.now();
+        let actions = action
+            .iter()
+            .map(|scheduled_action| {
+                now.checked_add(scheduled_action.delay)
+                    .map(|due_at| (scheduled_action.action.clone(), due_at))
+                    .ok_or(SchedulerError::DueTimeOverflow(scheduled_action.delay))
+            })
+            .collect::<Result<Vec<(A, SystemTime)>, SchedulerError>>()?;
+        self.scheduler.schedule(&actions).await?;
+        Ok(Vec::from(action))
+    }
+}
+
+/// Deadline Runner.
+///
+/// It is dispatching the due actions from the [Scheduler] into the [ActionPublisher].
+/// The time is read from the [Clock], so the runner can be driven deterministically in the tests, with the [ManualClock].
+/// Actions are acknowledged only after they are published, so they are delivered `at least once`.
+///
+/// Generic parameters:
+///
+/// - `A` - Action / Command
+/// - `Id` - Identifier of the scheduled action
+/// - `Storage` - Scheduler
+/// - `C` - Clock
+/// - `Publisher` - Action Publisher
+/// - `Error` - Error
+pub struct DeadlineRunner<A, Id, Storage, C, Publisher, Error>
+where
+    Storage: Scheduler<A, Id, Error>,
+    C: Clock,
+    Publisher: ActionPublisher<A, Error>,
+{
+    scheduler: Storage,
+    clock: C,
+    action_publisher: Publisher,
+    batch_size: usize,
+    _marker: PhantomData<(A, Id, Error)>,
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<A, Id, Storage, C, Publisher, Error> DeadlineRunner<A, Id, Storage, C, Publisher, Error>
+where
+    Storage: Scheduler<A, Id, Error> + Sync,
+    C: Clock + Sync,
+    Publisher: ActionPublisher<A, Error> + Sync,
+    A: Sync,
+    Id: Sync,
+    Error: Sync,
+{
+    /// Creates a new instance of [DeadlineRunner].
+    pub fn new(scheduler: Storage, clock: C, action_publisher: Publisher) -> Self {
+        DeadlineRunner {
+            scheduler,
+            clock,
+            action_publisher,
+            batch_size: DEFAULT_BATCH_SIZE,
+            _marker: PhantomData,
+        }
+    }
+    /// Sets the maximum number of actions fetched from the [Scheduler] at once.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+    /// Publishes all the actions that are due at the current time of the [Clock], and acknowledges them once published.
+    /// Returns the number of the dispatched actions.
+    pub async fn dispatch_due(&self) -> Result<usize, Error> {
+        let now = self.clock.now();
+        let mut total = 0;
+        loop {
+            let due = self.scheduler.fetch_due(&now, self.batch_size).await?;
+            if due.is_empty() {
+                return Ok(total);
+            }
+            let (ids, actions): (Vec<Id>, Vec<A>) = due.into_iter().unzip();
+            self.action_publisher.publish(&actions).await?;
+            self.scheduler.acknowledge(&ids).await?;
+            total += ids.len();
+        }
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<A, Id, Storage, C, Publisher, Error> DeadlineRunner<A, Id, Storage, C, Publisher, Error>
+where
+    Storage: Scheduler<A, Id, Error>,
+    C: Clock,
+    Publisher: ActionPublisher<A, Error>,
+{
+    /// Creates a new instance of [DeadlineRunner].
+    pub fn new(scheduler: Storage, clock: C, action_publisher: Publisher) -> Self {
+        DeadlineRunner {
+            scheduler,
+            clock,
+            action_publisher,
+            batch_size: DEFAULT_BATCH_SIZE,
+            _marker: PhantomData,
+        }
+    }
+    /// Sets the maximum number of actions fetched from the [Scheduler] at once.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+    /// Publishes all the actions that are due at the current time of the [Clock], and acknowledges them once published.
+    /// Returns the number of the dispatched actions.
+    pub async fn dispatch_due(&self) -> Result<usize, Error> {
+        let now = self.clock.now();
+        let mut total = 0;
+        loop {
+            let due = self.scheduler.fetch_due(&now, self.batch_size).await?;
+            if due.is_empty() {
+                return Ok(total);
+            }
+            let (ids, actions): (Vec<Id>, Vec<A>) = due.into_iter().unzip();
+            self.action_publisher.publish(&actions).await?;
+            self.scheduler.acknowledge(&ids).await?;
+            total += ids.len();
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+#[cfg(feature = "not-send-futures")]
+use std::rc::Rc;
+#[cfg(not(feature = "not-send-futures"))]
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use tower_service::Service;
+
+use crate::middleware::Handler;
+
+/// The [ServiceFuture] is the boxed future returned by the [HandlerService].
+#[cfg(not(feature = "not-send-futures"))]
+pub type ServiceFuture<Output, Error> =
+    Pin<Box<dyn Future<Output = Result<Output, Error>> + Send + 'static>>;
+
+/// The [ServiceFuture] is the boxed future returned by the [HandlerService].
+#[cfg(feature = "not-send-futures")]
+pub type ServiceFuture<Output, Error> =
+    Pin<Box<dyn Future<Output = Result<Output, Error>> + 'static>>;
+
+/// Handler Service.
+///
+/// Adapts any [Handler] (aggregate, materialized view, saga manager, or the [Intercepted](crate::middleware::Intercepted) one) to the `tower::Service`,
+/// so the middleware from the `tower` ecosystem (timeouts, rate limiting, load shedding, tracing, ...) applies directly,
+/// and the handler can be exposed behind `axum`/`tonic`.
+///
+/// The handler is shared, so the service is cheap to clone. It is always ready.
+///
+/// Generic parameters:
+///
+/// - `H` - Handler
+/// - `Input` - Command / Event / Action Result
+/// - `Output` - Result of the handling
+/// - `Error` - Error
+pub struct HandlerService<H, Input, Output, Error> {
+    #[cfg(not(feature = "not-send-futures"))]
+    handler: Arc<H>,
+    #[cfg(feature = "not-send-futures")]
+    handler: Rc<H>,
+    _marker: PhantomData<fn(Input) -> Result<Output, Error>>,
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<H, Input, Output, Error> HandlerService<H, Input, Output, Error> {
+    /// Creates a new instance of [HandlerService].
+    pub fn new(handler: H) -> Self {
+        HandlerService::from_shared(Arc::new(handler))
+    }
+    /// Creates a new instance of [HandlerService], from the already shared `handler`.
+    pub fn from_shared(handler: Arc<H>) -> Self {
+        HandlerService {
+            handler,
+            _marker: PhantomData,
+        }
+    }
+    /// Returns the handler.
+    pub fn handler(&self) -> &Arc<H> {
+        &self.handler
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<H, Input, Output, Error> HandlerService<H, Input, Output, Error> {
+    /// Creates a new instance of [HandlerService].
+    pub fn new(handler: H) -> Self {
+        HandlerService::from_shared(Rc::new(handler))
+    }
+    /// Creates a new instance of [HandlerService], from the already shared `handler`.
+    pub fn from_shared(handler: Rc<H>) -> Self {
+        HandlerService {
+            handler,
+            _marker: PhantomData,
+        }
+    }
+    /// Returns the handler.
+    pub fn handler(&self) -> &Rc<H> {
+        &self.handler
+    }
+}
+
+impl<H, Input, Output, Error> Clone for HandlerService<H, Input, Output, Error> {
+    fn clone(&self) -> Self {
+        HandlerService {
+            handler: self.handler.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<H, Input, Output, Error> Service<Input> for HandlerService<H, Input, Output, Error>
+where
+    H: Handler<Input, Output, Error> + Send + Sync + 'static,
+    Input: Send + Sync + 'static,
+    Output: 'static,
+    Error: 'static,
+{
+    type Response = Output;
+    type Error = Error;
+    type Future = ServiceFuture<Output, Error>;
+
+    /// The handler is always ready.
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    /// Handles the input by the handler.
+    fn call(&mut self, input: Input) -> Self::Future {
+        let handler = Arc::clone(&self.handler);
+        Box::pin(async move { handler.handle(&input).await })
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<H, Input, Output, Error> Service<Input> for HandlerService<H, Input, Output, Error>
+where
+    H: Handler<Input, Output, Error> + 'static,
+    Input: 'static,
+    Output: 'static,
+    Error: 'static,
+{
+    type Response = Output;
+    type Error = Error;
+    type Future = ServiceFuture<Output, Error>;
+
+    /// The handler is always ready.
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    /// Handles the input by the handler.
+    fn call(&mut self, input: Input) -> Self::Future {
+        let handler = Rc::clone(&self.handler);
+        Box::pin(async move { handler.handle(&input).await })
+    }
+}
@@ -0,0 +1,659 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension, TransactionBehavior};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::aggregate::{EventRepository, EventStream, StateRepository, StreamingEventRepository};
+use crate::materialized_view::{VersionedViewStateRepository, ViewStateRepository};
+use crate::Identifier;
+
+/// The schema of the SQLite event store.
+///
+/// - `position` - global position of the event, in the order the events are appended
+/// - `stream_id` - [Identifier] of the event stream
+/// - `version` - version of the event, within the stream. It starts at `0`.
+/// - `payload` - the event, encoded as JSON
+///
+/// The `(stream_id, version)` pair is unique, so the concurrent appends of the same version to the stream conflict.
+pub const EVENTS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS events (
+    position INTEGER PRIMARY KEY AUTOINCREMENT,
+    stream_id TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    payload TEXT NOT NULL,
+    UNIQUE (stream_id, version)
+)";
+
+/// The schema of the SQLite state store.
+///
+/// - `id` - [Identifier] of the state
+/// - `version` - version of the state. It starts at `0`, and is incremented by every save.
+/// - `payload` - the state, encoded as JSON
+pub const STATES_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS states (
+    id TEXT PRIMARY KEY,
+    version INTEGER NOT NULL,
+    payload TEXT NOT NULL
+)";
+
+/// The schema of the SQLite view store.
+///
+/// - `id` - [Identifier] of the view state
+/// - `version` - version of the last event applied to the view state, or the number of the saves (counting from `0`) if the events are not versioned
+/// - `payload` - the view state, encoded as JSON
+pub const VIEWS_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS views (
+    id TEXT PRIMARY KEY,
+    version INTEGER NOT NULL,
+    payload TEXT NOT NULL
+)";
+
+/// The error of the SQLite stores.
+#[derive(Debug)]
+pub enum SqliteError {
+    /// The database error.
+    Database(rusqlite::Error),
+    /// The error of encoding/decoding the payload.
+    Serialization(serde_json::Error),
+    /// The version was already taken by the concurrent write of the stream, identified by the identifier.
+    Conflict(String),
+}
+
+impl Display for SqliteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SqliteError::Database(error) => write!(f, "database error: {}", error),
+            SqliteError::Serialization(error) => write!(f, "serialization error: {}", error),
+            SqliteError::Conflict(identifier) => {
+                write!(f, "concurrent modification of `{}`", identifier)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SqliteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SqliteError::Database(error) => Some(error),
+            SqliteError::Serialization(error) => Some(error),
+            SqliteError::Conflict(_) => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for SqliteError {
+    fn from(error: rusqlite::Error) -> Self {
+        SqliteError::Database(error)
+    }
+}
+
+impl From<serde_json::Error> for SqliteError {
+    fn from(error: serde_json::Error) -> Self {
+        SqliteError::Serialization(error)
+    }
+}
+
+/// Fetches the payload and the version of the row, identified by the `id`, from the `table`.
+fn fetch_row<S: DeserializeOwned>(
+    connection: &Connection,
+    table: &str,
+    id: &str,
+) -> Result<Option<(S, i64)>, SqliteError> {
+    let row = connection
+        .query_row(
+            &format!("SELECT payload, version FROM {} WHERE id = ?1", table),
+            params![id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+        )
+        .optional()?;
+    match row {
+        Some((payload, version)) => Ok(Some((serde_json::from_str(&payload)?, version))),
+        None => Ok(None),
+    }
+}
+
+/// Maps the unique constraint violation to the [SqliteError::Conflict].
+fn conflict(error: rusqlite::Error, identifier: &str) -> SqliteError {
+    match error.sqlite_error_code() {
+        Some(ErrorCode::ConstraintViolation) => SqliteError::Conflict(identifier.to_string()),
+        _ => SqliteError::Database(error),
+    }
+}
+
+/// SQLite Event Repository.
+///
+/// [EventRepository] on the embedded SQLite database, storing the events in the `events` table (see [EVENTS_SCHEMA]).
+/// The events are encoded as JSON, and grouped into the streams by their [Identifier]. The command is routed to the stream by its [Identifier], too.
+/// The JSON is fixed, so the events stay queryable by the SQL; the [EventCodec](crate::codec::EventCodec) is not used by the store.
+///
+/// The new events are appended in the single (immediate) transaction, with the next versions of their streams.
+/// The aggregates save the events with [EventRepository::save_after], so the events of the command stream are appended only if the stream is still at the version the command was decided on.
+/// Otherwise, the save is rejected with [SqliteError::Conflict], and the command can be retried on the fresh state.
+/// The unique `(stream_id, version)` constraint backs the check up, if the database is shared with the other writers.
+///
+/// The connection is guarded by the mutex, and the queries are blocking. It fits the small services and the integration tests.
+pub struct SqliteEventRepository {
+    connection: Mutex<Connection>,
+    page_size: usize,
+}
+
+impl SqliteEventRepository {
+    /// Creates a new instance of [SqliteEventRepository] on the `connection`, creating the `events` table if it does not exist.
+    pub fn new(connection: Connection) -> Result<Self, SqliteError> {
+        connection.execute_batch(EVENTS_SCHEMA)?;
+        Ok(SqliteEventRepository {
+            connection: Mutex::new(connection),
+            page_size: STREAM_PAGE_SIZE,
+        })
+    }
+    /// Sets the number of the events read at once by the [SqliteEventStream]. It is [STREAM_PAGE_SIZE] by default.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+    /// Opens the database file at the `path`, creating it if it does not exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, SqliteError> {
+        SqliteEventRepository::new(Connection::open(path)?)
+    }
+    /// Opens the new in-memory database.
+    pub fn open_in_memory() -> Result<Self, SqliteError> {
+        SqliteEventRepository::new(Connection::open_in_memory()?)
+    }
+    /// Returns the connection, locked for the exclusive use.
+    pub fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap()
+    }
+    /// Fetches the events of the stream, identified by the `identifier`, ordered by their version.
+    fn fetch_stream<E: DeserializeOwned>(
+        &self,
+        identifier: &str,
+    ) -> Result<Vec<(E, i64)>, SqliteError> {
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached(
+            "SELECT payload, version FROM events WHERE stream_id = ?1 ORDER BY version",
+        )?;
+        let rows = statement.query_map(params![identifier], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        rows.map(|row| {
+            let (payload, version) = row?;
+            Ok((serde_json::from_str(&payload)?, version))
+        })
+        .collect()
+    }
+    /// Fetches the page of the events of the stream, identified by the `identifier`, starting with the `version`, ordered by their version.
+    fn fetch_page<E: DeserializeOwned>(
+        &self,
+        identifier: &str,
+        version: i64,
+    ) -> Result<Vec<(E, i64)>, SqliteError> {
+        let connection = self.connection();
+        let mut statement = connection.prepare_cached(
+            "SELECT payload, version FROM events WHERE stream_id = ?1 AND version >= ?2 ORDER BY version LIMIT ?3",
+        )?;
+        let rows = statement
+            .query_map(params![identifier, version, self.page_size as i64], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })?;
+        rows.map(|row| {
+            let (payload, version) = row?;
+            Ok((serde_json::from_str(&payload)?, version))
+        })
+        .collect()
+    }
+    /// Appends the events to their streams, in the single transaction.
+    /// If the `expected` stream is given, its events are appended only if it is still at the expected version, or [SqliteError::Conflict] is returned.
+    fn append<E: Identifier + Serialize + Clone>(
+        &self,
+        events: &[E],
+        expected: Option<(&str, Option<i64>)>,
+    ) -> Result<Vec<(E, i64)>, SqliteError> {
+        let mut connection = self.connection();
+        let transaction = connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let mut saved_events = Vec::with_capacity(events.len());
+        let mut versions: HashMap<String, i64> = HashMap::new();
+        for event in events {
+            let identifier = event.identifier();
+            let version = match versions.get(&identifier) {
+                Some(version) => version + 1,
+                None => {
+                    let latest_version = transaction.query_row(
+                        "SELECT MAX(version) FROM events WHERE stream_id = ?1",
+                        params![identifier],
+                        |row| row.get::<_, Option<i64>>(0),
+                    )?;
+                    if let Some((stream_id, expected_version)) = expected {
+                        if stream_id == identifier && latest_version != expected_version {
+                            return Err(SqliteError::Conflict(identifier));
+                        }
+                    }
+                    latest_version.map_or(0, |version| version + 1)
+                }
+            };
+            transaction
+                .execute(
+                    "INSERT INTO events (stream_id, version, payload) VALUES (?1, ?2, ?3)",
+                    params![identifier, version, serde_json::to_string(event)?],
+                )
+                .map_err(|error| conflict(error, &identifier))?;
+            versions.insert(identifier, version);
+            saved_events.push((event.clone(), version));
+        }
+        transaction.commit()?;
+        Ok(saved_events)
+    }
+    /// Streams the events of the stream, identified by the `identifier`, page by page.
+    fn stream<E>(&self, identifier: String) -> SqliteEventStream<'_, E> {
+        SqliteEventStream {
+            repository: self,
+            identifier,
+            page: Vec::new().into_iter(),
+            version: 0,
+            exhausted: false,
+        }
+    }
+    /// Returns the latest version of the stream, identified by the `identifier`.
+    fn latest_version(&self, identifier: &str) -> Result<Option<i64>, SqliteError> {
+        Ok(self.connection().query_row(
+            "SELECT MAX(version) FROM events WHERE stream_id = ?1",
+            params![identifier],
+            |row| row.get::<_, Option<i64>>(0),
+        )?)
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<C, E, Error> EventRepository<C, E, i64, Error> for SqliteEventRepository
+where
+    C: Identifier + Sync,
+    E: Identifier + Serialize + DeserializeOwned + Clone + Send + Sync,
+    Error: From<SqliteError> + Send,
+{
+    /// Fetches the events of the stream, identified by the command [Identifier].
+    async fn fetch_events(&self, command: &C) -> Result<Vec<(E, i64)>, Error> {
+        Ok(self.fetch_stream(&command.identifier())?)
+    }
+    /// Appends the events to their streams, with the next versions.
+    async fn save(&self, events: &[E]) -> Result<Vec<(E, i64)>, Error> {
+        Ok(self.append(events, None)?)
+    }
+    /// Appends the events to their streams, with the next versions, if the stream of the command is still at the `latest_version`.
+    async fn save_after(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<i64>,
+    ) -> Result<Vec<(E, i64)>, Error> {
+        Ok(self.append(events, Some((&command.identifier(), *latest_version)))?)
+    }
+    /// Returns the latest version of the event stream.
+    async fn version_provider(&self, event: &E) -> Result<Option<i64>, Error> {
+        Ok(self.latest_version(&event.identifier())?)
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<C, E, Error> EventRepository<C, E, i64, Error> for SqliteEventRepository
+where
+    C: Identifier,
+    E: Identifier + Serialize + DeserializeOwned + Clone,
+    Error: From<SqliteError>,
+{
+    /// Fetches the events of the stream, identified by the command [Identifier].
+    async fn fetch_events(&self, command: &C) -> Result<Vec<(E, i64)>, Error> {
+        Ok(self.fetch_stream(&command.identifier())?)
+    }
+    /// Appends the events to their streams, with the next versions.
+    async fn save(&self, events: &[E]) -> Result<Vec<(E, i64)>, Error> {
+        Ok(self.append(events, None)?)
+    }
+    /// Appends the events to their streams, with the next versions, if the stream of the command is still at the `latest_version`.
+    async fn save_after(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<i64>,
+    ) -> Result<Vec<(E, i64)>, Error> {
+        Ok(self.append(events, Some((&command.identifier(), *latest_version)))?)
+    }
+    /// Returns the latest version of the event stream.
+    async fn version_provider(&self, event: &E) -> Result<Option<i64>, Error> {
+        Ok(self.latest_version(&event.identifier())?)
+    }
+}
+
+/// The number of the events read at once by the [SqliteEventStream], by default.
+pub const STREAM_PAGE_SIZE: usize = 1000;
+
+/// SQLite Event Stream.
+///
+/// The events of the stream, read from the [SqliteEventRepository] page by page (see [SqliteEventRepository::with_page_size]), so only the single page is kept in the memory.
+/// The connection is locked only while the page is read. The events appended to the stream in the meantime are streamed, too.
+///
+/// The page is read by the blocking query, inside of the [EventStream::next] future, so it blocks the executor thread until the page is read.
+/// The stream borrows the repository, so the read can not be moved to the blocking thread pool (e.g. by `tokio::task::spawn_blocking`).
+/// Keep the pages small, to bound the blocking, or pull the stream from the `tokio::task::block_in_place` on the multi-threaded runtime.
+pub struct SqliteEventStream<'a, E> {
+    repository: &'a SqliteEventRepository,
+    identifier: String,
+    page: std::vec::IntoIter<(E, i64)>,
+    version: i64,
+    exhausted: bool,
+}
+
+impl<E: DeserializeOwned> SqliteEventStream<'_, E> {
+    /// Pulls the next event, reading the next page once the current one is exhausted.
+    fn pull(&mut self) -> Option<Result<(E, i64), SqliteError>> {
+        if let Some(event) = self.page.next() {
+            return Some(Ok(event));
+        }
+        if self.exhausted {
+            return None;
+        }
+        match self.repository.fetch_page(&self.identifier, self.version) {
+            Ok(page) => {
+                self.exhausted = page.len() < self.repository.page_size;
+                if let Some((_, version)) = page.last() {
+                    self.version = version + 1;
+                }
+                self.page = page.into_iter();
+                self.page.next().map(Ok)
+            }
+            Err(error) => {
+                self.exhausted = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<E, Error> EventStream<E, i64, Error> for SqliteEventStream<'_, E>
+where
+    E: DeserializeOwned + Send,
+    Error: From<SqliteError>,
+{
+    /// Pulls the next event, reading the next page once the current one is exhausted.
+    async fn next(&mut self) -> Option<Result<(E, i64), Error>> {
+        self.pull().map(|event| event.map_err(Error::from))
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<E, Error> EventStream<E, i64, Error> for SqliteEventStream<'_, E>
+where
+    E: DeserializeOwned,
+    Error: From<SqliteError>,
+{
+    /// Pulls the next event, reading the next page once the current one is exhausted.
+    async fn next(&mut self) -> Option<Result<(E, i64), Error>> {
+        self.pull().map(|event| event.map_err(Error::from))
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<C, E, Error> StreamingEventRepository<C, E, i64, Error> for SqliteEventRepository
+where
+    C: Identifier + Sync,
+    E: Identifier + Serialize + DeserializeOwned + Clone + Send + Sync,
+    Error: From<SqliteError> + Send,
+{
+    /// Streams the events of the stream, identified by the command [Identifier], page by page.
+    fn stream_events(&self, command: &C) -> impl EventStream<E, i64, Error> + Send {
+        self.stream(command.identifier())
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<C, E, Error> StreamingEventRepository<C, E, i64, Error> for SqliteEventRepository
+where
+    C: Identifier,
+    E: Identifier + Serialize + DeserializeOwned + Clone,
+    Error: From<SqliteError>,
+{
+    /// Streams the events of the stream, identified by the command [Identifier], page by page.
+    fn stream_events(&self, command: &C) -> impl EventStream<E, i64, Error> {
+        self.stream(command.identifier())
+    }
+}
+
+/// SQLite State Repository.
+///
+/// [StateRepository] on the embedded SQLite database, storing the states in the `states` table (see [STATES_SCHEMA]), keyed by their [Identifier].
+/// The states are encoded as JSON. The command is routed to the state by its [Identifier].
+///
+/// The state is saved with the compare-and-swap: the new state is inserted only if there is no state with the same identifier yet,
+/// and updated only if its version is still the one it was fetched with. Otherwise, it fails with [SqliteError::Conflict].
+///
+/// The connection is guarded by the mutex, and the queries are blocking. It fits the small services and the integration tests.
+pub struct SqliteStateRepository {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStateRepository {
+    /// Creates a new instance of [SqliteStateRepository] on the `connection`, creating the `states` table if it does not exist.
+    pub fn new(connection: Connection) -> Result<Self, SqliteError> {
+        connection.execute_batch(STATES_SCHEMA)?;
+        Ok(SqliteStateRepository {
+            connection: Mutex::new(connection),
+        })
+    }
+    /// Opens the database file at the `path`, creating it if it does not exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, SqliteError> {
+        SqliteStateRepository::new(Connection::open(path)?)
+    }
+    /// Opens the new in-memory database.
+    pub fn open_in_memory() -> Result<Self, SqliteError> {
+        SqliteStateRepository::new(Connection::open_in_memory()?)
+    }
+    /// Returns the connection, locked for the exclusive use.
+    pub fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap()
+    }
+    /// Saves the state, if its current version is the expected `version`.
+    fn compare_and_swap<S: Identifier + Serialize + Clone>(
+        &self,
+        state: &S,
+        version: &Option<i64>,
+    ) -> Result<(S, i64), SqliteError> {
+        let identifier = state.identifier();
+        let payload = serde_json::to_string(state)?;
+        let connection = self.connection();
+        let new_version = match version {
+            None => {
+                connection
+                    .execute(
+                        "INSERT INTO states (id, version, payload) VALUES (?1, 0, ?2)",
+                        params![identifier, payload],
+                    )
+                    .map_err(|error| conflict(error, &identifier))?;
+                0
+            }
+            Some(version) => {
+                let updated = connection.execute(
+                    "UPDATE states SET version = version + 1, payload = ?3 WHERE id = ?1 AND version = ?2",
+                    params![identifier, version, payload],
+                )?;
+                if updated == 0 {
+                    return Err(SqliteError::Conflict(identifier));
+                }
+                version + 1
+            }
+        };
+        Ok((state.clone(), new_version))
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<C, S, Error> StateRepository<C, S, i64, Error> for SqliteStateRepository
+where
+    C: Identifier + Sync,
+    S: Identifier + Serialize + DeserializeOwned + Clone + Send + Sync,
+    Error: From<SqliteError> + Send,
+{
+    /// Fetches the state, identified by the command [Identifier].
+    async fn fetch_state(&self, command: &C) -> Result<Option<(S, i64)>, Error> {
+        Ok(fetch_row(
+            &self.connection(),
+            "states",
+            &command.identifier(),
+        )?)
+    }
+    /// Saves the state, if it was not modified since it was fetched with the `version`.
+    async fn save(&self, state: &S, version: &Option<i64>) -> Result<(S, i64), Error> {
+        Ok(self.compare_and_swap(state, version)?)
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<C, S, Error> StateRepository<C, S, i64, Error> for SqliteStateRepository
+where
+    C: Identifier,
+    S: Identifier + Serialize + DeserializeOwned + Clone,
+    Error: From<SqliteError>,
+{
+    /// Fetches the state, identified by the command [Identifier].
+    async fn fetch_state(&self, command: &C) -> Result<Option<(S, i64)>, Error> {
+        Ok(fetch_row(
+            &self.connection(),
+            "states",
+            &command.identifier(),
+        )?)
+    }
+    /// Saves the state, if it was not modified since it was fetched with the `version`.
+    async fn save(&self, state: &S, version: &Option<i64>) -> Result<(S, i64), Error> {
+        Ok(self.compare_and_swap(state, version)?)
+    }
+}
+
+/// SQLite View State Repository.
+///
+/// [ViewStateRepository] and [VersionedViewStateRepository] on the embedded SQLite database, storing the view states in the `views` table (see [VIEWS_SCHEMA]), keyed by their [Identifier].
+/// The view states are encoded as JSON. The event is routed to the view state by its [Identifier].
+///
+/// As the [VersionedViewStateRepository], the view state is saved with the compare-and-swap: it is updated only if the version of the last event applied to it is lower than the new one.
+/// Otherwise, it fails with [SqliteError::Conflict], so the concurrent handler of the older event can not overwrite the newer state.
+/// As the [ViewStateRepository], the last save wins, and the version counts the saves.
+///
+/// The connection is guarded by the mutex, and the queries are blocking. It fits the small services and the integration tests.
+pub struct SqliteViewStateRepository {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteViewStateRepository {
+    /// Creates a new instance of [SqliteViewStateRepository] on the `connection`, creating the `views` table if it does not exist.
+    pub fn new(connection: Connection) -> Result<Self, SqliteError> {
+        connection.execute_batch(VIEWS_SCHEMA)?;
+        Ok(SqliteViewStateRepository {
+            connection: Mutex::new(connection),
+        })
+    }
+    /// Opens the database file at the `path`, creating it if it does not exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, SqliteError> {
+        SqliteViewStateRepository::new(Connection::open(path)?)
+    }
+    /// Opens the new in-memory database.
+    pub fn open_in_memory() -> Result<Self, SqliteError> {
+        SqliteViewStateRepository::new(Connection::open_in_memory()?)
+    }
+    /// Returns the connection, locked for the exclusive use.
+    pub fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap()
+    }
+    /// Saves the view state, counting the saves in its version.
+    fn upsert<S: Identifier + Serialize + Clone>(&self, state: &S) -> Result<S, SqliteError> {
+        self.connection().execute(
+            "INSERT INTO views (id, version, payload) VALUES (?1, 0, ?2)
+             ON CONFLICT (id) DO UPDATE SET version = version + 1, payload = excluded.payload",
+            params![state.identifier(), serde_json::to_string(state)?],
+        )?;
+        Ok(state.clone())
+    }
+    /// Saves the view state, if the version of the last event applied to it is lower than the new `version`.
+    fn compare_and_swap<S: Identifier + Serialize + Clone>(
+        &self,
+        state: &S,
+        version: &i64,
+    ) -> Result<(S, i64), SqliteError> {
+        let identifier = state.identifier();
+        let updated = self.connection().execute(
+            "INSERT INTO views (id, version, payload) VALUES (?1, ?2, ?3)
+             ON CONFLICT (id) DO UPDATE SET version = excluded.version, payload = excluded.payload
+             WHERE version < excluded.version",
+            params![identifier, version, serde_json::to_string(state)?],
+        )?;
+        if updated == 0 {
+            return Err(SqliteError::Conflict(identifier));
+        }
+        Ok((state.clone(), *version))
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<E, S, Error> ViewStateRepository<E, S, Error> for SqliteViewStateRepository
+where
+    E: Identifier + Sync,
+    S: Identifier + Serialize + DeserializeOwned + Clone + Send + Sync,
+    Error: From<SqliteError> + Send,
+{
+    /// Fetches the view state, identified by the event [Identifier].
+    async fn fetch_state(&self, event: &E) -> Result<Option<S>, Error> {
+        Ok(fetch_row(&self.connection(), "views", &event.identifier())?.map(|(state, _)| state))
+    }
+    /// Saves the view state.
+    async fn save(&self, state: &S) -> Result<S, Error> {
+        Ok(self.upsert(state)?)
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<E, S, Error> ViewStateRepository<E, S, Error> for SqliteViewStateRepository
+where
+    E: Identifier,
+    S: Identifier + Serialize + DeserializeOwned + Clone,
+    Error: From<SqliteError>,
+{
+    /// Fetches the view state, identified by the event [Identifier].
+    async fn fetch_state(&self, event: &E) -> Result<Option<S>, Error> {
+        Ok(fetch_row(&self.connection(), "views", &event.identifier())?.map(|(state, _)| state))
+    }
+    /// Saves the view state.
+    async fn save(&self, state: &S) -> Result<S, Error> {
+        Ok(self.upsert(state)?)
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<E, S, Error> VersionedViewStateRepository<E, S, i64, Error> for SqliteViewStateRepository
+where
+    E: Identifier + Sync,
+    S: Identifier + Serialize + DeserializeOwned + Clone + Send + Sync,
+    Error: From<SqliteError> + Send,
+{
+    /// Fetches the view state, and the version of the last event applied to it, identified by the event [Identifier].
+    async fn fetch_state(&self, event: &E) -> Result<Option<(S, i64)>, Error> {
+        Ok(fetch_row(&self.connection(), "views", &event.identifier())?)
+    }
+    /// Saves the view state, if the version of the last event applied to it is lower than the new `version`.
+    async fn save(&self, state: &S, version: &i64) -> Result<(S, i64), Error> {
+        Ok(self.compare_and_swap(state, version)?)
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<E, S, Error> VersionedViewStateRepository<E, S, i64, Error> for SqliteViewStateRepository
+where
+    E: Identifier,
+    S: Identifier + Serialize + DeserializeOwned + Clone,
+    Error: From<SqliteError>,
+{
+    /// Fetches the view state, and the version of the last event applied to it, identified by the event [Identifier].
+    async fn fetch_state(&self, event: &E) -> Result<Option<(S, i64)>, Error> {
+        Ok(fetch_row(&self.connection(), "views", &event.identifier())?)
+    }
+    /// Saves the view state, if the version of the last event applied to it is lower than the new `version`.
+    async fn save(&self, state: &S, version: &i64) -> Result<(S, i64), Error> {
+        Ok(self.compare_and_swap(state, version)?)
+    }
+}
@@ -0,0 +1,546 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use crate::aggregate::EventRepository;
+use crate::keyed_lock::KeyedLock;
+use crate::message_type::MessageType;
+use crate::middleware::Handler;
+use crate::projection::{EventSource, DEFAULT_BATCH_SIZE};
+use crate::Identifier;
+
+/// The predicate of the [EventFilter].
+#[cfg(not(feature = "not-send-futures"))]
+type Predicate<'a, E> = Box<dyn Fn(&E) -> bool + 'a + Send + Sync>;
+/// The predicate of the [EventFilter].
+#[cfg(feature = "not-send-futures")]
+type Predicate<'a, E> = Box<dyn Fn(&E) -> bool + 'a>;
+
+/// Event Filter.
+///
+/// Selects the events delivered by the [EventSubscription]: all of them, the events of the given identifiers (streams), the events of the given [MessageType]s, or any predicate.
+/// The filters are combined with [EventFilter::and].
+///
+/// Generic parameters:
+///
+/// - `E` - Event
+pub struct EventFilter<'a, E> {
+    predicate: Option<Predicate<'a, E>>,
+}
+
+impl<E> Default for EventFilter<'_, E> {
+    fn default() -> Self {
+        EventFilter { predicate: None }
+    }
+}
+
+impl<'a, E> EventFilter<'a, E> {
+    /// Selects all the events.
+    pub fn all() -> Self {
+        EventFilter::default()
+    }
+    /// Selects the events matching the `predicate`.
+    #[cfg(not(feature = "not-send-futures"))]
+    pub fn new(predicate: impl Fn(&E) -> bool + 'a + Send + Sync) -> Self {
+        EventFilter {
+            predicate: Some(Box::new(predicate)),
+        }
+    }
+    /// Selects the events matching the `predicate`.
+    #[cfg(feature = "not-send-futures")]
+    pub fn new(predicate: impl Fn(&E) -> bool + 'a) -> Self {
+        EventFilter {
+            predicate: Some(Box::new(predicate)),
+        }
+    }
+    /// Selects the events with the given [Identifier]s.
+    pub fn identifiers(identifiers: &[&str]) -> Self
+    where
+        E: Identifier,
+    {
+        let identifiers: HashSet<String> = identifiers.iter().map(|id| id.to_string()).collect();
+        EventFilter::new(move |event: &E| identifiers.contains(&event.identifier()))
+    }
+    /// Selects the events of the given [MessageType]s.
+    pub fn message_types(message_types: &[&'static str]) -> Self
+    where
+        E: MessageType,
+    {
+        let message_types: HashSet<&'static str> = message_types.iter().copied().collect();
+        EventFilter::new(move |event: &E| message_types.contains(event.message_type()))
+    }
+    /// Selects the events matching both this and the `other` filter.
+    pub fn and(self, other: EventFilter<'a, E>) -> Self
+    where
+        E: 'a,
+    {
+        match (self.predicate, other.predicate) {
+            (None, predicate) | (predicate, None) => EventFilter { predicate },
+            (Some(first), Some(second)) => EventFilter {
+                predicate: Some(Box::new(move |event: &E| first(event) && second(event))),
+            },
+        }
+    }
+    /// Does the event match the filter?
+    pub fn matches(&self, event: &E) -> bool {
+        self.predicate
+            .as_ref()
+            .is_none_or(|predicate| predicate(event))
+    }
+}
+
+/// Event Subscription.
+///
+/// Subscribes to the events of the [EventSource], stored after the global position, in the global order, and selected by the [EventFilter].
+/// The position moves past the filtered out events too, so they are not read again.
+///
+/// The events are delivered to any [Handler], e.g. the [MaterializedView](crate::materialized_view::MaterializedView) or the [SagaManager](crate::saga_manager::SagaManager).
+/// The event is consumed only once it is handled, so the event the handler failed on is delivered again on the next call.
+/// The position is not persisted; see [ProjectionRunner](crate::projection::ProjectionRunner) for the subscription resuming from the stored checkpoint.
+///
+/// Generic parameters:
+///
+/// - `E` - Event
+/// - `Position` - Global position/offset of the event in the event log
+/// - `Source` - Event source
+/// - `Error` - Error
+pub struct EventSubscription<'a, E, Position, Source, Error>
+where
+    Source: EventSource<E, Position, Error>,
+{
+    source: Source,
+    filter: EventFilter<'a, E>,
+    position: Option<Position>,
+    pending: VecDeque<(E, Position)>,
+    batch_size: usize,
+    _marker: PhantomData<Error>,
+}
+
+impl<'a, E, Position, Source, Error> EventSubscription<'a, E, Position, Source, Error>
+where
+    Source: EventSource<E, Position, Error>,
+    Position: Clone,
+{
+    /// Creates a new instance of [EventSubscription], subscribing to the events of the `source` from the beginning of the log.
+    pub fn new(source: Source, filter: EventFilter<'a, E>) -> Self {
+        EventSubscription {
+            source,
+            filter,
+            position: None,
+            pending: VecDeque::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            _marker: PhantomData,
+        }
+    }
+    /// Subscribes to the events stored after the `position`, instead of the beginning of the log.
+    pub fn after(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
+    /// Sets the maximum number of events read from the [EventSource] at once.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+    /// Returns the event source.
+    pub fn source(&self) -> &Source {
+        &self.source
+    }
+    /// Returns the position of the last event read from the [EventSource], if any.
+    pub fn position(&self) -> Option<&Position> {
+        self.position.as_ref()
+    }
+    /// Reads the events from the [EventSource] until any of them matches the filter, or the subscription has caught up with the source.
+    /// Returns `false` if it has caught up.
+    async fn fill(&mut self) -> Result<bool, Error> {
+        while self.pending.is_empty() {
+            let events = self
+                .source
+                .read_events(&self.position, self.batch_size)
+                .await?;
+            let Some((_, last_position)) = events.last() else {
+                return Ok(false);
+            };
+            self.position = Some(last_position.clone());
+            self.pending.extend(
+                events
+                    .into_iter()
+                    .filter(|(event, _)| self.filter.matches(event)),
+            );
+        }
+        Ok(true)
+    }
+    /// Returns the next matching event, waiting for it via [EventSource::wait_for_events] once the subscription has caught up with the source.
+    pub async fn next(&mut self) -> Result<(E, Position), Error> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+            if !self.fill().await? {
+                self.source.wait_for_events(&self.position).await?;
+            }
+        }
+    }
+    /// Catch-up mode. Delivers all the matching events stored after the position to the `handler`, until the subscription has caught up with the source.
+    /// Returns the number of the handled events.
+    pub async fn catch_up<Output, H>(&mut self, handler: &H) -> Result<usize, Error>
+    where
+        H: Handler<E, Output, Error>,
+    {
+        let mut count = 0;
+        while self.fill().await? {
+            while let Some((event, _)) = self.pending.front() {
+                handler.handle(event).await?;
+                self.pending.pop_front();
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+    /// Live mode. Catches up with the event source, and then keeps tailing it, delivering the new matching events to the `handler`.
+    /// It returns only on error. Drop the future to stop the subscription.
+    pub async fn run<Output, H>(&mut self, handler: &H) -> Result<(), Error>
+    where
+        H: Handler<E, Output, Error>,
+    {
+        loop {
+            self.catch_up(handler).await?;
+            self.source.wait_for_events(&self.position).await?;
+        }
+    }
+}
+
+/// The events, and the tasks waiting for the new ones, keyed by the identifier of their [Wait] future.
+struct Log<E> {
+    events: Vec<E>,
+    waiters: HashMap<u64, Waker>,
+    next_waiter: u64,
+}
+
+/// The future of the task waiting for the events stored after the `next` position.
+/// It keeps the single (latest) waker in the log, and removes it once resolved or dropped.
+struct Wait<'a, E> {
+    log: &'a Mutex<Log<E>>,
+    next: usize,
+    waiter: Option<u64>,
+}
+
+impl<E> Future for Wait<'_, E> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        let mut log = this.log.lock().unwrap();
+        if log.events.len() > this.next {
+            if let Some(waiter) = this.waiter.take() {
+                log.waiters.remove(&waiter);
+            }
+            return Poll::Ready(());
+        }
+        let waiter = *this.waiter.get_or_insert_with(|| {
+            log.next_waiter += 1;
+            log.next_waiter
+        });
+        match log.waiters.get_mut(&waiter) {
+            Some(waker) if waker.will_wake(cx.waker()) => {}
+            Some(waker) => *waker = cx.waker().clone(),
+            None => {
+                log.waiters.insert(waiter, cx.waker().clone());
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl<E> Drop for Wait<'_, E> {
+    fn drop(&mut self) {
+        if let Some(waiter) = self.waiter {
+            if let Ok(mut log) = self.log.lock() {
+                log.waiters.remove(&waiter);
+            }
+        }
+    }
+}
+
+/// In-Memory Event Log.
+///
+/// [EventSource] of the events appended in-process, e.g. by the [EventLogRepository] wrapping the repositories of the aggregates.
+/// The position of the event is its index in the log, starting at `0`. The log is cheap to clone; the clones share the events.
+///
+/// The log is not durable, and it keeps all the events. It fits the tests and the single-process deployments with the short-lived logs.
+///
+/// Generic parameters:
+///
+/// - `E` - Event
+pub struct InMemoryEventLog<E> {
+    log: Arc<Mutex<Log<E>>>,
+}
+
+impl<E> Clone for InMemoryEventLog<E> {
+    fn clone(&self) -> Self {
+        InMemoryEventLog {
+            log: Arc::clone(&self.log),
+        }
+    }
+}
+
+impl<E> Default for InMemoryEventLog<E> {
+    fn default() -> Self {
+        InMemoryEventLog {
+            log: Arc::new(Mutex::new(Log {
+                events: Vec::new(),
+                waiters: HashMap::new(),
+                next_waiter: 0,
+            })),
+        }
+    }
+}
+
+impl<E> InMemoryEventLog<E> {
+    /// Creates a new, empty instance of [InMemoryEventLog].
+    pub fn new() -> Self {
+        InMemoryEventLog::default()
+    }
+    /// Appends the events to the log, and wakes the subscriptions waiting for them.
+    pub fn append(&self, events: impl IntoIterator<Item = E>) {
+        let waiters = {
+            let mut log = self.log.lock().unwrap();
+            let len = log.events.len();
+            log.events.extend(events);
+            if log.events.len() == len {
+                return;
+            }
+            std::mem::take(&mut log.waiters)
+        };
+        // The woken waiters register again, if they are still waiting for the later events
+        for waiter in waiters.into_values() {
+            waiter.wake();
+        }
+    }
+    /// Number of the events in the log.
+    pub fn len(&self) -> usize {
+        self.log.lock().unwrap().events.len()
+    }
+    /// Returns `true` if the log has no events.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Reads at most `limit` events stored after the `position`.
+    fn read(&self, position: &Option<u64>, limit: usize) -> Vec<(E, u64)>
+    where
+        E: Clone,
+    {
+        let from = position.map_or(0, |position| position as usize + 1);
+        self.log
+            .lock()
+            .unwrap()
+            .events
+            .iter()
+            .enumerate()
+            .skip(from)
+            .take(limit)
+            .map(|(position, event)| (event.clone(), position as u64))
+            .collect()
+    }
+    /// Waits until there is any event stored after the `position`.
+    async fn wait(&self, position: &Option<u64>) {
+        Wait {
+            log: &self.log,
+            next: position.map_or(0, |position| position as usize + 1),
+            waiter: None,
+        }
+        .await
+    }
+    /// Number of the tasks waiting for the new events.
+    pub fn waiting(&self) -> usize {
+        self.log.lock().unwrap().waiters.len()
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<E, Error> EventSource<E, u64, Error> for InMemoryEventLog<E>
+where
+    E: Clone + Send,
+{
+    /// Reads at most `limit` events stored after the `position`.
+    async fn read_events(
+        &self,
+        position: &Option<u64>,
+        limit: usize,
+    ) -> Result<Vec<(E, u64)>, Error> {
+        Ok(self.read(position, limit))
+    }
+    /// Waits until there is any event stored after the `position`.
+    async fn wait_for_events(&self, position: &Option<u64>) -> Result<(), Error> {
+        self.wait(position).await;
+        Ok(())
+    }
+    /// Counts the events stored after the `position`.
+    async fn count_events(&self, position: &Option<u64>) -> Option<usize> {
+        Some(
+            self.len()
+                .saturating_sub(position.map_or(0, |position| position as usize + 1)),
+        )
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<E, Error> EventSource<E, u64, Error> for InMemoryEventLog<E>
+where
+    E: Clone,
+{
+    /// Reads at most `limit` events stored after the `position`.
+    async fn read_events(
+        &self,
+        position: &Option<u64>,
+        limit: usize,
+    ) -> Result<Vec<(E, u64)>, Error> {
+        Ok(self.read(position, limit))
+    }
+    /// Waits until there is any event stored after the `position`.
+    async fn wait_for_events(&self, position: &Option<u64>) -> Result<(), Error> {
+        self.wait(position).await;
+        Ok(())
+    }
+    /// Counts the events stored after the `position`.
+    async fn count_events(&self, position: &Option<u64>) -> Option<usize> {
+        Some(
+            self.len()
+                .saturating_sub(position.map_or(0, |position| position as usize + 1)),
+        )
+    }
+}
+
+/// Event Log Repository.
+///
+/// [EventRepository] appending the saved events to the [InMemoryEventLog] as well, so the subscriptions read them in the global order.
+///
+/// The events are appended once they are saved by the inner repository, and only if the save succeeds.
+/// The saves (together with their appends) are serialized by the in-process lock, so:
+///
+/// - the events of the single save are appended together, with no events of the other saves in between
+/// - the saves are appended in the order they are committed by the inner repository, so the events of every stream are appended in the order of their versions
+/// - the events saved by the other processes, or by the inner repository directly, are not appended
+///
+/// The saves are not concurrent, even for the different streams, so the save of the slow inner repository delays the others.
+/// The append is not atomic with the save: if the process stops (or the future is dropped) in between, the saved events are missing from the log.
+///
+/// Generic parameters:
+///
+/// - `Repository` - Inner event repository
+/// - `E` - Event
+pub struct EventLogRepository<Repository, E> {
+    repository: Repository,
+    log: InMemoryEventLog<E>,
+    lock: KeyedLock,
+}
+
+/// The key of the lock serializing the saves of the [EventLogRepository].
+const SAVE_KEY: &str = "save";
+
+impl<Repository, E> EventLogRepository<Repository, E> {
+    /// Creates a new instance of [EventLogRepository], appending the events saved by the `repository` to the `log`.
+    pub fn new(repository: Repository, log: InMemoryEventLog<E>) -> Self {
+        EventLogRepository {
+            repository,
+            log,
+            lock: KeyedLock::new(),
+        }
+    }
+    /// Returns the inner repository.
+    pub fn repository(&self) -> &Repository {
+        &self.repository
+    }
+    /// Returns the log.
+    pub fn log(&self) -> &InMemoryEventLog<E> {
+        &self.log
+    }
+}
+
+#[cfg(not(feature = "not-send-futures"))]
+impl<C, E, Version, Error, Repository> EventRepository<C, E, Version, Error>
+    for EventLogRepository<Repository, E>
+where
+    Repository: EventRepository<C, E, Version, Error> + Sync,
+    C: Sync,
+    E: Clone + Send + Sync,
+    Version: Send + Sync,
+    Error: Send,
+{
+    /// Fetches current events, based on the command.
+    async fn fetch_events(&self, command: &C) -> Result<Vec<(E, Version)>, Error> {
+        self.repository.fetch_events(command).await
+    }
+    /// Saves events, and appends them to the log.
+    async fn save(&self, events: &[E]) -> Result<Vec<(E, Version)>, Error> {
+        let _guard = self.lock.lock(SAVE_KEY).await;
+        let saved_events = self.repository.save(events).await?;
+        self.log
+            .append(saved_events.iter().map(|(event, _)| event.clone()));
+        Ok(saved_events)
+    }
+    /// Saves events, only if the stream of the command is still at the `latest_version`, and appends them to the log.
+    async fn save_after(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<Version>,
+    ) -> Result<Vec<(E, Version)>, Error> {
+        let _guard = self.lock.lock(SAVE_KEY).await;
+        let saved_events = self
+            .repository
+            .save_after(command, events, latest_version)
+            .await?;
+        self.log
+            .append(saved_events.iter().map(|(event, _)| event.clone()));
+        Ok(saved_events)
+    }
+    /// Version provider. It is used to provide the version/sequence of the event. Optimistic locking is useing this version to check if the event is already saved.
+    async fn version_provider(&self, event: &E) -> Result<Option<Version>, Error> {
+        self.repository.version_provider(event).await
+    }
+}
+
+#[cfg(feature = "not-send-futures")]
+impl<C, E, Version, Error, Repository> EventRepository<C, E, Version, Error>
+    for EventLogRepository<Repository, E>
+where
+    Repository: EventRepository<C, E, Version, Error>,
+    E: Clone,
+{
+    /// Fetches current events, based on the command.
+    async fn fetch_events(&self, command: &C) -> Result<Vec<(E, Version)>, Error> {
+        self.repository.fetch_events(command).await
+    }
+    /// Saves events, and appends them to the log.
+    async fn save(&self, events: &[E]) -> Result<Vec<(E, Version)>, Error> {
+        let _guard = self.lock.lock(SAVE_KEY).await;
+        let saved_events = self.repository.save(events).await?;
+        self.log
+            .append(saved_events.iter().map(|(event, _)| event.clone()));
+        Ok(saved_events)
+    }
+    /// Saves events, only if the stream of the command is still at the `latest_version`, and appends them to the log.
+    async fn save_after(
+        &self,
+        command: &C,
+        events: &[E],
+        latest_version: &Option<Version>,
+    ) -> Result<Vec<(E, Version)>, Error> {
+        let _guard = self.lock.lock(SAVE_KEY).await;
+        let saved_events = self
+            .repository
+            .save_after(command, events, latest_version)
+            .await?;
+        self.log
+            .append(saved_events.iter().map(|(event, _)| event.clone()));
+        Ok(saved_events)
+    }
+    /// Version provider. It is used to provide the version/sequence of the event. Optimistic locking is useing this version to check if the event is already saved.
+    async fn version_provider(&self, event: &E) -> Result<Option<Version>, Error> {
+        self.repository.version_provider(event).await
+    }
+}
@@ -0,0 +1,153 @@
+//! Diagnostics of the application components.
+//!
+//! With the `tracing` feature enabled, the components open the `tracing` spans. With the `metrics` feature enabled, they emit the metrics
+//! named by the constants below, via the `metrics` facade, labeled with the `component` (e.g. `EventSourcedAggregate`) and the `type`
+//! (the type name of the state for the aggregates and the views, and of the action result for the saga managers).
+//! With the features disabled, the spans and the metrics are no-ops.
+
+#[cfg(not(feature = "tracing"))]
+use std::future::Future;
+
+/// Counter of the commands handled by the aggregates, successfully or not.
+pub const COMMANDS_HANDLED: &str = "fmodel_commands_handled_total";
+/// Counter of the commands rejected by the decision-making (`decide`) of the aggregates.
+pub const DECIDE_ERRORS: &str = "fmodel_decide_errors_total";
+/// Counter of the events appended by the event-sourced aggregates.
+pub const EVENTS_APPENDED: &str = "fmodel_events_appended_total";
+/// Histogram of the number of the events folded (`evolve`) into the current state by the event-sourced aggregates, per command.
+pub const FOLD_LENGTH: &str = "fmodel_fold_length";
+/// Counter of the events handled by the materialized views.
+pub const EVENTS_HANDLED: &str = "fmodel_events_handled_total";
+/// Gauge of the number of the events stored after the checkpoint of the projection, set by the projection runners after every batch.
+/// It is labeled with the `projection` name, and reported only if the event source can count the events (see [EventSource::count_events](crate::projection::EventSource::count_events)).
+pub const PROJECTION_LAG: &str = "fmodel_projection_lag_events";
+/// Counter of the actions published by the saga managers.
+pub const ACTIONS_PUBLISHED: &str = "fmodel_actions_published_total";
+/// Counter of the failed publishing of the actions by the saga managers.
+pub const PUBLISH_ERRORS: &str = "fmodel_publish_errors_total";
+
+#[cfg(feature = "tracing")]
+pub(crate) use tracing::{Instrument, Span};
+
+/// Opens the `debug` span with the given name and fields, under the `fmodel` target.
+#[cfg(feature = "tracing")]
+macro_rules! span {
+    ($name:literal $(, $($fields:tt)+)?) => {
+        tracing::debug_span!(target: "fmodel", $name $(, $($fields)+)?)
+    };
+}
+
+/// Opens the no-op span. The fields are not evaluated.
+#[cfg(not(feature = "tracing"))]
+macro_rules! span {
+    ($name:literal $(, $($fields:tt)+)?) => {
+        $crate::telemetry::Span
+    };
+}
+
+/// Records the value of the field, declared as `tracing::field::Empty` when the span was opened.
+macro_rules! record {
+    ($span:expr, $field:literal, $value:expr) => {
+        #[cfg(feature = "tracing")]
+        {
+            $span.record($field, $value);
+        }
+    };
+}
+
+/// Records the outcome of the step: the field computed from the successful result, or the `error` flag.
+macro_rules! record_result {
+    ($span:expr, $result:expr) => {
+        #[cfg(feature = "tracing")]
+        {
+            if $result.is_err() {
+                $span.record("error", true);
+            }
+        }
+    };
+    ($span:expr, $result:expr, $field:literal, $value:expr) => {
+        #[cfg(feature = "tracing")]
+        {
+            match &$result {
+                Ok(output) => {
+                    $span.record($field, ($value)(output));
+                }
+                Err(_) => {
+                    $span.record("error", true);
+                }
+            }
+        }
+    };
+}
+
+pub(crate) use {record, record_result, span};
+
+/// Emits the metrics of the component, labeled with its `component` and `type`.
+/// The methods are no-ops, unless the `metrics` feature is enabled.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+pub(crate) struct Meter {
+    component: &'static str,
+    type_name: &'static str,
+}
+
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+impl Meter {
+    /// Creates a new instance of [Meter] for the `component`, handling the type `T`.
+    pub(crate) fn new<T: ?Sized>(component: &'static str) -> Self {
+        Meter {
+            component,
+            type_name: std::any::type_name::<T>(),
+        }
+    }
+    /// Increments the counter by the `value`.
+    pub(crate) fn increment(&self, name: &'static str, value: usize) {
+        #[cfg(feature = "metrics")]
+        metrics::counter!(name, "component" => self.component, "type" => self.type_name)
+            .increment(value as u64);
+    }
+    /// Increments the counter by one, if the `result` is an error.
+    pub(crate) fn increment_on_error<T, E>(&self, name: &'static str, result: &Result<T, E>) {
+        if result.is_err() {
+            self.increment(name, 1);
+        }
+    }
+    /// Records the `value` into the histogram.
+    pub(crate) fn record(&self, name: &'static str, value: usize) {
+        #[cfg(feature = "metrics")]
+        metrics::histogram!(name, "component" => self.component, "type" => self.type_name)
+            .record(value as f64);
+    }
+}
+
+/// Sets the lag of the projection.
+#[cfg(feature = "metrics")]
+pub(crate) fn projection_lag(projection: &str, lag: usize) {
+    metrics::gauge!(PROJECTION_LAG, "component" => "ProjectionRunner", "projection" => projection.to_string())
+        .set(lag as f64);
+}
+
+/// No-op span, used when the `tracing` feature is disabled.
+#[cfg(not(feature = "tracing"))]
+#[derive(Debug, Clone)]
+pub(crate) struct Span;
+
+#[cfg(not(feature = "tracing"))]
+impl Span {
+    /// Runs the function.
+    pub(crate) fn in_scope<F: FnOnce() -> T, T>(&self, f: F) -> T {
+        f()
+    }
+}
+
+/// No-op instrumentation of the futures, used when the `tracing` feature is disabled.
+#[cfg(not(feature = "tracing"))]
+pub(crate) trait Instrument: Sized {
+    /// Returns the future as it is.
+    fn instrument(self, _span: Span) -> Self {
+        self
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl<T: Future> Instrument for T {}
@@ -3,6 +3,7 @@ use std::rc::Rc;
 #[cfg(not(feature = "not-send-futures"))]
 use std::sync::Arc;
 
+use crate::telemetry::span;
 use crate::{EvolveFunction, InitialStateFunction, Sum, View3, View4, View5, View6};
 
 /// [View] represents the event handling algorithm, responsible for translating the events into denormalized state, which is more adequate for querying.
@@ -391,8 +392,10 @@ impl<S, E> ViewStateComputation<E, S> for View<'_, S, E> {
     /// Computes new state based on the current state and the events.
     fn compute_new_state(&self, current_state: Option<S>, events: &[&E]) -> S {
         let effective_current_state = current_state.unwrap_or_else(|| (self.initial_state)());
-        events.iter().fold(effective_current_state, |state, event| {
-            (self.evolve)(&state, event)
+        span!("evolve", events = events.len()).in_scope(|| {
+            events.iter().fold(effective_current_state, |state, event| {
+                (self.evolve)(&state, event)
+            })
         })
     }
 }
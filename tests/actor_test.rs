@@ -0,0 +1,248 @@
+#![cfg(all(feature = "actor", not(feature = "not-send-futures")))]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use fmodel_rust::actor::{ActorError, ActorRuntime};
+use fmodel_rust::aggregate::EventRepository;
+use fmodel_rust::decider::Decider;
+use fmodel_rust::Identifier;
+
+use crate::api::{
+    CancelOrderCommand, CreateOrderCommand, OrderCancelledEvent, OrderCommand, OrderCreatedEvent,
+    OrderEvent, OrderState, UpdateOrderCommand,
+};
+use crate::application::AggregateError;
+
+mod api;
+mod application;
+
+/// A simple in-memory event repository - infrastructure
+/// It counts the fetching of the event streams.
+#[derive(Default)]
+struct InMemoryOrderEventRepository {
+    events: RwLock<Vec<(OrderEvent, i32)>>,
+    fetched: AtomicUsize,
+}
+
+impl EventRepository<OrderCommand, OrderEvent, i32, AggregateError>
+    for InMemoryOrderEventRepository
+{
+    async fn fetch_events(
+        &self,
+        command: &OrderCommand,
+    ) -> Result<Vec<(OrderEvent, i32)>, AggregateError> {
+        self.fetched.fetch_add(1, Ordering::SeqCst);
+        Ok(self
+            .events
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(event, _)| event.identifier() == command.identifier())
+            .cloned()
+            .collect())
+    }
+
+    async fn save(&self, events: &[OrderEvent]) -> Result<Vec<(OrderEvent, i32)>, AggregateError> {
+        let mut latest_version = match events.first() {
+            Some(event) => self.version_provider(event).await?.unwrap_or(-1),
+            None => -1,
+        };
+        let events = events
+            .iter()
+            .map(|event| {
+                latest_version += 1;
+                (event.clone(), latest_version)
+            })
+            .collect::<Vec<(OrderEvent, i32)>>();
+        self.events.write().unwrap().extend_from_slice(&events);
+        Ok(events)
+    }
+
+    async fn version_provider(&self, event: &OrderEvent) -> Result<Option<i32>, AggregateError> {
+        Ok(self
+            .events
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(e, _)| e.identifier() == event.identifier())
+            .map(|(_, version)| *version)
+            .next_back())
+    }
+}
+
+impl From<ActorError> for AggregateError {
+    fn from(error: ActorError) -> Self {
+        AggregateError::SaveEvents(error.to_string())
+    }
+}
+
+/// Decider for the Order aggregate - Domain logic
+fn decider() -> Decider<'static, OrderCommand, OrderState, OrderEvent, AggregateError> {
+    Decider {
+        decide: Box::new(|command, state| match command {
+            OrderCommand::Create(cmd) => Ok(vec![OrderEvent::Created(OrderCreatedEvent {
+                order_id: cmd.order_id,
+                customer_name: cmd.customer_name.to_owned(),
+                items: cmd.items.to_owned(),
+            })]),
+            OrderCommand::Update(_) => Ok(vec![]),
+            OrderCommand::Cancel(cmd) => {
+                if state.order_id == cmd.order_id {
+                    Ok(vec![OrderEvent::Cancelled(OrderCancelledEvent {
+                        order_id: cmd.order_id,
+                    })])
+                } else {
+                    Err(AggregateError::DomainError(
+                        "Order does not exist".to_string(),
+                    ))
+                }
+            }
+        }),
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+fn create_order_command(order_id: u32) -> OrderCommand {
+    OrderCommand::Create(CreateOrderCommand {
+        order_id,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string()],
+    })
+}
+
+#[tokio::test]
+async fn test() {
+    let runtime = Arc::new(
+        ActorRuntime::new(InMemoryOrderEventRepository::default(), decider())
+            .with_idle_timeout(Duration::from_millis(50)),
+    );
+
+    let events = runtime.handle(&create_order_command(1)).await.unwrap();
+    assert_eq!(events.len(), 1);
+    let events = runtime
+        .handle(&OrderCommand::Update(UpdateOrderCommand {
+            order_id: 1,
+            new_items: vec!["Item 2".to_string()],
+        }))
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 0);
+    // The order exists in the cached state
+    let events = runtime
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }))
+        .await
+        .unwrap();
+    assert_eq!(
+        events,
+        vec![(
+            OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 }),
+            1
+        )]
+    );
+    // The stream is fetched once, when the actor is started
+    assert_eq!(runtime.repository().fetched.load(Ordering::SeqCst), 1);
+    assert_eq!(runtime.active(), 1);
+
+    // Rejected by the decider, the actor keeps running
+    let result = runtime
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 2 }))
+        .await;
+    assert!(result.is_err());
+    assert_eq!(runtime.active(), 2);
+
+    // The actors of the different orders handle the commands concurrently
+    let handles = (3..=6)
+        .map(|order_id| {
+            let runtime = runtime.clone();
+            tokio::spawn(async move { runtime.handle(&create_order_command(order_id)).await })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        assert_eq!(handle.await.unwrap().unwrap()[0].1, 0);
+    }
+    assert_eq!(runtime.active(), 6);
+    assert_eq!(runtime.repository().fetched.load(Ordering::SeqCst), 6);
+
+    // The idle actors are passivated, and started again by the next command
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(runtime.active(), 0);
+    let result = runtime
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 3 }))
+        .await
+        .unwrap();
+    assert_eq!(result[0].1, 1);
+    assert_eq!(runtime.repository().fetched.load(Ordering::SeqCst), 7);
+}
+
+#[tokio::test]
+async fn mailbox_full_test() {
+    let runtime = ActorRuntime::new(InMemoryOrderEventRepository::default(), decider())
+        .with_mailbox_capacity(1);
+
+    let create = create_order_command(1);
+    let cancel = OrderCommand::Cancel(CancelOrderCommand { order_id: 1 });
+    // The actor is not running until the test yields, so the mailbox takes the first command only
+    let (first, second, third) = tokio::join!(
+        runtime.handle(&create),
+        runtime.handle(&cancel),
+        runtime.handle(&cancel),
+    );
+    assert_eq!(first.unwrap().len(), 1);
+    for result in [second, third] {
+        assert!(matches!(
+            result,
+            Err(AggregateError::SaveEvents(message)) if message == ActorError::MailboxFull.to_string()
+        ));
+    }
+    // The rejected command can be retried
+    let events = runtime.handle(&cancel).await.unwrap();
+    assert_eq!(events[0].1, 1);
+}
+
+#[tokio::test]
+async fn stopped_test() {
+    let mut decider = decider();
+    decider.decide = Box::new(|command, _| match command {
+        OrderCommand::Update(_) => panic!("the decider has panicked"),
+        _ => Ok(vec![]),
+    });
+    let runtime = ActorRuntime::new(InMemoryOrderEventRepository::default(), decider);
+
+    let update = OrderCommand::Update(UpdateOrderCommand {
+        order_id: 1,
+        new_items: vec![],
+    });
+    let result = runtime.handle(&update).await;
+    assert!(matches!(
+        result,
+        Err(AggregateError::SaveEvents(message)) if message == ActorError::Stopped.to_string()
+    ));
+    // The panicked actor is started again by the next command
+    let events = runtime.handle(&create_order_command(1)).await.unwrap();
+    assert!(events.is_empty());
+    assert_eq!(runtime.repository().fetched.load(Ordering::SeqCst), 2);
+}
@@ -79,7 +79,7 @@ impl EventRepository<Command, Event, i32, AggregateError> for InMemoryEventRepos
             .into_iter()
             .filter(|(e, _)| e.identifier() == event.identifier())
             .map(|(_, version)| version)
-            .last())
+            .next_back())
     }
 }
 
@@ -0,0 +1,283 @@
+#![cfg(not(feature = "not-send-futures"))]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use fmodel_rust::aggregate::{EventRepository, EventSourcedAggregate, IdempotentEventRepository};
+use fmodel_rust::decider::Decider;
+use fmodel_rust::{IdempotencyKey, Identifier};
+
+use crate::api::{
+    CreateOrderCommand, OrderCommand, OrderCreatedEvent, OrderEvent, OrderState, OrderUpdatedEvent,
+    UpdateOrderCommand,
+};
+use crate::application::AggregateError;
+
+mod api;
+mod application;
+
+/// Command envelope, carrying the idempotency key of the client request
+#[derive(Debug, Clone)]
+struct CommandEnvelope {
+    idempotency_key: Option<String>,
+    command: OrderCommand,
+}
+
+impl Identifier for CommandEnvelope {
+    fn identifier(&self) -> String {
+        self.command.identifier()
+    }
+}
+
+impl IdempotencyKey for CommandEnvelope {
+    fn idempotency_key(&self) -> Option<String> {
+        self.idempotency_key.clone()
+    }
+}
+
+#[derive(Default)]
+struct Store {
+    events: Vec<(OrderEvent, i32)>,
+    processed: HashMap<(String, String), Vec<(OrderEvent, i32)>>,
+}
+
+/// A simple in-memory event repository, recording the idempotency keys per stream - infrastructure
+#[derive(Clone, Default)]
+struct InMemoryOrderEventRepository {
+    store: Arc<Mutex<Store>>,
+}
+
+impl InMemoryOrderEventRepository {
+    fn append(store: &mut Store, events: &[OrderEvent]) -> Vec<(OrderEvent, i32)> {
+        events
+            .iter()
+            .map(|event| {
+                let version = store
+                    .events
+                    .iter()
+                    .filter(|(e, _)| e.identifier() == event.identifier())
+                    .map(|(_, version)| *version)
+                    .next_back()
+                    .unwrap_or(-1)
+                    + 1;
+                store.events.push((event.clone(), version));
+                (event.clone(), version)
+            })
+            .collect()
+    }
+}
+
+impl EventRepository<CommandEnvelope, OrderEvent, i32, AggregateError>
+    for InMemoryOrderEventRepository
+{
+    async fn fetch_events(
+        &self,
+        command: &CommandEnvelope,
+    ) -> Result<Vec<(OrderEvent, i32)>, AggregateError> {
+        Ok(self
+            .store
+            .lock()
+            .unwrap()
+            .events
+            .iter()
+            .filter(|(event, _)| event.identifier() == command.identifier())
+            .cloned()
+            .collect())
+    }
+
+    async fn save(&self, events: &[OrderEvent]) -> Result<Vec<(OrderEvent, i32)>, AggregateError> {
+        Ok(Self::append(&mut self.store.lock().unwrap(), events))
+    }
+
+    async fn version_provider(&self, event: &OrderEvent) -> Result<Option<i32>, AggregateError> {
+        Ok(self
+            .store
+            .lock()
+            .unwrap()
+            .events
+            .iter()
+            .filter(|(e, _)| e.identifier() == event.identifier())
+            .map(|(_, version)| *version)
+            .next_back())
+    }
+}
+
+impl IdempotentEventRepository<CommandEnvelope, OrderEvent, i32, AggregateError>
+    for InMemoryOrderEventRepository
+{
+    async fn fetch_processed(
+        &self,
+        command: &CommandEnvelope,
+        idempotency_key: &str,
+    ) -> Result<Option<Vec<(OrderEvent, i32)>>, AggregateError> {
+        Ok(self
+            .store
+            .lock()
+            .unwrap()
+            .processed
+            .get(&(command.identifier(), idempotency_key.to_string()))
+            .cloned())
+    }
+
+    async fn save_processed(
+        &self,
+        command: &CommandEnvelope,
+        idempotency_key: &str,
+        events: &[OrderEvent],
+        _latest_version: &Option<i32>,
+    ) -> Result<Vec<(OrderEvent, i32)>, AggregateError> {
+        let mut store = self.store.lock().unwrap();
+        let key = (command.identifier(), idempotency_key.to_string());
+        // The concurrent duplicate has been processed in the meantime
+        if let Some(processed_events) = store.processed.get(&key) {
+            return Ok(processed_events.clone());
+        }
+        let saved_events = Self::append(&mut store, events);
+        store.processed.insert(key, saved_events.clone());
+        Ok(saved_events)
+    }
+}
+
+/// Decider for the Order aggregate - Domain logic
+fn decider<'a>() -> Decider<'a, OrderCommand, OrderState, OrderEvent> {
+    Decider {
+        decide: Box::new(|command, state| match command {
+            OrderCommand::Create(cmd) => Ok(vec![OrderEvent::Created(OrderCreatedEvent {
+                order_id: cmd.order_id,
+                customer_name: cmd.customer_name.to_owned(),
+                items: cmd.items.to_owned(),
+            })]),
+            OrderCommand::Update(cmd) => {
+                if state.order_id == cmd.order_id {
+                    Ok(vec![OrderEvent::Updated(OrderUpdatedEvent {
+                        order_id: cmd.order_id,
+                        updated_items: cmd.new_items.to_owned(),
+                    })])
+                } else {
+                    Ok(vec![])
+                }
+            }
+            OrderCommand::Cancel(_) => Ok(vec![]),
+        }),
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+#[tokio::test]
+async fn test() {
+    let repository = InMemoryOrderEventRepository::default();
+    let aggregate = EventSourcedAggregate::new(
+        repository.clone(),
+        decider()
+            .map_command(|envelope: &CommandEnvelope| envelope.command.clone())
+            .map_error(|()| AggregateError::DomainError("Decider error".to_string())),
+    );
+
+    let create = CommandEnvelope {
+        idempotency_key: Some("request-1".to_string()),
+        command: OrderCommand::Create(CreateOrderCommand {
+            order_id: 1,
+            customer_name: "John Doe".to_string(),
+            items: vec!["Item 1".to_string(), "Item 2".to_string()],
+        }),
+    };
+    let update = CommandEnvelope {
+        idempotency_key: Some("request-2".to_string()),
+        command: OrderCommand::Update(UpdateOrderCommand {
+            order_id: 1,
+            new_items: vec!["Item 3".to_string()],
+        }),
+    };
+
+    let created = aggregate.handle_idempotent(&create).await.unwrap();
+    assert_eq!(created.len(), 1);
+    assert_eq!(created[0].1, 0);
+    let updated = aggregate.handle_idempotent(&update).await.unwrap();
+    assert_eq!(updated[0].1, 1);
+
+    // The retried requests return the originally produced events, and nothing new is stored
+    assert_eq!(aggregate.handle_idempotent(&create).await.unwrap(), created);
+    assert_eq!(aggregate.handle_idempotent(&update).await.unwrap(), updated);
+    assert_eq!(repository.store.lock().unwrap().events.len(), 2);
+
+    // The same key in another stream is a different request
+    let create_another = CommandEnvelope {
+        idempotency_key: Some("request-1".to_string()),
+        command: OrderCommand::Create(CreateOrderCommand {
+            order_id: 2,
+            customer_name: "Jane Doe".to_string(),
+            items: vec!["Item 1".to_string()],
+        }),
+    };
+    let result = aggregate.handle_idempotent(&create_another).await.unwrap();
+    assert_eq!(result[0].0.identifier(), "2");
+
+    // Commands with no key are always handled
+    let update_without_key = CommandEnvelope {
+        idempotency_key: None,
+        ..update.clone()
+    };
+    let result = aggregate
+        .handle_idempotent(&update_without_key)
+        .await
+        .unwrap();
+    assert_eq!(result[0].1, 2);
+    let result = aggregate
+        .handle_idempotent(&update_without_key)
+        .await
+        .unwrap();
+    assert_eq!(result[0].1, 3);
+    assert_eq!(repository.store.lock().unwrap().events.len(), 5);
+}
+
+#[tokio::test]
+async fn concurrent_duplicate_test() {
+    let repository = InMemoryOrderEventRepository::default();
+    let create = CommandEnvelope {
+        idempotency_key: Some("request-1".to_string()),
+        command: OrderCommand::Create(CreateOrderCommand {
+            order_id: 1,
+            customer_name: "John Doe".to_string(),
+            items: vec!["Item 1".to_string()],
+        }),
+    };
+    let created = [OrderEvent::Created(OrderCreatedEvent {
+        order_id: 1,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string()],
+    })];
+
+    // Both duplicates have found no processed command, and save the events they decided
+    let first = repository
+        .save_processed(&create, "request-1", &created, &None)
+        .await
+        .unwrap();
+    let second = repository
+        .save_processed(&create, "request-1", &created, &None)
+        .await
+        .unwrap();
+    assert_eq!(first, second);
+    assert_eq!(repository.store.lock().unwrap().events.len(), 1);
+}
@@ -40,8 +40,8 @@ impl EventRepository<OrderCommand, OrderEvent, i32, AggregateError>
         let events = self.events.borrow(); // borrow the Vec immutably
         Ok(events
             .iter()
-            .cloned()
             .filter(|(e, _)| e.identifier() == command.identifier())
+            .cloned()
             .collect())
     }
 
@@ -56,7 +56,7 @@ impl EventRepository<OrderCommand, OrderEvent, i32, AggregateError>
                         .iter()
                         .filter(|(e, _)| e.identifier() == first_event.identifier())
                         .map(|(_, v)| *v)
-                        .last()
+                        .next_back()
                 })
                 .unwrap_or(-1)
         };
@@ -83,7 +83,7 @@ impl EventRepository<OrderCommand, OrderEvent, i32, AggregateError>
             .iter()
             .filter(|(e, _)| e.identifier() == event.identifier())
             .map(|(_, v)| *v)
-            .last())
+            .next_back())
     }
 }
 
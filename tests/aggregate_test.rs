@@ -4,14 +4,15 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex, RwLock};
 
 use fmodel_rust::aggregate::{
-    EventRepository, EventSourcedAggregate, StateRepository, StateStoredAggregate,
+    EventRepository, EventSourcedAggregate, EventStream, StateRepository, StateStoredAggregate,
+    StreamingEventRepository,
 };
 use fmodel_rust::decider::Decider;
 use fmodel_rust::Identifier;
 
 use crate::api::{
     CreateOrderCommand, OrderCancelledEvent, OrderCommand, OrderCreatedEvent, OrderEvent,
-    OrderState, OrderUpdatedEvent,
+    OrderState, OrderUpdatedEvent, UpdateOrderCommand,
 };
 use crate::application::AggregateError;
 use std::thread;
@@ -79,7 +80,41 @@ impl EventRepository<OrderCommand, OrderEvent, i32, AggregateError>
             .into_iter()
             .filter(|(e, _)| e.identifier() == event.identifier())
             .map(|(_, version)| version)
-            .last())
+            .next_back())
+    }
+}
+
+/// The events of the in-memory stream, pulled one at a time - infrastructure
+struct InMemoryOrderEventStream {
+    events: std::vec::IntoIter<(OrderEvent, i32)>,
+}
+
+/// Implementation of [EventStream] for [InMemoryOrderEventStream] - infrastructure
+impl EventStream<OrderEvent, i32, AggregateError> for InMemoryOrderEventStream {
+    async fn next(&mut self) -> Option<Result<(OrderEvent, i32), AggregateError>> {
+        self.events.next().map(Ok)
+    }
+}
+
+/// Implementation of [StreamingEventRepository] for [InMemoryOrderEventRepository] - infrastructure
+impl StreamingEventRepository<OrderCommand, OrderEvent, i32, AggregateError>
+    for InMemoryOrderEventRepository
+{
+    fn stream_events(
+        &self,
+        command: &OrderCommand,
+    ) -> impl EventStream<OrderEvent, i32, AggregateError> + Send {
+        let events: Vec<(OrderEvent, i32)> = self
+            .events
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(event, _)| event.identifier() == command.identifier())
+            .cloned()
+            .collect();
+        InMemoryOrderEventStream {
+            events: events.into_iter(),
+        }
     }
 }
 
@@ -248,3 +283,33 @@ async fn ss_test() {
     handle1.join().unwrap().await;
     handle2.join().unwrap().await;
 }
+
+#[tokio::test]
+async fn es_streaming_test() {
+    let aggregate = EventSourcedAggregate::new(
+        InMemoryOrderEventRepository::new(),
+        decider().map_error(|()| AggregateError::DomainError("Decider error".to_string())),
+    );
+    let command = OrderCommand::Create(CreateOrderCommand {
+        order_id: 1,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string(), "Item 2".to_string()],
+    });
+    aggregate.handle_streaming(&command).await.unwrap();
+    let command = OrderCommand::Update(UpdateOrderCommand {
+        order_id: 1,
+        new_items: vec!["Item 3".to_string()],
+    });
+    aggregate.handle_streaming(&command).await.unwrap();
+
+    // The state is folded from the streamed events, so the order is known to exist
+    let command = OrderCommand::Cancel(crate::api::CancelOrderCommand { order_id: 1 });
+    let result = aggregate.handle_streaming(&command).await.unwrap();
+    assert_eq!(
+        result,
+        vec![(
+            OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 }),
+            2
+        )]
+    );
+}
@@ -2,10 +2,12 @@
 // ############################ Order API ############################
 // ###################################################################
 
+use fmodel_rust::message_type::MessageType;
 use fmodel_rust::Identifier;
+use serde::{Deserialize, Serialize};
 
 /// The state of the Order entity
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct OrderState {
     pub order_id: u32,
@@ -15,7 +17,7 @@ pub struct OrderState {
 }
 
 /// The state of the ViewOrder entity / It represents the Query Model
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct OrderViewState {
     pub order_id: u32,
@@ -25,7 +27,7 @@ pub struct OrderViewState {
 }
 
 /// A second version of the ViewOrder entity / It represents the Query Model
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct OrderView2State {
     pub order_id: u32,
@@ -35,7 +37,7 @@ pub struct OrderView2State {
 }
 
 /// All variants of Order commands
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub enum OrderCommand {
     Create(CreateOrderCommand),
@@ -43,20 +45,20 @@ pub enum OrderCommand {
     Cancel(CancelOrderCommand),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CreateOrderCommand {
     pub order_id: u32,
     pub customer_name: String,
     pub items: Vec<String>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UpdateOrderCommand {
     pub order_id: u32,
     pub new_items: Vec<String>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CancelOrderCommand {
     pub order_id: u32,
 }
@@ -73,7 +75,7 @@ impl Identifier for OrderCommand {
 }
 
 /// All variants of Order events
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub enum OrderEvent {
     Created(OrderCreatedEvent),
@@ -81,20 +83,20 @@ pub enum OrderEvent {
     Cancelled(OrderCancelledEvent),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OrderCreatedEvent {
     pub order_id: u32,
     pub customer_name: String,
     pub items: Vec<String>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OrderUpdatedEvent {
     pub order_id: u32,
     pub updated_items: Vec<String>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OrderCancelledEvent {
     pub order_id: u32,
 }
@@ -111,12 +113,41 @@ impl Identifier for OrderEvent {
     }
 }
 
+/// Provides the stable names of the Order events
+impl MessageType for OrderEvent {
+    const MESSAGE_TYPES: &'static [&'static str] =
+        &["order.created", "order.updated", "order.cancelled"];
+    fn message_type(&self) -> &'static str {
+        match self {
+            OrderEvent::Created(_) => "order.created",
+            OrderEvent::Updated(_) => "order.updated",
+            OrderEvent::Cancelled(_) => "order.cancelled",
+        }
+    }
+}
+
+/// Provides a way to get the id of the Order state
+impl Identifier for OrderState {
+    #[allow(dead_code)]
+    fn identifier(&self) -> String {
+        self.order_id.to_string()
+    }
+}
+
+/// Provides a way to get the id of the Order view state
+impl Identifier for OrderViewState {
+    #[allow(dead_code)]
+    fn identifier(&self) -> String {
+        self.order_id.to_string()
+    }
+}
+
 // ######################################################################
 // ############################ Shipment API ############################
 // ######################################################################
 
 /// The state of the Shipment entity
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct ShipmentState {
     pub shipment_id: u32,
@@ -126,7 +157,7 @@ pub struct ShipmentState {
 }
 
 /// The state of the ViewShipment entity / It represents the Query Model
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct ShipmentViewState {
     pub shipment_id: u32,
@@ -136,13 +167,13 @@ pub struct ShipmentViewState {
 }
 
 /// All variants of Shipment commands
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub enum ShipmentCommand {
     Create(CreateShipmentCommand),
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct CreateShipmentCommand {
     pub shipment_id: u32,
     pub order_id: u32,
@@ -161,12 +192,12 @@ impl Identifier for ShipmentCommand {
 }
 
 /// All variants of Shipment events
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub enum ShipmentEvent {
     Created(ShipmentCreatedEvent),
 }
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct ShipmentCreatedEvent {
     pub shipment_id: u32,
     pub order_id: u32,
@@ -183,3 +214,13 @@ impl ShipmentEvent {
         }
     }
 }
+
+/// Provides the stable names of the Shipment events
+impl MessageType for ShipmentEvent {
+    const MESSAGE_TYPES: &'static [&'static str] = &["shipment.created"];
+    fn message_type(&self) -> &'static str {
+        match self {
+            ShipmentEvent::Created(_) => "shipment.created",
+        }
+    }
+}
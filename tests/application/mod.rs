@@ -1,6 +1,8 @@
 use derive_more::Display;
+use fmodel_rust::aggregate::EventRepository;
 use fmodel_rust::{Identifier, Sum};
 use std::error::Error;
+use std::sync::RwLock;
 
 use crate::api::{
     CancelOrderCommand, CreateOrderCommand, CreateShipmentCommand, OrderCancelledEvent,
@@ -157,3 +159,66 @@ pub enum SagaManagerError {
 }
 
 impl Error for SagaManagerError {}
+
+/// Error type for the process manager
+#[derive(Debug, Display)]
+#[allow(dead_code)]
+pub enum ProcessManagerError {
+    FetchState(String),
+    SaveState(String),
+    PublishAction(String),
+}
+
+impl Error for ProcessManagerError {}
+
+/// A simple in-memory event repository - infrastructure
+#[derive(Default)]
+#[allow(dead_code)]
+pub struct InMemoryOrderEventRepository {
+    pub events: RwLock<Vec<(OrderEvent, i32)>>,
+}
+
+impl EventRepository<OrderCommand, OrderEvent, i32, AggregateError>
+    for InMemoryOrderEventRepository
+{
+    async fn fetch_events(
+        &self,
+        command: &OrderCommand,
+    ) -> Result<Vec<(OrderEvent, i32)>, AggregateError> {
+        Ok(self
+            .events
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(event, _)| event.identifier() == command.identifier())
+            .cloned()
+            .collect())
+    }
+
+    async fn save(&self, events: &[OrderEvent]) -> Result<Vec<(OrderEvent, i32)>, AggregateError> {
+        let mut latest_version = match events.first() {
+            Some(event) => self.version_provider(event).await?.unwrap_or(-1),
+            None => -1,
+        };
+        let events = events
+            .iter()
+            .map(|event| {
+                latest_version += 1;
+                (event.clone(), latest_version)
+            })
+            .collect::<Vec<(OrderEvent, i32)>>();
+        self.events.write().unwrap().extend_from_slice(&events);
+        Ok(events)
+    }
+
+    async fn version_provider(&self, event: &OrderEvent) -> Result<Option<i32>, AggregateError> {
+        Ok(self
+            .events
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(e, _)| e.identifier() == event.identifier())
+            .map(|(_, version)| *version)
+            .next_back())
+    }
+}
@@ -0,0 +1,103 @@
+use fmodel_rust::codec::{event_type, CodecError};
+#[cfg(any(
+    feature = "json",
+    feature = "cbor",
+    feature = "msgpack",
+    feature = "bincode"
+))]
+use fmodel_rust::codec::{EncodedEvent, EventCodec};
+
+use crate::api::{OrderCancelledEvent, OrderCreatedEvent, OrderEvent, OrderUpdatedEvent};
+
+mod api;
+mod application;
+
+fn events() -> Vec<OrderEvent> {
+    vec![
+        OrderEvent::Created(OrderCreatedEvent {
+            order_id: 1,
+            customer_name: "John Doe".to_string(),
+            items: vec!["Item 1".to_string(), "Item 2".to_string()],
+        }),
+        OrderEvent::Updated(OrderUpdatedEvent {
+            order_id: 1,
+            updated_items: vec!["Item 3".to_string()],
+        }),
+        OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 }),
+    ]
+}
+
+/// Encodes and decodes the events by the codec, asserting the event types.
+#[cfg(any(
+    feature = "json",
+    feature = "cbor",
+    feature = "msgpack",
+    feature = "bincode"
+))]
+fn assert_round_trip<Codec: EventCodec<OrderEvent>>(codec: Codec) {
+    let encoded: Vec<EncodedEvent> = events()
+        .iter()
+        .map(|event| codec.encode(event).unwrap())
+        .collect();
+    assert_eq!(
+        encoded
+            .iter()
+            .map(|event| event.event_type.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Created", "Updated", "Cancelled"]
+    );
+    let decoded: Vec<OrderEvent> = encoded
+        .iter()
+        .map(|event| codec.decode(event).unwrap())
+        .collect();
+    assert_eq!(decoded, events());
+
+    let corrupted = EncodedEvent {
+        event_type: "Created".to_string(),
+        payload: vec![0xff; 3],
+    };
+    assert!(matches!(
+        codec.decode(&corrupted),
+        Err(CodecError::Decode(event_type, _)) if event_type == "Created"
+    ));
+}
+
+#[test]
+fn event_type_test() {
+    assert_eq!(event_type(&events()[0]).unwrap(), "Created");
+    assert_eq!(event_type(&events()[2]).unwrap(), "Cancelled");
+    assert_eq!(
+        event_type(&OrderCancelledEvent { order_id: 1 }).unwrap(),
+        "OrderCancelledEvent"
+    );
+    assert!(matches!(event_type(&1), Err(CodecError::Encode(_))));
+    assert!(event_type(&vec![events()[0].clone()]).is_err());
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_codec_test() {
+    let codec = fmodel_rust::codec::JsonCodec;
+    assert_eq!(EventCodec::<OrderEvent>::format(&codec), "json");
+    let encoded = codec.encode(&events()[2]).unwrap();
+    assert_eq!(encoded.payload, br#"{"Cancelled":{"order_id":1}}"#);
+    assert_round_trip(codec);
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn cbor_codec_test() {
+    assert_round_trip(fmodel_rust::codec::CborCodec);
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+fn message_pack_codec_test() {
+    assert_round_trip(fmodel_rust::codec::MessagePackCodec);
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn bincode_codec_test() {
+    assert_round_trip(fmodel_rust::codec::BincodeCodec);
+}
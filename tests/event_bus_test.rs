@@ -0,0 +1,334 @@
+#![cfg(all(feature = "event-bus", not(feature = "not-send-futures")))]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use fmodel_rust::aggregate::EventSourcedAggregate;
+use fmodel_rust::decider::Decider;
+use fmodel_rust::event_bus::{EventBus, EventBusRepository, Overflow};
+use fmodel_rust::materialized_view::{MaterializedView, ViewStateRepository};
+use fmodel_rust::middleware::Handler;
+use fmodel_rust::saga::Saga;
+use fmodel_rust::saga_manager::{ActionPublisher, SagaManager};
+use fmodel_rust::view::View;
+use fmodel_rust::Identifier;
+use tokio::sync::Semaphore;
+
+use crate::api::{
+    CancelOrderCommand, CreateOrderCommand, CreateShipmentCommand, OrderCancelledEvent,
+    OrderCommand, OrderCreatedEvent, OrderEvent, OrderState, OrderViewState, ShipmentCommand,
+};
+use crate::application::{
+    AggregateError, InMemoryOrderEventRepository, MaterializedViewError, SagaManagerError,
+};
+
+mod api;
+mod application;
+
+/// A simple in-memory view state repository, shared with the test - infrastructure
+#[derive(Clone, Default)]
+struct InMemoryViewOrderStateRepository {
+    states: Arc<RwLock<HashMap<u32, OrderViewState>>>,
+}
+
+impl ViewStateRepository<OrderEvent, OrderViewState, MaterializedViewError>
+    for InMemoryViewOrderStateRepository
+{
+    async fn fetch_state(
+        &self,
+        event: &OrderEvent,
+    ) -> Result<Option<OrderViewState>, MaterializedViewError> {
+        Ok(self
+            .states
+            .read()
+            .unwrap()
+            .get(&event.identifier().parse::<u32>().unwrap())
+            .cloned())
+    }
+
+    async fn save(&self, state: &OrderViewState) -> Result<OrderViewState, MaterializedViewError> {
+        self.states
+            .write()
+            .unwrap()
+            .insert(state.order_id, state.clone());
+        Ok(state.clone())
+    }
+}
+
+/// Action publisher collecting the published actions, shared with the test.
+#[derive(Clone, Default)]
+struct CollectingActionPublisher {
+    actions: Arc<Mutex<Vec<ShipmentCommand>>>,
+}
+
+impl ActionPublisher<ShipmentCommand, SagaManagerError> for CollectingActionPublisher {
+    async fn publish(
+        &self,
+        action: &[ShipmentCommand],
+    ) -> Result<Vec<ShipmentCommand>, SagaManagerError> {
+        self.actions.lock().unwrap().extend_from_slice(action);
+        Ok(Vec::from(action))
+    }
+}
+
+/// Handler failing on every event.
+struct Failing;
+
+impl Handler<OrderEvent, (), MaterializedViewError> for Failing {
+    async fn handle(&self, _event: &OrderEvent) -> Result<(), MaterializedViewError> {
+        Err(MaterializedViewError::SaveState("failed".to_string()))
+    }
+}
+
+/// Handler panicking on every event.
+struct Panicking;
+
+impl Handler<OrderEvent, (), MaterializedViewError> for Panicking {
+    async fn handle(&self, _event: &OrderEvent) -> Result<(), MaterializedViewError> {
+        panic!("the handler has panicked")
+    }
+}
+
+/// Handler waiting for the permit, for every event.
+struct Gated {
+    permits: Arc<Semaphore>,
+}
+
+impl Handler<OrderEvent, (), MaterializedViewError> for Gated {
+    async fn handle(&self, _event: &OrderEvent) -> Result<(), MaterializedViewError> {
+        self.permits.acquire().await.unwrap().forget();
+        Ok(())
+    }
+}
+
+fn decider() -> Decider<'static, OrderCommand, OrderState, OrderEvent, AggregateError> {
+    Decider {
+        decide: Box::new(|command, state| match command {
+            OrderCommand::Create(cmd) => Ok(vec![OrderEvent::Created(OrderCreatedEvent {
+                order_id: cmd.order_id,
+                customer_name: cmd.customer_name.to_owned(),
+                items: cmd.items.to_owned(),
+            })]),
+            OrderCommand::Update(_) => Ok(vec![]),
+            OrderCommand::Cancel(cmd) => {
+                if state.order_id == cmd.order_id {
+                    Ok(vec![OrderEvent::Cancelled(OrderCancelledEvent {
+                        order_id: cmd.order_id,
+                    })])
+                } else {
+                    Err(AggregateError::DomainError(
+                        "Order does not exist".to_string(),
+                    ))
+                }
+            }
+        }),
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+fn view() -> View<'static, OrderViewState, OrderEvent> {
+    View {
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderViewState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+fn saga() -> Saga<'static, OrderEvent, ShipmentCommand> {
+    Saga {
+        react: Box::new(|event| match event {
+            OrderEvent::Created(evt) => vec![ShipmentCommand::Create(CreateShipmentCommand {
+                shipment_id: evt.order_id,
+                order_id: evt.order_id,
+                customer_name: evt.customer_name.to_owned(),
+                items: evt.items.to_owned(),
+            })],
+            _ => vec![],
+        }),
+    }
+}
+
+fn created(order_id: u32) -> OrderEvent {
+    OrderEvent::Created(OrderCreatedEvent {
+        order_id,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string()],
+    })
+}
+
+#[tokio::test]
+async fn fan_out_test() {
+    let bus = EventBus::new();
+    let views = InMemoryViewOrderStateRepository::default();
+    let publisher = CollectingActionPublisher::default();
+    let view_subscriber = bus.subscribe("order-view", MaterializedView::new(views.clone(), view()));
+    let saga_subscriber =
+        bus.subscribe("shipment-saga", SagaManager::new(publisher.clone(), saga()));
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let failing_subscriber = bus.subscribe_with("failing", Failing, {
+        let errors = Arc::clone(&errors);
+        move |event: &OrderEvent, error: MaterializedViewError| {
+            errors
+                .lock()
+                .unwrap()
+                .push((event.identifier(), error.to_string()));
+        }
+    });
+    let panicking_subscriber = bus.subscribe("panicking", Panicking);
+
+    let aggregate = EventSourcedAggregate::new(
+        EventBusRepository::new(InMemoryOrderEventRepository::default(), bus.clone()),
+        decider(),
+    );
+    for order_id in 1..=2 {
+        aggregate
+            .handle(&OrderCommand::Create(CreateOrderCommand {
+                order_id,
+                customer_name: "John Doe".to_string(),
+                items: vec!["Item 1".to_string()],
+            }))
+            .await
+            .unwrap();
+    }
+    aggregate
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }))
+        .await
+        .unwrap();
+    // The panicked subscriber is unregistered
+    while !panicking_subscriber.is_stopped() {
+        tokio::task::yield_now().await;
+    }
+    assert_eq!(bus.subscribers().len(), 3);
+    assert!(bus
+        .subscribers()
+        .iter()
+        .all(|subscriber| subscriber.name() != "panicking"));
+    bus.shutdown().await;
+
+    assert!(views.states.read().unwrap().get(&1).unwrap().is_cancelled);
+    assert!(!views.states.read().unwrap().get(&2).unwrap().is_cancelled);
+    assert_eq!(publisher.actions.lock().unwrap().len(), 2);
+
+    // The failing and the panicking subscribers do not affect the others
+    assert_eq!(view_subscriber.handled(), 3);
+    assert_eq!(saga_subscriber.handled(), 3);
+    assert_eq!(failing_subscriber.handled(), 0);
+    assert_eq!(failing_subscriber.failed(), 3);
+    assert_eq!(
+        errors
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(identifier, _)| identifier.as_str())
+            .collect::<Vec<_>>(),
+        vec!["1", "2", "1"]
+    );
+    assert_eq!(panicking_subscriber.handled(), 0);
+    assert!(panicking_subscriber.is_stopped());
+    assert!(view_subscriber.is_stopped());
+    assert!(bus.subscribers().is_empty());
+}
+
+#[tokio::test]
+async fn overflow_test() {
+    let bus = EventBus::new().with_capacity(1);
+    let permits = Arc::new(Semaphore::new(0));
+    let dropping = bus.subscribe(
+        "dropping",
+        Gated {
+            permits: Arc::clone(&permits),
+        },
+    );
+    // The clones of the bus share the subscribers
+    let unsubscribed = bus.clone().with_overflow(Overflow::Unsubscribe).subscribe(
+        "unsubscribed",
+        Gated {
+            permits: Arc::clone(&permits),
+        },
+    );
+    assert_eq!(bus.subscribers()[0].name(), "dropping");
+
+    // The subscribers are not running until the test yields, so the mailboxes take the first event only, and the publisher does not wait
+    bus.publish(&[created(1), created(2), created(3)]);
+    assert_eq!(dropping.dropped(), 2);
+    assert_eq!(unsubscribed.dropped(), 2);
+    let names: Vec<String> = bus
+        .subscribers()
+        .iter()
+        .map(|subscriber| subscriber.name().to_string())
+        .collect();
+    assert_eq!(names, vec!["dropping"]);
+
+    // The dropping subscriber keeps receiving the later events, once it has the room
+    permits.add_permits(4);
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while dropping.handled() < 1 || !unsubscribed.is_stopped() {
+            tokio::task::yield_now().await;
+        }
+    })
+    .await
+    .unwrap();
+    bus.publish(&[created(4)]);
+    bus.shutdown().await;
+    assert_eq!(dropping.handled(), 2);
+    assert_eq!(unsubscribed.handled(), 1);
+}
+
+#[test]
+fn runtime_test() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    // The subscriber is registered from outside of the runtime
+    let bus = EventBus::new().with_runtime(runtime.handle().clone());
+    let subscriber = bus.subscribe(
+        "gated",
+        Gated {
+            permits: Arc::new(Semaphore::new(1)),
+        },
+    );
+    bus.publish(&[created(1)]);
+    runtime.block_on(bus.shutdown());
+    assert_eq!(subscriber.handled(), 1);
+}
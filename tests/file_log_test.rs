@@ -0,0 +1,299 @@
+#![cfg(all(feature = "file-log", not(feature = "not-send-futures")))]
+
+use std::fs::OpenOptions;
+use std::path::PathBuf;
+
+use fmodel_rust::aggregate::{EventRepository, EventSourcedAggregate};
+use fmodel_rust::decider::Decider;
+use fmodel_rust::file_log::{FileEventRepository, FileLogError};
+
+use crate::api::{
+    CancelOrderCommand, CreateOrderCommand, OrderCancelledEvent, OrderCommand, OrderCreatedEvent,
+    OrderEvent, OrderState,
+};
+use crate::application::AggregateError;
+
+mod api;
+mod application;
+
+impl From<FileLogError> for AggregateError {
+    fn from(error: FileLogError) -> Self {
+        AggregateError::SaveEvents(error.to_string())
+    }
+}
+
+/// Decider for the Order aggregate - Domain logic
+fn decider<'a>() -> Decider<'a, OrderCommand, OrderState, OrderEvent, AggregateError> {
+    Decider {
+        decide: Box::new(|command, state| match command {
+            OrderCommand::Create(cmd) => Ok(vec![OrderEvent::Created(OrderCreatedEvent {
+                order_id: cmd.order_id,
+                customer_name: cmd.customer_name.to_owned(),
+                items: cmd.items.to_owned(),
+            })]),
+            OrderCommand::Update(_) => Ok(vec![]),
+            OrderCommand::Cancel(cmd) => {
+                if state.order_id == cmd.order_id {
+                    Ok(vec![OrderEvent::Cancelled(OrderCancelledEvent {
+                        order_id: cmd.order_id,
+                    })])
+                } else {
+                    Err(AggregateError::DomainError(
+                        "Order does not exist".to_string(),
+                    ))
+                }
+            }
+        }),
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+/// The path of the fresh log file of the test.
+fn log_path(test: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "fmodel-file-log-{}-{}.log",
+        test,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn created_event(order_id: u32) -> OrderEvent {
+    OrderEvent::Created(OrderCreatedEvent {
+        order_id,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string()],
+    })
+}
+
+fn cancelled_event(order_id: u32) -> OrderEvent {
+    OrderEvent::Cancelled(OrderCancelledEvent { order_id })
+}
+
+async fn save(repository: &FileEventRepository, events: &[OrderEvent]) -> Vec<(OrderEvent, u64)> {
+    EventRepository::<OrderCommand, OrderEvent, u64, AggregateError>::save(repository, events)
+        .await
+        .unwrap()
+}
+
+async fn fetch(repository: &FileEventRepository, order_id: u32) -> Vec<(OrderEvent, u64)> {
+    EventRepository::<OrderCommand, OrderEvent, u64, AggregateError>::fetch_events(
+        repository,
+        &OrderCommand::Cancel(CancelOrderCommand { order_id }),
+    )
+    .await
+    .unwrap()
+}
+
+fn file_length(path: &PathBuf) -> u64 {
+    std::fs::metadata(path).unwrap().len()
+}
+
+#[tokio::test]
+async fn event_sourced_test() {
+    let path = log_path("event-sourced");
+    let aggregate =
+        EventSourcedAggregate::new(FileEventRepository::open(&path).unwrap(), decider());
+
+    let result = aggregate
+        .handle(&OrderCommand::Create(CreateOrderCommand {
+            order_id: 1,
+            customer_name: "John Doe".to_string(),
+            items: vec!["Item 1".to_string()],
+        }))
+        .await
+        .unwrap();
+    assert_eq!(result, vec![(created_event(1), 0)]);
+    let result = aggregate
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }))
+        .await
+        .unwrap();
+    assert_eq!(result, vec![(cancelled_event(1), 1)]);
+    let result = aggregate
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 2 }))
+        .await;
+    assert!(result.is_err());
+    drop(aggregate);
+
+    // The events are durable, and indexed again when the log is opened
+    let repository = FileEventRepository::open(&path).unwrap();
+    assert_eq!(repository.recovered(), 0);
+    assert_eq!(repository.streams(), 1);
+    assert_eq!(
+        fetch(&repository, 1).await,
+        vec![(created_event(1), 0), (cancelled_event(1), 1)]
+    );
+    assert_eq!(
+        EventRepository::<OrderCommand, OrderEvent, u64, AggregateError>::version_provider(
+            &repository,
+            &created_event(1),
+        )
+        .await
+        .unwrap(),
+        Some(1)
+    );
+    // The events of the different streams are appended at once
+    assert_eq!(
+        save(&repository, &[created_event(2), cancelled_event(1)]).await,
+        vec![(created_event(2), 0), (cancelled_event(1), 2)]
+    );
+    assert!(fetch(&repository, 3).await.is_empty());
+
+    // The events decided on the stale version of the stream are rejected
+    let result = EventRepository::<OrderCommand, OrderEvent, u64, FileLogError>::save_after(
+        &repository,
+        &OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }),
+        &[cancelled_event(1)],
+        &Some(1),
+    )
+    .await;
+    assert!(matches!(result, Err(FileLogError::Conflict(id)) if id == "1"));
+    let result = EventRepository::<OrderCommand, OrderEvent, u64, FileLogError>::save_after(
+        &repository,
+        &OrderCommand::Cancel(CancelOrderCommand { order_id: 2 }),
+        &[created_event(2)],
+        &None,
+    )
+    .await;
+    assert!(matches!(result, Err(FileLogError::Conflict(_))));
+    assert_eq!(fetch(&repository, 1).await.len(), 3);
+
+    drop(repository);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn torn_write_recovery_test() {
+    let path = log_path("torn-write");
+    let repository = FileEventRepository::open(&path).unwrap();
+    save(&repository, &[created_event(1), created_event(2)]).await;
+    let committed = file_length(&path);
+    save(&repository, &[cancelled_event(1), cancelled_event(2)]).await;
+    drop(repository);
+
+    // The crash while appending: the first event of the append is written, the second one is torn
+    let torn = file_length(&path) - 5;
+    OpenOptions::new()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_len(torn)
+        .unwrap();
+
+    let repository = FileEventRepository::open(&path).unwrap();
+    assert_eq!(repository.recovered(), torn - committed);
+    assert_eq!(file_length(&path), committed);
+    assert_eq!(fetch(&repository, 1).await, vec![(created_event(1), 0)]);
+    assert_eq!(fetch(&repository, 2).await, vec![(created_event(2), 0)]);
+    assert_eq!(
+        save(&repository, &[cancelled_event(2)]).await,
+        vec![(cancelled_event(2), 1)]
+    );
+    drop(repository);
+
+    let repository = FileEventRepository::open(&path).unwrap();
+    assert_eq!(repository.recovered(), 0);
+    assert_eq!(
+        fetch(&repository, 2).await,
+        vec![(created_event(2), 0), (cancelled_event(2), 1)]
+    );
+
+    drop(repository);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn corrupted_record_test() {
+    let path = log_path("corrupted");
+    let repository = FileEventRepository::open(&path).unwrap();
+    save(&repository, &[created_event(1)]).await;
+    save(&repository, &[cancelled_event(1)]).await;
+    drop(repository);
+
+    // The bit rot in the first record is not the torn write, so it is not truncated
+    let mut bytes = std::fs::read(&path).unwrap();
+    let position = bytes.iter().position(|byte| *byte == b'J').unwrap();
+    bytes[position] = b'j';
+    std::fs::write(&path, &bytes).unwrap();
+    assert!(matches!(
+        FileEventRepository::open(&path),
+        Err(FileLogError::Corrupted(0))
+    ));
+    assert_eq!(file_length(&path), bytes.len() as u64);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn compaction_test() {
+    let path = log_path("compaction");
+    let repository = FileEventRepository::open(&path).unwrap();
+    save(&repository, &[created_event(1), created_event(2)]).await;
+    save(&repository, &[cancelled_event(2), cancelled_event(1)]).await;
+    save(&repository, &[created_event(3)]).await;
+    let length = file_length(&path);
+
+    // The stream of the cancelled order 2 is archived
+    assert_eq!(repository.compact(|stream_id| stream_id != "2").unwrap(), 2);
+    assert_eq!(repository.compact(|stream_id| stream_id != "2").unwrap(), 0);
+    assert_eq!(repository.streams(), 2);
+    assert!(file_length(&path) < length);
+    assert!(fetch(&repository, 2).await.is_empty());
+    assert_eq!(
+        save(&repository, &[cancelled_event(3)]).await,
+        vec![(cancelled_event(3), 1)]
+    );
+    drop(repository);
+
+    let repository = FileEventRepository::open(&path).unwrap();
+    assert_eq!(repository.recovered(), 0);
+    assert_eq!(
+        fetch(&repository, 1).await,
+        vec![(created_event(1), 0), (cancelled_event(1), 1)]
+    );
+    assert_eq!(
+        fetch(&repository, 3).await,
+        vec![(created_event(3), 0), (cancelled_event(3), 1)]
+    );
+    assert!(fetch(&repository, 2).await.is_empty());
+
+    // The failed compaction keeps the log in use
+    let temporary = path.with_extension("compacting");
+    std::fs::create_dir(&temporary).unwrap();
+    assert!(repository.compact(|stream_id| stream_id != "3").is_err());
+    std::fs::remove_dir(&temporary).unwrap();
+    assert_eq!(
+        save(&repository, &[created_event(4)]).await,
+        vec![(created_event(4), 0)]
+    );
+    assert_eq!(fetch(&repository, 3).await.len(), 2);
+    drop(repository);
+    let repository = FileEventRepository::open(&path).unwrap();
+    assert_eq!(repository.streams(), 3);
+
+    drop(repository);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,254 @@
+#![cfg(not(feature = "not-send-futures"))]
+
+use std::collections::HashMap;
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
+
+use fmodel_rust::aggregate::{EventRepository, EventSourcedAggregate};
+use fmodel_rust::decider::Decider;
+use fmodel_rust::keyed_lock::{KeyedLock, Serialized};
+use fmodel_rust::middleware::Handler;
+use fmodel_rust::Identifier;
+
+use crate::api::{OrderCommand, OrderEvent, OrderState, OrderUpdatedEvent, UpdateOrderCommand};
+use crate::application::AggregateError;
+
+mod api;
+mod application;
+
+/// A simple in-memory event repository - infrastructure
+/// It detects the commands of the same order, handled concurrently (between fetching and saving the events).
+#[derive(Default)]
+struct InMemoryOrderEventRepository {
+    events: Mutex<Vec<(OrderEvent, i32)>>,
+    in_flight: Mutex<HashMap<String, usize>>,
+    races: Arc<Mutex<usize>>,
+}
+
+impl EventRepository<OrderCommand, OrderEvent, i32, AggregateError>
+    for InMemoryOrderEventRepository
+{
+    async fn fetch_events(
+        &self,
+        command: &OrderCommand,
+    ) -> Result<Vec<(OrderEvent, i32)>, AggregateError> {
+        {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            let count = in_flight.entry(command.identifier()).or_default();
+            *count += 1;
+            if *count > 1 {
+                *self.races.lock().unwrap() += 1;
+            }
+        }
+        // Gives the other commands the chance to interleave
+        tokio::task::yield_now().await;
+        Ok(self
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(event, _)| event.identifier() == command.identifier())
+            .cloned()
+            .collect())
+    }
+
+    async fn save(&self, events: &[OrderEvent]) -> Result<Vec<(OrderEvent, i32)>, AggregateError> {
+        tokio::task::yield_now().await;
+        let mut latest_version = match events.first() {
+            Some(event) => self.version_provider(event).await?.unwrap_or(-1),
+            None => -1,
+        };
+        let events = events
+            .iter()
+            .map(|event| {
+                latest_version += 1;
+                (event.clone(), latest_version)
+            })
+            .collect::<Vec<(OrderEvent, i32)>>();
+        self.events.lock().unwrap().extend_from_slice(&events);
+        if let Some((event, _)) = events.first() {
+            *self
+                .in_flight
+                .lock()
+                .unwrap()
+                .entry(event.identifier())
+                .or_default() -= 1;
+        }
+        Ok(events)
+    }
+
+    async fn version_provider(&self, event: &OrderEvent) -> Result<Option<i32>, AggregateError> {
+        Ok(self
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(e, _)| e.identifier() == event.identifier())
+            .map(|(_, version)| *version)
+            .next_back())
+    }
+}
+
+/// Decider, updating the order unconditionally - Domain logic
+fn decider<'a>() -> Decider<'a, OrderCommand, OrderState, OrderEvent, AggregateError> {
+    Decider {
+        decide: Box::new(|command, _state| match command {
+            OrderCommand::Update(cmd) => Ok(vec![OrderEvent::Updated(OrderUpdatedEvent {
+                order_id: cmd.order_id,
+                updated_items: cmd.new_items.to_owned(),
+            })]),
+            _ => Ok(vec![]),
+        }),
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            if let OrderEvent::Updated(evt) = event {
+                new_state.order_id = evt.order_id;
+                new_state.items = evt.updated_items.to_owned();
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+fn update_order_command(order_id: u32, item: usize) -> OrderCommand {
+    OrderCommand::Update(UpdateOrderCommand {
+        order_id,
+        new_items: vec![format!("Item {}", item)],
+    })
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test() {
+    let repository = InMemoryOrderEventRepository::default();
+    let races = repository.races.clone();
+    let aggregate = Arc::new(Serialized::new(EventSourcedAggregate::new(
+        repository,
+        decider(),
+    )));
+
+    let handles = (0..20)
+        .map(|item| {
+            let aggregate = aggregate.clone();
+            tokio::spawn(async move {
+                aggregate
+                    .handle(&update_order_command(item as u32 % 2 + 1, item))
+                    .await
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        assert_eq!(handle.await.unwrap().unwrap().len(), 1);
+    }
+
+    assert_eq!(*races.lock().unwrap(), 0);
+    let mut versions = aggregate
+        .handler()
+        .fetch_events(&update_order_command(1, 0))
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(_, version)| version)
+        .collect::<Vec<i32>>();
+    versions.sort();
+    assert_eq!(versions, (0..10).collect::<Vec<i32>>());
+    assert!(aggregate.lock().is_empty());
+}
+
+#[tokio::test]
+async fn keyed_lock_test() {
+    let lock = Arc::new(KeyedLock::new());
+    let log = Arc::new(Mutex::new(Vec::new()));
+
+    let guard = lock.lock("1").await;
+    // Another key is not blocked
+    let other = lock.lock("2").await;
+    assert_eq!(lock.len(), 2);
+    drop(other);
+
+    let waiting = {
+        let lock = lock.clone();
+        let log = log.clone();
+        tokio::spawn(async move {
+            let guard = lock.lock("1").await;
+            log.lock()
+                .unwrap()
+                .push(format!("acquired {}", guard.key()));
+        })
+    };
+    tokio::task::yield_now().await;
+    log.lock().unwrap().push("released 1".to_string());
+    drop(guard);
+    waiting.await.unwrap();
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec!["released 1".to_string(), "acquired 1".to_string()]
+    );
+    assert!(lock.is_empty());
+}
+
+#[tokio::test]
+async fn keyed_lock_fifo_test() {
+    let lock = Arc::new(KeyedLock::new());
+    let log = Arc::new(Mutex::new(Vec::new()));
+
+    let guard = lock.lock("1").await;
+    let waiting = (0..5)
+        .map(|waiter| {
+            let lock = lock.clone();
+            let log = log.clone();
+            let handle = tokio::spawn(async move {
+                let _guard = lock.lock("1").await;
+                log.lock().unwrap().push(waiter);
+                // Gives the other waiters the chance to jump the queue
+                tokio::task::yield_now().await;
+            });
+            (waiter, handle)
+        })
+        .collect::<Vec<_>>();
+    // All the waiters are queued, in the order they are spawned
+    tokio::task::yield_now().await;
+
+    // The waiter dropped from the queue does not block the others
+    let (_, canceled) = &waiting[2];
+    canceled.abort();
+    tokio::task::yield_now().await;
+
+    drop(guard);
+    for (_, handle) in waiting {
+        let _ = handle.await;
+    }
+    assert_eq!(*log.lock().unwrap(), vec![0, 1, 3, 4]);
+    assert!(lock.is_empty());
+}
+
+#[tokio::test]
+async fn keyed_lock_handed_off_to_dropped_waiter_test() {
+    let lock = KeyedLock::new();
+
+    let guard = lock.lock("1").await;
+    let mut first = Box::pin(lock.lock("1"));
+    let mut second = Box::pin(lock.lock("1"));
+    assert!(poll_once(first.as_mut()).await.is_pending());
+    assert!(poll_once(second.as_mut()).await.is_pending());
+
+    // The lock is handed off to the first waiter, which is dropped before it is polled again
+    drop(guard);
+    drop(first);
+    let guard = second.await;
+    assert_eq!(guard.key(), "1");
+    drop(guard);
+    assert!(lock.is_empty());
+}
+
+async fn poll_once<F: Future>(mut future: Pin<&mut F>) -> Poll<F::Output> {
+    poll_fn(|cx| Poll::Ready(future.as_mut().poll(cx))).await
+}
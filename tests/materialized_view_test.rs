@@ -1,6 +1,7 @@
 #![cfg(not(feature = "not-send-futures"))]
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
 use std::thread;
 
@@ -46,12 +47,16 @@ fn view<'a>() -> View<'a, OrderViewState, OrderEvent> {
 
 struct InMemoryViewOrderStateRepository {
     states: RwLock<HashMap<u32, OrderViewState>>,
+    fetches: Arc<AtomicUsize>,
+    saves: Arc<AtomicUsize>,
 }
 
 impl InMemoryViewOrderStateRepository {
     fn new() -> Self {
         InMemoryViewOrderStateRepository {
             states: RwLock::new(HashMap::new()),
+            fetches: Arc::new(AtomicUsize::new(0)),
+            saves: Arc::new(AtomicUsize::new(0)),
         }
     }
 }
@@ -64,6 +69,7 @@ impl ViewStateRepository<OrderEvent, OrderViewState, MaterializedViewError>
         &self,
         event: &OrderEvent,
     ) -> Result<Option<OrderViewState>, MaterializedViewError> {
+        self.fetches.fetch_add(1, Ordering::SeqCst);
         Ok(self
             .states
             .read()
@@ -73,6 +79,7 @@ impl ViewStateRepository<OrderEvent, OrderViewState, MaterializedViewError>
     }
 
     async fn save(&self, state: &OrderViewState) -> Result<OrderViewState, MaterializedViewError> {
+        self.saves.fetch_add(1, Ordering::SeqCst);
         self.states
             .write()
             .unwrap()
@@ -184,3 +191,57 @@ async fn test() {
     handle1.join().unwrap().await;
     handle2.join().unwrap().await;
 }
+
+#[tokio::test]
+async fn batch_test() {
+    let repository = InMemoryViewOrderStateRepository::new();
+    let fetches = Arc::clone(&repository.fetches);
+    let saves = Arc::clone(&repository.saves);
+    let materialized_view = MaterializedView::new(repository, view());
+
+    let events = vec![
+        OrderEvent::Created(OrderCreatedEvent {
+            order_id: 1,
+            customer_name: "John Doe".to_string(),
+            items: vec!["Item 1".to_string(), "Item 2".to_string()],
+        }),
+        OrderEvent::Created(OrderCreatedEvent {
+            order_id: 2,
+            customer_name: "Jane Doe".to_string(),
+            items: vec!["Item 1".to_string()],
+        }),
+        OrderEvent::Updated(OrderUpdatedEvent {
+            order_id: 1,
+            updated_items: vec!["Item 3".to_string(), "Item 4".to_string()],
+        }),
+        OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 }),
+    ];
+    let result = materialized_view.handle_batch(&events).await;
+    assert!(result.is_ok());
+    assert_eq!(
+        result.unwrap(),
+        vec![
+            OrderViewState {
+                order_id: 1,
+                customer_name: "John Doe".to_string(),
+                items: vec!["Item 3".to_string(), "Item 4".to_string()],
+                is_cancelled: true,
+            },
+            OrderViewState {
+                order_id: 2,
+                customer_name: "Jane Doe".to_string(),
+                items: vec!["Item 1".to_string()],
+                is_cancelled: false,
+            }
+        ]
+    );
+    // Every view state is fetched and saved once per batch
+    assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    assert_eq!(saves.load(Ordering::SeqCst), 2);
+
+    // Grouped by the key of the repository, all the events of the batch fall into a single group
+    let result = materialized_view.handle_batch_by(&events, |_| ()).await;
+    assert_eq!(result.unwrap().len(), 1);
+    assert_eq!(fetches.load(Ordering::SeqCst), 3);
+    assert_eq!(saves.load(Ordering::SeqCst), 3);
+}
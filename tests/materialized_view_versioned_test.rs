@@ -0,0 +1,168 @@
+#![cfg(not(feature = "not-send-futures"))]
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use fmodel_rust::materialized_view::{MaterializedView, VersionedViewStateRepository, ViewUpdate};
+use fmodel_rust::view::View;
+use fmodel_rust::Identifier;
+
+use crate::api::{
+    OrderCancelledEvent, OrderCreatedEvent, OrderEvent, OrderUpdatedEvent, OrderViewState,
+};
+use crate::application::MaterializedViewError;
+
+mod api;
+mod application;
+
+fn view<'a>() -> View<'a, OrderViewState, OrderEvent> {
+    View {
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderViewState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+struct InMemoryVersionedViewOrderStateRepository {
+    states: RwLock<HashMap<u32, (OrderViewState, i32)>>,
+}
+
+impl InMemoryVersionedViewOrderStateRepository {
+    fn new() -> Self {
+        InMemoryVersionedViewOrderStateRepository {
+            states: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+// Implementation of [VersionedViewStateRepository] for [InMemoryVersionedViewOrderStateRepository]
+impl VersionedViewStateRepository<OrderEvent, OrderViewState, i32, MaterializedViewError>
+    for InMemoryVersionedViewOrderStateRepository
+{
+    async fn fetch_state(
+        &self,
+        event: &OrderEvent,
+    ) -> Result<Option<(OrderViewState, i32)>, MaterializedViewError> {
+        Ok(self
+            .states
+            .read()
+            .unwrap()
+            .get(&event.identifier().parse::<u32>().unwrap())
+            .cloned())
+    }
+
+    async fn save(
+        &self,
+        state: &OrderViewState,
+        version: &i32,
+    ) -> Result<(OrderViewState, i32), MaterializedViewError> {
+        self.states
+            .write()
+            .unwrap()
+            .insert(state.order_id, (state.clone(), *version));
+        Ok((state.clone(), *version))
+    }
+}
+
+#[tokio::test]
+async fn test() {
+    let materialized_view =
+        MaterializedView::new(InMemoryVersionedViewOrderStateRepository::new(), view());
+
+    let created = OrderEvent::Created(OrderCreatedEvent {
+        order_id: 1,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string(), "Item 2".to_string()],
+    });
+    let updated = OrderEvent::Updated(OrderUpdatedEvent {
+        order_id: 1,
+        updated_items: vec!["Item 3".to_string(), "Item 4".to_string()],
+    });
+    let cancelled = OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 });
+
+    let created_state = OrderViewState {
+        order_id: 1,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string(), "Item 2".to_string()],
+        is_cancelled: false,
+    };
+    let updated_state = OrderViewState {
+        items: vec!["Item 3".to_string(), "Item 4".to_string()],
+        ..created_state.clone()
+    };
+
+    // The leading event with version 0 is missing
+    let result = materialized_view
+        .handle_versioned(&updated, &1)
+        .await
+        .unwrap();
+    assert_eq!(result, ViewUpdate::Gap(None));
+
+    let result = materialized_view
+        .handle_versioned(&created, &0)
+        .await
+        .unwrap();
+    assert_eq!(result, ViewUpdate::Applied(created_state.clone(), 0));
+
+    // Redelivery of the already applied event is skipped
+    let result = materialized_view
+        .handle_versioned(&created, &0)
+        .await
+        .unwrap();
+    assert_eq!(result, ViewUpdate::Duplicate(created_state.clone(), 0));
+
+    // The event with version 1 is missing
+    let result = materialized_view
+        .handle_versioned(&cancelled, &2)
+        .await
+        .unwrap();
+    assert_eq!(result, ViewUpdate::Gap(Some((created_state.clone(), 0))));
+
+    let result = materialized_view
+        .handle_versioned(&updated, &1)
+        .await
+        .unwrap();
+    assert_eq!(result, ViewUpdate::Applied(updated_state.clone(), 1));
+
+    let result = materialized_view
+        .handle_versioned(&cancelled, &2)
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        ViewUpdate::Applied(
+            OrderViewState {
+                is_cancelled: true,
+                ..updated_state.clone()
+            },
+            2
+        )
+    );
+
+    // Redelivery of the older event does not roll the state back
+    let result = materialized_view
+        .handle_versioned(&updated, &1)
+        .await
+        .unwrap();
+    assert!(matches!(result, ViewUpdate::Duplicate(state, 2) if state.is_cancelled));
+}
@@ -0,0 +1,150 @@
+#![cfg(feature = "json")]
+
+use fmodel_rust::codec::{CodecError, EventCodec, JsonCodec};
+use fmodel_rust::message_type::{MessageRegistry, MessageTypeCodec};
+
+use crate::api::{
+    OrderCancelledEvent, OrderCreatedEvent, OrderEvent, ShipmentCreatedEvent, ShipmentEvent,
+};
+
+mod api;
+mod application;
+
+/// The events of all the aggregates, read from the shared log
+#[derive(Debug, PartialEq)]
+enum Inbound {
+    Order(OrderEvent),
+    Shipment(ShipmentEvent),
+}
+
+fn order_created() -> OrderEvent {
+    OrderEvent::Created(OrderCreatedEvent {
+        order_id: 1,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string(), "Item 2".to_string()],
+    })
+}
+
+fn shipment_created() -> ShipmentEvent {
+    ShipmentEvent::Created(ShipmentCreatedEvent {
+        shipment_id: 1,
+        order_id: 1,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string(), "Item 2".to_string()],
+    })
+}
+
+#[test]
+fn message_type_codec_test() {
+    let codec = MessageTypeCodec(JsonCodec);
+    let encoded = codec.encode(&order_created()).unwrap();
+    assert_eq!(encoded.event_type, "order.created");
+    assert_eq!(
+        encoded.payload,
+        JsonCodec.encode(&order_created()).unwrap().payload
+    );
+    assert_eq!(EventCodec::<OrderEvent>::format(&codec), "json");
+
+    let decoded: OrderEvent = codec.decode(&encoded).unwrap();
+    assert_eq!(decoded, order_created());
+}
+
+#[test]
+fn message_registry_test() {
+    let registry = MessageRegistry::new()
+        .register(MessageTypeCodec(JsonCodec), Inbound::Order)
+        .register(MessageTypeCodec(JsonCodec), Inbound::Shipment);
+
+    let mut message_types: Vec<&str> = registry.message_types().collect();
+    message_types.sort();
+    assert_eq!(
+        message_types,
+        vec![
+            "order.cancelled",
+            "order.created",
+            "order.updated",
+            "shipment.created"
+        ]
+    );
+    assert!(registry.contains("order.cancelled"));
+    assert!(!registry.contains("Cancelled"));
+
+    let cancelled = OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 });
+    let log = [
+        MessageTypeCodec(JsonCodec)
+            .encode(&order_created())
+            .unwrap(),
+        MessageTypeCodec(JsonCodec)
+            .encode(&shipment_created())
+            .unwrap(),
+        MessageTypeCodec(JsonCodec).encode(&cancelled).unwrap(),
+    ];
+    let decoded: Vec<Inbound> = log
+        .iter()
+        .map(|message| registry.decode(message).unwrap())
+        .collect();
+    assert_eq!(
+        decoded,
+        vec![
+            Inbound::Order(order_created()),
+            Inbound::Shipment(shipment_created()),
+            Inbound::Order(cancelled),
+        ]
+    );
+
+    // named by the `serde`, not by the stable name
+    let unknown = JsonCodec.encode(&order_created()).unwrap();
+    assert!(matches!(
+        registry.decode(&unknown),
+        Err(CodecError::Unknown(event_type)) if event_type == "Created"
+    ));
+}
+
+#[test]
+#[should_panic(expected = "the message type `order.created` is registered twice")]
+fn message_registry_duplicate_test() {
+    let _ = MessageRegistry::new()
+        .register(JsonCodec, Inbound::Order)
+        .register(JsonCodec, Inbound::Order);
+}
+
+#[cfg(feature = "derive")]
+mod derive {
+    use fmodel_rust::message_type::MessageType;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(MessageType, Debug, PartialEq, Serialize, Deserialize)]
+    enum PaymentEvent {
+        #[message_type(name = "payment.received.v1")]
+        Received {
+            amount: u64,
+        },
+        Refunded(u64),
+        Voided,
+    }
+
+    #[derive(MessageType)]
+    #[message_type(name = "payment.requested")]
+    struct PaymentRequested;
+
+    #[derive(MessageType)]
+    struct PaymentExpired<T>(#[allow(dead_code)] T);
+
+    #[test]
+    fn derive_message_type_test() {
+        assert_eq!(
+            PaymentEvent::MESSAGE_TYPES,
+            &["payment.received.v1", "Refunded", "Voided"]
+        );
+        assert_eq!(
+            PaymentEvent::Received { amount: 1 }.message_type(),
+            "payment.received.v1"
+        );
+        assert_eq!(PaymentEvent::Refunded(1).message_type(), "Refunded");
+        assert_eq!(PaymentEvent::Voided.message_type(), "Voided");
+
+        assert_eq!(PaymentRequested::MESSAGE_TYPES, &["payment.requested"]);
+        assert_eq!(PaymentRequested.message_type(), "payment.requested");
+        assert_eq!(PaymentExpired(1).message_type(), "PaymentExpired");
+    }
+}
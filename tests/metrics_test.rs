@@ -0,0 +1,421 @@
+#![cfg(all(feature = "metrics", not(feature = "not-send-futures")))]
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use fmodel_rust::aggregate::EventSourcedAggregate;
+use fmodel_rust::decider::Decider;
+use fmodel_rust::materialized_view::{MaterializedView, ViewStateRepository};
+use fmodel_rust::projection::{CheckpointRepository, ProjectionRunner};
+use fmodel_rust::saga::Saga;
+use fmodel_rust::saga_manager::{ActionPublisher, DeadLetter, DeadLetterSink, SagaManager};
+use fmodel_rust::subscription::InMemoryEventLog;
+use fmodel_rust::telemetry::{
+    ACTIONS_PUBLISHED, COMMANDS_HANDLED, DECIDE_ERRORS, EVENTS_APPENDED, EVENTS_HANDLED,
+    FOLD_LENGTH, PROJECTION_LAG,
+};
+use fmodel_rust::view::View;
+use fmodel_rust::Identifier;
+use metrics::{
+    Counter, CounterFn, Gauge, GaugeFn, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+    SharedString, Unit,
+};
+
+use crate::api::{
+    CancelOrderCommand, CreateOrderCommand, CreateShipmentCommand, OrderCancelledEvent,
+    OrderCommand, OrderCreatedEvent, OrderEvent, OrderState, OrderViewState, ShipmentCommand,
+};
+use crate::application::{
+    AggregateError, InMemoryOrderEventRepository, MaterializedViewError, SagaManagerError,
+};
+
+mod api;
+mod application;
+
+/// Decider for the Order aggregate - Domain logic
+fn decider<'a>() -> Decider<'a, OrderCommand, OrderState, OrderEvent, AggregateError> {
+    Decider {
+        decide: Box::new(|command, state| match command {
+            OrderCommand::Create(cmd) => Ok(vec![OrderEvent::Created(OrderCreatedEvent {
+                order_id: cmd.order_id,
+                customer_name: cmd.customer_name.to_owned(),
+                items: cmd.items.to_owned(),
+            })]),
+            OrderCommand::Update(_) => Ok(vec![]),
+            OrderCommand::Cancel(cmd) => {
+                if state.order_id == cmd.order_id {
+                    Ok(vec![OrderEvent::Cancelled(OrderCancelledEvent {
+                        order_id: cmd.order_id,
+                    })])
+                } else {
+                    Err(AggregateError::DomainError(
+                        "Order does not exist".to_string(),
+                    ))
+                }
+            }
+        }),
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+/// A simple in-memory view state repository - infrastructure
+struct InMemoryViewOrderStateRepository {
+    states: RwLock<BTreeMap<u32, OrderViewState>>,
+}
+
+impl ViewStateRepository<OrderEvent, OrderViewState, MaterializedViewError>
+    for InMemoryViewOrderStateRepository
+{
+    async fn fetch_state(
+        &self,
+        event: &OrderEvent,
+    ) -> Result<Option<OrderViewState>, MaterializedViewError> {
+        Ok(self
+            .states
+            .read()
+            .unwrap()
+            .get(&event.identifier().parse::<u32>().unwrap())
+            .cloned())
+    }
+
+    async fn save(&self, state: &OrderViewState) -> Result<OrderViewState, MaterializedViewError> {
+        self.states
+            .write()
+            .unwrap()
+            .insert(state.order_id, state.clone());
+        Ok(state.clone())
+    }
+}
+
+/// View for the Order view state - Domain logic
+fn view<'a>() -> View<'a, OrderViewState, OrderEvent> {
+    View {
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            if let OrderEvent::Created(evt) = event {
+                new_state.order_id = evt.order_id;
+                new_state.customer_name = evt.customer_name.to_owned();
+                new_state.items = evt.items.to_owned();
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderViewState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+/// Saga for the Shipment - Domain logic
+fn saga<'a>() -> Saga<'a, OrderEvent, ShipmentCommand> {
+    Saga {
+        react: Box::new(|event| match event {
+            OrderEvent::Created(evt) => vec![ShipmentCommand::Create(CreateShipmentCommand {
+                shipment_id: evt.order_id,
+                order_id: evt.order_id,
+                customer_name: evt.customer_name.to_owned(),
+                items: evt.items.to_owned(),
+            })],
+            _ => vec![],
+        }),
+    }
+}
+
+/// Simple action publisher that just returns the action/command.
+struct SimpleActionPublisher;
+
+impl ActionPublisher<ShipmentCommand, SagaManagerError> for SimpleActionPublisher {
+    async fn publish(
+        &self,
+        action: &[ShipmentCommand],
+    ) -> Result<Vec<ShipmentCommand>, SagaManagerError> {
+        Ok(Vec::from(action))
+    }
+}
+
+/// Dead letter sink dropping the dead letters.
+struct NoDeadLetterSink;
+
+impl DeadLetterSink<OrderEvent, ShipmentCommand, SagaManagerError> for NoDeadLetterSink {
+    async fn send(
+        &self,
+        _dead_letter: DeadLetter<OrderEvent, ShipmentCommand, SagaManagerError>,
+    ) -> Result<(), SagaManagerError> {
+        Ok(())
+    }
+}
+
+/// A simple in-memory checkpoint repository - infrastructure
+#[derive(Default)]
+struct InMemoryCheckpointRepository {
+    checkpoints: Mutex<BTreeMap<String, u64>>,
+}
+
+impl CheckpointRepository<u64, MaterializedViewError> for InMemoryCheckpointRepository {
+    async fn fetch_checkpoint(
+        &self,
+        projection: &str,
+    ) -> Result<Option<u64>, MaterializedViewError> {
+        Ok(self.checkpoints.lock().unwrap().get(projection).copied())
+    }
+
+    async fn save_checkpoint(
+        &self,
+        projection: &str,
+        position: &u64,
+    ) -> Result<(), MaterializedViewError> {
+        self.checkpoints
+            .lock()
+            .unwrap()
+            .insert(projection.to_string(), *position);
+        Ok(())
+    }
+
+    async fn delete_checkpoint(&self, projection: &str) -> Result<(), MaterializedViewError> {
+        self.checkpoints.lock().unwrap().remove(projection);
+        Ok(())
+    }
+}
+
+/// The metrics, as recorded by the [InMemoryRecorder], keyed by the name and the `component` label
+type Recorded = Arc<Mutex<BTreeMap<(String, String), Vec<f64>>>>;
+
+/// Every increment/record/set is stored as the value of the metric
+struct RecordedMetric {
+    key: (String, String),
+    recorded: Recorded,
+}
+
+impl RecordedMetric {
+    fn push(&self, value: f64) {
+        self.recorded
+            .lock()
+            .unwrap()
+            .entry(self.key.clone())
+            .or_default()
+            .push(value);
+    }
+}
+
+impl CounterFn for RecordedMetric {
+    fn increment(&self, value: u64) {
+        self.push(value as f64);
+    }
+    fn absolute(&self, value: u64) {
+        self.push(value as f64);
+    }
+}
+
+impl GaugeFn for RecordedMetric {
+    fn increment(&self, value: f64) {
+        self.push(value);
+    }
+    fn decrement(&self, value: f64) {
+        self.push(-value);
+    }
+    fn set(&self, value: f64) {
+        self.push(value);
+    }
+}
+
+impl HistogramFn for RecordedMetric {
+    fn record(&self, value: f64) {
+        self.push(value);
+    }
+}
+
+/// A minimal metrics recorder - infrastructure
+#[derive(Default)]
+struct InMemoryRecorder {
+    recorded: Recorded,
+}
+
+impl InMemoryRecorder {
+    fn metric(&self, key: &Key) -> Arc<RecordedMetric> {
+        let component = key
+            .labels()
+            .find(|label| label.key() == "component")
+            .map(|label| label.value().to_string())
+            .unwrap_or_default();
+        Arc::new(RecordedMetric {
+            key: (key.name().to_string(), component),
+            recorded: self.recorded.clone(),
+        })
+    }
+    fn values(&self, name: &str, component: &str) -> Vec<f64> {
+        self.recorded
+            .lock()
+            .unwrap()
+            .get(&(name.to_string(), component.to_string()))
+            .cloned()
+            .unwrap_or_default()
+    }
+    fn total(&self, name: &str, component: &str) -> f64 {
+        self.values(name, component).iter().sum()
+    }
+}
+
+impl Recorder for InMemoryRecorder {
+    fn describe_counter(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+    fn describe_gauge(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+    fn describe_histogram(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+        Counter::from_arc(self.metric(key))
+    }
+    fn register_gauge(&self, key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+        Gauge::from_arc(self.metric(key))
+    }
+    fn register_histogram(&self, key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+        Histogram::from_arc(self.metric(key))
+    }
+}
+
+#[test]
+fn test() {
+    let recorder = InMemoryRecorder::default();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let aggregate = EventSourcedAggregate::new(
+        InMemoryOrderEventRepository {
+            events: RwLock::new(vec![]),
+        },
+        decider(),
+    );
+    let materialized_view = MaterializedView::new(
+        InMemoryViewOrderStateRepository {
+            states: RwLock::new(BTreeMap::new()),
+        },
+        view(),
+    );
+    let saga_manager = SagaManager::new(SimpleActionPublisher, saga());
+
+    metrics::with_local_recorder(&recorder, || {
+        runtime.block_on(async {
+            let events = aggregate
+                .handle(&OrderCommand::Create(CreateOrderCommand {
+                    order_id: 1,
+                    customer_name: "John Doe".to_string(),
+                    items: vec!["Item 1".to_string()],
+                }))
+                .await
+                .unwrap();
+            let events = [
+                events,
+                aggregate
+                    .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }))
+                    .await
+                    .unwrap(),
+            ]
+            .concat();
+            let result = aggregate
+                .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 2 }))
+                .await;
+            assert!(result.is_err());
+
+            for (event, _) in &events {
+                materialized_view.handle(event).await.unwrap();
+                saga_manager.handle(event).await.unwrap();
+            }
+            let events: Vec<OrderEvent> = events.into_iter().map(|(event, _)| event).collect();
+            materialized_view.handle_batch(&events).await.unwrap();
+
+            let actions = saga_manager
+                .handle_with_dead_letter(&events[0], &NoDeadLetterSink)
+                .await
+                .unwrap();
+            saga_manager
+                .redrive(&DeadLetter {
+                    action_result: events[0].clone(),
+                    actions,
+                    error: SagaManagerError::PublishAction("Publisher is down".to_string()),
+                    attempts: 1,
+                })
+                .await
+                .unwrap();
+        })
+    });
+
+    assert_eq!(
+        recorder.total(COMMANDS_HANDLED, "EventSourcedAggregate"),
+        3.0
+    );
+    assert_eq!(recorder.total(DECIDE_ERRORS, "EventSourcedAggregate"), 1.0);
+    assert_eq!(
+        recorder.total(EVENTS_APPENDED, "EventSourcedAggregate"),
+        2.0
+    );
+    assert_eq!(
+        recorder.values(FOLD_LENGTH, "EventSourcedAggregate"),
+        vec![0.0, 1.0, 0.0]
+    );
+    assert_eq!(recorder.total(EVENTS_HANDLED, "MaterializedView"), 4.0);
+    assert_eq!(recorder.total(ACTIONS_PUBLISHED, "SagaManager"), 3.0);
+}
+
+#[test]
+fn projection_lag_test() {
+    let recorder = InMemoryRecorder::default();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let log = InMemoryEventLog::new();
+    log.append((1..=5).map(|order_id| {
+        OrderEvent::Created(OrderCreatedEvent {
+            order_id,
+            customer_name: "John Doe".to_string(),
+            items: vec!["Item 1".to_string()],
+        })
+    }));
+    let runner = ProjectionRunner::new(
+        "order-view",
+        log,
+        InMemoryCheckpointRepository::default(),
+        MaterializedView::new(
+            InMemoryViewOrderStateRepository {
+                states: RwLock::new(BTreeMap::new()),
+            },
+            view(),
+        ),
+    )
+    .with_batch_size(2);
+
+    metrics::with_local_recorder(&recorder, || {
+        runtime.block_on(async {
+            assert_eq!(runner.catch_up().await.unwrap(), 5);
+        })
+    });
+
+    // The lag is set after every batch, including the last empty one
+    assert_eq!(
+        recorder.values(PROJECTION_LAG, "ProjectionRunner"),
+        vec![3.0, 1.0, 0.0, 0.0]
+    );
+}
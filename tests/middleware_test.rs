@@ -0,0 +1,251 @@
+#![cfg(not(feature = "not-send-futures"))]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use fmodel_rust::aggregate::EventSourcedAggregate;
+use fmodel_rust::decider::Decider;
+use fmodel_rust::materialized_view::{MaterializedView, ViewStateRepository};
+use fmodel_rust::middleware::{Handler, Intercepted, Interceptor};
+use fmodel_rust::view::View;
+use fmodel_rust::Identifier;
+
+use crate::api::{
+    CancelOrderCommand, CreateOrderCommand, OrderCancelledEvent, OrderCommand, OrderCreatedEvent,
+    OrderEvent, OrderState, OrderViewState,
+};
+use crate::application::{AggregateError, InMemoryOrderEventRepository, MaterializedViewError};
+
+mod api;
+mod application;
+
+/// A simple in-memory view state repository - infrastructure
+struct InMemoryViewOrderStateRepository {
+    states: RwLock<HashMap<u32, OrderViewState>>,
+}
+
+impl ViewStateRepository<OrderEvent, OrderViewState, MaterializedViewError>
+    for InMemoryViewOrderStateRepository
+{
+    async fn fetch_state(
+        &self,
+        event: &OrderEvent,
+    ) -> Result<Option<OrderViewState>, MaterializedViewError> {
+        Ok(self
+            .states
+            .read()
+            .unwrap()
+            .get(&event.identifier().parse::<u32>().unwrap())
+            .cloned())
+    }
+
+    async fn save(&self, state: &OrderViewState) -> Result<OrderViewState, MaterializedViewError> {
+        self.states
+            .write()
+            .unwrap()
+            .insert(state.order_id, state.clone());
+        Ok(state.clone())
+    }
+}
+
+/// Decider for the Order aggregate - Domain logic
+fn decider<'a>() -> Decider<'a, OrderCommand, OrderState, OrderEvent, AggregateError> {
+    Decider {
+        decide: Box::new(|command, state| match command {
+            OrderCommand::Create(cmd) => Ok(vec![OrderEvent::Created(OrderCreatedEvent {
+                order_id: cmd.order_id,
+                customer_name: cmd.customer_name.to_owned(),
+                items: cmd.items.to_owned(),
+            })]),
+            OrderCommand::Update(_) => Ok(vec![]),
+            OrderCommand::Cancel(cmd) => {
+                if state.order_id == cmd.order_id {
+                    Ok(vec![OrderEvent::Cancelled(OrderCancelledEvent {
+                        order_id: cmd.order_id,
+                    })])
+                } else {
+                    Err(AggregateError::DomainError(
+                        "Order does not exist".to_string(),
+                    ))
+                }
+            }
+        }),
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+/// View for the Order view state - Domain logic
+fn view<'a>() -> View<'a, OrderViewState, OrderEvent> {
+    View {
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderViewState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+/// Rejects the orders with no items
+struct ValidationInterceptor;
+
+impl Interceptor<OrderCommand, Vec<(OrderEvent, i32)>, AggregateError> for ValidationInterceptor {
+    async fn before(&self, command: &OrderCommand) -> Result<(), AggregateError> {
+        match command {
+            OrderCommand::Create(cmd) if cmd.items.is_empty() => Err(AggregateError::DomainError(
+                "Order has no items".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Records the audit log, shared by all the handlers
+#[derive(Clone, Default)]
+struct AuditInterceptor {
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl<Input, Output, Error> Interceptor<Input, Output, Error> for AuditInterceptor
+where
+    Input: Identifier + Sync,
+    Output: Sync,
+    Error: std::fmt::Display + Sync,
+{
+    async fn before(&self, input: &Input) -> Result<(), Error> {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("before {}", input.identifier()));
+        Ok(())
+    }
+
+    async fn after(&self, input: &Input, _output: &Output) {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("after {}", input.identifier()));
+    }
+
+    async fn on_error(&self, input: &Input, error: &Error) {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("error {}: {}", input.identifier(), error));
+    }
+}
+
+fn create_order_command(order_id: u32, items: Vec<String>) -> OrderCommand {
+    OrderCommand::Create(CreateOrderCommand {
+        order_id,
+        customer_name: "John Doe".to_string(),
+        items,
+    })
+}
+
+#[tokio::test]
+async fn aggregate_test() {
+    let audit = AuditInterceptor::default();
+    let aggregate = Intercepted::new(
+        EventSourcedAggregate::new(
+            InMemoryOrderEventRepository {
+                events: RwLock::new(vec![]),
+            },
+            decider(),
+        ),
+        ValidationInterceptor,
+    )
+    .intercept(audit.clone());
+
+    let result = aggregate
+        .handle(&create_order_command(1, vec!["Item 1".to_string()]))
+        .await;
+    assert_eq!(result.unwrap().len(), 1);
+
+    // Rejected by the validation, the aggregate is not called
+    let result = aggregate.handle(&create_order_command(2, vec![])).await;
+    assert!(result.is_err());
+
+    // Failed in the aggregate
+    let result = aggregate
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 3 }))
+        .await;
+    assert!(result.is_err());
+
+    assert_eq!(
+        *audit.log.lock().unwrap(),
+        vec![
+            "before 1".to_string(),
+            "after 1".to_string(),
+            "before 2".to_string(),
+            "error 2: Order has no items".to_string(),
+            "before 3".to_string(),
+            "error 3: Order does not exist".to_string(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn materialized_view_test() {
+    let audit = AuditInterceptor::default();
+    let materialized_view = Intercepted::new(
+        MaterializedView::new(
+            InMemoryViewOrderStateRepository {
+                states: RwLock::new(HashMap::new()),
+            },
+            view(),
+        ),
+        audit.clone(),
+    );
+
+    let event = OrderEvent::Created(OrderCreatedEvent {
+        order_id: 1,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string()],
+    });
+    let state = materialized_view.handle(&event).await.unwrap();
+    assert_eq!(state.order_id, 1);
+    assert_eq!(
+        *audit.log.lock().unwrap(),
+        vec!["before 1".to_string(), "after 1".to_string()]
+    );
+}
@@ -0,0 +1,385 @@
+#![cfg(not(feature = "not-send-futures"))]
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use fmodel_rust::aggregate::{
+    EventRepository, EventSourcedAggregate, StateRepository, StateStoredAggregate,
+};
+use fmodel_rust::decider::Decider;
+use fmodel_rust::outbox::{Outbox, OutboxEventRepository, OutboxRelay, OutboxStateRepository};
+use fmodel_rust::saga::Saga;
+use fmodel_rust::saga_manager::ActionPublisher;
+use fmodel_rust::Identifier;
+
+use crate::api::{
+    CancelOrderCommand, CreateOrderCommand, CreateShipmentCommand, OrderCancelledEvent,
+    OrderCommand, OrderCreatedEvent, OrderEvent, OrderState, OrderUpdatedEvent, ShipmentCommand,
+};
+use crate::application::AggregateError;
+
+mod api;
+mod application;
+
+/// The events and the outbox are guarded by the same lock, simulating a single database transaction
+#[derive(Default)]
+struct Store {
+    events: Vec<(OrderEvent, i32)>,
+    states: HashMap<u32, (OrderState, i32)>,
+    outbox: Vec<(u64, ShipmentCommand, bool)>,
+}
+
+/// A simple in-memory event/state repository with the outbox - infrastructure
+#[derive(Clone, Default)]
+struct InMemoryOrderRepository {
+    store: Arc<Mutex<Store>>,
+}
+
+impl InMemoryOrderRepository {
+    fn append(store: &mut Store, events: &[OrderEvent]) -> Vec<(OrderEvent, i32)> {
+        events
+            .iter()
+            .map(|event| {
+                let version = store
+                    .events
+                    .iter()
+                    .filter(|(e, _)| e.identifier() == event.identifier())
+                    .map(|(_, version)| *version)
+                    .next_back()
+                    .unwrap_or(-1)
+                    + 1;
+                store.events.push((event.clone(), version));
+                (event.clone(), version)
+            })
+            .collect()
+    }
+
+    fn enqueue(store: &mut Store, messages: &[ShipmentCommand]) {
+        for message in messages {
+            let id = store.outbox.len() as u64;
+            store.outbox.push((id, message.clone(), false));
+        }
+    }
+}
+
+impl EventRepository<OrderCommand, OrderEvent, i32, AggregateError> for InMemoryOrderRepository {
+    async fn fetch_events(
+        &self,
+        command: &OrderCommand,
+    ) -> Result<Vec<(OrderEvent, i32)>, AggregateError> {
+        Ok(self
+            .store
+            .lock()
+            .unwrap()
+            .events
+            .iter()
+            .filter(|(event, _)| event.identifier() == command.identifier())
+            .cloned()
+            .collect())
+    }
+
+    async fn save(&self, events: &[OrderEvent]) -> Result<Vec<(OrderEvent, i32)>, AggregateError> {
+        Ok(Self::append(&mut self.store.lock().unwrap(), events))
+    }
+
+    async fn version_provider(&self, event: &OrderEvent) -> Result<Option<i32>, AggregateError> {
+        Ok(self
+            .store
+            .lock()
+            .unwrap()
+            .events
+            .iter()
+            .filter(|(e, _)| e.identifier() == event.identifier())
+            .map(|(_, version)| *version)
+            .next_back())
+    }
+}
+
+impl OutboxEventRepository<OrderCommand, OrderEvent, ShipmentCommand, i32, AggregateError>
+    for InMemoryOrderRepository
+{
+    async fn save_with_outbox(
+        &self,
+        _command: &OrderCommand,
+        events: &[OrderEvent],
+        _latest_version: &Option<i32>,
+        messages: &[ShipmentCommand],
+    ) -> Result<Vec<(OrderEvent, i32)>, AggregateError> {
+        let mut store = self.store.lock().unwrap();
+        let saved_events = Self::append(&mut store, events);
+        Self::enqueue(&mut store, messages);
+        Ok(saved_events)
+    }
+}
+
+impl StateRepository<OrderCommand, OrderState, i32, AggregateError> for InMemoryOrderRepository {
+    async fn fetch_state(
+        &self,
+        command: &OrderCommand,
+    ) -> Result<Option<(OrderState, i32)>, AggregateError> {
+        Ok(self
+            .store
+            .lock()
+            .unwrap()
+            .states
+            .get(&command.identifier().parse::<u32>().unwrap())
+            .cloned())
+    }
+
+    async fn save(
+        &self,
+        state: &OrderState,
+        version: &Option<i32>,
+    ) -> Result<(OrderState, i32), AggregateError> {
+        let version = version.map(|v| v + 1).unwrap_or(0);
+        self.store
+            .lock()
+            .unwrap()
+            .states
+            .insert(state.order_id, (state.clone(), version));
+        Ok((state.clone(), version))
+    }
+}
+
+impl OutboxStateRepository<OrderCommand, OrderState, ShipmentCommand, i32, AggregateError>
+    for InMemoryOrderRepository
+{
+    async fn save_with_outbox(
+        &self,
+        state: &OrderState,
+        version: &Option<i32>,
+        messages: &[ShipmentCommand],
+    ) -> Result<(OrderState, i32), AggregateError> {
+        let mut store = self.store.lock().unwrap();
+        let version = version.map(|v| v + 1).unwrap_or(0);
+        store
+            .states
+            .insert(state.order_id, (state.clone(), version));
+        Self::enqueue(&mut store, messages);
+        Ok((state.clone(), version))
+    }
+}
+
+impl Outbox<ShipmentCommand, u64, AggregateError> for InMemoryOrderRepository {
+    async fn fetch_pending(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<(u64, ShipmentCommand)>, AggregateError> {
+        Ok(self
+            .store
+            .lock()
+            .unwrap()
+            .outbox
+            .iter()
+            .filter(|(_, _, delivered)| !delivered)
+            .take(limit)
+            .map(|(id, message, _)| (*id, message.clone()))
+            .collect())
+    }
+
+    async fn acknowledge(&self, ids: &[u64]) -> Result<(), AggregateError> {
+        for (id, _, delivered) in self.store.lock().unwrap().outbox.iter_mut() {
+            if ids.contains(id) {
+                *delivered = true;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Action publisher that can be switched off, to simulate the downstream outage
+#[derive(Clone, Default)]
+struct SwitchableActionPublisher {
+    down: Arc<AtomicBool>,
+    published: Arc<Mutex<Vec<ShipmentCommand>>>,
+}
+
+impl ActionPublisher<ShipmentCommand, AggregateError> for SwitchableActionPublisher {
+    async fn publish(
+        &self,
+        action: &[ShipmentCommand],
+    ) -> Result<Vec<ShipmentCommand>, AggregateError> {
+        if self.down.load(Ordering::SeqCst) {
+            return Err(AggregateError::SaveEvents("publisher is down".to_string()));
+        }
+        self.published.lock().unwrap().extend_from_slice(action);
+        Ok(Vec::from(action))
+    }
+}
+
+/// Decider for the Order aggregate - Domain logic
+fn decider<'a>() -> Decider<'a, OrderCommand, OrderState, OrderEvent> {
+    Decider {
+        decide: Box::new(|command, state| match command {
+            OrderCommand::Create(cmd) => Ok(vec![OrderEvent::Created(OrderCreatedEvent {
+                order_id: cmd.order_id,
+                customer_name: cmd.customer_name.to_owned(),
+                items: cmd.items.to_owned(),
+            })]),
+            OrderCommand::Update(cmd) => {
+                if state.order_id == cmd.order_id {
+                    Ok(vec![OrderEvent::Updated(OrderUpdatedEvent {
+                        order_id: cmd.order_id,
+                        updated_items: cmd.new_items.to_owned(),
+                    })])
+                } else {
+                    Ok(vec![])
+                }
+            }
+            OrderCommand::Cancel(cmd) => {
+                if state.order_id == cmd.order_id {
+                    Ok(vec![OrderEvent::Cancelled(OrderCancelledEvent {
+                        order_id: cmd.order_id,
+                    })])
+                } else {
+                    Ok(vec![])
+                }
+            }
+        }),
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+/// Saga reacting on the Order events - Domain logic
+fn event_saga<'a>() -> Saga<'a, OrderEvent, ShipmentCommand> {
+    Saga {
+        react: Box::new(|event| match event {
+            OrderEvent::Created(evt) => vec![ShipmentCommand::Create(CreateShipmentCommand {
+                shipment_id: evt.order_id,
+                order_id: evt.order_id,
+                customer_name: evt.customer_name.to_owned(),
+                items: evt.items.to_owned(),
+            })],
+            _ => vec![],
+        }),
+    }
+}
+
+/// Saga reacting on the Order state - Domain logic
+fn state_saga<'a>() -> Saga<'a, OrderState, ShipmentCommand> {
+    Saga {
+        react: Box::new(|state| {
+            if state.is_cancelled {
+                vec![]
+            } else {
+                vec![ShipmentCommand::Create(CreateShipmentCommand {
+                    shipment_id: state.order_id,
+                    order_id: state.order_id,
+                    customer_name: state.customer_name.to_owned(),
+                    items: state.items.to_owned(),
+                })]
+            }
+        }),
+    }
+}
+
+fn create_order_command(order_id: u32) -> OrderCommand {
+    OrderCommand::Create(CreateOrderCommand {
+        order_id,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string(), "Item 2".to_string()],
+    })
+}
+
+#[tokio::test]
+async fn es_outbox_test() {
+    let repository = InMemoryOrderRepository::default();
+    let publisher = SwitchableActionPublisher::default();
+    let aggregate = EventSourcedAggregate::new(
+        repository.clone(),
+        decider().map_error(|()| AggregateError::DomainError("Decider error".to_string())),
+    );
+    let relay = OutboxRelay::new(repository.clone(), publisher.clone()).with_batch_size(1);
+
+    let saga = event_saga();
+    aggregate
+        .handle_with_outbox(&create_order_command(1), &saga)
+        .await
+        .unwrap();
+    aggregate
+        .handle_with_outbox(&create_order_command(2), &saga)
+        .await
+        .unwrap();
+    aggregate
+        .handle_with_outbox(
+            &OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }),
+            &saga,
+        )
+        .await
+        .unwrap();
+    assert_eq!(repository.store.lock().unwrap().events.len(), 3);
+    assert_eq!(repository.store.lock().unwrap().outbox.len(), 2);
+
+    // The downstream is not available, the messages stay in the outbox
+    publisher.down.store(true, Ordering::SeqCst);
+    assert!(relay.drain().await.is_err());
+    assert!(publisher.published.lock().unwrap().is_empty());
+
+    publisher.down.store(false, Ordering::SeqCst);
+    assert_eq!(relay.drain().await.unwrap(), 2);
+    assert_eq!(relay.drain().await.unwrap(), 0);
+    assert_eq!(
+        publisher
+            .published
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|command| command.identifier())
+            .collect::<Vec<_>>(),
+        vec!["1".to_string(), "2".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn ss_outbox_test() {
+    let repository = InMemoryOrderRepository::default();
+    let publisher = SwitchableActionPublisher::default();
+    let aggregate = StateStoredAggregate::new(
+        repository.clone(),
+        decider().map_error(|()| AggregateError::DomainError("Decider error".to_string())),
+    );
+    let relay = OutboxRelay::new(repository.clone(), publisher.clone());
+
+    let saga = state_saga();
+    let (state, version) = aggregate
+        .handle_with_outbox(&create_order_command(1), &saga)
+        .await
+        .unwrap();
+    assert_eq!(state.order_id, 1);
+    assert_eq!(version, 0);
+    let (state, version) = aggregate
+        .handle_with_outbox(
+            &OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }),
+            &saga,
+        )
+        .await
+        .unwrap();
+    assert!(state.is_cancelled);
+    assert_eq!(version, 1);
+
+    assert_eq!(relay.drain().await.unwrap(), 1);
+    assert_eq!(publisher.published.lock().unwrap().len(), 1);
+}
@@ -0,0 +1,479 @@
+#![cfg(all(feature = "postgres", not(feature = "not-send-futures")))]
+
+use std::time::Duration;
+
+use fmodel_rust::aggregate::{
+    EventRepository, EventSourcedAggregate, StateRepository, StateStoredAggregate,
+};
+use fmodel_rust::decider::Decider;
+use fmodel_rust::materialized_view::{MaterializedView, ViewStateRepository, ViewUpdate};
+use fmodel_rust::postgres::{
+    PostgresError, PostgresEventRepository, PostgresEventSource, PostgresStateRepository,
+    PostgresViewStateRepository,
+};
+use fmodel_rust::projection::EventSource;
+use fmodel_rust::view::View;
+use tokio_postgres::{Client, NoTls};
+
+use crate::api::{
+    CancelOrderCommand, CreateOrderCommand, OrderCancelledEvent, OrderCommand, OrderCreatedEvent,
+    OrderEvent, OrderState, OrderUpdatedEvent, OrderViewState,
+};
+use crate::application::{AggregateError, MaterializedViewError};
+
+mod api;
+mod application;
+
+impl From<PostgresError> for AggregateError {
+    fn from(error: PostgresError) -> Self {
+        AggregateError::SaveEvents(error.to_string())
+    }
+}
+
+impl From<PostgresError> for MaterializedViewError {
+    fn from(error: PostgresError) -> Self {
+        MaterializedViewError::SaveState(error.to_string())
+    }
+}
+
+/// The connection string of the test database, e.g. `host=localhost user=postgres`.
+/// The tests are ignored by default; run them with `FMODEL_POSTGRES_URL` set, and `cargo test --features postgres -- --ignored`.
+fn config() -> String {
+    std::env::var("FMODEL_POSTGRES_URL")
+        .expect("FMODEL_POSTGRES_URL is set to the connection string of the test database")
+}
+
+/// Connects to the test database, with the `schema` (recreated by the first connection of the test) on the search path.
+async fn client(config: &str, schema: &str, recreate: bool) -> Client {
+    let (client, connection) = tokio_postgres::connect(config, NoTls).await.unwrap();
+    tokio::spawn(connection);
+    if recreate {
+        client
+            .batch_execute(&format!(
+                "DROP SCHEMA IF EXISTS {schema} CASCADE; CREATE SCHEMA {schema}"
+            ))
+            .await
+            .unwrap();
+    }
+    client
+        .batch_execute(&format!("SET search_path TO {schema}"))
+        .await
+        .unwrap();
+    client
+}
+
+/// Decider for the Order aggregate - Domain logic
+fn decider<'a>() -> Decider<'a, OrderCommand, OrderState, OrderEvent, AggregateError> {
+    Decider {
+        decide: Box::new(|command, state| match command {
+            OrderCommand::Create(cmd) => Ok(vec![OrderEvent::Created(OrderCreatedEvent {
+                order_id: cmd.order_id,
+                customer_name: cmd.customer_name.to_owned(),
+                items: cmd.items.to_owned(),
+            })]),
+            OrderCommand::Update(_) => Ok(vec![]),
+            OrderCommand::Cancel(cmd) => {
+                if state.order_id == cmd.order_id {
+                    Ok(vec![OrderEvent::Cancelled(OrderCancelledEvent {
+                        order_id: cmd.order_id,
+                    })])
+                } else {
+                    Err(AggregateError::DomainError(
+                        "Order does not exist".to_string(),
+                    ))
+                }
+            }
+        }),
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+/// View for the Order view state - Domain logic
+fn view<'a>() -> View<'a, OrderViewState, OrderEvent> {
+    View {
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderViewState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+fn create_order_command(order_id: u32) -> OrderCommand {
+    OrderCommand::Create(CreateOrderCommand {
+        order_id,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string()],
+    })
+}
+
+fn created_event(order_id: u32) -> OrderEvent {
+    OrderEvent::Created(OrderCreatedEvent {
+        order_id,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string()],
+    })
+}
+
+#[tokio::test]
+#[ignore = "requires FMODEL_POSTGRES_URL"]
+async fn event_sourced_test() {
+    let config = config();
+    let repository =
+        PostgresEventRepository::new(client(&config, "fmodel_event_sourced_test", true).await)
+            .await
+            .unwrap();
+    let aggregate = EventSourcedAggregate::new(repository, decider());
+
+    let result = aggregate.handle(&create_order_command(1)).await.unwrap();
+    assert_eq!(result, vec![(created_event(1), 0)]);
+    let result = aggregate
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }))
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        vec![(
+            OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 }),
+            1
+        )]
+    );
+    let result = aggregate
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 3 }))
+        .await;
+    assert!(result.is_err());
+
+    let repository =
+        PostgresEventRepository::new(client(&config, "fmodel_event_sourced_test", false).await)
+            .await
+            .unwrap();
+    // The events of the different streams are appended in the single transaction
+    let events: Vec<(OrderEvent, i64)> =
+        EventRepository::<OrderCommand, OrderEvent, i64, AggregateError>::save(
+            &repository,
+            &[
+                OrderEvent::Cancelled(OrderCancelledEvent { order_id: 2 }),
+                created_event(3),
+                OrderEvent::Cancelled(OrderCancelledEvent { order_id: 3 }),
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        events
+            .iter()
+            .map(|(_, version)| *version)
+            .collect::<Vec<_>>(),
+        vec![0, 0, 1]
+    );
+    let events: Vec<(OrderEvent, i64)> =
+        EventRepository::<OrderCommand, OrderEvent, i64, AggregateError>::fetch_events(
+            &repository,
+            &OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        EventRepository::<OrderCommand, OrderEvent, i64, AggregateError>::version_provider(
+            &repository,
+            &created_event(3),
+        )
+        .await
+        .unwrap(),
+        Some(1)
+    );
+
+    // The events decided on the stale version of the stream are rejected
+    let result = EventRepository::<OrderCommand, OrderEvent, i64, PostgresError>::save_after(
+        &repository,
+        &OrderCommand::Cancel(CancelOrderCommand { order_id: 3 }),
+        &[OrderEvent::Cancelled(OrderCancelledEvent { order_id: 3 })],
+        &Some(0),
+    )
+    .await;
+    assert!(matches!(result, Err(PostgresError::Conflict(id)) if id == "3"));
+    let result = EventRepository::<OrderCommand, OrderEvent, i64, PostgresError>::save_after(
+        &repository,
+        &create_order_command(3),
+        &[created_event(3)],
+        &None,
+    )
+    .await;
+    assert!(matches!(result, Err(PostgresError::Conflict(_))));
+
+    // The same version of the stream can not be appended twice
+    let result = repository
+        .client()
+        .await
+        .execute(
+            "INSERT INTO events (stream_id, version, payload) VALUES ('1', 1, '{}')",
+            &[],
+        )
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+#[ignore = "requires FMODEL_POSTGRES_URL"]
+async fn event_source_test() {
+    let config = config();
+    let repository =
+        PostgresEventRepository::new(client(&config, "fmodel_event_source_test", true).await)
+            .await
+            .unwrap();
+    let save = |events: Vec<OrderEvent>| {
+        let repository = &repository;
+        async move {
+            EventRepository::<OrderCommand, OrderEvent, i64, AggregateError>::save(
+                repository, &events,
+            )
+            .await
+            .unwrap()
+        }
+    };
+    save(vec![created_event(1)]).await;
+
+    let source = PostgresEventSource::connect(&format!(
+        "{config} options=-csearch_path=fmodel_event_source_test"
+    ))
+    .await
+    .unwrap();
+    let read = |position: Option<i64>| {
+        let source = &source;
+        async move {
+            EventSource::<OrderEvent, i64, MaterializedViewError>::read_events(
+                source, &position, 10,
+            )
+            .await
+            .unwrap()
+        }
+    };
+    let wait = |position: Option<i64>| {
+        let source = &source;
+        async move {
+            tokio::time::timeout(
+                Duration::from_secs(5),
+                EventSource::<OrderEvent, i64, MaterializedViewError>::wait_for_events(
+                    source, &position,
+                ),
+            )
+            .await
+            .expect("the events are notified")
+            .unwrap()
+        }
+    };
+
+    // The events appended before listening are available
+    wait(None).await;
+    let events = read(None).await;
+    assert_eq!(events, vec![(created_event(1), 1)]);
+
+    // The source is woken up by the notification of the append
+    let (_, appended) = tokio::join!(
+        wait(Some(1)),
+        save(vec![
+            created_event(2),
+            OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 })
+        ])
+    );
+    assert_eq!(appended.len(), 2);
+    let events = read(Some(1)).await;
+    assert_eq!(
+        events,
+        vec![
+            (created_event(2), 2),
+            (
+                OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 }),
+                3
+            )
+        ]
+    );
+    assert!(read(Some(3)).await.is_empty());
+
+    // The event committed late, after the event appended later, is not skipped
+    let mut late = client(&config, "fmodel_event_source_test", false).await;
+    let transaction = late.transaction().await.unwrap();
+    transaction
+        .execute(
+            "INSERT INTO events (stream_id, version, payload) VALUES ('4', 0, $1)",
+            &[&serde_json::to_value(created_event(4)).unwrap()],
+        )
+        .await
+        .unwrap();
+    save(vec![created_event(5)]).await;
+    assert!(read(Some(3)).await.is_empty());
+    let (_, committed) = tokio::join!(wait(Some(3)), transaction.commit());
+    committed.unwrap();
+    let events = read(Some(3)).await;
+    assert_eq!(events, vec![(created_event(4), 4), (created_event(5), 5)]);
+    assert!(read(Some(5)).await.is_empty());
+}
+
+#[tokio::test]
+#[ignore = "requires FMODEL_POSTGRES_URL"]
+async fn state_stored_test() {
+    let config = config();
+    let repository =
+        PostgresStateRepository::new(client(&config, "fmodel_state_stored_test", true).await)
+            .await
+            .unwrap();
+    let aggregate = StateStoredAggregate::new(repository, decider());
+
+    let (state, version) = aggregate.handle(&create_order_command(1)).await.unwrap();
+    assert_eq!(state.order_id, 1);
+    assert_eq!(version, 0);
+    let (state, version) = aggregate
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }))
+        .await
+        .unwrap();
+    assert!(state.is_cancelled);
+    assert_eq!(version, 1);
+}
+
+async fn save_state(
+    repository: &PostgresStateRepository,
+    state: &OrderState,
+    version: Option<i64>,
+) -> Result<(OrderState, i64), PostgresError> {
+    StateRepository::<OrderCommand, OrderState, i64, PostgresError>::save(
+        repository, state, &version,
+    )
+    .await
+}
+
+#[tokio::test]
+#[ignore = "requires FMODEL_POSTGRES_URL"]
+async fn state_compare_and_swap_test() {
+    let config = config();
+    let repository = PostgresStateRepository::new(
+        client(&config, "fmodel_state_compare_and_swap_test", true).await,
+    )
+    .await
+    .unwrap();
+    let state = OrderState {
+        order_id: 1,
+        customer_name: "John Doe".to_string(),
+        items: vec![],
+        is_cancelled: false,
+    };
+    assert_eq!(save_state(&repository, &state, None).await.unwrap().1, 0);
+    assert_eq!(save_state(&repository, &state, Some(0)).await.unwrap().1, 1);
+    // The state was modified since it was fetched with version 0
+    assert!(matches!(
+        save_state(&repository, &state, Some(0)).await,
+        Err(PostgresError::Conflict(id)) if id == "1"
+    ));
+    assert!(matches!(
+        save_state(&repository, &state, None).await,
+        Err(PostgresError::Conflict(_))
+    ));
+    let fetched: Option<(OrderState, i64)> =
+        StateRepository::<OrderCommand, OrderState, i64, PostgresError>::fetch_state(
+            &repository,
+            &create_order_command(1),
+        )
+        .await
+        .unwrap();
+    assert_eq!(fetched, Some((state, 1)));
+}
+
+#[tokio::test]
+#[ignore = "requires FMODEL_POSTGRES_URL"]
+async fn materialized_view_test() {
+    let config = config();
+    let repository = PostgresViewStateRepository::new(
+        client(&config, "fmodel_materialized_view_test", true).await,
+    )
+    .await
+    .unwrap();
+    let materialized_view: MaterializedView<_, _, _, _, MaterializedViewError> =
+        MaterializedView::new(repository, view());
+
+    let updated = OrderEvent::Updated(OrderUpdatedEvent {
+        order_id: 1,
+        updated_items: vec!["Item 2".to_string()],
+    });
+    materialized_view.handle(&created_event(1)).await.unwrap();
+    let state = materialized_view.handle(&updated).await.unwrap();
+    assert_eq!(state.items, vec!["Item 2".to_string()]);
+    let state: Option<OrderViewState> = ViewStateRepository::<
+        OrderEvent,
+        OrderViewState,
+        MaterializedViewError,
+    >::fetch_state(&materialized_view, &updated)
+    .await
+    .unwrap();
+    assert_eq!(state.unwrap().items, vec!["Item 2".to_string()]);
+}
+
+#[tokio::test]
+#[ignore = "requires FMODEL_POSTGRES_URL"]
+async fn versioned_materialized_view_test() {
+    let config = config();
+    let repository = PostgresViewStateRepository::new(
+        client(&config, "fmodel_versioned_materialized_view_test", true).await,
+    )
+    .await
+    .unwrap();
+    let materialized_view: MaterializedView<_, _, _, _, MaterializedViewError> =
+        MaterializedView::new(repository, view());
+
+    let cancelled = OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 });
+
+    let result = materialized_view
+        .handle_versioned(&created_event(1), &0)
+        .await
+        .unwrap();
+    assert!(matches!(result, ViewUpdate::Applied(_, 0)));
+    let result = materialized_view
+        .handle_versioned(&created_event(1), &0)
+        .await
+        .unwrap();
+    assert!(matches!(result, ViewUpdate::Duplicate(_, 0)));
+    let result = materialized_view
+        .handle_versioned(&cancelled, &1)
+        .await
+        .unwrap();
+    assert!(matches!(result, ViewUpdate::Applied(state, 1) if state.is_cancelled));
+}
@@ -0,0 +1,173 @@
+#![cfg(not(feature = "not-send-futures"))]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+use fmodel_rust::process::{Process, ProcessComputation};
+use fmodel_rust::process_manager::{ProcessManager, ProcessStateRepository};
+use fmodel_rust::saga_manager::ActionPublisher;
+use fmodel_rust::Identifier;
+
+use crate::api::{CreateShipmentCommand, ShipmentCommand};
+use crate::application::ProcessManagerError;
+
+mod api;
+mod application;
+
+/// Events the fulfillment process is waiting for
+#[derive(Debug, Clone, PartialEq)]
+enum FulfillmentEvent {
+    PaymentReceived(u32),
+    StockReserved(u32),
+}
+
+impl Identifier for FulfillmentEvent {
+    fn identifier(&self) -> String {
+        match self {
+            FulfillmentEvent::PaymentReceived(order_id) => order_id.to_string(),
+            FulfillmentEvent::StockReserved(order_id) => order_id.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+struct FulfillmentState {
+    paid: bool,
+    reserved: bool,
+    shipped: bool,
+}
+
+/// Ships the order once it is both, paid and reserved - Domain logic
+fn process<'a>() -> Process<'a, FulfillmentEvent, FulfillmentState, ShipmentCommand> {
+    Process {
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                FulfillmentEvent::PaymentReceived(_) => new_state.paid = true,
+                FulfillmentEvent::StockReserved(_) => new_state.reserved = true,
+            }
+            new_state.shipped = new_state.paid && new_state.reserved;
+            new_state
+        }),
+        react: Box::new(|state, event| {
+            if state.shipped {
+                let order_id = event.identifier().parse::<u32>().unwrap();
+                vec![ShipmentCommand::Create(CreateShipmentCommand {
+                    shipment_id: order_id,
+                    order_id,
+                    customer_name: "John Doe".to_string(),
+                    items: vec!["Item 1".to_string()],
+                })]
+            } else {
+                vec![]
+            }
+        }),
+        initial_state: Box::new(FulfillmentState::default),
+    }
+}
+
+/// A simple in-memory process state repository - infrastructure
+#[derive(Clone, Default)]
+struct InMemoryProcessStateRepository {
+    states: Arc<RwLock<HashMap<String, (FulfillmentState, i32)>>>,
+}
+
+impl ProcessStateRepository<FulfillmentState, i32, ProcessManagerError>
+    for InMemoryProcessStateRepository
+{
+    async fn fetch_state(
+        &self,
+        correlation_id: &str,
+    ) -> Result<Option<(FulfillmentState, i32)>, ProcessManagerError> {
+        Ok(self.states.read().unwrap().get(correlation_id).cloned())
+    }
+
+    async fn save(
+        &self,
+        correlation_id: &str,
+        state: &FulfillmentState,
+        version: &Option<i32>,
+    ) -> Result<(FulfillmentState, i32), ProcessManagerError> {
+        let version = version.map(|v| v + 1).unwrap_or(0);
+        self.states
+            .write()
+            .unwrap()
+            .insert(correlation_id.to_string(), (state.clone(), version));
+        Ok((state.clone(), version))
+    }
+}
+
+/// Simple action publisher, collecting the published actions
+#[derive(Clone, Default)]
+struct InMemoryActionPublisher {
+    published: Arc<Mutex<Vec<ShipmentCommand>>>,
+}
+
+impl ActionPublisher<ShipmentCommand, ProcessManagerError> for InMemoryActionPublisher {
+    async fn publish(
+        &self,
+        action: &[ShipmentCommand],
+    ) -> Result<Vec<ShipmentCommand>, ProcessManagerError> {
+        self.published.lock().unwrap().extend_from_slice(action);
+        Ok(Vec::from(action))
+    }
+}
+
+#[test]
+fn process_test() {
+    let process = process();
+    let (state, actions) =
+        process.compute_new_state_and_actions(None, &FulfillmentEvent::StockReserved(1));
+    assert!(state.reserved);
+    assert!(actions.is_empty());
+    let (state, actions) =
+        process.compute_new_state_and_actions(Some(state), &FulfillmentEvent::PaymentReceived(1));
+    assert!(state.shipped);
+    assert_eq!(actions.len(), 1);
+
+    let process = process.map_action(|command| command.identifier());
+    let (_, actions) = process.compute_new_state_and_actions(
+        Some(FulfillmentState {
+            paid: true,
+            ..FulfillmentState::default()
+        }),
+        &FulfillmentEvent::StockReserved(2),
+    );
+    assert_eq!(actions, vec!["2".to_string()]);
+}
+
+#[tokio::test]
+async fn test() {
+    let repository = InMemoryProcessStateRepository::default();
+    let publisher = InMemoryActionPublisher::default();
+    let process_manager = ProcessManager::new(repository.clone(), publisher.clone(), process());
+
+    let ((state, version), actions) = process_manager
+        .handle(&FulfillmentEvent::PaymentReceived(1))
+        .await
+        .unwrap();
+    assert!(state.paid && !state.shipped);
+    assert_eq!(version, 0);
+    assert!(actions.is_empty());
+
+    // Another order does not share the state with the first one
+    let ((state, version), actions) = process_manager
+        .handle(&FulfillmentEvent::StockReserved(2))
+        .await
+        .unwrap();
+    assert!(state.reserved && !state.shipped);
+    assert_eq!(version, 0);
+    assert!(actions.is_empty());
+
+    let ((state, version), actions) = process_manager
+        .handle(&FulfillmentEvent::StockReserved(1))
+        .await
+        .unwrap();
+    assert!(state.shipped);
+    assert_eq!(version, 1);
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[0].identifier(), "1");
+
+    assert_eq!(*publisher.published.lock().unwrap(), actions);
+    assert_eq!(repository.states.read().unwrap().len(), 2);
+}
@@ -0,0 +1,440 @@
+#![cfg(not(feature = "not-send-futures"))]
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use fmodel_rust::materialized_view::{
+    MaterializedView, RebuildStatus, ViewRebuild, ViewStateRepository,
+};
+use fmodel_rust::projection::{CheckpointRepository, EventSource, ProjectionRunner};
+use fmodel_rust::view::View;
+use fmodel_rust::Identifier;
+use tokio::sync::Notify;
+
+use crate::api::{
+    OrderCancelledEvent, OrderCreatedEvent, OrderEvent, OrderUpdatedEvent, OrderViewState,
+};
+use crate::application::MaterializedViewError;
+
+mod api;
+mod application;
+
+fn view<'a>() -> View<'a, OrderViewState, OrderEvent> {
+    View {
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderViewState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+/// A simple in-memory event log, ordered by the global position - infrastructure
+#[derive(Clone)]
+struct InMemoryEventLog {
+    events: Arc<RwLock<Vec<OrderEvent>>>,
+    notify: Arc<Notify>,
+}
+
+impl InMemoryEventLog {
+    fn new() -> Self {
+        InMemoryEventLog {
+            events: Arc::new(RwLock::new(vec![])),
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    fn append(&self, event: OrderEvent) {
+        self.events.write().unwrap().push(event);
+        self.notify.notify_waiters();
+    }
+}
+
+impl EventSource<OrderEvent, u64, MaterializedViewError> for InMemoryEventLog {
+    async fn read_events(
+        &self,
+        position: &Option<u64>,
+        limit: usize,
+    ) -> Result<Vec<(OrderEvent, u64)>, MaterializedViewError> {
+        let from = position.map(|p| p as usize + 1).unwrap_or(0);
+        Ok(self
+            .events
+            .read()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .skip(from)
+            .take(limit)
+            .map(|(position, event)| (event.clone(), position as u64))
+            .collect())
+    }
+
+    async fn wait_for_events(&self, position: &Option<u64>) -> Result<(), MaterializedViewError> {
+        let notified = self.notify.notified();
+        let next = position.map(|p| p as usize + 1).unwrap_or(0);
+        if self.events.read().unwrap().len() > next {
+            return Ok(());
+        }
+        notified.await;
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+struct InMemoryCheckpointRepository {
+    checkpoints: Arc<RwLock<HashMap<String, u64>>>,
+}
+
+impl InMemoryCheckpointRepository {
+    fn new() -> Self {
+        InMemoryCheckpointRepository {
+            checkpoints: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+}
+
+impl CheckpointRepository<u64, MaterializedViewError> for InMemoryCheckpointRepository {
+    async fn fetch_checkpoint(
+        &self,
+        projection: &str,
+    ) -> Result<Option<u64>, MaterializedViewError> {
+        Ok(self.checkpoints.read().unwrap().get(projection).copied())
+    }
+
+    async fn save_checkpoint(
+        &self,
+        projection: &str,
+        position: &u64,
+    ) -> Result<(), MaterializedViewError> {
+        self.checkpoints
+            .write()
+            .unwrap()
+            .insert(projection.to_string(), *position);
+        Ok(())
+    }
+
+    async fn delete_checkpoint(&self, projection: &str) -> Result<(), MaterializedViewError> {
+        self.checkpoints.write().unwrap().remove(projection);
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+struct InMemoryViewOrderStateRepository {
+    states: Arc<RwLock<HashMap<u32, OrderViewState>>>,
+}
+
+impl InMemoryViewOrderStateRepository {
+    fn new() -> Self {
+        InMemoryViewOrderStateRepository {
+            states: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+}
+
+impl ViewStateRepository<OrderEvent, OrderViewState, MaterializedViewError>
+    for InMemoryViewOrderStateRepository
+{
+    async fn fetch_state(
+        &self,
+        event: &OrderEvent,
+    ) -> Result<Option<OrderViewState>, MaterializedViewError> {
+        Ok(self
+            .states
+            .read()
+            .unwrap()
+            .get(&event.identifier().parse::<u32>().unwrap())
+            .cloned())
+    }
+
+    async fn save(&self, state: &OrderViewState) -> Result<OrderViewState, MaterializedViewError> {
+        self.states
+            .write()
+            .unwrap()
+            .insert(state.order_id, state.clone());
+        Ok(state.clone())
+    }
+}
+
+fn created(order_id: u32) -> OrderEvent {
+    OrderEvent::Created(OrderCreatedEvent {
+        order_id,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string(), "Item 2".to_string()],
+    })
+}
+
+#[tokio::test]
+async fn catch_up_and_resume_test() {
+    let log = InMemoryEventLog::new();
+    let checkpoints = InMemoryCheckpointRepository::new();
+    let repository = InMemoryViewOrderStateRepository::new();
+
+    log.append(created(1));
+    log.append(created(2));
+    log.append(OrderEvent::Updated(OrderUpdatedEvent {
+        order_id: 1,
+        updated_items: vec!["Item 3".to_string()],
+    }));
+
+    let runner = ProjectionRunner::new(
+        "order-view",
+        log.clone(),
+        checkpoints.clone(),
+        MaterializedView::new(repository.clone(), view()),
+    )
+    .with_batch_size(2);
+
+    assert_eq!(runner.catch_up().await.unwrap(), 3);
+    assert_eq!(
+        checkpoints.fetch_checkpoint("order-view").await.unwrap(),
+        Some(2)
+    );
+    assert_eq!(
+        repository.states.read().unwrap().get(&1).unwrap().items,
+        vec!["Item 3".to_string()]
+    );
+    // Nothing new to handle
+    assert_eq!(runner.catch_up().await.unwrap(), 0);
+
+    // Restart: a new runner resumes from the stored checkpoint
+    drop(runner);
+    log.append(OrderEvent::Cancelled(OrderCancelledEvent { order_id: 2 }));
+    let runner = ProjectionRunner::new(
+        "order-view",
+        log.clone(),
+        checkpoints.clone(),
+        MaterializedView::new(repository.clone(), view()),
+    );
+    assert_eq!(runner.catch_up().await.unwrap(), 1);
+    assert_eq!(
+        checkpoints.fetch_checkpoint("order-view").await.unwrap(),
+        Some(3)
+    );
+    assert!(
+        repository
+            .states
+            .read()
+            .unwrap()
+            .get(&2)
+            .unwrap()
+            .is_cancelled
+    );
+    assert!(
+        !repository
+            .states
+            .read()
+            .unwrap()
+            .get(&1)
+            .unwrap()
+            .is_cancelled
+    );
+}
+
+#[tokio::test]
+async fn live_tailing_test() {
+    let log = InMemoryEventLog::new();
+    let checkpoints = InMemoryCheckpointRepository::new();
+    let repository = InMemoryViewOrderStateRepository::new();
+    log.append(created(1));
+
+    let runner = Arc::new(ProjectionRunner::new(
+        "order-view",
+        log.clone(),
+        checkpoints.clone(),
+        MaterializedView::new(repository.clone(), view()),
+    ));
+    let live = tokio::spawn({
+        let runner = Arc::clone(&runner);
+        async move { runner.run().await }
+    });
+
+    log.append(created(2));
+    log.append(OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 }));
+
+    while checkpoints.fetch_checkpoint("order-view").await.unwrap() != Some(2) {
+        tokio::task::yield_now().await;
+    }
+    live.abort();
+
+    let states = repository.states.read().unwrap();
+    assert!(states.get(&1).unwrap().is_cancelled);
+    assert_eq!(states.get(&2).unwrap().order_id, 2);
+}
+
+#[tokio::test]
+async fn rebuild_and_switch_over_test() {
+    let log = InMemoryEventLog::new();
+    let checkpoints = InMemoryCheckpointRepository::new();
+    let blue = InMemoryViewOrderStateRepository::new();
+    let green = InMemoryViewOrderStateRepository::new();
+    for order_id in 1..=5 {
+        log.append(created(order_id));
+    }
+
+    let runner = ProjectionRunner::new(
+        "order-view",
+        log.clone(),
+        checkpoints.clone(),
+        MaterializedView::new(blue.clone(), view()),
+    );
+    runner.catch_up().await.unwrap();
+
+    // Rebuild into a fresh repository, while the old one keeps serving
+    let rebuild = ViewRebuild::new(log.clone(), MaterializedView::new(green.clone(), view()))
+        .with_batch_size(2);
+    let mut reported = vec![];
+    let status = rebuild
+        .run(|progress| reported.push(progress.handled))
+        .await
+        .unwrap();
+    let progress = match status {
+        RebuildStatus::CaughtUp(progress) => progress,
+        RebuildStatus::Aborted(_) => panic!("rebuild should not be aborted"),
+    };
+    assert_eq!(reported, vec![2, 4, 5]);
+    assert_eq!(progress.position, Some(4));
+    assert_eq!(rebuild.position(), Some(4));
+    assert_eq!(green.states.read().unwrap().len(), 5);
+    assert_eq!(blue.states.read().unwrap().len(), 5);
+
+    // New events arrive after the rebuild caught up, and before the switch over
+    log.append(OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 }));
+    runner.catch_up().await.unwrap();
+    drop(runner);
+
+    let runner = rebuild
+        .switch_over("order-view", checkpoints.clone())
+        .await
+        .unwrap();
+    assert_eq!(runner.catch_up().await.unwrap(), 1);
+    assert!(green.states.read().unwrap().get(&1).unwrap().is_cancelled);
+    assert_eq!(
+        checkpoints.fetch_checkpoint("order-view").await.unwrap(),
+        Some(5)
+    );
+}
+
+#[tokio::test]
+async fn rebuild_abort_test() {
+    let log = InMemoryEventLog::new();
+    let green = InMemoryViewOrderStateRepository::new();
+    for order_id in 1..=5 {
+        log.append(created(order_id));
+    }
+
+    let rebuild = ViewRebuild::new(log.clone(), MaterializedView::new(green.clone(), view()))
+        .with_batch_size(2);
+    let status = rebuild.run(|_| rebuild.abort()).await.unwrap();
+    match status {
+        RebuildStatus::Aborted(progress) => {
+            assert_eq!(progress.handled, 2);
+            assert_eq!(progress.position, Some(1));
+        }
+        RebuildStatus::CaughtUp(_) => panic!("rebuild should be aborted"),
+    }
+    assert_eq!(green.states.read().unwrap().len(), 2);
+    assert!(rebuild.is_aborted());
+}
+
+#[tokio::test]
+async fn rebuild_switch_over_without_events_test() {
+    let log = InMemoryEventLog::new();
+    let checkpoints = InMemoryCheckpointRepository::new();
+    let green = InMemoryViewOrderStateRepository::new();
+    checkpoints.save_checkpoint("order-view", &3).await.unwrap();
+
+    let rebuild = ViewRebuild::new(log.clone(), MaterializedView::new(green.clone(), view()));
+    let status = rebuild.run(|_| {}).await.unwrap();
+    assert!(matches!(status, RebuildStatus::CaughtUp(progress) if progress.position.is_none()));
+    assert_eq!(rebuild.position(), None);
+
+    // The stale checkpoint of the old view is deleted, so the rebuilt view does not skip the upcoming events
+    let runner = rebuild
+        .switch_over("order-view", checkpoints.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        checkpoints.fetch_checkpoint("order-view").await.unwrap(),
+        None
+    );
+    log.append(created(1));
+    assert_eq!(runner.catch_up().await.unwrap(), 1);
+    assert_eq!(green.states.read().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn rebuild_resume_after_abort_test() {
+    let log = InMemoryEventLog::new();
+    let green = InMemoryViewOrderStateRepository::new();
+    for order_id in 1..=5 {
+        log.append(created(order_id));
+    }
+
+    let rebuild = ViewRebuild::new(log.clone(), MaterializedView::new(green.clone(), view()))
+        .with_batch_size(2);
+    let position = match rebuild.run(|_| rebuild.abort()).await.unwrap() {
+        RebuildStatus::Aborted(progress) => progress.position,
+        RebuildStatus::CaughtUp(_) => panic!("rebuild should be aborted"),
+    };
+
+    // The abort request is cleared by the resume, so it continues from the reached position
+    let status = rebuild.resume(position, |_| {}).await.unwrap();
+    match status {
+        RebuildStatus::CaughtUp(progress) => {
+            assert_eq!(progress.handled, 3);
+            assert_eq!(progress.position, Some(4));
+        }
+        RebuildStatus::Aborted(_) => panic!("rebuild should not be aborted"),
+    }
+    assert!(!rebuild.is_aborted());
+    assert_eq!(rebuild.position(), Some(4));
+    assert_eq!(green.states.read().unwrap().len(), 5);
+}
+
+#[tokio::test]
+async fn rebuild_switch_over_after_resume_without_events_test() {
+    let log = InMemoryEventLog::new();
+    let checkpoints = InMemoryCheckpointRepository::new();
+    let green = InMemoryViewOrderStateRepository::new();
+    for order_id in 1..=5 {
+        log.append(created(order_id));
+    }
+
+    // Resumed at the end of the event source, so there is no event left to replay
+    let rebuild = ViewRebuild::new(log.clone(), MaterializedView::new(green.clone(), view()));
+    let status = rebuild.resume(Some(4), |_| {}).await.unwrap();
+    assert!(matches!(status, RebuildStatus::CaughtUp(progress) if progress.handled == 0));
+    assert_eq!(rebuild.position(), Some(4));
+
+    // The checkpoint is set to the resumed position, so the already replayed events are not handled again
+    let runner = rebuild
+        .switch_over("order-view", checkpoints.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        checkpoints.fetch_checkpoint("order-view").await.unwrap(),
+        Some(4)
+    );
+    log.append(created(6));
+    assert_eq!(runner.catch_up().await.unwrap(), 1);
+    assert_eq!(green.states.read().unwrap().len(), 1);
+}
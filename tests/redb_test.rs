@@ -0,0 +1,375 @@
+#![cfg(all(feature = "redb", not(feature = "not-send-futures")))]
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use fmodel_rust::aggregate::{
+    EventRepository, EventSourcedAggregate, StateRepository, StateStoredAggregate,
+};
+use fmodel_rust::decider::Decider;
+use fmodel_rust::materialized_view::{MaterializedView, ViewStateRepository, ViewUpdate};
+use fmodel_rust::redb::{
+    RedbError, RedbEventRepository, RedbStateRepository, RedbViewStateRepository,
+};
+use fmodel_rust::view::View;
+use redb::Database;
+
+use crate::api::{
+    CancelOrderCommand, CreateOrderCommand, OrderCancelledEvent, OrderCommand, OrderCreatedEvent,
+    OrderEvent, OrderState, OrderUpdatedEvent, OrderViewState,
+};
+use crate::application::{AggregateError, MaterializedViewError};
+
+mod api;
+mod application;
+
+impl From<RedbError> for AggregateError {
+    fn from(error: RedbError) -> Self {
+        AggregateError::SaveEvents(error.to_string())
+    }
+}
+
+impl From<RedbError> for MaterializedViewError {
+    fn from(error: RedbError) -> Self {
+        MaterializedViewError::SaveState(error.to_string())
+    }
+}
+
+/// The path of the fresh database file of the test.
+fn database_path(test: &str) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("fmodel-redb-{}-{}.redb", test, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+/// Decider for the Order aggregate - Domain logic
+fn decider<'a>() -> Decider<'a, OrderCommand, OrderState, OrderEvent, AggregateError> {
+    Decider {
+        decide: Box::new(|command, state| match command {
+            OrderCommand::Create(cmd) => Ok(vec![OrderEvent::Created(OrderCreatedEvent {
+                order_id: cmd.order_id,
+                customer_name: cmd.customer_name.to_owned(),
+                items: cmd.items.to_owned(),
+            })]),
+            OrderCommand::Update(_) => Ok(vec![]),
+            OrderCommand::Cancel(cmd) => {
+                if state.order_id == cmd.order_id {
+                    Ok(vec![OrderEvent::Cancelled(OrderCancelledEvent {
+                        order_id: cmd.order_id,
+                    })])
+                } else {
+                    Err(AggregateError::DomainError(
+                        "Order does not exist".to_string(),
+                    ))
+                }
+            }
+        }),
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+/// View for the Order view state - Domain logic
+fn view<'a>() -> View<'a, OrderViewState, OrderEvent> {
+    View {
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderViewState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+fn create_order_command(order_id: u32) -> OrderCommand {
+    OrderCommand::Create(CreateOrderCommand {
+        order_id,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string()],
+    })
+}
+
+fn created_event(order_id: u32) -> OrderEvent {
+    OrderEvent::Created(OrderCreatedEvent {
+        order_id,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string()],
+    })
+}
+
+#[tokio::test]
+async fn event_sourced_test() {
+    let path = database_path("event-sourced");
+    let aggregate =
+        EventSourcedAggregate::new(RedbEventRepository::create(&path).unwrap(), decider());
+
+    let result = aggregate.handle(&create_order_command(1)).await.unwrap();
+    assert_eq!(result, vec![(created_event(1), 0)]);
+    aggregate.handle(&create_order_command(2)).await.unwrap();
+    let result = aggregate
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }))
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        vec![(
+            OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 }),
+            1
+        )]
+    );
+    let result = aggregate
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 3 }))
+        .await;
+    assert!(result.is_err());
+    drop(aggregate);
+
+    // The events are durable
+    let repository = RedbEventRepository::create(&path).unwrap();
+    let events: Vec<(OrderEvent, u64)> =
+        EventRepository::<OrderCommand, OrderEvent, u64, AggregateError>::fetch_events(
+            &repository,
+            &OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        EventRepository::<OrderCommand, OrderEvent, u64, AggregateError>::version_provider(
+            &repository,
+            &events[0].0,
+        )
+        .await
+        .unwrap(),
+        Some(1)
+    );
+    // The events of the different streams are appended in the single transaction
+    let events: Vec<(OrderEvent, u64)> =
+        EventRepository::<OrderCommand, OrderEvent, u64, AggregateError>::save(
+            &repository,
+            &[
+                OrderEvent::Cancelled(OrderCancelledEvent { order_id: 2 }),
+                created_event(3),
+                OrderEvent::Cancelled(OrderCancelledEvent { order_id: 3 }),
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        events
+            .iter()
+            .map(|(_, version)| *version)
+            .collect::<Vec<_>>(),
+        vec![1, 0, 1]
+    );
+
+    // The events decided on the stale version of the stream are rejected, and none of them is appended
+    let result = EventRepository::<OrderCommand, OrderEvent, u64, RedbError>::save_after(
+        &repository,
+        &OrderCommand::Cancel(CancelOrderCommand { order_id: 3 }),
+        &[
+            created_event(4),
+            OrderEvent::Cancelled(OrderCancelledEvent { order_id: 3 }),
+        ],
+        &Some(0),
+    )
+    .await;
+    assert!(matches!(result, Err(RedbError::Conflict(id)) if id == "3"));
+    let result = EventRepository::<OrderCommand, OrderEvent, u64, RedbError>::save_after(
+        &repository,
+        &create_order_command(3),
+        &[created_event(3)],
+        &None,
+    )
+    .await;
+    assert!(matches!(result, Err(RedbError::Conflict(_))));
+    let events: Vec<(OrderEvent, u64)> =
+        EventRepository::<OrderCommand, OrderEvent, u64, RedbError>::save_after(
+            &repository,
+            &OrderCommand::Cancel(CancelOrderCommand { order_id: 3 }),
+            &[
+                created_event(4),
+                OrderEvent::Cancelled(OrderCancelledEvent { order_id: 3 }),
+            ],
+            &Some(1),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        events
+            .iter()
+            .map(|(_, version)| *version)
+            .collect::<Vec<_>>(),
+        vec![0, 2]
+    );
+
+    drop(repository);
+    std::fs::remove_file(&path).unwrap();
+}
+
+async fn save_state(
+    repository: &RedbStateRepository,
+    state: &OrderState,
+    version: Option<u64>,
+) -> Result<(OrderState, u64), RedbError> {
+    StateRepository::<OrderCommand, OrderState, u64, RedbError>::save(repository, state, &version)
+        .await
+}
+
+#[tokio::test]
+async fn state_stored_test() {
+    let path = database_path("state-stored");
+    let database = Arc::new(Database::create(&path).unwrap());
+    let aggregate = StateStoredAggregate::new(
+        RedbStateRepository::new(database.clone()).unwrap(),
+        decider(),
+    );
+
+    let (state, version) = aggregate.handle(&create_order_command(1)).await.unwrap();
+    assert_eq!(state.order_id, 1);
+    assert_eq!(version, 0);
+    let (state, version) = aggregate
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }))
+        .await
+        .unwrap();
+    assert!(state.is_cancelled);
+    assert_eq!(version, 1);
+
+    // The repositories share the database
+    let repository = RedbStateRepository::new(database).unwrap();
+    // The state was modified since it was fetched with version 0
+    assert!(matches!(
+        save_state(&repository, &state, Some(0)).await,
+        Err(RedbError::Conflict(id)) if id == "1"
+    ));
+    assert!(matches!(
+        save_state(&repository, &state, None).await,
+        Err(RedbError::Conflict(_))
+    ));
+    assert_eq!(save_state(&repository, &state, Some(1)).await.unwrap().1, 2);
+
+    drop(aggregate);
+    drop(repository);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn materialized_view_test() {
+    let path = database_path("materialized-view");
+    let database = Arc::new(Database::create(&path).unwrap());
+    let materialized_view: MaterializedView<_, _, _, _, MaterializedViewError> =
+        MaterializedView::new(
+            RedbViewStateRepository::new(database.clone()).unwrap(),
+            view(),
+        );
+
+    let updated = OrderEvent::Updated(OrderUpdatedEvent {
+        order_id: 1,
+        updated_items: vec!["Item 2".to_string()],
+    });
+    materialized_view.handle(&created_event(1)).await.unwrap();
+    let state = materialized_view.handle(&updated).await.unwrap();
+    assert_eq!(state.items, vec!["Item 2".to_string()]);
+    let state: Option<OrderViewState> = ViewStateRepository::<
+        OrderEvent,
+        OrderViewState,
+        MaterializedViewError,
+    >::fetch_state(&materialized_view, &updated)
+    .await
+    .unwrap();
+    assert_eq!(state.unwrap().items, vec!["Item 2".to_string()]);
+
+    let versioned_view: MaterializedView<_, _, _, _, MaterializedViewError> =
+        MaterializedView::new(RedbViewStateRepository::new(database).unwrap(), view());
+    let cancelled = OrderEvent::Cancelled(OrderCancelledEvent { order_id: 2 });
+    let result = versioned_view
+        .handle_versioned(&created_event(2), &0)
+        .await
+        .unwrap();
+    assert!(matches!(result, ViewUpdate::Applied(_, 0)));
+    let result = versioned_view
+        .handle_versioned(&created_event(2), &0)
+        .await
+        .unwrap();
+    assert!(matches!(result, ViewUpdate::Duplicate(_, 0)));
+    let result = versioned_view
+        .handle_versioned(&cancelled, &1)
+        .await
+        .unwrap();
+    assert!(matches!(result, ViewUpdate::Applied(state, 1) if state.is_cancelled));
+
+    drop(materialized_view);
+    drop(versioned_view);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "msgpack")]
+#[tokio::test]
+async fn codec_test() {
+    use fmodel_rust::codec::MessagePackCodec;
+
+    let path = database_path("codec");
+    let database = Arc::new(Database::create(&path).unwrap());
+    let repository = RedbEventRepository::with_codec(database.clone(), MessagePackCodec).unwrap();
+    let aggregate = EventSourcedAggregate::new(repository, decider());
+    aggregate.handle(&create_order_command(1)).await.unwrap();
+    aggregate
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }))
+        .await
+        .unwrap();
+
+    // The event type is stored next to the payload, encoded by the codec
+    let transaction = database.begin_read().unwrap();
+    let table = transaction.open_table(fmodel_rust::redb::EVENTS).unwrap();
+    let row = table.get(("1", 1)).unwrap().unwrap();
+    let (event_type, payload) = row.value();
+    assert_eq!(event_type, "Cancelled");
+    assert_eq!(
+        rmp_serde::from_slice::<OrderEvent>(payload).unwrap(),
+        OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 })
+    );
+    drop(row);
+    drop(table);
+    drop(transaction);
+    drop(aggregate);
+    drop(database);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,157 @@
+#![cfg(not(feature = "not-send-futures"))]
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use fmodel_rust::saga::Saga;
+use fmodel_rust::saga_manager::{
+    ActionPublisher, DeadLetter, DeadLetterSink, RetryPolicy, SagaManager,
+};
+
+use crate::api::{CreateShipmentCommand, OrderCreatedEvent, OrderEvent, ShipmentCommand};
+use crate::application::SagaManagerError;
+
+mod api;
+mod application;
+
+fn saga<'a>() -> Saga<'a, OrderEvent, ShipmentCommand> {
+    Saga {
+        react: Box::new(|event| match event {
+            OrderEvent::Created(evt) => {
+                vec![ShipmentCommand::Create(CreateShipmentCommand {
+                    shipment_id: evt.order_id,
+                    order_id: evt.order_id,
+                    customer_name: evt.customer_name.to_owned(),
+                    items: evt.items.to_owned(),
+                })]
+            }
+            OrderEvent::Updated(_) => {
+                vec![]
+            }
+            OrderEvent::Cancelled(_) => {
+                vec![]
+            }
+        }),
+    }
+}
+
+/// Action publisher that fails the given number of times, before it starts publishing the actions.
+struct FlakyActionPublisher {
+    failures: Arc<AtomicU32>,
+}
+
+impl ActionPublisher<ShipmentCommand, SagaManagerError> for FlakyActionPublisher {
+    async fn publish(
+        &self,
+        action: &[ShipmentCommand],
+    ) -> Result<Vec<ShipmentCommand>, SagaManagerError> {
+        let remaining = self.failures.load(Ordering::SeqCst);
+        if remaining > 0 {
+            self.failures.store(remaining - 1, Ordering::SeqCst);
+            return Err(SagaManagerError::PublishAction(
+                "downstream unavailable".to_string(),
+            ));
+        }
+        Ok(Vec::from(action))
+    }
+}
+
+/// Simple in-memory dead letter sink
+struct InMemoryDeadLetterSink {
+    dead_letters: Mutex<Vec<DeadLetter<OrderEvent, ShipmentCommand, SagaManagerError>>>,
+}
+
+impl DeadLetterSink<OrderEvent, ShipmentCommand, SagaManagerError> for InMemoryDeadLetterSink {
+    async fn send(
+        &self,
+        dead_letter: DeadLetter<OrderEvent, ShipmentCommand, SagaManagerError>,
+    ) -> Result<(), SagaManagerError> {
+        self.dead_letters.lock().unwrap().push(dead_letter);
+        Ok(())
+    }
+}
+
+fn order_created_event() -> OrderEvent {
+    OrderEvent::Created(OrderCreatedEvent {
+        order_id: 1,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string(), "Item 2".to_string()],
+    })
+}
+
+fn shipment_command() -> ShipmentCommand {
+    ShipmentCommand::Create(CreateShipmentCommand {
+        shipment_id: 1,
+        order_id: 1,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string(), "Item 2".to_string()],
+    })
+}
+
+fn retry_policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 3,
+        initial_backoff: Duration::from_millis(100),
+        multiplier: 2,
+        max_backoff: Duration::from_millis(150),
+    }
+}
+
+#[tokio::test]
+async fn retry_test() {
+    let failures = Arc::new(AtomicU32::new(2));
+    let sleeps = Arc::new(Mutex::new(vec![]));
+    let recorded_sleeps = Arc::clone(&sleeps);
+    let saga_manager = SagaManager::new(
+        FlakyActionPublisher {
+            failures: Arc::clone(&failures),
+        },
+        saga(),
+    )
+    .with_retry(retry_policy(), move |duration| {
+        recorded_sleeps.lock().unwrap().push(duration);
+        async {}
+    });
+
+    let result = saga_manager.handle(&order_created_event()).await;
+    assert_eq!(result.unwrap(), vec![shipment_command()]);
+    assert_eq!(
+        *sleeps.lock().unwrap(),
+        vec![Duration::from_millis(100), Duration::from_millis(150)]
+    );
+
+    // Retries are exhausted, the error of the last attempt is returned
+    failures.store(3, Ordering::SeqCst);
+    let result = saga_manager.handle(&order_created_event()).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn dead_letter_test() {
+    let failures = Arc::new(AtomicU32::new(3));
+    let sink = InMemoryDeadLetterSink {
+        dead_letters: Mutex::new(vec![]),
+    };
+    let saga_manager = SagaManager::new(
+        FlakyActionPublisher {
+            failures: Arc::clone(&failures),
+        },
+        saga(),
+    )
+    .with_retry(retry_policy(), |_| async {});
+
+    let result = saga_manager
+        .handle_with_dead_letter(&order_created_event(), &sink)
+        .await;
+    assert_eq!(result.unwrap(), vec![]);
+
+    let dead_letter = sink.dead_letters.lock().unwrap().pop().unwrap();
+    assert_eq!(dead_letter.action_result, order_created_event());
+    assert_eq!(dead_letter.actions, vec![shipment_command()]);
+    assert_eq!(dead_letter.attempts, 3);
+
+    // The downstream is healthy again, re-drive the dead letter
+    let result = saga_manager.redrive(&dead_letter).await;
+    assert_eq!(result.unwrap(), vec![shipment_command()]);
+}
@@ -0,0 +1,144 @@
+#![cfg(not(feature = "not-send-futures"))]
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use fmodel_rust::saga::Saga;
+use fmodel_rust::saga_manager::{ActionPublisher, SagaManager};
+use fmodel_rust::scheduler::{
+    Clock, DeadlineRunner, InMemoryScheduler, ManualClock, ScheduledAction,
+    ScheduledActionPublisher, Scheduler, SchedulerError,
+};
+
+use crate::api::{CancelOrderCommand, OrderCommand, OrderCreatedEvent, OrderEvent};
+use crate::application::SagaManagerError;
+
+mod api;
+mod application;
+
+impl From<SchedulerError> for SagaManagerError {
+    fn from(error: SchedulerError) -> Self {
+        SagaManagerError::PublishAction(error.to_string())
+    }
+}
+
+/// Cancels the order, if it is not paid in 30 minutes - Domain logic
+fn saga<'a>() -> Saga<'a, OrderEvent, ScheduledAction<OrderCommand>> {
+    Saga {
+        react: Box::new(|event| match event {
+            OrderEvent::Created(evt) => vec![ScheduledAction::after(
+                Duration::from_secs(30 * 60),
+                OrderCommand::Cancel(CancelOrderCommand {
+                    order_id: evt.order_id,
+                }),
+            )],
+            OrderEvent::Updated(_) => vec![],
+            OrderEvent::Cancelled(_) => vec![],
+        }),
+    }
+}
+
+/// Simple action publisher, collecting the published actions
+#[derive(Clone, Default)]
+struct InMemoryActionPublisher {
+    published: Arc<Mutex<Vec<OrderCommand>>>,
+}
+
+impl ActionPublisher<OrderCommand, SagaManagerError> for InMemoryActionPublisher {
+    async fn publish(
+        &self,
+        action: &[OrderCommand],
+    ) -> Result<Vec<OrderCommand>, SagaManagerError> {
+        self.published.lock().unwrap().extend_from_slice(action);
+        Ok(Vec::from(action))
+    }
+}
+
+fn order_created_event(order_id: u32) -> OrderEvent {
+    OrderEvent::Created(OrderCreatedEvent {
+        order_id,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string(), "Item 2".to_string()],
+    })
+}
+
+#[tokio::test]
+async fn test() {
+    let clock = ManualClock::default();
+    let scheduler = InMemoryScheduler::new();
+    let publisher = InMemoryActionPublisher::default();
+    let saga_manager = SagaManager::new(
+        ScheduledActionPublisher::new(scheduler.clone(), clock.clone()),
+        saga(),
+    );
+    let runner =
+        DeadlineRunner::new(scheduler.clone(), clock.clone(), publisher.clone()).with_batch_size(1);
+
+    let result: Result<_, SagaManagerError> = saga_manager.handle(&order_created_event(1)).await;
+    assert_eq!(result.unwrap().len(), 1);
+    clock.advance(Duration::from_secs(10 * 60)).unwrap();
+    saga_manager.handle(&order_created_event(2)).await.unwrap();
+    assert_eq!(scheduler.len(), 2);
+
+    // Nothing is due yet
+    assert_eq!(runner.dispatch_due().await.unwrap(), 0);
+    assert!(publisher.published.lock().unwrap().is_empty());
+
+    // The deadline of the first order is reached
+    clock.advance(Duration::from_secs(20 * 60)).unwrap();
+    assert_eq!(runner.dispatch_due().await.unwrap(), 1);
+    assert_eq!(
+        *publisher.published.lock().unwrap(),
+        vec![OrderCommand::Cancel(CancelOrderCommand { order_id: 1 })]
+    );
+    assert_eq!(runner.dispatch_due().await.unwrap(), 0);
+
+    // The deadline of the second order is overdue
+    clock.advance(Duration::from_secs(60 * 60)).unwrap();
+    assert_eq!(runner.dispatch_due().await.unwrap(), 1);
+    assert_eq!(publisher.published.lock().unwrap().len(), 2);
+    assert!(scheduler.is_empty());
+
+    // The deadline of the third order is canceled, before it is due
+    saga_manager.handle(&order_created_event(3)).await.unwrap();
+    let canceled: Result<usize, SagaManagerError> = scheduler.cancel("3").await;
+    assert_eq!(canceled.unwrap(), 1);
+    clock.advance(Duration::from_secs(60 * 60)).unwrap();
+    assert_eq!(runner.dispatch_due().await.unwrap(), 0);
+    assert_eq!(publisher.published.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn due_time_overflow_test() {
+    let scheduler = InMemoryScheduler::new();
+    let publisher = ScheduledActionPublisher::new(scheduler.clone(), ManualClock::default());
+
+    let result: Result<_, SagaManagerError> = publisher
+        .publish(&[
+            ScheduledAction::now(OrderCommand::Cancel(CancelOrderCommand { order_id: 1 })),
+            ScheduledAction::after(
+                Duration::MAX,
+                OrderCommand::Cancel(CancelOrderCommand { order_id: 2 }),
+            ),
+        ])
+        .await;
+    assert!(result.is_err());
+    // None of the actions is scheduled
+    assert!(scheduler.is_empty());
+}
+
+#[test]
+fn clock_overflow_test() {
+    let clock = ManualClock::default();
+    clock.advance(Duration::from_secs(60)).unwrap();
+
+    // The clock is left at its time
+    assert_eq!(
+        clock.advance(Duration::MAX),
+        Err(SchedulerError::ClockOverflow(Duration::MAX))
+    );
+    assert_eq!(
+        clock.now(),
+        std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(60)
+    );
+}
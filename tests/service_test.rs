@@ -0,0 +1,111 @@
+#![cfg(all(feature = "tower", not(feature = "not-send-futures")))]
+
+use std::future::poll_fn;
+use std::sync::RwLock;
+
+use fmodel_rust::aggregate::{EventRepository, EventSourcedAggregate};
+use fmodel_rust::decider::Decider;
+use fmodel_rust::middleware::{Intercepted, Interceptor};
+use fmodel_rust::service::HandlerService;
+use tower_service::Service;
+
+use crate::api::{CreateOrderCommand, OrderCommand, OrderCreatedEvent, OrderEvent, OrderState};
+use crate::application::{AggregateError, InMemoryOrderEventRepository};
+
+mod api;
+mod application;
+
+/// Decider for the Order aggregate - Domain logic
+fn decider() -> Decider<'static, OrderCommand, OrderState, OrderEvent, AggregateError> {
+    Decider {
+        decide: Box::new(|command, _state| match command {
+            OrderCommand::Create(cmd) => Ok(vec![OrderEvent::Created(OrderCreatedEvent {
+                order_id: cmd.order_id,
+                customer_name: cmd.customer_name.to_owned(),
+                items: cmd.items.to_owned(),
+            })]),
+            _ => Ok(vec![]),
+        }),
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            if let OrderEvent::Created(evt) = event {
+                new_state.order_id = evt.order_id;
+                new_state.customer_name = evt.customer_name.to_owned();
+                new_state.items = evt.items.to_owned();
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+/// Rejects the orders with no items
+struct ValidationInterceptor;
+
+impl Interceptor<OrderCommand, Vec<(OrderEvent, i32)>, AggregateError> for ValidationInterceptor {
+    async fn before(&self, command: &OrderCommand) -> Result<(), AggregateError> {
+        match command {
+            OrderCommand::Create(cmd) if cmd.items.is_empty() => Err(AggregateError::DomainError(
+                "Order has no items".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn create_order_command(order_id: u32, items: Vec<String>) -> OrderCommand {
+    OrderCommand::Create(CreateOrderCommand {
+        order_id,
+        customer_name: "John Doe".to_string(),
+        items,
+    })
+}
+
+#[tokio::test]
+async fn test() {
+    let aggregate = EventSourcedAggregate::new(
+        InMemoryOrderEventRepository {
+            events: RwLock::new(vec![]),
+        },
+        decider(),
+    );
+    let service = HandlerService::new(Intercepted::new(aggregate, ValidationInterceptor));
+
+    // The service is cloned into the spawned tasks, as the web frameworks do
+    let handles = (1..=3)
+        .map(|order_id| {
+            let mut service = service.clone();
+            tokio::spawn(async move {
+                poll_fn(|cx| service.poll_ready(cx)).await.unwrap();
+                service
+                    .call(create_order_command(order_id, vec!["Item 1".to_string()]))
+                    .await
+            })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        let events = handle.await.unwrap().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].1, 0);
+    }
+
+    let mut service = service.clone();
+    poll_fn(|cx| service.poll_ready(cx)).await.unwrap();
+    let result = service.call(create_order_command(4, vec![])).await;
+    assert!(result.is_err());
+    assert_eq!(
+        service
+            .handler()
+            .handler()
+            .fetch_events(&create_order_command(1, vec![]))
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
@@ -0,0 +1,477 @@
+#![cfg(all(feature = "sqlite", not(feature = "not-send-futures")))]
+
+use fmodel_rust::aggregate::{
+    EventRepository, EventSourcedAggregate, EventStream, StateRepository, StateStoredAggregate,
+    StreamingEventRepository,
+};
+use fmodel_rust::decider::Decider;
+use fmodel_rust::materialized_view::{MaterializedView, ViewStateRepository, ViewUpdate};
+use fmodel_rust::sqlite::{
+    SqliteError, SqliteEventRepository, SqliteStateRepository, SqliteViewStateRepository,
+};
+use fmodel_rust::view::View;
+
+use crate::api::{
+    CancelOrderCommand, CreateOrderCommand, OrderCancelledEvent, OrderCommand, OrderCreatedEvent,
+    OrderEvent, OrderState, OrderUpdatedEvent, OrderViewState, UpdateOrderCommand,
+};
+use crate::application::{AggregateError, MaterializedViewError};
+
+mod api;
+mod application;
+
+impl From<SqliteError> for AggregateError {
+    fn from(error: SqliteError) -> Self {
+        AggregateError::SaveEvents(error.to_string())
+    }
+}
+
+impl From<SqliteError> for MaterializedViewError {
+    fn from(error: SqliteError) -> Self {
+        MaterializedViewError::SaveState(error.to_string())
+    }
+}
+
+/// Decider for the Order aggregate - Domain logic
+fn decider<'a>() -> Decider<'a, OrderCommand, OrderState, OrderEvent, AggregateError> {
+    Decider {
+        decide: Box::new(|command, state| match command {
+            OrderCommand::Create(cmd) => Ok(vec![OrderEvent::Created(OrderCreatedEvent {
+                order_id: cmd.order_id,
+                customer_name: cmd.customer_name.to_owned(),
+                items: cmd.items.to_owned(),
+            })]),
+            OrderCommand::Update(cmd) => {
+                if state.order_id == cmd.order_id {
+                    Ok(vec![OrderEvent::Updated(OrderUpdatedEvent {
+                        order_id: cmd.order_id,
+                        updated_items: cmd.new_items.to_owned(),
+                    })])
+                } else {
+                    Ok(vec![])
+                }
+            }
+            OrderCommand::Cancel(cmd) => {
+                if state.order_id == cmd.order_id {
+                    Ok(vec![OrderEvent::Cancelled(OrderCancelledEvent {
+                        order_id: cmd.order_id,
+                    })])
+                } else {
+                    Err(AggregateError::DomainError(
+                        "Order does not exist".to_string(),
+                    ))
+                }
+            }
+        }),
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+/// View for the Order view state - Domain logic
+fn view<'a>() -> View<'a, OrderViewState, OrderEvent> {
+    View {
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderViewState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+fn create_order_command(order_id: u32) -> OrderCommand {
+    OrderCommand::Create(CreateOrderCommand {
+        order_id,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string()],
+    })
+}
+
+#[tokio::test]
+async fn event_sourced_test() {
+    let path = std::env::temp_dir().join(format!("fmodel-sqlite-test-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let aggregate =
+        EventSourcedAggregate::new(SqliteEventRepository::open(&path).unwrap(), decider());
+    let result = aggregate.handle(&create_order_command(1)).await.unwrap();
+    assert_eq!(
+        result,
+        vec![(
+            OrderEvent::Created(OrderCreatedEvent {
+                order_id: 1,
+                customer_name: "John Doe".to_string(),
+                items: vec!["Item 1".to_string()],
+            }),
+            0
+        )]
+    );
+    aggregate.handle(&create_order_command(2)).await.unwrap();
+    let result = aggregate
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }))
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        vec![(
+            OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 }),
+            1
+        )]
+    );
+    let result = aggregate
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 3 }))
+        .await;
+    assert!(result.is_err());
+    drop(aggregate);
+
+    // The events are durable, and the global position keeps the order they were appended in
+    let repository = SqliteEventRepository::open(&path).unwrap();
+    let events: Vec<(OrderEvent, i64)> =
+        EventRepository::<OrderCommand, OrderEvent, i64, AggregateError>::fetch_events(
+            &repository,
+            &OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        EventRepository::<OrderCommand, OrderEvent, i64, AggregateError>::version_provider(
+            &repository,
+            &events[0].0,
+        )
+        .await
+        .unwrap(),
+        Some(1)
+    );
+    let positions = repository
+        .connection()
+        .prepare("SELECT stream_id, version FROM events ORDER BY position")
+        .unwrap()
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        positions,
+        vec![
+            ("1".to_string(), 0),
+            ("2".to_string(), 0),
+            ("1".to_string(), 1)
+        ]
+    );
+
+    // The same version of the stream can not be appended twice
+    let result = repository.connection().execute(
+        "INSERT INTO events (stream_id, version, payload) VALUES ('1', 1, '{}')",
+        [],
+    );
+    assert!(result.is_err());
+
+    drop(repository);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn optimistic_locking_test() {
+    let path = std::env::temp_dir().join(format!(
+        "fmodel-sqlite-locking-test-{}.db",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let aggregate =
+        EventSourcedAggregate::new(SqliteEventRepository::open(&path).unwrap(), decider());
+    aggregate.handle(&create_order_command(1)).await.unwrap();
+
+    // Two handlers fetch the same stream, and both save the events decided on it
+    let first = SqliteEventRepository::open(&path).unwrap();
+    let second = SqliteEventRepository::open(&path).unwrap();
+    let command = OrderCommand::Cancel(CancelOrderCommand { order_id: 1 });
+    let cancelled = [OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 })];
+    let first_events: Vec<(OrderEvent, i64)> =
+        EventRepository::<OrderCommand, OrderEvent, i64, SqliteError>::fetch_events(
+            &first, &command,
+        )
+        .await
+        .unwrap();
+    let second_events: Vec<(OrderEvent, i64)> =
+        EventRepository::<OrderCommand, OrderEvent, i64, SqliteError>::fetch_events(
+            &second, &command,
+        )
+        .await
+        .unwrap();
+    let first_result = EventRepository::<OrderCommand, OrderEvent, i64, SqliteError>::save_after(
+        &first,
+        &command,
+        &cancelled,
+        &first_events.last().map(|(_, version)| *version),
+    )
+    .await;
+    let second_result = EventRepository::<OrderCommand, OrderEvent, i64, SqliteError>::save_after(
+        &second,
+        &command,
+        &cancelled,
+        &second_events.last().map(|(_, version)| *version),
+    )
+    .await;
+    assert_eq!(first_result.unwrap(), vec![(cancelled[0].clone(), 1)]);
+    assert!(matches!(second_result, Err(SqliteError::Conflict(id)) if id == "1"));
+
+    // The stream that exists is not created again
+    let result = EventRepository::<OrderCommand, OrderEvent, i64, SqliteError>::save_after(
+        &second,
+        &create_order_command(1),
+        &cancelled,
+        &None,
+    )
+    .await;
+    assert!(matches!(result, Err(SqliteError::Conflict(_))));
+
+    // The aggregate decides on the fresh state
+    let result = aggregate.handle(&command).await;
+    assert!(result.is_ok());
+
+    drop((aggregate, first, second));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn streaming_test() {
+    let aggregate = EventSourcedAggregate::new(
+        SqliteEventRepository::open_in_memory()
+            .unwrap()
+            .with_page_size(2),
+        decider(),
+    );
+    aggregate.handle(&create_order_command(1)).await.unwrap();
+    aggregate.handle(&create_order_command(2)).await.unwrap();
+    for item in 2..=5 {
+        let result = aggregate
+            .handle_streaming(&OrderCommand::Update(UpdateOrderCommand {
+                order_id: 1,
+                new_items: vec![format!("Item {}", item)],
+            }))
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].1, item - 1);
+    }
+    let result = aggregate
+        .handle_streaming(&OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }))
+        .await
+        .unwrap();
+    assert_eq!(
+        result,
+        vec![(
+            OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 }),
+            5
+        )]
+    );
+    // The decider sees no state of the stream that does not exist
+    let result = aggregate
+        .handle_streaming(&OrderCommand::Cancel(CancelOrderCommand { order_id: 3 }))
+        .await;
+    assert!(result.is_err());
+
+    // The events are streamed page by page, in the order of their versions
+    let repository = SqliteEventRepository::open_in_memory()
+        .unwrap()
+        .with_page_size(2);
+    repository
+        .connection()
+        .execute_batch(
+            "INSERT INTO events (stream_id, version, payload) VALUES
+                ('1', 0, '{\"Cancelled\":{\"order_id\":1}}'),
+                ('1', 1, '{\"Cancelled\":{\"order_id\":1}}'),
+                ('1', 2, 'not json')",
+        )
+        .unwrap();
+    let command = OrderCommand::Cancel(CancelOrderCommand { order_id: 1 });
+    let mut stream =
+        StreamingEventRepository::<OrderCommand, OrderEvent, i64, AggregateError>::stream_events(
+            &repository,
+            &command,
+        );
+    assert_eq!(stream.next().await.unwrap().unwrap().1, 0);
+    assert_eq!(stream.next().await.unwrap().unwrap().1, 1);
+    assert!(stream.next().await.unwrap().is_err());
+    assert!(stream.next().await.is_none());
+}
+
+#[cfg(feature = "stream")]
+#[tokio::test]
+async fn stream_adapter_test() {
+    use fmodel_rust::aggregate::EventStreamAdapter;
+    use futures_core::Stream;
+    use std::pin::Pin;
+
+    let repository = SqliteEventRepository::open_in_memory()
+        .unwrap()
+        .with_page_size(2);
+    let events = vec![OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 }); 3];
+    EventRepository::<OrderCommand, OrderEvent, i64, AggregateError>::save(&repository, &events)
+        .await
+        .unwrap();
+
+    let command = OrderCommand::Cancel(CancelOrderCommand { order_id: 1 });
+    let mut stream = EventStreamAdapter::new(StreamingEventRepository::<
+        OrderCommand,
+        OrderEvent,
+        i64,
+        AggregateError,
+    >::stream_events(&repository, &command));
+    let mut versions = Vec::new();
+    while let Some(event) = std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+        versions.push(event.unwrap().1);
+    }
+    assert_eq!(versions, vec![0, 1, 2]);
+    // The exhausted stream stays exhausted
+    assert!(
+        std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx))
+            .await
+            .is_none()
+    );
+}
+
+#[tokio::test]
+async fn state_stored_test() {
+    let aggregate =
+        StateStoredAggregate::new(SqliteStateRepository::open_in_memory().unwrap(), decider());
+
+    let (state, version) = aggregate.handle(&create_order_command(1)).await.unwrap();
+    assert_eq!(state.order_id, 1);
+    assert_eq!(version, 0);
+    let (state, version) = aggregate
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }))
+        .await
+        .unwrap();
+    assert!(state.is_cancelled);
+    assert_eq!(version, 1);
+}
+
+async fn save_state(
+    repository: &SqliteStateRepository,
+    state: &OrderState,
+    version: Option<i64>,
+) -> Result<(OrderState, i64), SqliteError> {
+    StateRepository::<OrderCommand, OrderState, i64, SqliteError>::save(repository, state, &version)
+        .await
+}
+
+#[tokio::test]
+async fn state_compare_and_swap_test() {
+    let repository = SqliteStateRepository::open_in_memory().unwrap();
+    let state = OrderState {
+        order_id: 1,
+        customer_name: "John Doe".to_string(),
+        items: vec![],
+        is_cancelled: false,
+    };
+    assert_eq!(save_state(&repository, &state, None).await.unwrap().1, 0);
+    assert_eq!(save_state(&repository, &state, Some(0)).await.unwrap().1, 1);
+    // The state was modified since it was fetched with version 0
+    assert!(matches!(
+        save_state(&repository, &state, Some(0)).await,
+        Err(SqliteError::Conflict(id)) if id == "1"
+    ));
+    assert!(matches!(
+        save_state(&repository, &state, None).await,
+        Err(SqliteError::Conflict(_))
+    ));
+}
+
+#[tokio::test]
+async fn materialized_view_test() {
+    let materialized_view: MaterializedView<_, _, _, _, MaterializedViewError> =
+        MaterializedView::new(SqliteViewStateRepository::open_in_memory().unwrap(), view());
+
+    let created = OrderEvent::Created(OrderCreatedEvent {
+        order_id: 1,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string()],
+    });
+    let updated = OrderEvent::Updated(OrderUpdatedEvent {
+        order_id: 1,
+        updated_items: vec!["Item 2".to_string()],
+    });
+    materialized_view.handle(&created).await.unwrap();
+    let state = materialized_view.handle(&updated).await.unwrap();
+    assert_eq!(state.items, vec!["Item 2".to_string()]);
+    let state: Option<OrderViewState> = ViewStateRepository::<
+        OrderEvent,
+        OrderViewState,
+        MaterializedViewError,
+    >::fetch_state(&materialized_view, &created)
+    .await
+    .unwrap();
+    assert_eq!(state.unwrap().items, vec!["Item 2".to_string()]);
+}
+
+#[tokio::test]
+async fn versioned_materialized_view_test() {
+    let materialized_view: MaterializedView<_, _, _, _, MaterializedViewError> =
+        MaterializedView::new(SqliteViewStateRepository::open_in_memory().unwrap(), view());
+
+    let created = OrderEvent::Created(OrderCreatedEvent {
+        order_id: 1,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string()],
+    });
+    let cancelled = OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 });
+
+    let result = materialized_view
+        .handle_versioned(&created, &0)
+        .await
+        .unwrap();
+    assert!(matches!(result, ViewUpdate::Applied(_, 0)));
+    let result = materialized_view
+        .handle_versioned(&created, &0)
+        .await
+        .unwrap();
+    assert!(matches!(result, ViewUpdate::Duplicate(_, 0)));
+    let result = materialized_view
+        .handle_versioned(&cancelled, &1)
+        .await
+        .unwrap();
+    assert!(matches!(result, ViewUpdate::Applied(state, 1) if state.is_cancelled));
+}
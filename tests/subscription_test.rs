@@ -0,0 +1,369 @@
+#![cfg(not(feature = "not-send-futures"))]
+
+use std::collections::HashMap;
+use std::future::{poll_fn, Future};
+use std::pin::{pin, Pin};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::task::Poll;
+
+use fmodel_rust::aggregate::{EventRepository, EventSourcedAggregate};
+use fmodel_rust::decider::Decider;
+use fmodel_rust::materialized_view::{MaterializedView, ViewStateRepository};
+use fmodel_rust::middleware::Handler;
+use fmodel_rust::projection::EventSource;
+use fmodel_rust::saga::Saga;
+use fmodel_rust::saga_manager::{ActionPublisher, SagaManager};
+use fmodel_rust::subscription::{
+    EventFilter, EventLogRepository, EventSubscription, InMemoryEventLog,
+};
+use fmodel_rust::view::View;
+use fmodel_rust::Identifier;
+
+use crate::api::{
+    CancelOrderCommand, CreateOrderCommand, CreateShipmentCommand, OrderCancelledEvent,
+    OrderCommand, OrderCreatedEvent, OrderEvent, OrderState, OrderViewState, ShipmentCommand,
+};
+use crate::application::{AggregateError, MaterializedViewError, SagaManagerError};
+
+mod api;
+mod application;
+
+/// A simple in-memory event repository - infrastructure
+struct InMemoryOrderEventRepository {
+    events: RwLock<Vec<(OrderEvent, i32)>>,
+}
+
+impl EventRepository<OrderCommand, OrderEvent, i32, AggregateError>
+    for InMemoryOrderEventRepository
+{
+    async fn fetch_events(
+        &self,
+        command: &OrderCommand,
+    ) -> Result<Vec<(OrderEvent, i32)>, AggregateError> {
+        Ok(self
+            .events
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(event, _)| event.identifier() == command.identifier())
+            .cloned()
+            .collect())
+    }
+
+    async fn save(&self, events: &[OrderEvent]) -> Result<Vec<(OrderEvent, i32)>, AggregateError> {
+        let mut stored = self.events.write().unwrap();
+        let saved: Vec<(OrderEvent, i32)> = events
+            .iter()
+            .map(|event| {
+                let version = stored
+                    .iter()
+                    .filter(|(e, _)| e.identifier() == event.identifier())
+                    .count() as i32;
+                stored.push((event.clone(), version));
+                (event.clone(), version)
+            })
+            .collect();
+        Ok(saved)
+    }
+
+    async fn version_provider(&self, event: &OrderEvent) -> Result<Option<i32>, AggregateError> {
+        Ok(self
+            .events
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(e, _)| e.identifier() == event.identifier())
+            .map(|(_, version)| *version)
+            .next_back())
+    }
+}
+
+/// A simple in-memory view state repository - infrastructure
+struct InMemoryViewOrderStateRepository {
+    states: RwLock<HashMap<u32, OrderViewState>>,
+}
+
+impl ViewStateRepository<OrderEvent, OrderViewState, MaterializedViewError>
+    for InMemoryViewOrderStateRepository
+{
+    async fn fetch_state(
+        &self,
+        event: &OrderEvent,
+    ) -> Result<Option<OrderViewState>, MaterializedViewError> {
+        Ok(self
+            .states
+            .read()
+            .unwrap()
+            .get(&event.identifier().parse::<u32>().unwrap())
+            .cloned())
+    }
+
+    async fn save(&self, state: &OrderViewState) -> Result<OrderViewState, MaterializedViewError> {
+        self.states
+            .write()
+            .unwrap()
+            .insert(state.order_id, state.clone());
+        Ok(state.clone())
+    }
+}
+
+/// Simple action publisher that just returns the action/command.
+struct SimpleActionPublisher;
+
+impl ActionPublisher<ShipmentCommand, SagaManagerError> for SimpleActionPublisher {
+    async fn publish(
+        &self,
+        action: &[ShipmentCommand],
+    ) -> Result<Vec<ShipmentCommand>, SagaManagerError> {
+        Ok(Vec::from(action))
+    }
+}
+
+/// Handler failing on the first event it handles.
+struct FailingOnce {
+    failed: AtomicBool,
+}
+
+impl Handler<OrderEvent, (), MaterializedViewError> for FailingOnce {
+    async fn handle(&self, _event: &OrderEvent) -> Result<(), MaterializedViewError> {
+        if self.failed.swap(true, Ordering::SeqCst) {
+            Ok(())
+        } else {
+            Err(MaterializedViewError::SaveState("failed".to_string()))
+        }
+    }
+}
+
+fn decider<'a>() -> Decider<'a, OrderCommand, OrderState, OrderEvent, AggregateError> {
+    Decider {
+        decide: Box::new(|command, state| match command {
+            OrderCommand::Create(cmd) => Ok(vec![OrderEvent::Created(OrderCreatedEvent {
+                order_id: cmd.order_id,
+                customer_name: cmd.customer_name.to_owned(),
+                items: cmd.items.to_owned(),
+            })]),
+            OrderCommand::Update(_) => Ok(vec![]),
+            OrderCommand::Cancel(cmd) => {
+                if state.order_id == cmd.order_id {
+                    Ok(vec![OrderEvent::Cancelled(OrderCancelledEvent {
+                        order_id: cmd.order_id,
+                    })])
+                } else {
+                    Err(AggregateError::DomainError(
+                        "Order does not exist".to_string(),
+                    ))
+                }
+            }
+        }),
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+fn view<'a>() -> View<'a, OrderViewState, OrderEvent> {
+    View {
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderViewState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+fn saga<'a>() -> Saga<'a, OrderEvent, ShipmentCommand> {
+    Saga {
+        react: Box::new(|event| match event {
+            OrderEvent::Created(evt) => vec![ShipmentCommand::Create(CreateShipmentCommand {
+                shipment_id: evt.order_id,
+                order_id: evt.order_id,
+                customer_name: evt.customer_name.to_owned(),
+                items: evt.items.to_owned(),
+            })],
+            _ => vec![],
+        }),
+    }
+}
+
+fn create_order_command(order_id: u32) -> OrderCommand {
+    OrderCommand::Create(CreateOrderCommand {
+        order_id,
+        customer_name: "John Doe".to_string(),
+        items: vec!["Item 1".to_string()],
+    })
+}
+
+#[tokio::test]
+async fn subscription_test() {
+    let log = InMemoryEventLog::new();
+    let aggregate = EventSourcedAggregate::new(
+        EventLogRepository::new(
+            InMemoryOrderEventRepository {
+                events: RwLock::new(vec![]),
+            },
+            log.clone(),
+        ),
+        decider(),
+    );
+    aggregate.handle(&create_order_command(1)).await.unwrap();
+    aggregate.handle(&create_order_command(2)).await.unwrap();
+    aggregate
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }))
+        .await
+        .unwrap();
+    assert_eq!(log.len(), 3);
+
+    // The materialized view is fed with the events of the order `1` only
+    let materialized_view = MaterializedView::new(
+        InMemoryViewOrderStateRepository {
+            states: RwLock::new(HashMap::new()),
+        },
+        view(),
+    );
+    let mut subscription =
+        EventSubscription::new(log.clone(), EventFilter::identifiers(&["1"])).with_batch_size(2);
+    assert_eq!(subscription.catch_up(&materialized_view).await.unwrap(), 2);
+    assert_eq!(subscription.position(), Some(&2));
+    assert_eq!(subscription.catch_up(&materialized_view).await.unwrap(), 0);
+    let state = materialized_view
+        .fetch_state(&OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 }))
+        .await
+        .unwrap();
+    assert!(state.unwrap().is_cancelled);
+    let state = materialized_view
+        .fetch_state(&OrderEvent::Cancelled(OrderCancelledEvent { order_id: 2 }))
+        .await
+        .unwrap();
+    assert!(state.is_none());
+
+    // The saga manager is fed with the created orders only, by their stable message type
+    let saga_manager = SagaManager::new(SimpleActionPublisher, saga());
+    let mut subscription = EventSubscription::<_, _, _, SagaManagerError>::new(
+        log.clone(),
+        EventFilter::message_types(&["order.created"]),
+    );
+    assert_eq!(subscription.catch_up(&saga_manager).await.unwrap(), 2);
+
+    // The filters are combined
+    let filter =
+        EventFilter::message_types(&["order.created"]).and(EventFilter::identifiers(&["2"]));
+    assert!(filter.matches(&OrderEvent::Created(OrderCreatedEvent {
+        order_id: 2,
+        customer_name: "John Doe".to_string(),
+        items: vec![],
+    })));
+    assert!(!filter.matches(&OrderEvent::Cancelled(OrderCancelledEvent { order_id: 2 })));
+    assert!(EventFilter::all().matches(&OrderEvent::Cancelled(OrderCancelledEvent { order_id: 2 })));
+}
+
+#[tokio::test]
+async fn redelivery_test() {
+    let log = InMemoryEventLog::new();
+    log.append([
+        OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 }),
+        OrderEvent::Cancelled(OrderCancelledEvent { order_id: 2 }),
+    ]);
+    let handler = FailingOnce {
+        failed: AtomicBool::new(false),
+    };
+    let mut subscription = EventSubscription::new(log, EventFilter::all());
+    assert!(subscription.catch_up(&handler).await.is_err());
+    // The event the handler failed on is delivered again
+    assert_eq!(subscription.catch_up(&handler).await.unwrap(), 2);
+}
+
+#[tokio::test]
+async fn tail_test() {
+    let log = InMemoryEventLog::new();
+    let mut subscription = EventSubscription::<_, _, _, MaterializedViewError>::new(
+        log.clone(),
+        EventFilter::identifiers(&["2"]),
+    )
+    .after(0);
+    log.append([OrderEvent::Cancelled(OrderCancelledEvent { order_id: 2 })]);
+
+    let appender = tokio::spawn(async move {
+        tokio::task::yield_now().await;
+        log.append([OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 })]);
+        tokio::task::yield_now().await;
+        log.append([OrderEvent::Cancelled(OrderCancelledEvent { order_id: 2 })]);
+    });
+    // The first event (at the position `0`) is skipped, and the event of the order `1` is filtered out
+    let (event, position) = subscription.next().await.unwrap();
+    assert_eq!(
+        event,
+        OrderEvent::Cancelled(OrderCancelledEvent { order_id: 2 })
+    );
+    assert_eq!(position, 2);
+    appender.await.unwrap();
+}
+
+#[tokio::test]
+async fn waiters_test() {
+    let log = InMemoryEventLog::<OrderEvent>::new();
+    let mut wait = pin!(EventSource::<_, _, MaterializedViewError>::wait_for_events(
+        &log, &None
+    ));
+    // The waiter keeps the single waker, however many times it is polled
+    for _ in 0..3 {
+        assert!(poll_once(wait.as_mut()).await.is_pending());
+    }
+    assert_eq!(log.waiting(), 1);
+    log.append([OrderEvent::Cancelled(OrderCancelledEvent { order_id: 1 })]);
+    assert!(poll_once(wait.as_mut()).await.is_ready());
+    assert_eq!(log.waiting(), 0);
+
+    // The dropped waiter leaves the log
+    let mut wait = Box::pin(EventSource::<_, _, MaterializedViewError>::wait_for_events(
+        &log,
+        &Some(0),
+    ));
+    assert!(poll_once(wait.as_mut()).await.is_pending());
+    assert_eq!(log.waiting(), 1);
+    drop(wait);
+    assert_eq!(log.waiting(), 0);
+}
+
+/// Polls the future once.
+async fn poll_once<F: Future>(mut future: Pin<&mut F>) -> Poll<F::Output> {
+    poll_fn(|cx| Poll::Ready(future.as_mut().poll(cx))).await
+}
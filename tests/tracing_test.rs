@@ -0,0 +1,212 @@
+#![cfg(all(feature = "tracing", not(feature = "not-send-futures")))]
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use fmodel_rust::aggregate::EventSourcedAggregate;
+use fmodel_rust::decider::Decider;
+use fmodel_rust::middleware::{Handler, Traced};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+use crate::api::{
+    CancelOrderCommand, CreateOrderCommand, OrderCancelledEvent, OrderCommand, OrderCreatedEvent,
+    OrderEvent, OrderState,
+};
+use crate::application::{AggregateError, InMemoryOrderEventRepository};
+
+mod api;
+mod application;
+
+/// Decider for the Order aggregate - Domain logic
+fn decider<'a>() -> Decider<'a, OrderCommand, OrderState, OrderEvent, AggregateError> {
+    Decider {
+        decide: Box::new(|command, state| match command {
+            OrderCommand::Create(cmd) => Ok(vec![OrderEvent::Created(OrderCreatedEvent {
+                order_id: cmd.order_id,
+                customer_name: cmd.customer_name.to_owned(),
+                items: cmd.items.to_owned(),
+            })]),
+            OrderCommand::Update(_) => Ok(vec![]),
+            OrderCommand::Cancel(cmd) => {
+                if state.order_id == cmd.order_id {
+                    Ok(vec![OrderEvent::Cancelled(OrderCancelledEvent {
+                        order_id: cmd.order_id,
+                    })])
+                } else {
+                    Err(AggregateError::DomainError(
+                        "Order does not exist".to_string(),
+                    ))
+                }
+            }
+        }),
+        evolve: Box::new(|state, event| {
+            let mut new_state = state.clone();
+            match event {
+                OrderEvent::Created(evt) => {
+                    new_state.order_id = evt.order_id;
+                    new_state.customer_name = evt.customer_name.to_owned();
+                    new_state.items = evt.items.to_owned();
+                }
+                OrderEvent::Updated(evt) => {
+                    new_state.items = evt.updated_items.to_owned();
+                }
+                OrderEvent::Cancelled(_) => {
+                    new_state.is_cancelled = true;
+                }
+            }
+            new_state
+        }),
+        initial_state: Box::new(|| OrderState {
+            order_id: 0,
+            customer_name: "".to_string(),
+            items: Vec::new(),
+            is_cancelled: false,
+        }),
+    }
+}
+
+/// The span, as recorded by the [RecordingSubscriber]
+#[derive(Debug, Clone, PartialEq)]
+struct RecordedSpan {
+    name: &'static str,
+    fields: BTreeMap<String, String>,
+}
+
+impl Visit for RecordedSpan {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.fields
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+}
+
+/// A minimal subscriber, recording the spans in the order they are created - infrastructure
+#[derive(Clone, Default)]
+struct RecordingSubscriber {
+    next_id: Arc<AtomicU64>,
+    spans: Arc<Mutex<Vec<RecordedSpan>>>,
+}
+
+impl RecordingSubscriber {
+    fn span(&self, name: &str) -> RecordedSpan {
+        self.spans
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|span| span.name == name)
+            .cloned()
+            .unwrap()
+    }
+    fn names(&self) -> Vec<&'static str> {
+        self.spans
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|span| span.name)
+            .collect()
+    }
+}
+
+impl Subscriber for RecordingSubscriber {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let mut span = RecordedSpan {
+            name: attributes.metadata().name(),
+            fields: BTreeMap::new(),
+        };
+        attributes.record(&mut span);
+        self.spans.lock().unwrap().push(span);
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    fn record(&self, id: &Id, values: &Record<'_>) {
+        let index = (id.into_u64() - 1) as usize;
+        values.record(&mut self.spans.lock().unwrap()[index]);
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[tokio::test]
+async fn test() {
+    let subscriber = RecordingSubscriber::default();
+    let _guard = tracing::subscriber::set_default(subscriber.clone());
+
+    let aggregate = Traced::new(EventSourcedAggregate::new(
+        InMemoryOrderEventRepository {
+            events: RwLock::new(vec![]),
+        },
+        decider(),
+    ));
+
+    let result = aggregate
+        .handle(&OrderCommand::Create(CreateOrderCommand {
+            order_id: 1,
+            customer_name: "John Doe".to_string(),
+            items: vec!["Item 1".to_string()],
+        }))
+        .await;
+    assert!(result.is_ok());
+    assert_eq!(
+        subscriber.names(),
+        vec!["message", "handle", "fetch", "evolve", "decide", "save"]
+    );
+    assert_eq!(subscriber.span("message").fields["identifier"], "1");
+    let handle = subscriber.span("handle");
+    assert_eq!(handle.fields["component"], "\"EventSourcedAggregate\"");
+    assert_eq!(handle.fields["events_loaded"], "0");
+    assert_eq!(handle.fields["events_produced"], "1");
+    assert!(!handle.fields.contains_key("identifier"));
+    assert!(!handle.fields.contains_key("version"));
+    assert!(!handle.fields.contains_key("error"));
+
+    subscriber.spans.lock().unwrap().clear();
+    subscriber.next_id.store(0, Ordering::SeqCst);
+
+    let result = aggregate
+        .handler()
+        .handle_traced(&OrderCommand::Cancel(CancelOrderCommand { order_id: 1 }))
+        .await;
+    assert!(result.is_ok());
+    assert_eq!(
+        subscriber.names(),
+        vec!["handle", "fetch", "evolve", "decide", "save"]
+    );
+    let handle = subscriber.span("handle");
+    assert_eq!(handle.fields["identifier"], "1");
+    assert_eq!(handle.fields["events_loaded"], "1");
+    assert_eq!(handle.fields["events_produced"], "1");
+    assert_eq!(handle.fields["version"], "1");
+    assert!(!handle.fields.contains_key("error"));
+
+    subscriber.spans.lock().unwrap().clear();
+    subscriber.next_id.store(0, Ordering::SeqCst);
+
+    let result = aggregate
+        .handle(&OrderCommand::Cancel(CancelOrderCommand { order_id: 2 }))
+        .await;
+    assert!(result.is_err());
+    assert_eq!(
+        subscriber.names(),
+        vec!["message", "handle", "fetch", "evolve", "decide"]
+    );
+    assert_eq!(
+        subscriber.span("message").fields["error"],
+        "Order does not exist"
+    );
+    assert_eq!(subscriber.span("decide").fields["error"], "true");
+    assert_eq!(subscriber.span("handle").fields["error"], "true");
+    assert!(!subscriber.span("handle").fields.contains_key("version"));
+}